@@ -0,0 +1,298 @@
+//! Physical buttons for the most common commands, since pressing a button is much faster than
+//! typing during live tracking. Buttons come from a MIDI controller or a Stream Deck, read
+//! straight from their Linux device files, and are bound to commands in a JSON file:
+//!
+//! ```json
+//! {
+//!     "midi": "/dev/snd/midiC1D0",
+//!     "streamdeck": "/dev/hidraw3",
+//!     "bindings": {
+//!         "note 36": ":t",
+//!         "note 37": ":h1 -1",
+//!         "cc 20": ":h2 -4",
+//!         "key 0": ":z"
+//!     }
+//! }
+//! ```
+//!
+//! A Stream Deck driven by other software can send MIDI notes instead.
+
+use std::{collections::HashMap, fmt, fs::File, io::Read, str::FromStr, thread};
+
+use futures::{
+    channel::mpsc::{self, UnboundedSender},
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use serde::Deserialize;
+
+use crate::extract_life_update;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Stream Deck input reports start with the report id, the kind of event and the key count,
+/// then one byte per key. Decks from before the MK.2 aren't supported.
+const STREAMDECK_KEY_OFFSET: usize = 4;
+const STREAMDECK_REPORT_ID: u8 = 1;
+/// Kind of event for key presses, rather than e.g. the Plus's dials
+const STREAMDECK_KEY_EVENT: u8 = 0;
+const STREAMDECK_REPORT_SIZE: usize = 512;
+
+/// A button press
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Input {
+    /// MIDI note on, e.g. a pad
+    Note(u8),
+    /// MIDI control change above 0, e.g. a button sending 127 while held
+    Control(u8),
+    /// Stream Deck key, counting from 0 at the top left
+    Key(u8),
+}
+
+impl FromStr for Input {
+    type Err = String;
+
+    fn from_str(text: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || format!("'{}' isn't an input like note 36, cc 20 or key 0", text);
+        let (kind, number) = text.trim().split_once(' ').ok_or_else(invalid)?;
+        let number: u8 = number.trim().parse().map_err(|_| invalid())?;
+        match kind {
+            "note" => Ok(Input::Note(number)),
+            "cc" => Ok(Input::Control(number)),
+            "key" => Ok(Input::Key(number)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Input::Note(number) => write!(f, "note {}", number),
+            Input::Control(number) => write!(f, "cc {}", number),
+            Input::Key(number) => write!(f, "key {}", number),
+        }
+    }
+}
+
+/// Commands a button can run, written as they're typed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HotkeyAction {
+    /// `:t`
+    Turn,
+    /// `:h1 -2`
+    Life(u8, String),
+    /// `:z`
+    Zoom,
+    /// `:uz`
+    Unzoom,
+    /// `:u`
+    Undo,
+}
+
+impl FromStr for HotkeyAction {
+    type Err = String;
+
+    fn from_str(text: &str) -> std::result::Result<Self, Self::Err> {
+        match text.trim() {
+            ":t" => Ok(HotkeyAction::Turn),
+            ":z" => Ok(HotkeyAction::Zoom),
+            ":uz" => Ok(HotkeyAction::Unzoom),
+            ":u" => Ok(HotkeyAction::Undo),
+            text if text.starts_with(":h") && text.len() > 2 => extract_life_update(text)
+                .map(|(player, update)| HotkeyAction::Life(player, update))
+                .ok_or_else(|| format!("'{}' isn't a life update like :h1 -2", text)),
+            _ => Err(format!(
+                "'{}' can't be bound, only :t, :h, :z, :uz and :u can",
+                text
+            )),
+        }
+    }
+}
+
+/// What the listeners pass on
+pub enum Hotkey {
+    Action(HotkeyAction),
+    /// Pressed but not bound, shown so buttons can be found while writing the bindings
+    Unbound(Input),
+    /// A device stopped being readable, e.g. it was unplugged
+    Lost(String),
+}
+
+#[derive(Deserialize)]
+pub struct HotkeyConfig {
+    /// Raw MIDI device, e.g. /dev/snd/midiC1D0
+    midi: Option<String>,
+    /// Stream Deck's hidraw device, e.g. /dev/hidraw3
+    streamdeck: Option<String>,
+    /// Input, e.g. "note 36", to the command it runs, e.g. ":h1 -2"
+    bindings: HashMap<String, String>,
+}
+
+impl HotkeyConfig {
+    pub fn read_from_path(fp: &str) -> Result<Self> {
+        let config: HotkeyConfig = serde_json::from_reader(File::open(fp)?)?;
+        if config.midi.is_none() && config.streamdeck.is_none() {
+            return Err("The hotkeys need a midi or streamdeck device".into());
+        }
+        Ok(config)
+    }
+
+    fn bindings(&self) -> Result<HashMap<Input, HotkeyAction>> {
+        self.bindings
+            .iter()
+            .map(|(input, command)| -> Result<(Input, HotkeyAction)> {
+                Ok((input.parse()?, command.parse()?))
+            })
+            .collect()
+    }
+
+    /// Starts reading the devices, each on its own thread. The stream never ends, so it can
+    /// be waited on alongside the terminal whether or not there are hotkeys.
+    pub fn listen(&self) -> Result<BoxStream<'static, Hotkey>> {
+        let bindings = self.bindings()?;
+        let (sender, receiver) = mpsc::unbounded();
+        if let Some(fp) = &self.midi {
+            let device = File::open(fp).map_err(|err| format!("Couldn't open {}: {}", fp, err))?;
+            let (sender, bindings) = (sender.clone(), bindings.clone());
+            thread::spawn(move || read_midi(device, &bindings, &sender));
+        }
+        if let Some(fp) = &self.streamdeck {
+            let device = File::open(fp).map_err(|err| format!("Couldn't open {}: {}", fp, err))?;
+            thread::spawn(move || read_streamdeck(device, &bindings, &sender));
+        }
+        Ok(receiver.chain(stream::pending()).boxed())
+    }
+}
+
+/// Stream for when there are no hotkeys
+pub fn none() -> BoxStream<'static, Hotkey> {
+    stream::pending().boxed()
+}
+
+/// Sends the bound action for `input`. Returns false once nothing is listening.
+fn press(
+    input: Input,
+    bindings: &HashMap<Input, HotkeyAction>,
+    sender: &UnboundedSender<Hotkey>,
+) -> bool {
+    let hotkey = match bindings.get(&input) {
+        Some(action) => Hotkey::Action(action.clone()),
+        None => Hotkey::Unbound(input),
+    };
+    sender.unbounded_send(hotkey).is_ok()
+}
+
+/// Splits raw MIDI bytes into messages, keeping note ons and control changes
+#[derive(Default)]
+struct MidiParser {
+    /// Kept between messages, since a status can be sent once for several
+    status: Option<u8>,
+    data: Vec<u8>,
+}
+
+impl MidiParser {
+    fn push(&mut self, byte: u8) -> Option<Input> {
+        match byte {
+            // Clock and other real time bytes can arrive in the middle of a message
+            0xF8..=0xFF => None,
+            0x80..=0xEF => {
+                self.status = Some(byte);
+                self.data.clear();
+                None
+            }
+            // System exclusive and common messages are skipped
+            0xF0..=0xF7 => {
+                self.status = None;
+                self.data.clear();
+                None
+            }
+            _ => {
+                let status = self.status?;
+                self.data.push(byte);
+                // Program and channel pressure changes have a single data byte
+                let length = if matches!(status >> 4, 0xC | 0xD) {
+                    1
+                } else {
+                    2
+                };
+                if self.data.len() < length {
+                    return None;
+                }
+                let data = std::mem::take(&mut self.data);
+                match (status >> 4, data.as_slice()) {
+                    // A note on without velocity is a note off
+                    (0x9, &[note, velocity]) if velocity > 0 => Some(Input::Note(note)),
+                    (0xB, &[control, value]) if value > 0 => Some(Input::Control(control)),
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+fn read_midi(
+    mut device: File,
+    bindings: &HashMap<Input, HotkeyAction>,
+    sender: &UnboundedSender<Hotkey>,
+) {
+    let mut parser = MidiParser::default();
+    let mut buffer = [0; 64];
+    loop {
+        let read = match device.read(&mut buffer) {
+            Ok(0) => {
+                let _ = sender.unbounded_send(Hotkey::Lost("MIDI device closed".to_owned()));
+                return;
+            }
+            Ok(read) => read,
+            Err(err) => {
+                let _ = sender.unbounded_send(Hotkey::Lost(format!("MIDI: {}", err)));
+                return;
+            }
+        };
+        for byte in &buffer[..read] {
+            if let Some(input) = parser.push(*byte) {
+                if !press(input, bindings, sender) {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn read_streamdeck(
+    mut device: File,
+    bindings: &HashMap<Input, HotkeyAction>,
+    sender: &UnboundedSender<Hotkey>,
+) {
+    let mut report = [0; STREAMDECK_REPORT_SIZE];
+    // Reports carry every key's state, so presses are keys that weren't down before
+    let mut down: Vec<bool> = Vec::new();
+    loop {
+        let read = match device.read(&mut report) {
+            Ok(0) => {
+                let _ = sender.unbounded_send(Hotkey::Lost("Stream Deck closed".to_owned()));
+                return;
+            }
+            Ok(read) => read,
+            Err(err) => {
+                let _ = sender.unbounded_send(Hotkey::Lost(format!("Stream Deck: {}", err)));
+                return;
+            }
+        };
+        if read <= STREAMDECK_KEY_OFFSET
+            || report[0] != STREAMDECK_REPORT_ID
+            || report[1] != STREAMDECK_KEY_EVENT
+        {
+            continue;
+        }
+        let keys = &report[STREAMDECK_KEY_OFFSET..read];
+        for (key, state) in keys.iter().enumerate() {
+            let was_down = down.get(key).copied().unwrap_or(false);
+            if *state != 0 && !was_down && !press(Input::Key(key as u8), bindings, sender) {
+                return;
+            }
+        }
+        down = keys.iter().map(|state| *state != 0).collect();
+    }
+}
@@ -0,0 +1,2304 @@
+use clap::Parser;
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::Write,
+    path::Path,
+    time::Duration,
+};
+
+use futures::{future::FutureExt, select, stream::BoxStream, StreamExt};
+use tracing::{error, info, warn};
+
+use crossterm::{
+    event::{Event, EventStream, KeyCode},
+    terminal::{disable_raw_mode, enable_raw_mode},
+};
+
+mod hotkeys;
+mod playback;
+mod tools;
+mod ui;
+mod web;
+
+use hotkeys::{Hotkey, HotkeyAction, HotkeyConfig};
+use opencv::{
+    core::{Mat, Rect},
+    highgui,
+};
+use playback::{Backend, LiveClock, Player, Rgb};
+use tools::Tool;
+use ui::{Ui, View};
+
+use lib::{
+    annotation::{
+        self, Annotation, AnnotationFile, Event as AnnotationEvent, Format, Header, MatchSetup,
+        PlayerInfo,
+    },
+    annotation_store::{self, SessionWriter, StorageFormat},
+    assets,
+    autocomplete::{AutocompleteSuggestionManager, Named},
+    card::{CardDB, CardData, CardImageDB},
+    card_alias::CardAliases,
+    card_match::{CardHashIndex, CARD_HASH_FILE},
+    card_query::{self, CardQuery},
+    card_update,
+    config::Config,
+    deck_odds::DeckOdds,
+    decklist::Decklist,
+    life_tracker::LifeTracker,
+    logging::LogArgs,
+    match_state::MatchState,
+    ocr,
+    retime::Retime,
+    time_tick::TimeTick,
+};
+use overlay::render;
+
+const MILLI: f64 = 1000.0;
+/// Seek granularities, cycled with the up and down arrows
+const SEEK_STEPS: [f64; 4] = [0.5, 2.0, 10.0, 60.0];
+const DEFAULT_SEEK_STEP: usize = 1;
+/// Playback speeds, cycled with `[` and `]`
+const PLAYBACK_SPEEDS: [f64; 7] = [0.5, 0.75, 1.0, 1.5, 2.0, 3.0, 4.0];
+const DEFAULT_PLAYBACK_SPEED: usize = 2;
+/// Number of recently entered cards suggested first
+const RECENT_CARDS: usize = 8;
+/// Cards suggested from the video in assist mode
+const DETECTED_CARDS: usize = 3;
+/// How long the state stays on the video after it changes
+const OSD_DURATION_MS: u64 = 3000;
+/// Colours of the flash cues
+const RECORD_CUE_COLOR: Rgb = (0, 255, 0);
+const UNDO_CUE_COLOR: Rgb = (255, 0, 0);
+/// Amount a record's timestamp moves per nudge in edit mode
+const NUDGE_MILLI: f64 = 100.0;
+/// Number of actions that can be undone
+const UNDO_LIMIT: usize = 200;
+/// Number of actions listed after an undo or redo
+const RECENT_ACTIONS: usize = 5;
+/// How often the status bar is redrawn in live mode
+const CLOCK_REDRAW: Duration = Duration::from_secs(1);
+
+#[derive(Parser)]
+#[command(
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    tool: Option<Tool>,
+
+    #[arg(short, long, required_unless_present = "live")]
+    video_file: Option<String>,
+
+    #[arg(short, long, required_unless_present = "edit")]
+    output_fp: Option<String>,
+
+    #[arg(short, long, action)]
+    update_db: bool,
+
+    /// Session file of an interrupted annotation to pick back up
+    #[arg(short, long, conflicts_with = "edit")]
+    resume: Option<String>,
+
+    /// Existing annotation file to correct. It is overwritten in its own format.
+    #[arg(short, long, conflicts_with = "output_fp")]
+    edit: Option<String>,
+
+    /// Decklist for player 1: a fabdb.net deck URL, a URL or file with a text export, or a
+    /// file with one card per line. Its cards are suggested first.
+    #[arg(long)]
+    deck1: Option<String>,
+
+    /// Decklist for player 2
+    #[arg(long)]
+    deck2: Option<String>,
+
+    /// Only suggest cards from the decklists
+    #[arg(long, action)]
+    deck_only: bool,
+
+    /// Output format: tsv, jsonl or sqlite
+    #[arg(short = 'f', long, default_value = "tsv")]
+    output_format: StorageFormat,
+
+    /// Format being played: cc, blitz or upf. Only heroes legal in it are offered, and heroes
+    /// without a life or intellect get its defaults.
+    #[arg(long)]
+    format: Option<Format>,
+
+    /// Render the overlay video from the annotation once annotating ends
+    #[arg(long, action)]
+    render: bool,
+
+    /// Video written by --render. Defaults to <annotation name>.mp4 in the configured render
+    /// directory, output_videos unless set
+    #[arg(long, requires = "render")]
+    render_output: Option<String>,
+
+    /// Player 1's team. Players without any metadata flags are prompted for it instead.
+    #[arg(long)]
+    team1: Option<String>,
+
+    /// Player 1's country code, e.g. US
+    #[arg(long)]
+    country1: Option<String>,
+
+    /// Player 1's pronouns, e.g. they/them
+    #[arg(long)]
+    pronouns1: Option<String>,
+
+    /// Player 1's seed or record, e.g. 3 or 5-1
+    #[arg(long)]
+    seed1: Option<String>,
+
+    #[arg(long)]
+    team2: Option<String>,
+
+    #[arg(long)]
+    country2: Option<String>,
+
+    #[arg(long)]
+    pronouns2: Option<String>,
+
+    #[arg(long)]
+    seed2: Option<String>,
+
+    /// Confirm each record and undo with the terminal bell or a marker flashed on the video
+    #[arg(long, value_enum)]
+    cue: Option<CueMode>,
+
+    /// Suggest life updates read off a life tracker on the video. Needs tesseract installed.
+    #[arg(long, action)]
+    ocr: bool,
+
+    /// Annotate from a browser instead of the terminal, e.g. on a tablet on the same network
+    #[arg(long, action, conflicts_with = "edit")]
+    web: bool,
+
+    /// Port the web interface is served on
+    #[arg(long, default_value_t = 8080, requires = "web")]
+    port: u16,
+
+    /// Annotate a match as it happens, with no video. Records are timed by a clock that starts
+    /// with the annotation; line them up with the footage later with `:o` in edit mode.
+    #[arg(long, action, conflicts_with_all = ["video_file", "edit", "web", "render"])]
+    live: bool,
+
+    /// JSON file binding MIDI or Stream Deck buttons to commands like :t and :h1 -2
+    #[arg(long, conflicts_with = "web")]
+    hotkeys: Option<String>,
+
+    /// Video player. Auto uses mpv when it's available and falls back to OpenCV.
+    #[arg(long, value_enum, default_value_t)]
+    backend: Backend,
+
+    /// Suggest the card shown on the paused video by matching it against card art. Run with
+    /// --update-card-hashes once first.
+    #[arg(long, action)]
+    assist: bool,
+
+    /// Download and hash every card image for --assist. This takes a while.
+    #[arg(long, action)]
+    update_card_hashes: bool,
+
+    #[command(flatten)]
+    pub log: LogArgs,
+}
+
+/// How records and undos are confirmed away from the terminal
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum CueMode {
+    /// One bell for a record, two for an undo
+    Bell,
+    /// Green marker for a record, red for an undo
+    Flash,
+}
+
+/// Change to confirm with a cue
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Cue {
+    Record,
+    Undo,
+}
+
+impl Cli {
+    /// Metadata given by flags for each player, `None` for players without any
+    fn players(&self) -> [Option<PlayerInfo>; 2] {
+        [
+            (&self.team1, &self.country1, &self.pronouns1, &self.seed1),
+            (&self.team2, &self.country2, &self.pronouns2, &self.seed2),
+        ]
+        .map(|(team, country, pronouns, seed)| {
+            let info = PlayerInfo {
+                team: team.clone(),
+                country: country.clone(),
+                pronouns: pronouns.clone(),
+                seed: seed.clone(),
+            };
+            (info != PlayerInfo::default()).then_some(info)
+        })
+    }
+}
+
+enum Command {
+    HEALTH,
+    DAMAGE,
+    HEAL,
+    VERIFY,
+    GOTO,
+    RECORD,
+    TURN,
+    QUIT,
+    UNDO,
+    REDO,
+    ACTIONS,
+    WIN1,
+    WIN2,
+    ZOOM,
+    ZOOM_CARD,
+    UNZOOM,
+    BLOCK,
+    PAIR,
+    GAME,
+    LOWER_THIRD1,
+    LOWER_THIRD2,
+    NOTE,
+    TIME,
+    TOKEN,
+    // Edit mode
+    NEXT,
+    PREVIOUS,
+    DELETE,
+    EARLIER,
+    LATER,
+    RETIME,
+    REPLACE,
+    OFFSET,
+    // OCR
+    LIFE_REGIONS,
+    LIFE_READ,
+    // Assist
+    CARD_REGION,
+    CARD_DETECT,
+    // Live
+    CLOCK,
+}
+
+impl Command {
+    fn get_all() -> Vec<Self> {
+        Vec::from([
+            Command::HEALTH,
+            Command::DAMAGE,
+            Command::HEAL,
+            Command::VERIFY,
+            Command::GOTO,
+            Command::RECORD,
+            Command::TURN,
+            Command::QUIT,
+            Command::UNDO,
+            Command::REDO,
+            Command::ACTIONS,
+            Command::WIN1,
+            Command::WIN2,
+            Command::ZOOM,
+            Command::ZOOM_CARD,
+            Command::UNZOOM,
+            Command::BLOCK,
+            Command::PAIR,
+            Command::GAME,
+            Command::LOWER_THIRD1,
+            Command::LOWER_THIRD2,
+            Command::NOTE,
+            Command::TIME,
+            Command::TOKEN,
+        ])
+    }
+
+    fn get_all_edit() -> Vec<Self> {
+        let mut commands = Self::get_all();
+        commands.extend([
+            Command::NEXT,
+            Command::PREVIOUS,
+            Command::DELETE,
+            Command::EARLIER,
+            Command::LATER,
+            Command::RETIME,
+            Command::REPLACE,
+            Command::OFFSET,
+        ]);
+        commands
+    }
+
+    fn get_all_ocr() -> [Self; 2] {
+        [Command::LIFE_REGIONS, Command::LIFE_READ]
+    }
+
+    fn get_all_live() -> [Self; 1] {
+        [Command::CLOCK]
+    }
+
+    fn get_all_assist() -> [Self; 2] {
+        [Command::CARD_REGION, Command::CARD_DETECT]
+    }
+}
+
+impl Command {
+    fn description(&self) -> &'static str {
+        match self {
+            Command::HEALTH => "life, e.g. :h1 -3",
+            Command::DAMAGE => "damage to defender, e.g. :d 4",
+            Command::HEAL => "heal turn player, e.g. :heal 2",
+            Command::VERIFY => "check life totals, e.g. :v 20 18",
+            Command::GOTO => "go to time, e.g. :g 12:34",
+            Command::RECORD => "go to record, e.g. :r 12",
+            Command::TURN => "next turn",
+            Command::QUIT => "save and quit",
+            Command::UNDO => "undo",
+            Command::REDO => "redo",
+            Command::ACTIONS => "list recent actions",
+            Command::WIN1 => "player 1 wins",
+            Command::WIN2 => "player 2 wins",
+            Command::ZOOM => "zoom current card",
+            Command::ZOOM_CARD => "zoom recent card, e.g. :zc erase",
+            Command::UNZOOM => "end zoom",
+            Command::BLOCK => "next card blocks",
+            Command::PAIR => "next card shows beside the last",
+            Command::GAME => "start next game",
+            Command::LOWER_THIRD1 => "show player 1's name plate",
+            Command::LOWER_THIRD2 => "show player 2's name plate",
+            Command::NOTE => "show a note, e.g. :note Top 8 on the line",
+            Command::TIME => "time called, extra turns follow",
+            Command::TOKEN => "token on a hero, e.g. :tk1 quicken",
+            Command::NEXT => "next record",
+            Command::PREVIOUS => "previous record",
+            Command::DELETE => "delete record",
+            Command::EARLIER => "nudge record earlier",
+            Command::LATER => "nudge record later",
+            Command::RETIME => "move record to now",
+            Command::REPLACE => "replace record's card",
+            Command::OFFSET => "shift every record, e.g. :o -12.5",
+            Command::LIFE_REGIONS => "select life totals on video",
+            Command::LIFE_READ => "suggest life from video",
+            Command::CARD_REGION => "select card area on video",
+            Command::CARD_DETECT => "suggest card from video",
+            Command::CLOCK => "pause or resume the clock",
+        }
+    }
+}
+
+/// Keys that work while nothing has been typed
+const KEY_HELP: [(&str, &str); 9] = [
+    ("<- ->", "seek"),
+    ("up dn", "seek step"),
+    ("[ ]", "playback speed"),
+    (", .", "frame step"),
+    ("tab", "next suggestion"),
+    ("esc", "clear input"),
+    ("t:", "card type filter"),
+    ("c:", "pitch color filter"),
+    ("cost<=", "card cost filter"),
+];
+
+impl Named for Command {
+    fn get_name(&self) -> &str {
+        match self {
+            Command::HEALTH => ":h",
+            Command::DAMAGE => ":d",
+            Command::HEAL => ":heal",
+            Command::VERIFY => ":v",
+            Command::GOTO => ":g",
+            Command::RECORD => ":r",
+            Command::TURN => ":t",
+            Command::QUIT => ":q",
+            Command::UNDO => ":u",
+            Command::REDO => ":y",
+            Command::ACTIONS => ":l",
+            Command::WIN1 => ":w1",
+            Command::WIN2 => ":w2",
+            Command::ZOOM => ":z",
+            Command::ZOOM_CARD => ":zc",
+            Command::UNZOOM => ":uz",
+            Command::BLOCK => ":b",
+            Command::PAIR => ":pair",
+            Command::GAME => ":game",
+            Command::LOWER_THIRD1 => ":lt1",
+            Command::LOWER_THIRD2 => ":lt2",
+            Command::NOTE => ":note",
+            Command::TIME => ":time",
+            Command::TOKEN => ":tk",
+            Command::NEXT => ":n",
+            Command::PREVIOUS => ":p",
+            Command::DELETE => ":x",
+            Command::EARLIER => ":<",
+            Command::LATER => ":>",
+            Command::RETIME => ":s",
+            Command::REPLACE => ":c",
+            Command::OFFSET => ":o",
+            Command::LIFE_REGIONS => ":lr",
+            Command::LIFE_READ => ":lo",
+            Command::CARD_REGION => ":cr",
+            Command::CARD_DETECT => ":cd",
+            Command::CLOCK => ":pause",
+        }
+    }
+}
+
+fn is_command(text: &str) -> bool {
+    text.starts_with(":")
+}
+
+fn is_life_update(text: &str) -> bool {
+    text.starts_with(":h")
+}
+
+fn is_heal(text: &str) -> bool {
+    text.starts_with(":heal")
+}
+
+fn is_damage(text: &str) -> bool {
+    text.starts_with(":d")
+}
+
+fn is_verify(text: &str) -> bool {
+    text.starts_with(":v")
+}
+
+fn is_goto(text: &str) -> bool {
+    text.starts_with(":g")
+}
+
+/// Shares the `:g` prefix, so it is typed out like the commands with arguments
+fn is_game(text: &str) -> bool {
+    text.trim() == ":game"
+}
+
+fn is_record_jump(text: &str) -> bool {
+    text.starts_with(":r")
+}
+
+fn is_zoom_card(text: &str) -> bool {
+    text.starts_with(":zc")
+}
+
+fn is_offset(text: &str) -> bool {
+    text.starts_with(":o")
+}
+
+fn is_note(text: &str) -> bool {
+    text.starts_with(":note")
+}
+
+fn is_token(text: &str) -> bool {
+    text.starts_with(":tk")
+}
+
+/// Commands that take an argument after their name
+fn is_argument_command(text: &str) -> bool {
+    is_life_update(text)
+        || is_damage(text)
+        || is_verify(text)
+        || is_goto(text)
+        || is_record_jump(text)
+        || is_zoom_card(text)
+        || is_offset(text)
+        || is_note(text)
+        || is_token(text)
+}
+
+/// Expected format:
+/// :v 20 18
+fn extract_verify(text: &str) -> Option<(i32, i32)> {
+    let mut args = text.strip_prefix(":v")?.split_whitespace();
+    let player1 = args.next()?.parse().ok()?;
+    let player2 = args.next()?.parse().ok()?;
+    args.next().is_none().then_some((player1, player2))
+}
+
+/// Expected format:
+/// :d 4
+/// :heal 2
+fn extract_shorthand_amount(text: &str) -> Option<u32> {
+    let args = text
+        .strip_prefix(":heal")
+        .or_else(|| text.strip_prefix(":d"))?;
+    args.trim().parse::<u32>().ok()
+}
+
+/// Expected format, with optional fractional seconds:
+/// :g 12:34
+/// :g 1:02:03.5
+/// :g 95
+fn extract_goto(text: &str) -> Option<f64> {
+    let args = text.strip_prefix(":g")?;
+    TimeTick::parse(args).map(|time| time.as_f64())
+}
+
+/// Expected format, where the argument is the start of the card's name:
+/// :zc erase
+fn extract_zoom_card(text: &str) -> Option<&str> {
+    text.strip_prefix(":zc").map(|name| name.trim())
+}
+
+/// Expected format:
+/// :note Top 8 on the line
+fn extract_note(text: &str) -> Option<&str> {
+    text.strip_prefix(":note")
+        .map(|note| note.trim())
+        .filter(|note| !note.is_empty())
+}
+
+/// Expected format, where the player's hero is the one the token is on:
+/// :tk1 quicken
+/// :tk2 aim counter
+fn extract_token(text: &str) -> Option<(u8, &str)> {
+    let args = text.strip_prefix(":tk")?;
+    let player = match args.chars().next()? {
+        '1' => 1,
+        '2' => 2,
+        _ => return None,
+    };
+    let name = args[1..].trim();
+    (!name.is_empty()).then_some((player, name))
+}
+
+/// Expected format, in seconds:
+/// :o -12.5
+fn extract_offset(text: &str) -> Option<f64> {
+    let seconds: f64 = text.strip_prefix(":o")?.trim().parse().ok()?;
+    seconds.is_finite().then_some(seconds)
+}
+
+/// Expected format, counting records from 1:
+/// :r 12
+fn extract_record_jump(text: &str) -> Option<usize> {
+    let (cmd, args) = text.split_at(2);
+    if cmd != ":r" {
+        return None;
+    }
+    args.trim().parse::<usize>().ok().filter(|n| *n > 0)
+}
+
+/// Expected format:
+/// :h1 -2
+/// :h 1 - 2
+/// :h1 - 2
+/// :h1-2
+fn extract_life_update(text: &str) -> Option<(u8, String)> {
+    let mut player = None;
+    let mut update = None;
+
+    let (cmd, args) = text.split_at(2);
+    let args: Vec<char> = args.chars().filter(|c| !c.is_whitespace()).collect();
+
+    let player_char = args.first();
+    let update_str = args[1..].iter().collect::<String>();
+
+    if cmd == ":h" {
+        // Parse player
+        if player_char == Some(&'1') {
+            player.replace(1);
+        }
+        if player_char == Some(&'2') {
+            player.replace(2);
+        }
+
+        // Parse update
+        if LifeTracker::parse_update(&update_str).is_ok() {
+            update.replace(&update_str);
+        }
+    }
+    if player.is_some() && update.is_some() {
+        return Some((player.unwrap(), update.unwrap().to_owned()));
+    }
+    return None;
+}
+
+/// Makes string title case (assumes one word -- sue me)
+fn title_case(txt: &str) -> String {
+    let mut txt = txt.to_owned();
+    let tail = txt.split_off(1);
+    let mut txt = txt.to_uppercase();
+    txt.push_str(&tail.to_lowercase());
+    txt
+}
+
+/// One line summary of a record, e.g. "[12.345] card: Erase Face (1)"
+fn describe_record(record: &Annotation) -> String {
+    let detail = match &record.event {
+        AnnotationEvent::Card { name, pitch }
+        | AnnotationEvent::Block { name, pitch }
+        | AnnotationEvent::Pair { name, pitch }
+        | AnnotationEvent::Zoom {
+            name: Some(name),
+            pitch,
+        } => match pitch {
+            Some(pitch) => format!(": {} ({})", name, pitch),
+            None => format!(": {}", name),
+        },
+        AnnotationEvent::Life { player1, player2 } => format!(
+            ": {} / {}",
+            player1.as_deref().unwrap_or("-"),
+            player2.as_deref().unwrap_or("-")
+        ),
+        AnnotationEvent::Note { text } => format!(": {}", text),
+        AnnotationEvent::Token { player, name } => format!(": {} on player {}", name, player),
+        _ => String::new(),
+    };
+    format!(
+        "[{:.3}] {}{}",
+        record.time.as_f64(),
+        record.event.update_type(),
+        detail
+    )
+}
+
+/// Orders card suggestions: recently entered cards, then the turn player's deck, then the
+/// other deck, then everything else
+struct CardPriority {
+    /// Most recent first
+    recent: VecDeque<(String, Option<u32>)>,
+    decks: [Decklist; 2],
+    /// Hide cards that aren't in either deck
+    deck_only: bool,
+}
+
+impl CardPriority {
+    fn build(decks: [Decklist; 2], deck_only: bool) -> Self {
+        CardPriority {
+            recent: VecDeque::new(),
+            decks,
+            deck_only,
+        }
+    }
+
+    fn used(&mut self, name: &str, pitch: Option<u32>) {
+        self.recent.retain(|(n, p)| !(n == name && *p == pitch));
+        self.recent.push_front((name.to_owned(), pitch));
+        self.recent.truncate(RECENT_CARDS);
+    }
+
+    fn rank(&self, card: &CardData, turn_player: u8) -> Option<u32> {
+        let recent = RECENT_CARDS as u32;
+        if let Some(idx) = self
+            .recent
+            .iter()
+            .position(|(name, pitch)| *name == card.name && *pitch == card.pitch)
+        {
+            return Some(idx as u32);
+        }
+        let (turn_deck, other_deck) = if turn_player == 1 {
+            (&self.decks[0], &self.decks[1])
+        } else {
+            (&self.decks[1], &self.decks[0])
+        };
+        if turn_deck.contains(card) {
+            Some(recent)
+        } else if other_deck.contains(card) {
+            Some(recent + 1)
+        } else if self.deck_only && self.decks.iter().any(|deck| !deck.entries.is_empty()) {
+            None
+        } else {
+            Some(recent + 2)
+        }
+    }
+}
+
+/// State of the records before an action, so the action can be undone
+struct Checkpoint {
+    description: String,
+    records: Vec<Annotation>,
+    selected: Option<usize>,
+}
+
+struct RecordKeeper {
+    header: Header,
+    records: Vec<Annotation>,
+    /// Every change is streamed here so a crash doesn't lose the annotation. Edit mode has
+    /// no session since the file being edited already exists.
+    session: Option<SessionWriter>,
+    /// Record being corrected in edit mode
+    selected: Option<usize>,
+    undo_stack: Vec<Checkpoint>,
+    redo_stack: Vec<Checkpoint>,
+    /// Set once a write to the session file fails
+    session_failed: bool,
+    /// Where the sorted records are written when annotating ends, including by a panic
+    output: Option<(String, StorageFormat)>,
+    /// Changed since the output was last written. Edit mode saves after every change, since it
+    /// has no session to recover from if annotate is killed.
+    unsaved: bool,
+    /// Last change, until it has been confirmed
+    cue: Option<Cue>,
+}
+
+impl RecordKeeper {
+    fn build(
+        hero1: (&str, &CardData),
+        hero2: (&str, &CardData),
+        first: &str,
+        format: Option<Format>,
+        players: [PlayerInfo; 2],
+        session_fp: &str,
+    ) -> RecordKeeper {
+        let (player1, hero1) = hero1;
+        let (player2, hero2) = hero2;
+
+        let setup = MatchSetup {
+            player1: title_case(player1),
+            player2: title_case(player2),
+            hero1: hero1.name.to_owned(),
+            hero2: hero2.name.to_owned(),
+            hero1_life: hero1.starting_life(format).to_string(),
+            hero2_life: hero2.starting_life(format).to_string(),
+            hero1_intellect: Some(hero1.starting_intellect(format)),
+            hero2_intellect: Some(hero2.starting_intellect(format)),
+            first: if first == "1" { 1 } else { 2 },
+        };
+        let mut header = Header::new(setup);
+        header.format = format;
+        header.players = players;
+        let session =
+            SessionWriter::create(session_fp, &header).expect("Couldn't write session file");
+        RecordKeeper {
+            header,
+            records: Vec::new(),
+            session: Some(session),
+            selected: None,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            session_failed: false,
+            unsaved: false,
+            output: None,
+            cue: None,
+        }
+    }
+
+    /// Picks up the records of an interrupted session
+    fn resume(session_fp: &str) -> RecordKeeper {
+        let file = annotation_store::read_session(session_fp).expect("Couldn't read session file");
+        let session =
+            SessionWriter::create_from(session_fp, &file).expect("Couldn't write session file");
+        RecordKeeper {
+            header: file.header,
+            records: file.events,
+            session: Some(session),
+            selected: None,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            session_failed: false,
+            unsaved: false,
+            output: None,
+            cue: None,
+        }
+    }
+
+    /// Loads a finished annotation for correcting
+    fn edit(fp: &str) -> RecordKeeper {
+        let mut file = annotation_store::read_from_path(fp).expect("Couldn't read annotation file");
+        annotation::sort(&mut file.events);
+        RecordKeeper {
+            header: file.header,
+            records: file.events,
+            session: None,
+            selected: None,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            session_failed: false,
+            unsaved: false,
+            output: None,
+            cue: None,
+        }
+    }
+
+    fn last_time(&self) -> Option<TimeTick> {
+        self.records.last().map(|rec| rec.time)
+    }
+
+    fn get_time(video: &dyn Player) -> TimeTick {
+        Self::time_at(video.position())
+    }
+
+    fn time_at(timestamp: f64) -> TimeTick {
+        let sec = timestamp.trunc() as u64;
+        let milli = (timestamp.fract() * MILLI).trunc();
+        TimeTick::build(sec, milli)
+    }
+
+    fn add(&mut self, video: &dyn Player, event: AnnotationEvent) {
+        self.add_at(Self::get_time(video), event);
+    }
+
+    fn add_at(&mut self, time: TimeTick, event: AnnotationEvent) {
+        let record = Annotation::new(time, event);
+        self.checkpoint(describe_record(&record));
+        if let Some(session) = self.session.as_mut() {
+            self.session_failed |= session.push(&record).is_err();
+        }
+        self.records.push(record);
+        // Records inserted while editing go in order and become the selection
+        if self.session.is_none() {
+            self.sort_records();
+            self.selected = self
+                .records
+                .iter()
+                .rposition(|rec| rec.time <= time);
+        }
+    }
+
+    /// Saves the current records before an action described by `description`
+    fn checkpoint(&mut self, description: String) {
+        self.undo_stack.push(Checkpoint {
+            description,
+            records: self.records.clone(),
+            selected: self.selected,
+        });
+        if self.undo_stack.len() > UNDO_LIMIT {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
+        self.cue = Some(Cue::Record);
+        self.unsaved = true;
+    }
+
+    /// Swaps the current records with `checkpoint`, returning the current state under the
+    /// same description
+    fn restore(&mut self, checkpoint: Checkpoint) -> Checkpoint {
+        Checkpoint {
+            description: checkpoint.description,
+            records: std::mem::replace(&mut self.records, checkpoint.records),
+            selected: std::mem::replace(&mut self.selected, checkpoint.selected),
+        }
+    }
+
+    /// Reverts the most recent action, returning its description
+    fn undo(&mut self) -> Option<String> {
+        let checkpoint = self.undo_stack.pop()?;
+        let current = self.restore(checkpoint);
+        // Sessions only ever add records, so undoing always removes the last one
+        if let Some(session) = self.session.as_mut() {
+            self.session_failed |= session.undo().is_err();
+        }
+        let description = current.description.clone();
+        self.redo_stack.push(current);
+        self.cue = Some(Cue::Undo);
+        self.unsaved = true;
+        Some(description)
+    }
+
+    /// Reapplies the most recently undone action, returning its description
+    fn redo(&mut self) -> Option<String> {
+        let checkpoint = self.redo_stack.pop()?;
+        let current = self.restore(checkpoint);
+        if let (Some(session), Some(record)) = (self.session.as_mut(), self.records.last()) {
+            self.session_failed |= session.push(record).is_err();
+        }
+        let description = current.description.clone();
+        self.undo_stack.push(current);
+        self.cue = Some(Cue::Record);
+        self.unsaved = true;
+        Some(description)
+    }
+
+    /// Descriptions of the most recent actions that can be undone, newest first
+    fn recent_actions(&self) -> impl Iterator<Item = &str> {
+        self.undo_stack
+            .iter()
+            .rev()
+            .take(RECENT_ACTIONS)
+            .map(|checkpoint| checkpoint.description.as_str())
+    }
+
+    fn state(&self) -> MatchState {
+        MatchState::replay(&self.header.setup, &self.records)
+    }
+
+    /// What `player` has left to draw of `deck`
+    fn deck_odds(&self, deck: &Decklist, player: u8) -> DeckOdds {
+        DeckOdds::replay(deck, player, &self.header.setup, &self.records)
+    }
+
+    /// Most recent card played whose name starts with `query`, ignoring case
+    fn recent_card(&self, query: &str) -> Option<(String, Option<u32>)> {
+        let query = query.to_lowercase();
+        self.records.iter().rev().find_map(|record| match &record.event {
+            AnnotationEvent::Card { name, pitch } if name.to_lowercase().starts_with(&query) => {
+                Some((name.clone(), *pitch))
+            }
+            _ => None,
+        })
+    }
+
+    fn selected_record(&self) -> Option<&Annotation> {
+        self.selected.and_then(|idx| self.records.get(idx))
+    }
+
+    /// Moves the selection by `step` records and seeks the video to it
+    fn select(&mut self, video: &dyn Player, step: isize) -> Option<&Annotation> {
+        if self.records.is_empty() {
+            return None;
+        }
+        let idx = match self.selected {
+            Some(idx) => idx.saturating_add_signed(step).min(self.records.len() - 1),
+            None if step < 0 => self.records.len() - 1,
+            None => 0,
+        };
+        self.jump_to(video, idx)
+    }
+
+    /// Selects the record at `idx` and seeks the video to it
+    fn jump_to(&mut self, video: &dyn Player, idx: usize) -> Option<&Annotation> {
+        let record = self.records.get(idx)?;
+        video.seek_absolute(record.time.as_f64());
+        self.selected = Some(idx);
+        self.records.get(idx)
+    }
+
+    fn delete_selected(&mut self) -> Option<Annotation> {
+        let idx = self.selected.filter(|idx| *idx < self.records.len())?;
+        self.checkpoint(format!("delete {}", describe_record(&self.records[idx])));
+        let record = self.records.remove(idx);
+        self.selected = if self.records.is_empty() {
+            None
+        } else {
+            Some(idx.min(self.records.len() - 1))
+        };
+        Some(record)
+    }
+
+    /// Moves the selected record's time by `milli`, keeping the records in order
+    fn nudge_selected(&mut self, milli: f64) -> Option<&Annotation> {
+        let idx = self.selected.filter(|idx| *idx < self.records.len())?;
+        self.checkpoint(format!("nudge {}", describe_record(&self.records[idx])));
+        let record = &mut self.records[idx];
+        if milli >= 0.0 {
+            record.time.increment_milli(milli);
+        } else {
+            record.time = record.time - TimeTick::build(0, -milli);
+        }
+        let time = record.time;
+        self.reselect(idx, time)
+    }
+
+    /// Moves the selected record to the current playback position
+    fn retime_selected(&mut self, video: &dyn Player) -> Option<&Annotation> {
+        let idx = self.selected.filter(|idx| *idx < self.records.len())?;
+        self.checkpoint(format!("retime {}", describe_record(&self.records[idx])));
+        let time = Self::get_time(video);
+        self.records[idx].time = time;
+        self.reselect(idx, time)
+    }
+
+    /// Shifts every record by `seconds`, e.g. to line a live annotation up with the footage.
+    /// Records can't move before the start. Returns the number of records moved.
+    fn offset_all(&mut self, seconds: f64) -> usize {
+        self.checkpoint(format!("offset {}s", seconds));
+        Retime::offset(seconds).apply_all(&mut self.records);
+        self.records.len()
+    }
+
+    /// Swaps the card of the selected record, keeping its time
+    fn replace_selected_card(&mut self, name: &str, pitch: Option<u32>) -> Option<&Annotation> {
+        let idx = self.selected?;
+        if !matches!(self.records.get(idx)?.event, AnnotationEvent::Card { .. }) {
+            return None;
+        }
+        self.checkpoint(format!("replace {}", describe_record(&self.records[idx])));
+        let record = &mut self.records[idx];
+        record.event = AnnotationEvent::Card {
+            name: name.to_owned(),
+            pitch,
+        };
+        Some(record)
+    }
+
+    /// Re-sorts after a time change and follows the record at `idx` to its new position
+    fn reselect(&mut self, idx: usize, time: TimeTick) -> Option<&Annotation> {
+        let record = self.records.remove(idx);
+        let new_idx = self.records.partition_point(|rec| rec.time <= time);
+        self.records.insert(new_idx, record);
+        self.selected = Some(new_idx);
+        self.records.get(new_idx)
+    }
+
+    fn add_card_update(&mut self, video: &dyn Player, name: &str, pitch: Option<u32>) {
+        self.add(
+            video,
+            AnnotationEvent::Card {
+                name: name.to_owned(),
+                pitch,
+            },
+        );
+    }
+
+    fn add_block_update(&mut self, video: &dyn Player, name: &str, pitch: Option<u32>) {
+        self.add(
+            video,
+            AnnotationEvent::Block {
+                name: name.to_owned(),
+                pitch,
+            },
+        );
+    }
+
+    fn add_pair_update(&mut self, video: &dyn Player, name: &str, pitch: Option<u32>) {
+        self.add(
+            video,
+            AnnotationEvent::Pair {
+                name: name.to_owned(),
+                pitch,
+            },
+        );
+    }
+
+    fn add_player_life_update(&mut self, video: &dyn Player, player: u8, update: &str) {
+        let (player1, player2) = if player == 1 {
+            (Some(update.to_string()), None)
+        } else {
+            (None, Some(update.to_string()))
+        };
+        self.add(video, AnnotationEvent::Life { player1, player2 });
+    }
+
+    fn add_turn_update(&mut self, video: &dyn Player) {
+        self.add(video, AnnotationEvent::Turn);
+    }
+
+    fn add_winner_update(&mut self, video: &dyn Player, player: u8) {
+        self.add(video, AnnotationEvent::Win { player });
+    }
+
+    /// Zooms in on the given card, or on whatever card is displayed
+    fn add_zoom_update(&mut self, video: &dyn Player, name: Option<&str>, pitch: Option<u32>) {
+        self.add(
+            video,
+            AnnotationEvent::Zoom {
+                name: name.map(|name| name.to_owned()),
+                pitch,
+            },
+        );
+    }
+
+    fn add_time_called_update(&mut self, video: &dyn Player) {
+        self.add(video, AnnotationEvent::TimeCalled);
+    }
+
+    fn add_game_update(&mut self, video: &dyn Player) {
+        self.add(video, AnnotationEvent::Game);
+    }
+
+    fn add_unzoom_update(&mut self, video: &dyn Player) {
+        self.add(video, AnnotationEvent::Unzoom);
+    }
+
+    fn add_lower_third_update(&mut self, video: &dyn Player, player: u8) {
+        self.add(video, AnnotationEvent::LowerThird { player });
+    }
+
+    fn add_note_update(&mut self, video: &dyn Player, text: &str) {
+        self.add(
+            video,
+            AnnotationEvent::Note {
+                text: text.to_owned(),
+            },
+        );
+    }
+
+    fn add_token_update(&mut self, video: &dyn Player, player: u8, name: &str) {
+        self.add(
+            video,
+            AnnotationEvent::Token {
+                player,
+                name: name.to_owned(),
+            },
+        );
+    }
+
+    fn sort_records(&mut self) {
+        annotation::sort(&mut self.records);
+    }
+
+    /// Sorts the records and writes them to the output
+    fn save(&mut self) -> Result<(), annotation::AnnotationError> {
+        let Some((fp, format)) = self.output.clone() else {
+            return Ok(());
+        };
+        self.sort_records();
+        let file = AnnotationFile::new(self.header.clone(), self.records.clone());
+        annotation_store::write_to_path(&fp, &file, format)?;
+        self.unsaved = false;
+        Ok(())
+    }
+
+    /// Writes the output after a change in edit mode. Sessions rely on their journal instead.
+    fn autosave(&mut self) -> Result<(), annotation::AnnotationError> {
+        if self.unsaved && self.session.is_none() {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Saves for the last time, once
+    fn finalize(&mut self) -> Result<(), annotation::AnnotationError> {
+        if self.output.is_none() {
+            return Ok(());
+        }
+        let saved = self.save();
+        self.output = None;
+        saved?;
+        // The annotation is saved, so the session isn't needed to recover it
+        if let Some(session) = self.session.take() {
+            if let Err(err) = session.finish() {
+                warn!("Couldn't remove the session file: {}", err);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for RecordKeeper {
+    fn drop(&mut self) {
+        if let Err(err) = self.finalize() {
+            eprintln!("Could not save annotations: {}\r", err);
+        }
+    }
+}
+
+/// Life totals, turn, last card, combat chain and what is left of each loaded deck, one per
+/// line
+fn state_osd_text(record_keeper: &RecordKeeper, decks: &[Decklist; 2]) -> String {
+    let state = record_keeper.state();
+    let setup = &record_keeper.header.setup;
+    let mut text = format!(
+        "{} {} | {} {}\nTurn {} ({})",
+        setup.player1,
+        state.player1_life,
+        setup.player2,
+        state.player2_life,
+        state.turn,
+        if state.turn_player == 1 {
+            &setup.player1
+        } else {
+            &setup.player2
+        },
+    );
+    if let Some(score) = score_text(&state) {
+        text.push_str(&format!("\n{}", score));
+    }
+    if let Some(card) = &state.last_card {
+        text.push_str(&format!("\nLast card: {}", card));
+    }
+    if let Some(link) = state.chain.last() {
+        text.push_str(&format!(
+            "\nChain link {}: {}",
+            state.chain.len(),
+            link.attack
+        ));
+        if !link.defenders.is_empty() {
+            text.push_str(&format!(", defended with {}", link.defenders.join(", ")));
+        }
+        if link.damage > 0 {
+            text.push_str(&format!(", {} damage", link.damage));
+        }
+    }
+    text.push_str(&format!(
+        "\nCards used {}-{} | Damage dealt {}-{}",
+        state.cards_used[0], state.cards_used[1], state.damage_dealt[0], state.damage_dealt[1]
+    ));
+    for (i, deck) in decks.iter().enumerate() {
+        if deck.entries.is_empty() {
+            continue;
+        }
+        let player = i as u8 + 1;
+        let odds = record_keeper.deck_odds(deck, player);
+        let name = if player == 1 {
+            &setup.player1
+        } else {
+            &setup.player2
+        };
+        text.push_str(&format!("\n{} deck: {}", name, odds));
+        // Copies left of the card just played, e.g. of a card that's only run as a few
+        if state.turn_player == player {
+            let in_deck = |card: &String| {
+                odds.unseen
+                    .iter()
+                    .any(|entry| entry.name.eq_ignore_ascii_case(card))
+            };
+            if let Some(card) = state.last_card.as_ref().filter(|card| in_deck(card)) {
+                text.push_str(&format!(" | {} left: {}", card, odds.copies(card)));
+            }
+        }
+    }
+    text
+}
+
+/// e.g. "Game 2 (1-0)", or `None` until the match goes past one game
+fn score_text(state: &MatchState) -> Option<String> {
+    (state.game > 1).then(|| {
+        format!(
+            "Game {} ({}-{})",
+            state.game, state.score[0], state.score[1]
+        )
+    })
+}
+
+/// Records the end of the current game, resetting lives and swapping who goes first
+fn next_game(ui: &mut Ui, video: &dyn Player, record_keeper: &mut RecordKeeper) {
+    record_keeper.add_game_update(video);
+    let state = record_keeper.state();
+    ui.message(&format!(
+        "Game {} started, player {} goes first",
+        state.game, state.turn_player
+    ));
+}
+
+/// Live clock position as "mm:ss", or "h:mm:ss" past an hour, marked when paused
+fn clock_text(video: &dyn Player) -> String {
+    let seconds = video.position() as u64;
+    let mut text = match seconds / 3600 {
+        0 => format!("{:02}:{:02}", seconds / 60, seconds % 60),
+        hours => format!("{}:{:02}:{:02}", hours, seconds / 60 % 60, seconds % 60),
+    };
+    if video.is_paused() {
+        text.push_str(" (paused)");
+    }
+    text
+}
+
+/// Shows `text` on the video, so changes are confirmed where the annotator is looking
+fn show_osd(video: &dyn Player, text: &str) {
+    video.show_text(text, None, OSD_DURATION_MS);
+}
+
+/// Shows `text` with a coloured marker above it for `cue`
+fn show_osd_cue(video: &dyn Player, text: &str, cue: Cue) {
+    let color = match cue {
+        Cue::Record => RECORD_CUE_COLOR,
+        Cue::Undo => UNDO_CUE_COLOR,
+    };
+    video.show_text(text, Some(color), OSD_DURATION_MS);
+}
+
+/// Has the annotator drag a box around player 1's and then player 2's life total
+fn select_life_regions(video: &dyn Player) -> Result<[Rect; 2], Box<dyn std::error::Error>> {
+    let frame = video.frame()?;
+    let mut regions = [Rect::default(); 2];
+    for (idx, region) in regions.iter_mut().enumerate() {
+        let window = format!("Player {} life, then Enter", idx + 1);
+        *region = highgui::select_roi(&window, &frame, false, false, false)?;
+        highgui::destroy_window(&window)?;
+        if region.area() == 0 {
+            return Err("no region selected".into());
+        }
+    }
+    Ok(regions)
+}
+
+/// Has the annotator drag a box around where cards are shown on the video
+fn select_card_region(video: &dyn Player) -> Result<Rect, Box<dyn std::error::Error>> {
+    let frame = video.frame()?;
+    let window = "Card area, then Enter";
+    let region = highgui::select_roi(window, &frame, false, false, false)?;
+    highgui::destroy_window(window)?;
+    if region.area() == 0 {
+        return Err("no region selected".into());
+    }
+    Ok(region)
+}
+
+/// Cards that look most like the one in `region` of the current frame, best match first
+fn detect_cards(
+    video: &dyn Player,
+    region: Rect,
+    index: &CardHashIndex,
+) -> Result<Vec<(String, Option<u32>)>, Box<dyn std::error::Error>> {
+    let frame = video.frame()?;
+    let card = Mat::roi(&frame, region)?;
+    Ok(index
+        .identify(&*card, DETECTED_CARDS)?
+        .into_iter()
+        .map(|card| (card.name.to_owned(), card.pitch))
+        .collect())
+}
+
+/// Life updates that bring the recorded totals in line with the ones on the video
+fn read_life_updates(
+    video: &dyn Player,
+    regions: &[Rect; 2],
+    state: &MatchState,
+) -> Result<Vec<(u8, String)>, Box<dyn std::error::Error>> {
+    let frame = video.frame()?;
+    let mut updates = Vec::new();
+    for (player, (region, current)) in
+        (1..).zip(regions.iter().zip([state.player1_life, state.player2_life]))
+    {
+        if let Some(update) = ocr::read_life(&frame, *region)?
+            .and_then(|read| ocr::suggest_update(current, read))
+        {
+            updates.push((player, update));
+        }
+    }
+    Ok(updates)
+}
+
+fn ring_bell(cue: Cue) {
+    let bells = match cue {
+        Cue::Record => "\x07",
+        Cue::Undo => "\x07\x07",
+    };
+    let mut out = std::io::stdout();
+    let _ = out.write_all(bells.as_bytes());
+    let _ = out.flush();
+}
+
+/// Warns when a life update leaves a player below zero without a win being recorded, which is
+/// almost always a typo
+fn warn_negative_life(ui: &mut Ui, record_keeper: &RecordKeeper) {
+    let state = record_keeper.state();
+    if state.winner.is_some() {
+        return;
+    }
+    for (player, life) in [(1, state.player1_life), (2, state.player2_life)] {
+        if life < 0 {
+            ui.message(&format!(
+                "Warning: player {} is at {} life with no win recorded",
+                player, life
+            ));
+        }
+    }
+}
+
+fn display_recent_actions(ui: &mut Ui, record_keeper: &RecordKeeper) {
+    ui.message("Last actions:");
+    for (idx, action) in record_keeper.recent_actions().enumerate() {
+        ui.message(&format!("  {}. {}", idx + 1, action));
+    }
+}
+
+/// Life totals, turn and playback settings for the status bar
+fn status_text(
+    record_keeper: &RecordKeeper,
+    seek_step: f64,
+    playback_speed: f64,
+    edit: bool,
+) -> String {
+    let state = record_keeper.state();
+    let setup = &record_keeper.header.setup;
+    let mut text = format!(
+        "{} {} | {} {} | Turn {} ({}) | Seek {}s | Speed {}x",
+        setup.player1,
+        state.player1_life,
+        setup.player2,
+        state.player2_life,
+        state.turn,
+        if state.turn_player == 1 {
+            &setup.player1
+        } else {
+            &setup.player2
+        },
+        seek_step,
+        playback_speed,
+    );
+    if let Some(score) = score_text(&state) {
+        text.push_str(&format!(" | {}", score));
+    }
+    if edit {
+        text.push_str(" | EDIT");
+    }
+    if record_keeper.session_failed {
+        text.push_str(" | SESSION FILE NOT SAVING");
+    }
+    text
+}
+
+async fn handle_events(
+    output_fp: &str,
+    format: StorageFormat,
+    video: &dyn Player,
+    cards: &[CardData],
+    mut record_keeper: RecordKeeper,
+    mut card_priority: CardPriority,
+    edit: bool,
+    cue_mode: Option<CueMode>,
+    ocr: bool,
+    card_hashes: Option<CardHashIndex>,
+    mut hotkeys: BoxStream<'static, Hotkey>,
+) {
+    // fail before annotating rather than after
+    if !edit {
+        File::create(output_fp).expect("Couldn't write to file");
+    }
+    record_keeper.output = Some((output_fp.to_owned(), format));
+    let mut reader = EventStream::new();
+    let mut text = String::new();
+    // Cards can be narrowed with filters before the name, e.g. "t:action c:red"
+    let mut card_suggestions =
+        AutocompleteSuggestionManager::build_with_matcher(cards.to_vec(), card_query::matches);
+    let aliases = CardAliases::init();
+    card_suggestions.set_aliases(aliases.iter());
+    let mut priority_turn_player = record_keeper.state().turn_player;
+    card_suggestions.prioritize(|card| card_priority.rank(card, priority_turn_player));
+    let mut commands = if edit {
+        Command::get_all_edit()
+    } else {
+        Command::get_all()
+    };
+    if ocr {
+        commands.extend(Command::get_all_ocr());
+    }
+    if card_hashes.is_some() {
+        commands.extend(Command::get_all_assist());
+    }
+    let live = video.is_live();
+    if live {
+        commands.extend(Command::get_all_live());
+    }
+    // Where each player's life total is shown on the video, once selected
+    let mut life_regions: Option<[Rect; 2]> = None;
+    // Input left after a command, so a suggestion can be confirmed with Enter
+    let mut prefill: Option<String> = None;
+    // Where cards are shown on the video, once selected
+    let mut card_region: Option<Rect> = None;
+    // Cards recognised on the video, offered once the command input is cleared
+    let mut detected: Vec<(String, Option<u32>)> = Vec::new();
+    let help: Vec<(String, &str)> = commands
+        .iter()
+        .map(|command| (command.get_name().to_owned(), command.description()))
+        .chain(KEY_HELP.iter().map(|(key, description)| (key.to_string(), *description)))
+        .collect();
+    let help: Vec<(&str, &str)> = help
+        .iter()
+        .map(|(key, description)| (key.as_str(), *description))
+        .collect();
+    let mut command_suggestions = AutocompleteSuggestionManager::build(commands);
+    // In edit mode the next card entered replaces the selected card instead of being inserted
+    let mut replace_card = false;
+    // The next card entered defends against the last card instead of being played
+    let mut block_card = false;
+    let mut pair_card = false;
+
+    let mut seek_step = DEFAULT_SEEK_STEP;
+    let mut playback_speed = DEFAULT_PLAYBACK_SPEED;
+    // Stepping frame by frame pauses the video until some other key is pressed
+    let mut frame_stepping = false;
+
+    // Last text pushed to the OSD, so it is only refreshed when the state changes
+    let mut osd_text = state_osd_text(&record_keeper, &card_priority.decks);
+    show_osd(video, &osd_text);
+
+    let mut ui = Ui::build().expect("Couldn't start the interface");
+
+    video.unpause();
+
+    loop {
+        // Show user autocomplete
+        let (completion, suggestions): (&str, Vec<&str>) =
+            if let Some(suggest) = card_suggestions.current_suggestion() {
+                let typed = CardQuery::parse(&text).name.len();
+                (
+                    suggest.display.get(typed..).unwrap_or(""),
+                    card_suggestions.suggestions().map(|card| card.display.as_str()).collect(),
+                )
+            } else if let Some(suggest) = command_suggestions.current_suggestion() {
+                (
+                    suggest.get_name().get(text.len()..).unwrap_or(""),
+                    command_suggestions.suggestions().map(|command| command.get_name()).collect(),
+                )
+            } else {
+                ("", Vec::new())
+            };
+        let records: Vec<String> = record_keeper.records.iter().map(describe_record).collect();
+        let mut status = status_text(
+            &record_keeper,
+            SEEK_STEPS[seek_step],
+            PLAYBACK_SPEEDS[playback_speed],
+            edit,
+        );
+        if live {
+            status.push_str(&format!(" | Clock {}", clock_text(video)));
+        }
+        let view = View {
+            status,
+            records,
+            selected: record_keeper.selected,
+            input: &text,
+            completion,
+            suggestions,
+            help: &help,
+        };
+        let _ = ui.draw(&view);
+
+        let mut event = reader.next().fuse();
+        let mut hotkey = hotkeys.next().fuse();
+        // Live clocks are redrawn as they run, other players only when something happens
+        let mut redraw = Box::pin(tokio::time::sleep(if live {
+            CLOCK_REDRAW
+        } else {
+            Duration::MAX
+        }))
+        .fuse();
+        select! {
+            _ = redraw => {}
+            maybe_hotkey = hotkey => {
+                match maybe_hotkey {
+                    Some(Hotkey::Action(HotkeyAction::Turn)) => {
+                        record_keeper.add_turn_update(video);
+                        ui.message("Next turn started");
+                    }
+                    Some(Hotkey::Action(HotkeyAction::Life(player, update))) => {
+                        record_keeper.add_player_life_update(video, player, &update);
+                        ui.message(&format!("Player {} {}", player, update));
+                        warn_negative_life(&mut ui, &record_keeper);
+                    }
+                    Some(Hotkey::Action(HotkeyAction::Zoom)) => {
+                        record_keeper.add_zoom_update(video, None, None);
+                        ui.message("Zoom triggered");
+                    }
+                    Some(Hotkey::Action(HotkeyAction::Unzoom)) => {
+                        record_keeper.add_unzoom_update(video);
+                        ui.message("Zoom ended");
+                    }
+                    Some(Hotkey::Action(HotkeyAction::Undo)) => {
+                        match record_keeper.undo() {
+                            Some(action) => ui.message(&format!("Undid {}", action)),
+                            None => ui.message("Nothing to undo"),
+                        }
+                    }
+                    Some(Hotkey::Unbound(input)) => ui.message(&format!("Nothing is bound to {}", input)),
+                    Some(Hotkey::Lost(err)) => ui.message(&format!("Hotkeys stopped: {}", err)),
+                    None => {}
+                }
+            }
+            maybe_event = event => {
+                match maybe_event {
+                    Some(Ok(event)) => {
+                        if let Event::Key(key) = event {
+                            frame_stepping = false;
+
+                            // Seek back
+                            if key.code == KeyCode::Left && text.is_empty() {
+                                video.seek_relative(-SEEK_STEPS[seek_step]);
+
+                            // Seek forward
+                            } else if key.code == KeyCode::Right && text.is_empty() {
+                                video.seek_relative(SEEK_STEPS[seek_step]);
+
+                            // Seek granularity
+                            } else if (key.code == KeyCode::Up || key.code == KeyCode::Down) && text.is_empty() {
+                                seek_step = if key.code == KeyCode::Up {
+                                    (seek_step + 1).min(SEEK_STEPS.len() - 1)
+                                } else {
+                                    seek_step.saturating_sub(1)
+                                };
+                                ui.message(&format!("Seek step {}s", SEEK_STEPS[seek_step]));
+
+                            // Playback speed
+                            } else if (key.code == KeyCode::Char('[') || key.code == KeyCode::Char(']')) && text.is_empty() {
+                                playback_speed = if key.code == KeyCode::Char(']') {
+                                    (playback_speed + 1).min(PLAYBACK_SPEEDS.len() - 1)
+                                } else {
+                                    playback_speed.saturating_sub(1)
+                                };
+                                video.set_speed(PLAYBACK_SPEEDS[playback_speed]);
+                                ui.message(&format!("Playback speed {}x", PLAYBACK_SPEEDS[playback_speed]));
+
+                            // Frame step
+                            } else if (key.code == KeyCode::Char(',') || key.code == KeyCode::Char('.')) && text.is_empty() {
+                                video.frame_step(key.code == KeyCode::Char('.'));
+                                frame_stepping = true;
+
+                            // Life update
+                            } else if is_argument_command(&text) {
+                                command_suggestions.reset();
+                                match key.code {
+                                    // Checked before `:h` since it shares the prefix
+                                    KeyCode::Enter if is_heal(&text) || is_damage(&text) => {
+                                        if let Some(amount) = extract_shorthand_amount(&text) {
+                                            let turn_player = record_keeper.state().turn_player;
+                                            let (player, update) = if is_heal(&text) {
+                                                (turn_player, format!("+{}", amount))
+                                            } else {
+                                                (if turn_player == 1 { 2 } else { 1 }, format!("-{}", amount))
+                                            };
+                                            record_keeper.add_player_life_update(video, player, &update);
+                                            ui.message(&format!("Player {} {}", player, update));
+                                            warn_negative_life(&mut ui, &record_keeper);
+                                            text = String::new();
+                                        } else {
+                                            ui.message("Invalid amount.");
+                                        }
+                                    },
+                                    KeyCode::Enter if is_life_update(&text) => {
+                                        if let Some((player, update)) = extract_life_update(&text) {
+                                            record_keeper.add_player_life_update(video, player, &update);
+                                            ui.message("Player health updated");
+                                            warn_negative_life(&mut ui, &record_keeper);
+                                            text = String::new();
+                                        } else {
+                                            ui.message("Invalid life format.");
+                                        }
+                                    },
+                                    KeyCode::Enter if is_verify(&text) => {
+                                        if let Some(expected) = extract_verify(&text) {
+                                            let state = record_keeper.state();
+                                            let actual = (state.player1_life, state.player2_life);
+                                            if actual == expected {
+                                                ui.message("Life totals match");
+                                            } else {
+                                                ui.message(&format!(
+                                                    "Warning: life totals are {} / {}, expected {} / {}",
+                                                    actual.0, actual.1, expected.0, expected.1
+                                                ));
+                                            }
+                                            text = String::new();
+                                        } else {
+                                            ui.message("Invalid life totals.");
+                                        }
+                                    },
+                                    KeyCode::Enter if is_game(&text) => {
+                                        next_game(&mut ui, video, &mut record_keeper);
+                                        text = String::new();
+                                    },
+                                    KeyCode::Enter if is_goto(&text) => {
+                                        if let Some(seconds) = extract_goto(&text) {
+                                            video.seek_absolute(seconds);
+                                            ui.message(&format!("Jumped to {}", text[2..].trim()));
+                                            text = String::new();
+                                        } else {
+                                            ui.message("Invalid time format.");
+                                        }
+                                    },
+                                    KeyCode::Enter if is_offset(&text) && edit => {
+                                        if let Some(seconds) = extract_offset(&text) {
+                                            let count = record_keeper.offset_all(seconds);
+                                            ui.message(&format!("Shifted {} records by {}s", count, seconds));
+                                            text = String::new();
+                                        } else {
+                                            ui.message("Invalid offset.");
+                                        }
+                                    },
+                                    KeyCode::Enter if is_offset(&text) => {
+                                        ui.message("Shifting every record only works in edit mode.");
+                                    },
+                                    KeyCode::Enter if is_note(&text) => {
+                                        if let Some(note) = extract_note(&text) {
+                                            record_keeper.add_note_update(video, note);
+                                            ui.message(&format!("Note shown: {}", note));
+                                            text = String::new();
+                                        } else {
+                                            ui.message("Type the note after :note");
+                                        }
+                                    },
+                                    KeyCode::Enter if is_token(&text) => {
+                                        if let Some((player, name)) = extract_token(&text) {
+                                            record_keeper.add_token_update(video, player, name);
+                                            ui.message(&format!("{} on player {}'s hero", name, player));
+                                            text = String::new();
+                                        } else {
+                                            ui.message("Invalid token format, e.g. :tk1 quicken");
+                                        }
+                                    },
+                                    KeyCode::Enter if is_zoom_card(&text) => {
+                                        let card = extract_zoom_card(&text).and_then(|query| record_keeper.recent_card(query));
+                                        if let Some((name, pitch)) = card {
+                                            record_keeper.add_zoom_update(video, Some(&name), pitch);
+                                            ui.message(&format!("Zoom triggered on {}", name));
+                                            text = String::new();
+                                        } else {
+                                            ui.message("No recent card by that name.");
+                                        }
+                                    },
+                                    KeyCode::Enter => {
+                                        match extract_record_jump(&text).and_then(|n| record_keeper.jump_to(video, n - 1)) {
+                                            Some(rec) => {
+                                                ui.message(&describe_record(rec));
+                                                text = String::new();
+                                            }
+                                            None => ui.message("Invalid record number."),
+                                        }
+                                    },
+                                    KeyCode::Char(c) => {
+                                        text.push(c);
+                                    },
+                                    KeyCode::Backspace => {
+                                        text.pop();
+                                    },
+                                    KeyCode::Esc => {
+                                        text = String::new();
+                                        command_suggestions.reset();
+                                        card_suggestions.reset();
+                                    },
+                                    _ => continue
+                                }
+
+                            // Submit suggestion
+                            } else if key.code == KeyCode::Enter {
+                                // card
+                                if let Some(card) = card_suggestions.current_suggestion() {
+                                        card_priority.used(&card.name, card.pitch);
+                                        if replace_card {
+                                            replace_card = false;
+                                            match record_keeper.replace_selected_card(&card.name, card.pitch) {
+                                                Some(rec) => ui.message(&format!("Replaced {}", describe_record(rec))),
+                                                None => ui.message("Selected record is not a card"),
+                                            }
+                                        } else if block_card {
+                                            block_card = false;
+                                            ui.message(&format!("Blocked with {}", card.display));
+                                            record_keeper.add_block_update(video, &card.name, card.pitch);
+                                        } else if pair_card {
+                                            pair_card = false;
+                                            ui.message(&format!("Paired with {}", card.display));
+                                            record_keeper.add_pair_update(video, &card.name, card.pitch);
+                                        } else {
+                                            ui.message(&card.display);
+                                            record_keeper.add_card_update(video, &card.name, card.pitch);
+                                        }
+                                        text = String::new();
+                                        card_suggestions.reset();
+                                        command_suggestions.reset();
+                                        card_suggestions.prioritize(|card| card_priority.rank(card, priority_turn_player));
+                                // command
+                                } else if let Some(command) = command_suggestions.current_suggestion() {
+                                        match command {
+                                            Command::TURN => {
+                                                record_keeper.add_turn_update(video);
+                                                ui.message("Next turn started");
+                                            },
+                                            Command::QUIT => {
+                                                break;
+                                            },
+                                            Command::UNDO => {
+                                                match record_keeper.undo() {
+                                                    Some(action) => ui.message(&format!("Undid {}", action)),
+                                                    None => ui.message("Nothing to undo"),
+                                                }
+                                                display_recent_actions(&mut ui, &record_keeper);
+                                            }
+                                            Command::REDO => {
+                                                match record_keeper.redo() {
+                                                    Some(action) => ui.message(&format!("Redid {}", action)),
+                                                    None => ui.message("Nothing to redo"),
+                                                }
+                                                display_recent_actions(&mut ui, &record_keeper);
+                                            }
+                                            Command::ACTIONS => {
+                                                display_recent_actions(&mut ui, &record_keeper);
+                                            }
+                                            Command::WIN1 => {
+                                                record_keeper.add_winner_update(video, 1);
+                                                ui.message("Player 1 declared winner, :game for the next game");
+                                            }
+                                            Command::WIN2 => {
+                                                record_keeper.add_winner_update(video, 2);
+                                                ui.message("Player 2 declared winner, :game for the next game");
+                                            }
+                                            Command::ZOOM => {
+                                                record_keeper.add_zoom_update(video, None, None);
+                                                ui.message("Zoom triggered");
+                                            }
+                                            Command::UNZOOM => {
+                                                record_keeper.add_unzoom_update(video);
+                                                ui.message("Zoom ended");
+                                            }
+                                            Command::GAME => {
+                                                next_game(&mut ui, video, &mut record_keeper);
+                                            }
+                                            Command::TIME => {
+                                                record_keeper.add_time_called_update(video);
+                                                ui.message("Time called, the next turns are extra turns");
+                                            }
+                                            Command::LOWER_THIRD1 | Command::LOWER_THIRD2 => {
+                                                let player = if matches!(command, Command::LOWER_THIRD1) { 1 } else { 2 };
+                                                record_keeper.add_lower_third_update(video, player);
+                                                ui.message(&format!("Player {} name plate shown", player));
+                                            }
+                                            Command::BLOCK => {
+                                                block_card = true;
+                                                pair_card = false;
+                                                // Blocks come from the defender's deck
+                                                let defender = if priority_turn_player == 1 { 2 } else { 1 };
+                                                card_suggestions.prioritize(|card| card_priority.rank(card, defender));
+                                                ui.message("Enter the blocking card");
+                                            }
+                                            Command::PAIR => {
+                                                pair_card = true;
+                                                block_card = false;
+                                                ui.message("Enter the card to show beside the last one");
+                                            }
+                                            Command::NEXT | Command::PREVIOUS => {
+                                                let step = if matches!(command, Command::NEXT) { 1 } else { -1 };
+                                                match record_keeper.select(video, step) {
+                                                    Some(rec) => ui.message(&describe_record(rec)),
+                                                    None => ui.message("No records"),
+                                                }
+                                            }
+                                            Command::DELETE => {
+                                                if let Some(rec) = record_keeper.delete_selected() {
+                                                    ui.message(&format!("Deleted {}", describe_record(&rec)));
+                                                }
+                                            }
+                                            Command::EARLIER | Command::LATER => {
+                                                let milli = if matches!(command, Command::LATER) { NUDGE_MILLI } else { -NUDGE_MILLI };
+                                                if let Some(rec) = record_keeper.nudge_selected(milli) {
+                                                    ui.message(&describe_record(rec));
+                                                }
+                                            }
+                                            Command::RETIME => {
+                                                if let Some(rec) = record_keeper.retime_selected(video) {
+                                                    ui.message(&describe_record(rec));
+                                                }
+                                            }
+                                            Command::LIFE_REGIONS => {
+                                                match select_life_regions(video) {
+                                                    Ok(regions) => {
+                                                        life_regions = Some(regions);
+                                                        ui.message("Life total regions selected");
+                                                    }
+                                                    Err(err) => ui.message(&format!("Couldn't select regions: {}", err)),
+                                                }
+                                            }
+                                            Command::LIFE_READ => {
+                                                match life_regions.map(|regions| read_life_updates(video, &regions, &record_keeper.state())) {
+                                                    None => ui.message("Select the life totals first with :lr"),
+                                                    Some(Ok(updates)) if updates.is_empty() => ui.message("Life totals match the video"),
+                                                    Some(Ok(updates)) => {
+                                                        for (player, update) in updates.iter().skip(1) {
+                                                            ui.message(&format!("Video also suggests :h{} {}", player, update));
+                                                        }
+                                                        let (player, update) = &updates[0];
+                                                        prefill = Some(format!(":h{} {}", player, update));
+                                                        ui.message("Press Enter to record the suggested life update");
+                                                    }
+                                                    Some(Err(err)) => ui.message(&format!("Couldn't read life totals: {}", err)),
+                                                }
+                                            }
+                                            Command::CLOCK => {
+                                                if video.is_paused() {
+                                                    video.unpause();
+                                                    ui.message("Clock running");
+                                                } else {
+                                                    video.pause();
+                                                    ui.message("Clock paused");
+                                                }
+                                            }
+                                            Command::CARD_REGION => {
+                                                match select_card_region(video) {
+                                                    Ok(region) => {
+                                                        card_region = Some(region);
+                                                        ui.message("Card area selected");
+                                                    }
+                                                    Err(err) => ui.message(&format!("Couldn't select card area: {}", err)),
+                                                }
+                                            }
+                                            Command::CARD_DETECT => {
+                                                match card_region.zip(card_hashes.as_ref()).map(|(region, index)| detect_cards(video, region, index)) {
+                                                    None => ui.message("Select the card area first with :cr"),
+                                                    Some(Ok(cards)) if cards.is_empty() => ui.message("No card recognised"),
+                                                    Some(Ok(cards)) => {
+                                                        detected = cards;
+                                                        ui.message("Press Enter to record the suggested card, tab for others");
+                                                    }
+                                                    Some(Err(err)) => ui.message(&format!("Couldn't read card: {}", err)),
+                                                }
+                                            }
+                                            Command::REPLACE => {
+                                                if record_keeper.selected_record().is_some() {
+                                                    replace_card = true;
+                                                    ui.message("Enter the replacement card");
+                                                }
+                                            }
+                                            _ => {
+                                            }
+                                        }
+                                        text = prefill.take().unwrap_or_default();
+                                        card_suggestions.reset();
+                                        command_suggestions.reset();
+                                        if !detected.is_empty() {
+                                            card_suggestions.suggest(|card| {
+                                                detected
+                                                    .iter()
+                                                    .position(|(name, pitch)| *name == card.name && *pitch == card.pitch)
+                                                    .map(|idx| idx as u32)
+                                            });
+                                            detected.clear();
+                                        }
+                                }
+
+                            // Command suggestion update
+                            } else if is_command(&text) || (text.is_empty() && key.code == KeyCode::Char(':')) {
+                                text = command_suggestions.get_user_input_for_autocomplete(&text, key);
+                            // Card suggestion update
+                            } else {
+                                text = card_suggestions.get_user_input_for_autocomplete(&text, key);
+                            }
+                        }
+                    },
+                    Some(Err(e)) => ui.message(&format!("Error: {:?}", e)),
+                    None => break,
+                };
+            }
+        }
+
+        // Turns, undos and edits can all change whose deck comes first
+        let turn_player = record_keeper.state().turn_player;
+        if turn_player != priority_turn_player {
+            priority_turn_player = turn_player;
+            card_suggestions.prioritize(|card| card_priority.rank(card, priority_turn_player));
+        }
+
+        if let Err(err) = record_keeper.autosave() {
+            ui.message(&format!("Couldn't save: {}", err));
+        }
+
+        let cue = record_keeper.cue.take();
+        let state_text = state_osd_text(&record_keeper, &card_priority.decks);
+        match (cue, cue_mode) {
+            (Some(cue), Some(CueMode::Flash)) => show_osd_cue(video, &state_text, cue),
+            (Some(cue), Some(CueMode::Bell)) => {
+                ring_bell(cue);
+                if state_text != osd_text {
+                    show_osd(video, &state_text);
+                }
+            }
+            _ if state_text != osd_text => show_osd(video, &state_text),
+            _ => {}
+        }
+        osd_text = state_text;
+
+        // Live clocks keep running while typing and only stop for :pause
+        if !live {
+            if !text.is_empty()
+                || card_suggestions.has_suggestions()
+                || command_suggestions.has_suggestions()
+            {
+                if !video.is_paused() {
+                    video.pause();
+                }
+            } else if !frame_stepping {
+                if video.is_paused() {
+                    video.unpause();
+                }
+            }
+        }
+    }
+
+    drop(ui);
+    if let Err(err) = record_keeper.finalize() {
+        eprintln!("Could not save annotations: {}\r", err);
+    }
+}
+
+/// Runs annotate, or one of its tools. Logging is set up by the caller.
+pub async fn run(mut args: Cli) -> std::io::Result<()> {
+    if let Some(tool) = args.tool.take() {
+        if let Err(err) = tools::run(tool).await {
+            error!("{}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Check update
+    if args.update_db {
+        info!("Updating card db...");
+        if card_update::update_card_db()
+            .await
+            .expect("Couldn't update card db")
+        {
+            info!("Card db updated!");
+        } else {
+            info!("Card db already up to date!");
+        }
+    }
+    if args.update_card_hashes {
+        info!("Hashing card images...");
+        // Images are downloaded with blocking requests
+        let index = tokio::task::spawn_blocking(|| CardHashIndex::build(&CardImageDB::init()))
+            .await
+            .expect("Couldn't hash card images");
+        index
+            .write_to_path(&assets::path(CARD_HASH_FILE))
+            .expect("Couldn't write card hashes");
+        info!("Hashed {} card images!", index.hashes.len());
+    }
+
+    // Verify video fp
+    let video_fp = args.video_file.as_deref().unwrap_or_default();
+    if !args.live {
+        if !std::fs::exists(video_fp)? {
+            error!("File does not exist");
+            return Ok(());
+        }
+        info!("{}", video_fp);
+    }
+
+    // Edits are written back over the original file, in its own format
+    let (output_fp, format) = match (&args.edit, &args.output_fp) {
+        (Some(edit_fp), _) => {
+            let format = StorageFormat::detect_path(edit_fp).expect("Couldn't read annotation file");
+            (edit_fp.to_owned(), format)
+        }
+        (None, Some(output_fp)) => (
+            format!("annotations/{}.{}", output_fp, args.output_format.extension()),
+            args.output_format,
+        ),
+        (None, None) => unreachable!("clap requires output_fp without edit"),
+    };
+    let session_fp = format!(
+        "annotations/{}.session.jsonl",
+        args.output_fp.as_deref().unwrap_or_default()
+    );
+    let loaded = match (&args.edit, &args.resume) {
+        (Some(edit_fp), _) => Some(RecordKeeper::edit(edit_fp)),
+        (None, Some(session_fp)) => Some(RecordKeeper::resume(session_fp)),
+        (None, None) => None,
+    };
+    // A session left by a crash is only picked up with --resume, never started over
+    if loaded.is_none() && Path::new(&session_fp).exists() {
+        error!(
+            "{} is left from an unfinished annotation. Pick it up with --resume {} or move it \
+             aside to start over.",
+            session_fp, session_fp
+        );
+        return Ok(());
+    }
+
+    // Load video file, picking up where an interrupted session stopped
+    let start = loaded
+        .as_ref()
+        .filter(|_| args.resume.is_some())
+        .and_then(|rk| rk.last_time())
+        .map(|time| time.as_f64());
+    // The browser plays the video in web mode
+    let video: Option<Box<dyn Player>> = if args.web {
+        None
+    } else if args.live {
+        Some(Box::new(LiveClock::build(start.unwrap_or_default())))
+    } else {
+        match playback::open(video_fp, start, args.backend) {
+            Ok(video) => Some(video),
+            Err(err) => {
+                error!("Couldn't open video: {}", err);
+                return Ok(());
+            }
+        }
+    };
+
+    // Opened before the match is set up, so a missing device is found straight away
+    let listening = args
+        .hotkeys
+        .as_deref()
+        .map(|fp| HotkeyConfig::read_from_path(fp).and_then(|config| config.listen()));
+    let hotkeys = match listening {
+        None => hotkeys::none(),
+        Some(Ok(hotkeys)) => hotkeys,
+        Some(Err(err)) => {
+            error!("Couldn't start the hotkeys: {}", err);
+            return Ok(());
+        }
+    };
+
+    let card_db = CardDB::init();
+    let card_hashes = args.assist.then(|| {
+        CardHashIndex::read_from_path(&assets::path(CARD_HASH_FILE))
+            .expect("Couldn't read card hashes, run with --update-card-hashes first")
+    });
+    let mut decks = [Decklist::default(), Decklist::default()];
+    for (deck, source) in decks.iter_mut().zip([&args.deck1, &args.deck2]) {
+        if let Some(source) = source {
+            *deck = Decklist::load(source).await.expect("Couldn't load decklist");
+            let (_, missing) = deck.resolve(&card_db);
+            for entry in missing {
+                warn!("Decklist card not found: {}", entry.name);
+            }
+        }
+    }
+    let card_priority = CardPriority::build(decks, args.deck_only);
+    let record_keeper = match loaded {
+        Some(record_keeper) => {
+            info!("Loaded {} records", record_keeper.records.len());
+            enable_raw_mode()?;
+            record_keeper
+        }
+        None => setup_match(&card_db, &session_fp, args.format, args.players()).await?,
+    };
+
+    if let Some(video) = video {
+        println!("Press ENTER to start:");
+        let mut reader = EventStream::new();
+        loop {
+            let mut event = reader.next().fuse();
+            select! {
+                maybe_event = event => {
+                    match maybe_event {
+                        Some(Ok(Event::Key(key))) => {
+                            if key.code == KeyCode::Enter {
+                                break;
+                            }
+                        },
+                        Some(Err(e)) => println!("Error: {:?}\r", e),
+                        _ => {}
+                    };
+                }
+            }
+        }
+
+        handle_events(
+            &output_fp,
+            format,
+            video.as_ref(),
+            &card_db.cards,
+            record_keeper,
+            card_priority,
+            args.edit.is_some(),
+            args.cue,
+            args.ocr,
+            card_hashes,
+            hotkeys,
+        )
+        .await;
+
+        disable_raw_mode()?;
+    } else {
+        disable_raw_mode()?;
+        web::serve(
+            args.port,
+            video_fp,
+            &output_fp,
+            format,
+            &card_db.cards,
+            record_keeper,
+            card_priority,
+        )
+        .await?;
+    }
+    if args.render {
+        render_overlay(video_fp, &output_fp, args.render_output.as_deref()).await?;
+    }
+    Ok(())
+}
+
+/// Runs the overlay renderer on the video with the annotation just written
+async fn render_overlay(
+    video_fp: &str,
+    annotation_fp: &str,
+    output_fp: Option<&str>,
+) -> std::io::Result<()> {
+    if !Path::new(annotation_fp).exists() {
+        error!("No annotation to render at {}", annotation_fp);
+        return Ok(());
+    }
+    let output_fp = match output_fp {
+        Some(fp) => fp.to_owned(),
+        None => {
+            let render_dir = &Config::get().render_dir;
+            std::fs::create_dir_all(render_dir)?;
+            let name = Path::new(annotation_fp)
+                .file_stem()
+                .and_then(|name| name.to_str())
+                .unwrap_or("annotation");
+            format!("{}/{}.mp4", render_dir, name)
+        }
+    };
+    let args = render::Cli::try_parse_from([
+        "overlay",
+        "--video-file",
+        video_fp,
+        "--card-file",
+        annotation_fp,
+        "--output-file",
+        &output_fp,
+    ])
+    .map_err(std::io::Error::other)?;
+
+    info!("Rendering {}", output_fp);
+    // The renderer downloads card images with blocking requests
+    tokio::task::spawn_blocking(move || render::run(args, None).map_err(|e| e.to_string()))
+        .await?
+        .map_err(|e| std::io::Error::other(format!("Couldn't render {}: {}", output_fp, e)))
+}
+
+/// Prompts for the players, their metadata unless given by flags, heroes and first player,
+/// then starts a new session. With a format, only heroes for it are offered.
+async fn setup_match(
+    card_db: &CardDB,
+    session_fp: &str,
+    format: Option<Format>,
+    players: [Option<PlayerInfo>; 2],
+) -> std::io::Result<RecordKeeper> {
+    let heroes = match format {
+        Some(format) => card_db.heroes_for(format),
+        None => card_db.heroes(),
+    };
+
+    let mut player1 = String::new();
+    println!("Enter Player 1's name (left player):");
+    std::io::stdin().read_line(&mut player1)?;
+    let player1 = player1.trim();
+
+    let mut player2 = String::new();
+    println!("Enter Player 2's name (right player):");
+    std::io::stdin().read_line(&mut player2)?;
+    let player2 = player2.trim();
+
+    let [info1, info2] = players;
+    let info1 = match info1 {
+        Some(info) => info,
+        None => prompt_player_info(player1)?,
+    };
+    let info2 = match info2 {
+        Some(info) => info,
+        None => prompt_player_info(player2)?,
+    };
+
+    enable_raw_mode()?;
+    println!("Enter hero for {}:", player1);
+    let hero1 = lib::commands::enter_card(&heroes).await;
+    println!("Enter hero for {}:", player2);
+    let hero2 = lib::commands::enter_card(&heroes).await;
+    println!("Enter player going first:");
+    let options = Vec::from([
+        lib::autocomplete::AutocompleteOption::new("1".to_string()),
+        lib::autocomplete::AutocompleteOption::new("2".to_string()),
+    ]);
+    let first = lib::commands::get_user_input(&options).await;
+    Ok(RecordKeeper::build(
+        (player1, hero1),
+        (player2, hero2),
+        first.text(),
+        format,
+        [info1, info2],
+        session_fp,
+    ))
+}
+
+fn prompt_player_info(player: &str) -> std::io::Result<PlayerInfo> {
+    let mut text = String::new();
+    println!(
+        "Enter {}'s team, country, pronouns and seed, comma separated (blank to skip):",
+        player
+    );
+    std::io::stdin().read_line(&mut text)?;
+    Ok(parse_player_info(&text))
+}
+
+/// Expected format, with any field left empty:
+/// Team Covenant, US, she/her, 5-1
+/// , CA
+fn parse_player_info(text: &str) -> PlayerInfo {
+    let mut fields = text
+        .split(',')
+        .map(|field| Some(field.trim().to_owned()).filter(|field| !field.is_empty()));
+    let mut next = || fields.next().flatten();
+    PlayerInfo {
+        team: next(),
+        country: next(),
+        pronouns: next(),
+        seed: next(),
+    }
+}
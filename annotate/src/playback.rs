@@ -0,0 +1,463 @@
+use std::{
+    cell::Cell,
+    sync::{mpsc, Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "mpv")]
+use libmpv::{FileState, Mpv};
+use opencv::{
+    core::{Mat, Point, Rect, Scalar},
+    highgui, imgproc,
+    prelude::{MatTraitConst, VideoCaptureTrait, VideoCaptureTraitConst},
+    videoio::{self, VideoCapture},
+};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Colour as red, green and blue
+pub type Rgb = (u8, u8, u8);
+
+/// Video player the annotator watches. Commands are best effort: a failed seek or pause leaves
+/// the video where it was.
+pub trait Player {
+    /// Seconds into the video
+    fn position(&self) -> f64;
+    fn seek_absolute(&self, seconds: f64);
+    /// Seeks forward, or back when `seconds` is negative
+    fn seek_relative(&self, seconds: f64);
+    fn is_paused(&self) -> bool;
+    fn pause(&self);
+    fn unpause(&self);
+    fn set_speed(&self, speed: f64);
+    /// Shows the next frame, or the previous one, and pauses
+    fn frame_step(&self, forward: bool);
+    /// Shows `text` over the video for `duration_ms`, with an optional coloured marker above it
+    fn show_text(&self, text: &str, marker: Option<Rgb>, duration_ms: u64);
+    /// Frame currently shown, in BGR
+    fn frame(&self) -> Result<Mat>;
+    /// Live clocks keep running while the annotator types
+    fn is_live(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Backend {
+    /// mpv when it's available, otherwise OpenCV
+    #[default]
+    Auto,
+    Mpv,
+    /// Plain OpenCV window, for when libmpv isn't installed
+    Opencv,
+}
+
+/// Opens `video_fp` paused, at `start` seconds if given
+pub fn open(video_fp: &str, start: Option<f64>, backend: Backend) -> Result<Box<dyn Player>> {
+    match backend {
+        Backend::Opencv => Ok(Box::new(CvPlayer::open(video_fp, start)?)),
+        #[cfg(feature = "mpv")]
+        Backend::Mpv => Ok(Box::new(open_mpv(video_fp, start)?)),
+        #[cfg(not(feature = "mpv"))]
+        Backend::Mpv => Err("annotate was built without mpv support".into()),
+        #[cfg(feature = "mpv")]
+        Backend::Auto => match open_mpv(video_fp, start) {
+            Ok(mpv) => Ok(Box::new(mpv)),
+            Err(err) => {
+                tracing::warn!(
+                    "Couldn't start mpv ({}), falling back to OpenCV playback",
+                    err
+                );
+                Ok(Box::new(CvPlayer::open(video_fp, start)?))
+            }
+        },
+        #[cfg(not(feature = "mpv"))]
+        Backend::Auto => Ok(Box::new(CvPlayer::open(video_fp, start)?)),
+    }
+}
+
+#[cfg(feature = "mpv")]
+fn open_mpv(video_fp: &str, start: Option<f64>) -> Result<Mpv> {
+    let mpv = Mpv::new().map_err(|err| format!("{:?}", err))?;
+    if let Some(start) = start {
+        mpv.set_property("start", format!("{}", start))
+            .map_err(|err| format!("{:?}", err))?;
+    }
+    mpv.playlist_load_files(&[(video_fp, FileState::AppendPlay, None)])
+        .map_err(|err| format!("{:?}", err))?;
+    let _ = mpv.pause();
+    Ok(mpv)
+}
+
+#[cfg(feature = "mpv")]
+impl Player for Mpv {
+    fn position(&self) -> f64 {
+        self.get_property("playback-time").unwrap_or(0.0)
+    }
+
+    fn seek_absolute(&self, seconds: f64) {
+        let _ = Mpv::seek_absolute(self, seconds);
+    }
+
+    fn seek_relative(&self, seconds: f64) {
+        let _ = if seconds < 0.0 {
+            self.seek_backward(-seconds)
+        } else {
+            self.seek_forward(seconds)
+        };
+    }
+
+    fn is_paused(&self) -> bool {
+        self.get_property("pause").unwrap_or(true)
+    }
+
+    fn pause(&self) {
+        let _ = Mpv::pause(self);
+    }
+
+    fn unpause(&self) {
+        let _ = Mpv::unpause(self);
+    }
+
+    fn set_speed(&self, speed: f64) {
+        let _ = self.set_property("speed", speed);
+    }
+
+    fn frame_step(&self, forward: bool) {
+        let step = if forward { "frame-step" } else { "frame-back-step" };
+        let _ = self.command(step, &[]);
+    }
+
+    fn show_text(&self, text: &str, marker: Option<Rgb>, duration_ms: u64) {
+        let text = match marker {
+            // osd-ass-cc/0 turns on ASS styling and osd-ass-cc/1 turns it back off. ASS
+            // colours are in blue green red order.
+            Some((r, g, b)) => format!(
+                "${{osd-ass-cc/0}}{{\\fs64\\1c&H{:02X}{:02X}{:02X}&}}\u{25A0}${{osd-ass-cc/1}}\n{}",
+                b, g, r, text
+            ),
+            None => text.to_owned(),
+        };
+        let _ = self.command("show-text", &[&text, &duration_ms.to_string()]);
+    }
+
+    fn frame(&self) -> Result<Mat> {
+        let fp = std::env::temp_dir().join(format!("annotate_frame_{}.png", std::process::id()));
+        let fp = fp.to_str().ok_or("temp path is not valid unicode")?;
+        self.command("screenshot-to-file", &[fp, "video"])
+            .map_err(|err| format!("{:?}", err))?;
+        let frame = opencv::imgcodecs::imread(fp, opencv::imgcodecs::IMREAD_COLOR);
+        let _ = std::fs::remove_file(fp);
+        Ok(frame?)
+    }
+}
+
+const CV_WINDOW: &str = "annotate";
+/// Frame rate assumed when the video doesn't report one
+const DEFAULT_FPS: f64 = 30.0;
+/// How often the window is redrawn while paused
+const PAUSED_DELAY_MS: i32 = 30;
+
+struct OsdText {
+    text: String,
+    marker: Option<Rgb>,
+    until: Instant,
+}
+
+/// Playback state shared with the thread showing the video
+struct CvState {
+    position: f64,
+    paused: bool,
+    speed: f64,
+    seek: Option<f64>,
+    step: Option<bool>,
+    osd: Option<OsdText>,
+    frame: Mat,
+    closed: bool,
+}
+
+/// Shows the video in an OpenCV window, driven by its own thread
+pub struct CvPlayer {
+    state: Arc<Mutex<CvState>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl CvPlayer {
+    pub fn open(video_fp: &str, start: Option<f64>) -> Result<Self> {
+        let state = Arc::new(Mutex::new(CvState {
+            position: 0.0,
+            paused: true,
+            speed: 1.0,
+            seek: start,
+            step: None,
+            osd: None,
+            frame: Mat::default(),
+            closed: false,
+        }));
+        let (opened_tx, opened_rx) = mpsc::channel();
+        let video_fp = video_fp.to_owned();
+        let thread_state = state.clone();
+        let thread = thread::spawn(move || {
+            let capture = match VideoCapture::from_file(&video_fp, videoio::CAP_ANY) {
+                Ok(capture) if capture.is_opened().unwrap_or(false) => capture,
+                Ok(_) => {
+                    let _ = opened_tx.send(Err(format!("couldn't open {}", video_fp)));
+                    return;
+                }
+                Err(err) => {
+                    let _ = opened_tx.send(Err(err.to_string()));
+                    return;
+                }
+            };
+            let _ = opened_tx.send(Ok(()));
+            Self::run(capture, &thread_state);
+        });
+        opened_rx.recv()??;
+
+        // Show the first frame rather than an empty window
+        state.lock().unwrap().step.get_or_insert(true);
+        Ok(CvPlayer {
+            state,
+            thread: Some(thread),
+        })
+    }
+
+    fn run(mut capture: VideoCapture, state: &Mutex<CvState>) {
+        let fps = capture
+            .get(videoio::CAP_PROP_FPS)
+            .ok()
+            .filter(|fps| *fps > 0.0)
+            .unwrap_or(DEFAULT_FPS);
+        loop {
+            let (seek, step, playing, speed) = {
+                let mut state = state.lock().unwrap();
+                if state.closed {
+                    break;
+                }
+                (state.seek.take(), state.step.take(), !state.paused, state.speed)
+            };
+
+            let mut advance = playing;
+            if let Some(seconds) = seek {
+                let _ = capture.set(videoio::CAP_PROP_POS_MSEC, seconds.max(0.0) * 1000.0);
+                advance = true;
+            } else if let Some(forward) = step {
+                if !forward {
+                    // The next frame read is the one after the frame shown
+                    let next = capture.get(videoio::CAP_PROP_POS_FRAMES).unwrap_or(0.0);
+                    let _ = capture.set(videoio::CAP_PROP_POS_FRAMES, (next - 2.0).max(0.0));
+                }
+                advance = true;
+            }
+
+            let mut frame = Mat::default();
+            let shown = {
+                let mut state = state.lock().unwrap();
+                if advance {
+                    match capture.read(&mut frame) {
+                        Ok(true) if !frame.empty() => {
+                            state.position =
+                                capture.get(videoio::CAP_PROP_POS_MSEC).unwrap_or(0.0) / 1000.0;
+                            state.frame = frame;
+                        }
+                        // End of the video
+                        _ => state.paused = true,
+                    }
+                }
+                if step.is_some() {
+                    state.paused = true;
+                }
+                if state.osd.as_ref().is_some_and(|osd| osd.until < Instant::now()) {
+                    state.osd = None;
+                }
+                let mut shown = state.frame.clone();
+                if let Some(osd) = &state.osd {
+                    let _ = draw_osd(&mut shown, osd);
+                }
+                shown
+            };
+            if !shown.empty() {
+                let _ = highgui::imshow(CV_WINDOW, &shown);
+            }
+
+            let delay = if playing && seek.is_none() && step.is_none() {
+                ((1000.0 / (fps * speed)) as i32).max(1)
+            } else {
+                PAUSED_DELAY_MS
+            };
+            let _ = highgui::wait_key(delay);
+        }
+        let _ = highgui::destroy_window(CV_WINDOW);
+    }
+}
+
+/// Draws the OSD text in the top left corner, one line under the other
+fn draw_osd(frame: &mut Mat, osd: &OsdText) -> opencv::Result<()> {
+    let scale = frame.rows() as f64 / 720.0;
+    let line_height = (36.0 * scale) as i32;
+    let mut y = line_height;
+    if let Some((r, g, b)) = osd.marker {
+        let size = line_height;
+        imgproc::rectangle(
+            frame,
+            Rect::new(line_height / 2, y - size + size / 4, size, size),
+            Scalar::new(b as f64, g as f64, r as f64, 0.0),
+            imgproc::FILLED,
+            imgproc::LINE_8,
+            0,
+        )?;
+        y += line_height + line_height / 2;
+    }
+    for line in osd.text.lines() {
+        for (color, thickness) in [(Scalar::all(0.0), 4), (Scalar::all(255.0), 1)] {
+            imgproc::put_text(
+                frame,
+                line,
+                Point::new(line_height / 2, y),
+                imgproc::FONT_HERSHEY_SIMPLEX,
+                scale,
+                color,
+                ((thickness as f64 * scale) as i32).max(1),
+                imgproc::LINE_AA,
+                false,
+            )?;
+        }
+        y += line_height;
+    }
+    Ok(())
+}
+
+impl Player for CvPlayer {
+    fn position(&self) -> f64 {
+        let state = self.state.lock().unwrap();
+        state.seek.unwrap_or(state.position)
+    }
+
+    fn seek_absolute(&self, seconds: f64) {
+        self.state.lock().unwrap().seek = Some(seconds);
+    }
+
+    fn seek_relative(&self, seconds: f64) {
+        let mut state = self.state.lock().unwrap();
+        let from = state.seek.unwrap_or(state.position);
+        state.seek = Some((from + seconds).max(0.0));
+    }
+
+    fn is_paused(&self) -> bool {
+        self.state.lock().unwrap().paused
+    }
+
+    fn pause(&self) {
+        self.state.lock().unwrap().paused = true;
+    }
+
+    fn unpause(&self) {
+        self.state.lock().unwrap().paused = false;
+    }
+
+    fn set_speed(&self, speed: f64) {
+        self.state.lock().unwrap().speed = speed;
+    }
+
+    fn frame_step(&self, forward: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.paused = true;
+        state.step = Some(forward);
+    }
+
+    fn show_text(&self, text: &str, marker: Option<Rgb>, duration_ms: u64) {
+        self.state.lock().unwrap().osd = Some(OsdText {
+            text: text.to_owned(),
+            marker,
+            until: Instant::now() + Duration::from_millis(duration_ms),
+        });
+    }
+
+    fn frame(&self) -> Result<Mat> {
+        let frame = self.state.lock().unwrap().frame.clone();
+        if frame.empty() {
+            return Err("no frame shown yet".into());
+        }
+        Ok(frame)
+    }
+}
+
+impl Drop for CvPlayer {
+    fn drop(&mut self) {
+        self.state.lock().unwrap().closed = true;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Wall clock for annotating a match as it happens, with no video. Seeking moves the clock, so
+/// it can be lined up with the event's own clock.
+pub struct LiveClock {
+    /// Seconds counted before the clock was last started
+    banked: Cell<f64>,
+    /// When the clock was last started, `None` while paused
+    started: Cell<Option<Instant>>,
+}
+
+impl LiveClock {
+    /// Paused at `start` seconds
+    pub fn build(start: f64) -> Self {
+        LiveClock {
+            banked: Cell::new(start.max(0.0)),
+            started: Cell::new(None),
+        }
+    }
+}
+
+impl Player for LiveClock {
+    fn position(&self) -> f64 {
+        let running = self
+            .started
+            .get()
+            .map_or(0.0, |started| started.elapsed().as_secs_f64());
+        self.banked.get() + running
+    }
+
+    fn seek_absolute(&self, seconds: f64) {
+        self.banked.set(seconds.max(0.0));
+        if self.started.get().is_some() {
+            self.started.set(Some(Instant::now()));
+        }
+    }
+
+    fn seek_relative(&self, seconds: f64) {
+        self.seek_absolute(self.position() + seconds);
+    }
+
+    fn is_paused(&self) -> bool {
+        self.started.get().is_none()
+    }
+
+    fn pause(&self) {
+        self.banked.set(self.position());
+        self.started.set(None);
+    }
+
+    fn unpause(&self) {
+        if self.started.get().is_none() {
+            self.started.set(Some(Instant::now()));
+        }
+    }
+
+    /// Real time can't be sped up
+    fn set_speed(&self, _speed: f64) {}
+
+    fn frame_step(&self, _forward: bool) {}
+
+    fn show_text(&self, _text: &str, _marker: Option<Rgb>, _duration_ms: u64) {}
+
+    fn frame(&self) -> Result<Mat> {
+        Err("there is no video in live mode".into())
+    }
+
+    fn is_live(&self) -> bool {
+        true
+    }
+}
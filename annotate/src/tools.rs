@@ -0,0 +1,546 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use lib::{
+    annotation::{self, Event},
+    annotation_store::{self, StorageFormat},
+    audio_sync::SyncOffset,
+    bundle,
+    card::CardDB,
+    decklist::Decklist,
+    game_report::GameReport,
+    game_start::{find_starts, sample_activity},
+    merge,
+    perspective::Perspective,
+    retime::Retime,
+    time_tick::TimeTick,
+};
+use opencv::{
+    core::{Mat, MatTraitConst, Point, Point2f, Scalar},
+    highgui, imgproc,
+    videoio::{self, VideoCapture, VideoCaptureTrait},
+};
+use tracing::{info, warn};
+
+use crate::describe_record;
+
+/// Events this many seconds apart are treated as the same moment when merging
+const DEFAULT_MERGE_TOLERANCE: f64 = 0.5;
+/// Seconds between frames sampled to find the start of play
+const DEFAULT_START_STEP: f64 = 1.0;
+/// Seconds of footage compared either side of a possible start of play
+const DEFAULT_START_WINDOW: f64 = 30.0;
+/// Seconds of the reference's audio matched against the target
+const DEFAULT_SYNC_WINDOW: f64 = 120.0;
+/// Furthest apart in seconds the two files' audio is looked for
+const DEFAULT_SYNC_MAX_OFFSET: f64 = 60.0;
+const CALIBRATE_WINDOW: &str = "Click the play area's corners from the top left, clockwise";
+const CORNER_NAMES: [&str; 4] = ["top left", "top right", "bottom right", "bottom left"];
+const CORNER_COLOR: Scalar = Scalar::new(0.0, 255.0, 255.0, 0.0);
+const KEY_ENTER: i32 = 13;
+const KEY_ESCAPE: i32 = 27;
+const KEY_BACKSPACE: i32 = 8;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Work on annotation files without a video
+#[derive(clap::Subcommand)]
+pub enum Tool {
+    /// Shift or stretch every timestamp in an annotation file, e.g. after the video was trimmed
+    /// differently than the footage that was annotated
+    Retime {
+        /// Annotation file. It is overwritten in its own format unless `--output` is given.
+        file: String,
+
+        /// Seconds to add to every timestamp, negative to move them earlier
+        #[arg(long, allow_hyphen_values = true, required_unless_present = "from")]
+        offset: Option<f64>,
+
+        /// Two times in the annotation, e.g. `--from 0:05 1:02:10`, that move to the `--to` times.
+        /// Everything else is stretched to match.
+        #[arg(long, num_args = 2, value_parser = parse_time, requires = "to", conflicts_with = "offset")]
+        from: Option<Vec<f64>>,
+
+        /// Where the `--from` times are in the final video
+        #[arg(long, num_args = 2, value_parser = parse_time, requires = "from")]
+        to: Option<Vec<f64>>,
+
+        /// Write here instead of over the input, in the same format
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Combine two annotations of the same video, e.g. from one operator logging cards and
+    /// another logging life totals
+    Merge {
+        /// Annotation file whose header is kept
+        first: String,
+
+        second: String,
+
+        #[arg(short, long)]
+        output: String,
+
+        /// Output format: tsv, jsonl or sqlite. Defaults to the first file's format.
+        #[arg(short = 'f', long)]
+        output_format: Option<StorageFormat>,
+
+        /// Seconds apart that events can be and still count as the same moment
+        #[arg(long, default_value_t = DEFAULT_MERGE_TOLERANCE)]
+        tolerance: f64,
+    },
+    /// Download every card image and hero animation the given matches need into a folder that
+    /// the overlay can be run from without a network connection
+    Bundle {
+        /// Annotation files whose cards and heroes are bundled
+        annotations: Vec<String>,
+
+        /// Decklist files or URLs whose cards are bundled, e.g. before the match is annotated
+        #[arg(short, long)]
+        decklist: Vec<String>,
+
+        /// Folder to create the bundle in
+        #[arg(short, long)]
+        output: String,
+    },
+    /// Find where play starts in footage that opens with setup, and the `video_offset` that
+    /// lines an annotation up with it
+    FindStart {
+        video: String,
+
+        /// Annotation whose first event is the start of play. Its offset is suggested.
+        #[arg(short, long)]
+        annotation: Option<String>,
+
+        /// Save the best offset into the annotation's header
+        #[arg(long, requires = "annotation")]
+        write: bool,
+
+        /// Seconds between the frames compared
+        #[arg(long, default_value_t = DEFAULT_START_STEP)]
+        step: f64,
+
+        /// Seconds of footage compared either side of each possible start
+        #[arg(long, default_value_t = DEFAULT_START_WINDOW)]
+        window: f64,
+    },
+    /// Click the four corners of the play area on a frame of a video, for the overlay's
+    /// `--perspective` to straighten footage from a camera at an angle
+    Calibrate {
+        video: String,
+
+        /// Time of the frame to click on, e.g. 1:30
+        #[arg(long, value_parser = parse_time, default_value = "0")]
+        at: f64,
+
+        /// Where to save the corners
+        #[arg(short, long, default_value = "perspective.json")]
+        output: String,
+    },
+    /// Find how far apart two exports of the same footage are by their audio, and the offset
+    /// that moves an annotation of the first onto the second
+    Sync {
+        /// Footage the annotation was made against
+        reference: String,
+
+        /// Footage the annotation should line up with
+        target: String,
+
+        /// Match the first clap or card shuffle after `--at` instead of the whole soundtrack
+        #[arg(long)]
+        taps: bool,
+
+        /// Where in the reference to start listening, e.g. 2:00 to skip a silent intro
+        #[arg(long, value_parser = parse_time, default_value = "0")]
+        at: f64,
+
+        /// Seconds of audio compared
+        #[arg(long, default_value_t = DEFAULT_SYNC_WINDOW)]
+        window: f64,
+
+        /// Furthest apart in seconds the two files can be
+        #[arg(long, default_value_t = DEFAULT_SYNC_MAX_OFFSET)]
+        max_offset: f64,
+
+        /// Annotation of the reference to apply the offset to
+        #[arg(short, long)]
+        annotation: Option<String>,
+
+        /// Retime the annotation in place
+        #[arg(long, requires = "annotation")]
+        write: bool,
+    },
+    /// Cards played, blocks and damage per turn for each game of a match, e.g. for an endcard
+    /// once the match is over
+    Report {
+        file: String,
+
+        /// Write here instead of printing. A `.md` file gets the markdown tables, anything
+        /// else JSON.
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+}
+
+fn parse_time(text: &str) -> std::result::Result<f64, String> {
+    TimeTick::parse(text)
+        .map(|time| time.as_f64())
+        .ok_or_else(|| format!("'{}' isn't a time like 95, 12:34 or 1:02:03.5", text))
+}
+
+pub async fn run(tool: Tool) -> Result<()> {
+    match tool {
+        Tool::Retime {
+            file,
+            offset,
+            from,
+            to,
+            output,
+        } => {
+            let retime = match (offset, from, to) {
+                (Some(offset), _, _) => Retime::offset(offset),
+                (None, Some(from), Some(to)) => Retime::anchors((from[0], from[1]), (to[0], to[1]))
+                    .ok_or("the --from times must be different")?,
+                _ => unreachable!("clap requires --offset or --from and --to"),
+            };
+            retime_file(&file, output.as_deref().unwrap_or(&file), retime)
+        }
+        Tool::Merge {
+            first,
+            second,
+            output,
+            output_format,
+            tolerance,
+        } => {
+            let format = match output_format {
+                Some(format) => format,
+                None => StorageFormat::detect_path(&first)?,
+            };
+            merge_files(&first, &second, &output, format, tolerance)
+        }
+        Tool::Bundle {
+            annotations,
+            decklist,
+            output,
+        } => bundle_files(&annotations, &decklist, output).await,
+        Tool::FindStart {
+            video,
+            annotation,
+            write,
+            step,
+            window,
+        } => find_start(&video, annotation.as_deref(), write, step, window),
+        Tool::Calibrate { video, at, output } => calibrate(&video, at, &output),
+        Tool::Sync {
+            reference,
+            target,
+            taps,
+            at,
+            window,
+            max_offset,
+            annotation,
+            write,
+        } => {
+            info!("Listening to {} and {}...", reference, target);
+            let offset = if taps {
+                SyncOffset::taps(&reference, &target, at, window)?
+            } else {
+                SyncOffset::correlate(&reference, &target, at, window, max_offset)?
+            };
+            info!(
+                "{} is {:.2}s later than {} (match {:.2})",
+                target, offset.seconds, reference, offset.score
+            );
+            match annotation {
+                Some(annotation) if write => {
+                    retime_file(&annotation, &annotation, Retime::offset(offset.seconds))
+                }
+                Some(annotation) => {
+                    info!(
+                        "Apply it with `retime {} --offset {:.2}` or pass --write",
+                        annotation, offset.seconds
+                    );
+                    Ok(())
+                }
+                None => Ok(()),
+            }
+        }
+        Tool::Report { file, output } => report(&file, output.as_deref()),
+    }
+}
+
+fn retime_file(fp: &str, output_fp: &str, retime: Retime) -> Result<()> {
+    let format = StorageFormat::detect_path(fp)?;
+    let mut file = annotation_store::read_from_path(fp)?;
+    retime.apply_all(&mut file.events);
+    annotation::sort(&mut file.events);
+    annotation_store::write_to_path(output_fp, &file, format)?;
+    info!("Retimed {} records into {}", file.events.len(), output_fp);
+    Ok(())
+}
+
+fn merge_files(
+    first_fp: &str,
+    second_fp: &str,
+    output_fp: &str,
+    format: StorageFormat,
+    tolerance: f64,
+) -> Result<()> {
+    let first = annotation_store::read_from_path(first_fp)?;
+    let second = annotation_store::read_from_path(second_fp)?;
+    let (merged, report) = merge::merge(&first, &second, tolerance);
+
+    if report.setup_differs {
+        warn!(
+            "{} and {} have different players or heroes, keeping {}'s",
+            first_fp, second_fp, first_fp
+        );
+    }
+    for (kept, other) in report.conflicts.iter() {
+        warn!(
+            "Conflict: {} in {} but {} in {}",
+            describe_record(kept),
+            first_fp,
+            describe_record(other),
+            second_fp
+        );
+    }
+    annotation_store::write_to_path(output_fp, &merged, format)?;
+    info!(
+        "Merged {} records into {}: {} duplicates dropped, {} conflicts kept for review",
+        merged.events.len(),
+        output_fp,
+        report.duplicates.len(),
+        report.conflicts.len()
+    );
+    Ok(())
+}
+
+async fn bundle_files(
+    annotation_fps: &[String],
+    decklists: &[String],
+    output: String,
+) -> Result<()> {
+    let mut cards = Vec::new();
+    let mut heroes = Vec::new();
+    for fp in annotation_fps {
+        let file = annotation_store::read_from_path(fp)?;
+        heroes.push(file.header.setup.hero1.clone());
+        heroes.push(file.header.setup.hero2.clone());
+        for annotation in file.events.iter() {
+            if let Event::Card { name, pitch }
+            | Event::Block { name, pitch }
+            | Event::Pair { name, pitch } = &annotation.event
+            {
+                cards.push((name.clone(), *pitch));
+            }
+        }
+    }
+    if !decklists.is_empty() {
+        let card_db = CardDB::init();
+        for source in decklists {
+            let (found, missing) = Decklist::load(source).await?.resolve(&card_db);
+            for entry in missing {
+                warn!("Not in the card db: {}", entry.name);
+            }
+            for (card, _) in found {
+                if card.types.iter().any(|t| t == "hero") {
+                    heroes.push(card.name.clone());
+                }
+                cards.push((card.name.clone(), card.pitch));
+            }
+        }
+    }
+    cards.sort();
+    cards.dedup();
+    heroes.sort();
+    heroes.dedup();
+
+    info!(
+        "Bundling {} cards and {} heroes...",
+        cards.len(),
+        heroes.len()
+    );
+    let report = bundle::export(Path::new(&output), &cards, &heroes).await?;
+    for missing in report.missing.iter() {
+        warn!("Missing: {}", missing);
+    }
+    info!("Bundled {} files", report.files.len());
+    Ok(())
+}
+
+fn report(fp: &str, output_fp: Option<&str>) -> Result<()> {
+    let file = annotation_store::read_from_path(fp)?;
+    let reports = GameReport::for_match(&file.header.setup, &file.events);
+    let markdown = || {
+        reports
+            .iter()
+            .map(GameReport::to_markdown)
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    match output_fp {
+        Some(output_fp) if output_fp.ends_with(".md") => std::fs::write(output_fp, markdown())?,
+        Some(output_fp) => std::fs::write(output_fp, serde_json::to_string_pretty(&reports)?)?,
+        None => print!("{}", markdown()),
+    }
+    if let Some(output_fp) = output_fp {
+        info!("Wrote a report of {} games to {}", reports.len(), output_fp);
+    }
+    Ok(())
+}
+
+fn find_start(
+    video_fp: &str,
+    annotation_fp: Option<&str>,
+    write: bool,
+    step: f64,
+    window: f64,
+) -> Result<()> {
+    info!("Sampling {} every {}s...", video_fp, step);
+    let samples = sample_activity(video_fp, step)?;
+    let starts = find_starts(&samples, window);
+    let Some(best) = starts.first() else {
+        return Err("Couldn't find a start of play, the footage may start with it".into());
+    };
+    for (i, start) in starts.iter().enumerate() {
+        info!(
+            "{}. {} (score {:.2}{})",
+            i + 1,
+            clock(start.time),
+            start.score,
+            if start.cut { ", after a cut" } else { "" }
+        );
+    }
+
+    let Some(annotation_fp) = annotation_fp else {
+        return Ok(());
+    };
+    let mut file = annotation_store::read_from_path(annotation_fp)?;
+    let first = file
+        .events
+        .iter()
+        .find(|annotation| !annotation.event.is_setup())
+        .ok_or("The annotation has no events to line up")?;
+    let offset = best.time - first.time.as_f64();
+    if write {
+        file.header.video_offset = offset;
+        let format = StorageFormat::detect_path(annotation_fp)?;
+        annotation_store::write_to_path(annotation_fp, &file, format)?;
+        info!(
+            "Saved a video offset of {:.2}s to {}",
+            offset, annotation_fp
+        );
+    } else {
+        info!(
+            "The first event is at {}, so the video offset would be {:.2}s (currently {:.2}s). \
+             Pass --write to save it.",
+            clock(first.time.as_f64()),
+            offset,
+            file.header.video_offset
+        );
+    }
+    Ok(())
+}
+
+/// "mm:ss", or "h:mm:ss" past an hour
+fn clock(seconds: f64) -> String {
+    let seconds = seconds as u64;
+    match seconds / 3600 {
+        0 => format!("{:02}:{:02}", seconds / 60, seconds % 60),
+        hours => format!("{}:{:02}:{:02}", hours, seconds / 60 % 60, seconds % 60),
+    }
+}
+
+/// Shows a frame of the video until four corners are clicked and Enter is pressed. Backspace
+/// takes back the last corner and Escape gives up.
+fn calibrate(video_fp: &str, at: f64, output_fp: &str) -> Result<()> {
+    let mut cap = VideoCapture::from_file(video_fp, videoio::CAP_ANY)?;
+    cap.set(videoio::CAP_PROP_POS_MSEC, at * 1000.0)?;
+    let mut frame = Mat::default();
+    if !cap.read(&mut frame)? {
+        return Err(format!("{} has no frame at {}s", video_fp, at).into());
+    }
+
+    let corners: Arc<Mutex<Vec<Point2f>>> = Arc::new(Mutex::new(Vec::new()));
+    highgui::named_window(CALIBRATE_WINDOW, highgui::WINDOW_AUTOSIZE)?;
+    let clicked = corners.clone();
+    highgui::set_mouse_callback(
+        CALIBRATE_WINDOW,
+        Some(Box::new(move |event, x, y, _flags| {
+            let mut clicked = clicked.lock().unwrap();
+            if event == highgui::EVENT_LBUTTONDOWN && clicked.len() < 4 {
+                clicked.push(Point2f::new(x as f32, y as f32));
+            }
+        })),
+    )?;
+    info!("Click the play area's corners: top left, top right, bottom right, bottom left");
+
+    let result = loop {
+        let picked = corners.lock().unwrap().clone();
+        highgui::imshow(CALIBRATE_WINDOW, &draw_corners(&frame, &picked)?)?;
+        match highgui::wait_key(30)? {
+            KEY_ESCAPE => break Err("Calibration cancelled".into()),
+            KEY_BACKSPACE => {
+                corners.lock().unwrap().pop();
+            }
+            KEY_ENTER if picked.len() == 4 => {
+                let picked = [picked[0], picked[1], picked[2], picked[3]];
+                match Perspective::from_pixels(picked, frame.size()?) {
+                    Ok(perspective) => break Ok(perspective),
+                    Err(e) => {
+                        warn!("{}, pick them again", e);
+                        corners.lock().unwrap().clear();
+                    }
+                }
+            }
+            _ => {}
+        }
+    };
+    highgui::destroy_window(CALIBRATE_WINDOW)?;
+
+    let perspective = result?;
+    perspective.save(output_fp)?;
+    info!("Saved the play area's corners to {}", output_fp);
+    Ok(())
+}
+
+/// `frame` with the corners picked so far joined up and labelled
+fn draw_corners(frame: &Mat, corners: &[Point2f]) -> Result<Mat> {
+    let mut shown = frame.try_clone()?;
+    let points: Vec<Point> = corners
+        .iter()
+        .map(|corner| Point::new(corner.x as i32, corner.y as i32))
+        .collect();
+    for (i, point) in points.iter().enumerate() {
+        imgproc::circle(&mut shown, *point, 6, CORNER_COLOR, -1, imgproc::LINE_AA, 0)?;
+        imgproc::put_text(
+            &mut shown,
+            CORNER_NAMES[i],
+            *point + Point::new(10, -10),
+            imgproc::FONT_HERSHEY_SIMPLEX,
+            0.7,
+            CORNER_COLOR,
+            2,
+            imgproc::LINE_AA,
+            false,
+        )?;
+        // Back to the first corner once all four are in
+        if let Some(next) = points
+            .get(i + 1)
+            .or((points.len() == 4).then_some(&points[0]))
+        {
+            imgproc::line(
+                &mut shown,
+                *point,
+                *next,
+                CORNER_COLOR,
+                2,
+                imgproc::LINE_AA,
+                0,
+            )?;
+        }
+    }
+    Ok(shown)
+}
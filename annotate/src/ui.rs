@@ -0,0 +1,201 @@
+use std::{
+    collections::VecDeque,
+    io::{stdout, Stdout},
+};
+
+use crossterm::{
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout, Position, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
+    Terminal,
+};
+
+/// Messages kept for the message pane
+const MESSAGE_HISTORY: usize = 50;
+/// Most suggestions shown in the autocomplete dropdown
+const DROPDOWN_LENGTH: usize = 6;
+
+/// Everything the interface shows for a single frame
+pub struct View<'a> {
+    pub status: String,
+    /// Record descriptions in order
+    pub records: Vec<String>,
+    /// Record highlighted in edit mode
+    pub selected: Option<usize>,
+    pub input: &'a str,
+    /// Rest of the top suggestion after what has been typed
+    pub completion: &'a str,
+    pub suggestions: Vec<&'a str>,
+    /// Key or command, and what it does
+    pub help: &'a [(&'a str, &'a str)],
+}
+
+/// Full screen terminal interface. The terminal is restored when dropped.
+pub struct Ui {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    messages: VecDeque<String>,
+}
+
+impl Ui {
+    /// Takes over the terminal. Raw mode must already be enabled.
+    pub fn build() -> std::io::Result<Self> {
+        execute!(stdout(), EnterAlternateScreen)?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
+        terminal.clear()?;
+        Ok(Ui {
+            terminal,
+            messages: VecDeque::new(),
+        })
+    }
+
+    pub fn message(&mut self, text: &str) {
+        self.messages.push_back(text.to_owned());
+        if self.messages.len() > MESSAGE_HISTORY {
+            self.messages.pop_front();
+        }
+    }
+
+    pub fn draw(&mut self, view: &View) -> std::io::Result<()> {
+        let messages = &self.messages;
+        self.terminal.draw(|frame| {
+            let [status_area, main_area, input_area] = Layout::vertical([
+                Constraint::Length(3),
+                Constraint::Min(6),
+                Constraint::Length(3),
+            ])
+            .areas(frame.area());
+            let [log_area, side_area] =
+                Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                    .areas(main_area);
+            let [message_area, help_area] =
+                Layout::vertical([Constraint::Percentage(40), Constraint::Percentage(60)])
+                    .areas(side_area);
+
+            frame.render_widget(
+                Paragraph::new(view.status.as_str())
+                    .block(Block::default().borders(Borders::ALL).title("Match")),
+                status_area,
+            );
+
+            frame.render_widget(
+                records_list(view, log_area.height.saturating_sub(2) as usize),
+                log_area,
+            );
+
+            let visible = message_area.height.saturating_sub(2) as usize;
+            let items: Vec<ListItem> = messages
+                .iter()
+                .skip(messages.len().saturating_sub(visible))
+                .map(|message| ListItem::new(message.as_str()))
+                .collect();
+            frame.render_widget(
+                List::new(items).block(Block::default().borders(Borders::ALL).title("Messages")),
+                message_area,
+            );
+
+            let items: Vec<ListItem> = view
+                .help
+                .iter()
+                .map(|(key, description)| {
+                    ListItem::new(Line::from(vec![
+                        Span::styled(format!("{:<6}", key), Style::default().fg(Color::Yellow)),
+                        Span::raw(*description),
+                    ]))
+                })
+                .collect();
+            frame.render_widget(
+                List::new(items).block(Block::default().borders(Borders::ALL).title("Help")),
+                help_area,
+            );
+
+            let input = Line::from(vec![
+                Span::raw("> "),
+                Span::raw(view.input),
+                Span::styled(view.completion, Style::default().fg(Color::DarkGray)),
+            ]);
+            frame.render_widget(
+                Paragraph::new(input).block(Block::default().borders(Borders::ALL).title("Input")),
+                input_area,
+            );
+            frame.set_cursor_position(Position::new(
+                input_area.x + 3 + view.input.chars().count() as u16,
+                input_area.y + 1,
+            ));
+
+            if !view.suggestions.is_empty() {
+                let area = dropdown_area(input_area, view.suggestions.len());
+                let items: Vec<ListItem> = view
+                    .suggestions
+                    .iter()
+                    .take(DROPDOWN_LENGTH)
+                    .enumerate()
+                    .map(|(idx, suggestion)| {
+                        let style = if idx == 0 {
+                            Style::default().add_modifier(Modifier::REVERSED)
+                        } else {
+                            Style::default()
+                        };
+                        ListItem::new(*suggestion).style(style)
+                    })
+                    .collect();
+                frame.render_widget(Clear, area);
+                frame.render_widget(
+                    List::new(items).block(Block::default().borders(Borders::ALL)),
+                    area,
+                );
+            }
+        })?;
+        Ok(())
+    }
+}
+
+impl Drop for Ui {
+    fn drop(&mut self) {
+        let _ = execute!(stdout(), LeaveAlternateScreen);
+    }
+}
+
+/// Numbered records, scrolled so the selection (or else the newest record) is visible
+fn records_list<'a>(view: &'a View, height: usize) -> List<'a> {
+    let len = view.records.len();
+    let end = match view.selected {
+        Some(selected) => (selected + height / 2 + 1).clamp(height.min(len), len),
+        None => len,
+    };
+    let start = end.saturating_sub(height);
+    let items: Vec<ListItem> = view.records[start..end]
+        .iter()
+        .enumerate()
+        .map(|(offset, record)| {
+            let idx = start + offset;
+            let style = if Some(idx) == view.selected {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            ListItem::new(format!("{:>4}. {}", idx + 1, record)).style(style)
+        })
+        .collect();
+    List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Records ({})", len)),
+    )
+}
+
+/// Box directly above the input for the autocomplete suggestions
+fn dropdown_area(input_area: Rect, suggestions: usize) -> Rect {
+    let height = suggestions.min(DROPDOWN_LENGTH) as u16 + 2;
+    Rect {
+        x: input_area.x + 2,
+        y: input_area.y.saturating_sub(height),
+        width: (input_area.width / 2).max(20).min(input_area.width),
+        height,
+    }
+}
@@ -0,0 +1,341 @@
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    response::{Html, IntoResponse},
+    routing::{get, get_service},
+    Json, Router,
+};
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, Notify};
+use tower_http::services::ServeFile;
+use tracing::{error, info};
+
+use lib::{
+    annotation::Event as AnnotationEvent, annotation_store::StorageFormat, card::CardData,
+    card_alias::CardAliases, card_query, life_tracker::LifeTracker,
+};
+
+use crate::{describe_record, state_osd_text, CardPriority, RecordKeeper};
+
+const PAGE: &str = include_str!("web.html");
+/// Most card suggestions sent for a search
+const CARD_RESULTS: usize = 10;
+/// Most recent records sent with each update
+const RECORD_RESULTS: usize = 20;
+/// Updates queued for a slow client before it misses some
+const UPDATE_BACKLOG: usize = 32;
+
+/// Action sent by the browser. Times are the video element's `currentTime` when the action
+/// was entered.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Card {
+        time: f64,
+        name: String,
+        pitch: Option<u32>,
+    },
+    Block {
+        time: f64,
+        name: String,
+        pitch: Option<u32>,
+    },
+    Pair {
+        time: f64,
+        name: String,
+        pitch: Option<u32>,
+    },
+    Life {
+        time: f64,
+        player: u8,
+        update: String,
+    },
+    Turn {
+        time: f64,
+    },
+    Win {
+        time: f64,
+        player: u8,
+    },
+    Zoom {
+        time: f64,
+    },
+    Unzoom {
+        time: f64,
+    },
+    Game {
+        time: f64,
+    },
+    Time {
+        time: f64,
+    },
+    Token {
+        time: f64,
+        player: u8,
+        name: String,
+    },
+    Undo,
+    Redo,
+    Quit,
+}
+
+/// State pushed to every browser after each change
+#[derive(Debug, Clone, Serialize)]
+struct Update {
+    status: String,
+    /// Most recent last
+    records: Vec<String>,
+    message: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct CardSuggestion {
+    name: String,
+    pitch: Option<u32>,
+    display: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CardSearch {
+    q: String,
+}
+
+struct WebState {
+    record_keeper: Mutex<RecordKeeper>,
+    card_priority: Mutex<CardPriority>,
+    cards: Vec<CardData>,
+    aliases: CardAliases,
+    updates: broadcast::Sender<Update>,
+    quit: Notify,
+}
+
+impl WebState {
+    fn update(&self, message: Option<String>) -> Update {
+        let record_keeper = self.record_keeper.lock().unwrap();
+        let card_priority = self.card_priority.lock().unwrap();
+        let records = &record_keeper.records;
+        Update {
+            status: state_osd_text(&record_keeper, &card_priority.decks),
+            records: records[records.len().saturating_sub(RECORD_RESULTS)..]
+                .iter()
+                .map(describe_record)
+                .collect(),
+            message,
+        }
+    }
+
+    /// Applies an action, returning the message to show
+    fn apply(&self, message: ClientMessage) -> String {
+        let mut record_keeper = self.record_keeper.lock().unwrap();
+        let (time, event) = match message {
+            ClientMessage::Undo => {
+                return match record_keeper.undo() {
+                    Some(description) => format!("Undid {}", description),
+                    None => "Nothing to undo".to_owned(),
+                }
+            }
+            ClientMessage::Redo => {
+                return match record_keeper.redo() {
+                    Some(description) => format!("Redid {}", description),
+                    None => "Nothing to redo".to_owned(),
+                }
+            }
+            ClientMessage::Quit => {
+                self.quit.notify_one();
+                return "Saving and quitting".to_owned();
+            }
+            ClientMessage::Card { time, name, pitch } => {
+                self.card_priority.lock().unwrap().used(&name, pitch);
+                (time, AnnotationEvent::Card { name, pitch })
+            }
+            ClientMessage::Block { time, name, pitch } => {
+                self.card_priority.lock().unwrap().used(&name, pitch);
+                (time, AnnotationEvent::Block { name, pitch })
+            }
+            ClientMessage::Pair { time, name, pitch } => {
+                self.card_priority.lock().unwrap().used(&name, pitch);
+                (time, AnnotationEvent::Pair { name, pitch })
+            }
+            ClientMessage::Life {
+                time,
+                player,
+                update,
+            } => {
+                if !matches!(player, 1 | 2) || LifeTracker::parse_update(&update).is_err() {
+                    return "Invalid life format.".to_owned();
+                }
+                let (player1, player2) = if player == 1 {
+                    (Some(update), None)
+                } else {
+                    (None, Some(update))
+                };
+                (time, AnnotationEvent::Life { player1, player2 })
+            }
+            ClientMessage::Turn { time } => (time, AnnotationEvent::Turn),
+            ClientMessage::Win { time, player } => (time, AnnotationEvent::Win { player }),
+            ClientMessage::Zoom { time } => (
+                time,
+                AnnotationEvent::Zoom {
+                    name: None,
+                    pitch: None,
+                },
+            ),
+            ClientMessage::Unzoom { time } => (time, AnnotationEvent::Unzoom),
+            ClientMessage::Game { time } => (time, AnnotationEvent::Game),
+            ClientMessage::Time { time } => (time, AnnotationEvent::TimeCalled),
+            ClientMessage::Token { time, player, name } => {
+                if !matches!(player, 1 | 2) || name.trim().is_empty() {
+                    return "Invalid token format.".to_owned();
+                }
+                (time, AnnotationEvent::Token { player, name })
+            }
+        };
+        record_keeper.add_at(RecordKeeper::time_at(time.max(0.0)), event);
+        // The browser shows its own confirmation, so drop the terminal cue
+        record_keeper.cue = None;
+        record_keeper
+            .records
+            .last()
+            .map(describe_record)
+            .unwrap_or_default()
+    }
+}
+
+/// Serves the annotation page on `port` until a browser quits or Ctrl-C, then writes the annotation
+/// to `output_fp`. Every connected browser shares the same records.
+pub async fn serve(
+    port: u16,
+    video_fp: &str,
+    output_fp: &str,
+    format: StorageFormat,
+    cards: &[CardData],
+    mut record_keeper: RecordKeeper,
+    card_priority: CardPriority,
+) -> std::io::Result<()> {
+    // fail before annotating rather than after, without emptying a file that is being edited
+    std::fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(output_fp)?;
+    record_keeper.output = Some((output_fp.to_owned(), format));
+    let (updates, _) = broadcast::channel(UPDATE_BACKLOG);
+    let state = Arc::new(WebState {
+        record_keeper: Mutex::new(record_keeper),
+        card_priority: Mutex::new(card_priority),
+        cards: cards.to_vec(),
+        aliases: CardAliases::init(),
+        updates,
+        quit: Notify::new(),
+    });
+
+    let app = Router::new()
+        .route("/", get(|| async { Html(PAGE) }))
+        .route("/video", get_service(ServeFile::new(video_fp)))
+        .route("/cards", get(search_cards))
+        .route("/ws", get(connect))
+        .with_state(state.clone());
+
+    let listener = tokio::net::TcpListener::bind(("0.0.0.0", port)).await?;
+    info!("Annotating at http://localhost:{}", port);
+    info!("Quit from the page or with Ctrl-C to save");
+    // Open sockets would hold up a graceful shutdown, so the server is just dropped
+    tokio::select! {
+        result = axum::serve(listener, app) => result?,
+        _ = state.quit.notified() => {}
+        _ = tokio::signal::ctrl_c() => {}
+    }
+
+    let result = state.record_keeper.lock().unwrap().finalize();
+    if let Err(err) = result {
+        error!("Could not save annotations: {}", err);
+    }
+    Ok(())
+}
+
+/// Cards matching the search, in the same order as the terminal suggestions
+async fn search_cards(
+    State(state): State<Arc<WebState>>,
+    Query(search): Query<CardSearch>,
+) -> Json<Vec<CardSuggestion>> {
+    let turn_player = state.record_keeper.lock().unwrap().state().turn_player;
+    let card_priority = state.card_priority.lock().unwrap();
+    let aliased = state.aliases.names_for_prefix(&search.q);
+    let mut matches: Vec<(u32, &CardData)> = state
+        .cards
+        .iter()
+        .filter(|card| {
+            card_query::matches(card, &search.q) || aliased.contains(&card.name.as_str())
+        })
+        .filter_map(|card| Some((card_priority.rank(card, turn_player)?, card)))
+        .collect();
+    matches.sort_by_key(|(rank, _)| *rank);
+    Json(
+        matches
+            .into_iter()
+            .take(CARD_RESULTS)
+            .map(|(_, card)| CardSuggestion {
+                name: card.name.clone(),
+                pitch: card.pitch,
+                display: card.display.clone(),
+            })
+            .collect(),
+    )
+}
+
+async fn connect(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<WebState>>,
+) -> impl IntoResponse {
+    ws.on_upgrade(|socket| handle_socket(socket, state))
+}
+
+async fn handle_socket(socket: WebSocket, state: Arc<WebState>) {
+    let (mut sender, mut receiver) = socket.split();
+    let mut updates = state.updates.subscribe();
+    if send_update(&mut sender, &state.update(None)).await.is_err() {
+        return;
+    }
+
+    // Forward every change, including ones made from other browsers
+    let mut forward = tokio::spawn(async move {
+        while let Ok(update) = updates.recv().await {
+            if send_update(&mut sender, &update).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let receive_state = state.clone();
+    let mut receive = tokio::spawn(async move {
+        while let Some(Ok(message)) = receiver.next().await {
+            let Message::Text(text) = message else {
+                continue;
+            };
+            let message = match serde_json::from_str::<ClientMessage>(&text) {
+                Ok(message) => receive_state.apply(message),
+                Err(err) => format!("Unknown action: {}", err),
+            };
+            let _ = receive_state
+                .updates
+                .send(receive_state.update(Some(message)));
+        }
+    });
+
+    tokio::select! {
+        _ = &mut forward => receive.abort(),
+        _ = &mut receive => forward.abort(),
+    }
+}
+
+async fn send_update(
+    sender: &mut futures::stream::SplitSink<WebSocket, Message>,
+    update: &Update,
+) -> Result<(), axum::Error> {
+    let text = serde_json::to_string(update).expect("Update is always serializable");
+    sender.send(Message::Text(text.into())).await
+}
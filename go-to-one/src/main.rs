@@ -0,0 +1,279 @@
+use clap::{Parser, Subcommand};
+use std::{fs, path::Path, process::ExitCode};
+use tracing::{error, info};
+
+use lib::{
+    annotation::Event,
+    annotation_store,
+    background::{parse_palette, Palette},
+    card::CardDB,
+    card_alias::CardAliases,
+    card_update,
+    config::Config,
+    hero_art::prepare_hero,
+    image::FullArtHeroManager,
+    logging::LogArgs,
+    starting_soon::StartingSoon,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Every go-to-one tool in one binary
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+
+    #[command(flatten)]
+    log: LogArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Annotate a match video
+    Annotate(annotate::Cli),
+    /// Render the overlay onto a video
+    Render(overlay::render::Cli),
+    /// Render an intro video
+    Intro(intro::Cli),
+    /// Check annotation files can be read and only name known cards and heroes
+    Validate {
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+    /// Download the latest card data and rebuild the card store
+    UpdateDb,
+    /// Render several matches, one per line of a tab separated file: video, annotation and
+    /// optionally the output file. Blank lines and lines starting with # are skipped.
+    Batch { jobs: String },
+    /// Render a "Starting Soon" screen with both heroes and a countdown, to show before a stream
+    StartingSoon {
+        #[arg(long)]
+        hero1: String,
+        #[arg(long)]
+        hero2: String,
+        /// Minutes to count down from. The video is this long.
+        #[arg(long, default_value_t = 5.0)]
+        minutes: f64,
+        /// Event logo to show instead of the go-to-one logo
+        #[arg(long)]
+        logo: Option<String>,
+        #[arg(long, default_value = "Starting Soon")]
+        title: String,
+        #[arg(long, default_value_t = 30.0)]
+        fps: f64,
+        /// Colors of the background as "top,bottom,lines" in hex RGB
+        #[arg(long, value_parser = parse_palette, default_value_t = Palette::default())]
+        background_colors: Palette,
+        #[arg(short, long)]
+        output_file: String,
+    },
+    /// Crop, scale and re-encode raw full art hero animations for the overlay, and add them to
+    /// the full art hero map
+    PrepareHeroes {
+        /// Hero name and raw animation, e.g. "Kayo, Armed and Dangerous=raw/kayo.mov"
+        #[arg(required = true)]
+        heroes: Vec<String>,
+        /// Width to scale the animations to, keeping their aspect ratio
+        #[arg(long)]
+        width: Option<i32>,
+        /// Frame rate to re-encode at. Should match the videos being rendered.
+        #[arg(long)]
+        fps: Option<f64>,
+    },
+}
+
+/// Prints every problem found in the file and returns how many there were
+fn validate(fp: &str, card_db: &CardDB, aliases: &CardAliases) -> usize {
+    let file = match annotation_store::read_from_path(fp) {
+        Ok(file) => file,
+        Err(err) => {
+            println!("{}: {}", fp, err);
+            return 1;
+        }
+    };
+    let known = |name: &str, pitch: Option<u32>| {
+        card_db.find(name, pitch).is_some()
+            || card_db.find(aliases.canonical(name), pitch).is_some()
+    };
+
+    let mut problems = 0;
+    for hero in [&file.header.setup.hero1, &file.header.setup.hero2] {
+        if !card_db.heroes().iter().any(|card| &card.name == hero) {
+            println!("{}: unknown hero {}", fp, hero);
+            problems += 1;
+        }
+    }
+    for annotation in file.events.iter() {
+        if let Event::Card { name, pitch }
+        | Event::Block { name, pitch }
+        | Event::Pair { name, pitch } = &annotation.event
+        {
+            if !known(name, *pitch) {
+                println!(
+                    "{}: unknown card {} ({:?}) at {:.1}s",
+                    fp,
+                    name,
+                    pitch,
+                    annotation.time.as_f64()
+                );
+                problems += 1;
+            }
+        }
+    }
+    if problems == 0 {
+        println!("{}: {} events ok", fp, file.events.len());
+    }
+    problems
+}
+
+fn batch(jobs_fp: &str) -> Result<bool> {
+    let jobs = fs::read_to_string(jobs_fp)?;
+    let mut failed = Vec::new();
+    for (line_no, line) in jobs.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let columns: Vec<&str> = line.split('\t').map(|c| c.trim()).collect();
+        let (video_fp, annotation_fp) = match columns[..] {
+            [video_fp, annotation_fp, ..] => (video_fp, annotation_fp),
+            _ => {
+                return Err(format!("Line {} needs a video and an annotation", line_no + 1).into())
+            }
+        };
+        let output_fp = match columns.get(2) {
+            Some(output_fp) => output_fp.to_string(),
+            None => {
+                let render_dir = &Config::get().render_dir;
+                fs::create_dir_all(render_dir)?;
+                let name = Path::new(annotation_fp)
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .unwrap_or("render");
+                format!("{}/{}.mp4", render_dir, name)
+            }
+        };
+
+        info!("Rendering {}", output_fp);
+        // Each job renders in its own process, so one that panics doesn't stop the rest
+        let status = std::process::Command::new(std::env::current_exe()?)
+            .arg("render")
+            .args(["--video-file", video_fp])
+            .args(["--card-file", annotation_fp])
+            .args(["--output-file", &output_fp])
+            .status()
+            .map_err(|e| format!("Couldn't render {}: {}", output_fp, e))?;
+        if !status.success() {
+            failed.push(output_fp);
+        }
+    }
+    for output_fp in failed.iter() {
+        error!("Failed to render {}", output_fp);
+    }
+    Ok(failed.is_empty())
+}
+
+/// Only annotate and update-db need a runtime. The renderers download with blocking requests,
+/// which can't be made from inside one.
+fn block_on<F: std::future::Future>(future: F) -> Result<F::Output> {
+    Ok(tokio::runtime::Runtime::new()?.block_on(future))
+}
+
+fn run(command: Command) -> Result<bool> {
+    match command {
+        Command::Annotate(args) => {
+            block_on(annotate::run(args))??;
+            Ok(true)
+        }
+        Command::Render(args) => {
+            overlay::render::run(args, Some("render"))?;
+            Ok(true)
+        }
+        Command::Intro(args) => {
+            intro::run(args)?;
+            Ok(true)
+        }
+        Command::Validate { files } => {
+            let card_db = CardDB::init();
+            let aliases = CardAliases::init();
+            let problems: usize = files
+                .iter()
+                .map(|fp| validate(fp, &card_db, &aliases))
+                .sum();
+            Ok(problems == 0)
+        }
+        Command::UpdateDb => {
+            info!("Updating card db...");
+            if block_on(card_update::update_card_db())?? {
+                info!("Card db updated!");
+            } else {
+                info!("Card db already up to date!");
+            }
+            Ok(true)
+        }
+        Command::Batch { jobs } => batch(&jobs),
+        Command::StartingSoon {
+            hero1,
+            hero2,
+            minutes,
+            logo,
+            title,
+            fps,
+            background_colors,
+            output_file,
+        } => {
+            let mut screen = StartingSoon::build([&hero1, &hero2], minutes * 60.0)?;
+            if let Some(logo) = logo {
+                screen.logo_fp = logo;
+            }
+            screen.title = title;
+            screen.palette = background_colors;
+            info!("Rendering {}...", output_file);
+            screen.render(&output_file, fps)?;
+            Ok(true)
+        }
+        Command::PrepareHeroes { heroes, width, fps } => {
+            let mut manager = FullArtHeroManager::new();
+            for hero in heroes.iter() {
+                let (name, source_fp) = hero
+                    .split_once('=')
+                    .ok_or_else(|| format!("Expected HERO=FILE, got {}", hero))?;
+                info!("Preparing {}...", name);
+                let file = prepare_hero(&mut manager, name, source_fp, width, fps)?;
+                // Saved as each is done, so a failure doesn't lose the ones before it
+                manager.save()?;
+                info!("{} is {}", name, file);
+            }
+            Ok(true)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = Cli::parse();
+    // Render's --debug changes its logging
+    let log = match &args.command {
+        Command::Annotate(args) => args.log.clone(),
+        Command::Render(args) => args.log_args(),
+        Command::Intro(args) => args.log.clone(),
+        _ => args.log.clone(),
+    };
+    if let Err(err) = log.init() {
+        eprintln!("Couldn't set up logging: {}", err);
+        return ExitCode::FAILURE;
+    }
+    if let Err(err) = Config::init() {
+        eprintln!("{}", err);
+        return ExitCode::FAILURE;
+    }
+    match run(args.command) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
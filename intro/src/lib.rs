@@ -0,0 +1,311 @@
+use std::{collections::HashMap, fs::File, process::Command};
+
+use clap::Parser;
+use lib::{
+    assets,
+    audio_bed::AudioBed,
+    bracket::Bracket,
+    image::{load_image, load_image_url, resize_preserve_ratio},
+    intro::{display_image, overlay_video_with_fade},
+    logging::LogArgs,
+    movement::place_umat,
+    text::center_text_at_rect,
+};
+use opencv::{
+    core::{tempfile, Rect, Scalar, Size, UMat, UMatTraitConst, CV_8UC3},
+    imgproc::{resize, FONT_HERSHEY_SIMPLEX},
+    videoio::{
+        self, VideoCapture, VideoCaptureTraitConst, VideoWriter, VideoWriterTrait,
+        CAP_PROP_FRAME_HEIGHT, CAP_PROP_FRAME_WIDTH,
+    },
+};
+
+use crate::template::{SlideContent, Template, Transition};
+
+mod template;
+
+// Duration constants
+const FADE_IN_DURATION: f64 = 2.0;
+const FADE_OUT_DURATION: f64 = 2.0;
+
+// File constants
+const HERO_FILE: &'static str = "data/hero_link.json";
+const IMAGE_FILE: &'static str = "data/asset_link.json";
+const EXUDE_FILE: &'static str = "data/exude_full_1239a0sf123.original.jpg";
+const FIRE_OVERLAY: &'static str = "data/199621-910995780.mp4";
+
+// Text slides
+const TEXT_FONT_FACE: i32 = FONT_HERSHEY_SIMPLEX;
+const TEXT_FONT_SCALE: f64 = 3.0;
+const TEXT_FONT_THICKNESS: i32 = 6;
+const TEXT_FONT_BUFFER: i32 = 20;
+const TEXT_COLOR: Scalar = Scalar::new(255.0, 255.0, 255.0, 0.0);
+/// Height of each line, as a fraction of the frame
+const TEXT_LINE_HEIGHT_RATIO: f64 = 1.0 / 8.0;
+/// Text slide backgrounds are darkened so the text stands out
+const TEXT_BACKGROUND_BRIGHTNESS: f64 = 0.5;
+
+#[derive(Parser)]
+pub struct Cli {
+    #[arg(short, long)]
+    video_file: String,
+
+    #[arg(short, long)]
+    output_file: String,
+
+    #[arg(long)]
+    hero1: String,
+
+    #[arg(long)]
+    hero2: String,
+
+    #[arg(long)]
+    images: Vec<String>,
+
+    /// JSON list of slides to show instead of the images then the heroes, e.g.
+    /// {"slides": [{"type": "text", "text": "Top 8", "transition": "cut"}, {"type": "heroes"}]}
+    #[arg(long, conflicts_with = "images")]
+    template: Option<String>,
+
+    /// Music played under the intro, fading out at the end
+    #[arg(long)]
+    music: Option<String>,
+
+    /// Volume of the --music, where 1 leaves it as it is
+    #[arg(long, default_value_t = 1.0, requires = "music")]
+    music_volume: f64,
+
+    #[command(flatten)]
+    pub log: LogArgs,
+}
+
+fn load_map(fp: &str) -> HashMap<String, String> {
+    let mut map: HashMap<String, String> = HashMap::new();
+    let file = File::open(&fp).expect(&format!("Could not find {}", fp));
+    let hero_json: serde_json::Value =
+        serde_json::from_reader(file).expect("Image map file improperly formatted.");
+
+    for (key, value) in hero_json.as_object().unwrap().iter() {
+        map.insert(
+            key.to_owned(),
+            value
+                .as_str()
+                .expect("Value is not valid string")
+                .to_string(),
+        );
+    }
+
+    map
+}
+
+/// Loads an image by its key in the asset map, or from a URL or local file
+fn load_asset(
+    source: &str,
+    image_map: &HashMap<String, String>,
+) -> Result<UMat, Box<dyn std::error::Error>> {
+    if let Some(url) = image_map.get(source) {
+        return load_image_url(url);
+    }
+    if source.starts_with("http://") || source.starts_with("https://") {
+        return load_image_url(source);
+    }
+    let img = load_image(source)?;
+    if img.empty() {
+        return Err(format!("could not find asset {}", source).into());
+    }
+    Ok(img)
+}
+
+/// `background` stretched to `size` and darkened for text to go over it
+fn darkened_background(background: &UMat, size: Size) -> Result<UMat, Box<dyn std::error::Error>> {
+    let mut stretched = UMat::new_def();
+    resize(background, &mut stretched, size, 0., 0., 0)?;
+    let mut frame = UMat::new_def();
+    stretched.convert_to(&mut frame, -1, TEXT_BACKGROUND_BRIGHTNESS, 0.0)?;
+    Ok(frame)
+}
+
+/// `text` over `background` stretched to `size`, one line of text under another
+fn text_frame(
+    text: &str,
+    background: &UMat,
+    size: Size,
+) -> Result<UMat, Box<dyn std::error::Error>> {
+    let mut frame = darkened_background(background, size)?;
+
+    let lines: Vec<&str> = text.lines().collect();
+    let line_height = (size.height as f64 * TEXT_LINE_HEIGHT_RATIO) as i32;
+    let top = (size.height - line_height * lines.len() as i32).div_euclid(2);
+    for (i, line) in lines.iter().enumerate() {
+        center_text_at_rect(
+            &mut frame,
+            line,
+            TEXT_FONT_FACE,
+            TEXT_FONT_SCALE,
+            TEXT_COLOR,
+            TEXT_FONT_THICKNESS,
+            Rect::new(
+                size.width.div_euclid(10),
+                top + i as i32 * line_height,
+                4 * size.width.div_euclid(5),
+                line_height,
+            ),
+            TEXT_FONT_BUFFER,
+        )?;
+    }
+    Ok(frame)
+}
+
+/// Renders the intro. Logging is set up by the caller.
+pub fn run(args: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    // Get stats from associated video
+    let video = VideoCapture::from_file(&args.video_file, videoio::CAP_ANY)?;
+    let width = video.get(CAP_PROP_FRAME_WIDTH)? as i32;
+    let height = video.get(CAP_PROP_FRAME_HEIGHT)? as i32;
+    let fps = video.get(videoio::CAP_PROP_FPS)?;
+
+    let template = match &args.template {
+        Some(fp) => Template::load(fp)?,
+        None => Template::from_images(&args.images),
+    };
+    let music = match &args.music {
+        Some(fp) => {
+            let duration = template.slides.iter().map(|slide| slide.duration()).sum();
+            Some(AudioBed::build(fp, duration, args.music_volume)?)
+        }
+        None => None,
+    };
+
+    // Start video writer. With music, the video is written silent then muxed with it.
+    let video_fp = match music {
+        Some(_) => tempfile(".mp4")?,
+        None => args.output_file.clone(),
+    };
+    let mut out = VideoWriter::new(
+        &video_fp,
+        VideoWriter::fourcc('m', 'p', '4', 'v').unwrap(),
+        fps,
+        Size::new(width, height),
+        true,
+    )?;
+
+    // Init url maps
+    let hero_map = load_map(&assets::path(HERO_FILE));
+    let image_map = load_map(&assets::path(IMAGE_FILE));
+
+    // Load hero images
+    let mut hero1_img = load_image_url(
+        &hero_map
+            .get(&args.hero1)
+            .expect(&format!("could not find key {}", &args.hero1)),
+    )?;
+    let mut hero2_img = load_image_url(
+        &hero_map
+            .get(&args.hero2)
+            .expect(&format!("could not find key {}", &args.hero1)),
+    )?;
+    hero1_img = resize_preserve_ratio(&hero1_img, &Size::new(width.div_euclid(2), height))?;
+    hero2_img = resize_preserve_ratio(&hero2_img, &Size::new(width.div_euclid(2), height))?;
+
+    // Create hero frame
+    let mut hero_frame = UMat::new_size_def(Size::new(width, height), CV_8UC3)?;
+    place_umat(
+        &hero1_img,
+        &mut hero_frame,
+        Rect::new(0, 0, width.div_euclid(2), height),
+    )?;
+    place_umat(
+        &hero2_img,
+        &mut hero_frame,
+        Rect::new(width.div_euclid(2), 0, width.div_euclid(2), height),
+    )?;
+
+    for slide in template.slides.iter() {
+        // Calculate frame count for the slide
+        let (fade_in_count, fade_out_count) = match slide.transition {
+            Transition::Fade => (
+                (FADE_IN_DURATION * fps) as u32,
+                (FADE_OUT_DURATION * fps) as u32,
+            ),
+            Transition::Cut => (0, 0),
+        };
+        let display_count =
+            ((slide.duration() * fps) as u32).saturating_sub(fade_in_count + fade_out_count);
+
+        match &slide.content {
+            SlideContent::Image { image } => {
+                let img_frame = load_asset(image, &image_map)?;
+                let img_frame = resize_preserve_ratio(&img_frame, &Size::new(width, height))?;
+                display_image(
+                    &mut out,
+                    &img_frame,
+                    fade_in_count,
+                    display_count,
+                    fade_out_count,
+                )?;
+            }
+            SlideContent::Heroes { overlay } => {
+                let overlay = overlay
+                    .clone()
+                    .unwrap_or_else(|| assets::path(FIRE_OVERLAY));
+                overlay_video_with_fade(
+                    &mut out,
+                    &overlay,
+                    &hero_frame,
+                    10,
+                    fade_in_count,
+                    display_count,
+                    fade_out_count,
+                )?;
+            }
+            SlideContent::Text { text, background } => {
+                let background = background
+                    .clone()
+                    .unwrap_or_else(|| assets::path(EXUDE_FILE));
+                let background = load_asset(&background, &image_map)?;
+                let text_frame = text_frame(text, &background, Size::new(width, height))?;
+                display_image(
+                    &mut out,
+                    &text_frame,
+                    fade_in_count,
+                    display_count,
+                    fade_out_count,
+                )?;
+            }
+            SlideContent::Bracket {
+                bracket,
+                background,
+            } => {
+                let background = background
+                    .clone()
+                    .unwrap_or_else(|| assets::path(EXUDE_FILE));
+                let background = load_asset(&background, &image_map)?;
+                let mut bracket_frame = darkened_background(&background, Size::new(width, height))?;
+                Bracket::load(bracket)?.draw(&mut bracket_frame)?;
+                display_image(
+                    &mut out,
+                    &bracket_frame,
+                    fade_in_count,
+                    display_count,
+                    fade_out_count,
+                )?;
+            }
+        }
+    }
+    out.release()?;
+
+    if let Some(music) = music {
+        let status = Command::new("ffmpeg")
+            .args(["-i", &video_fp])
+            .args(music.ffmpeg_args(1, None))
+            .args(["-shortest", &args.output_file, "-y"])
+            .output()?
+            .status;
+        let _ = std::fs::remove_file(&video_fp);
+        if !status.success() {
+            return Err("Couldn't add the music with ffmpeg".into());
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use std::{fs::File, io::BufReader};
+
+use serde::Deserialize;
+
+// Default durations, in seconds
+const IMAGE_DURATION: f64 = 5.0;
+const HERO_DURATION: f64 = 8.0;
+const TEXT_DURATION: f64 = 5.0;
+const BRACKET_DURATION: f64 = 8.0;
+
+/// How a slide comes in and goes out
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Transition {
+    /// From and to black
+    #[default]
+    Fade,
+    Cut,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SlideContent {
+    /// A key of the asset map, a URL or a local file, e.g. a sponsor's logo
+    Image { image: String },
+    /// Both heroes side by side under a looping overlay video
+    Heroes {
+        /// Defaults to the fire overlay
+        overlay: Option<String>,
+    },
+    /// Lines of text, e.g. the event and round
+    Text {
+        text: String,
+        /// Image behind the text, as for `Image`. Defaults to the exude art.
+        background: Option<String>,
+    },
+    /// A top cut bracket or Swiss standings, e.g. between rounds
+    Bracket {
+        /// JSON file or URL of the bracket or standings
+        bracket: String,
+        /// Image behind the bracket, as for `Text`
+        background: Option<String>,
+    },
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Slide {
+    #[serde(flatten)]
+    pub content: SlideContent,
+    /// Seconds on screen, including the transitions. Defaults by the kind of slide.
+    pub duration: Option<f64>,
+    #[serde(default)]
+    pub transition: Transition,
+}
+
+impl Slide {
+    pub fn duration(&self) -> f64 {
+        self.duration.unwrap_or(match self.content {
+            SlideContent::Image { .. } => IMAGE_DURATION,
+            SlideContent::Heroes { .. } => HERO_DURATION,
+            SlideContent::Text { .. } => TEXT_DURATION,
+            SlideContent::Bracket { .. } => BRACKET_DURATION,
+        })
+    }
+}
+
+/// The slides of an intro, in order. Read from JSON like
+/// `{"slides": [{"type": "image", "image": "sponsor"}, {"type": "heroes", "duration": 6}]}`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Template {
+    pub slides: Vec<Slide>,
+}
+
+impl Template {
+    pub fn load(fp: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = File::open(fp).map_err(|e| format!("Couldn't open template {}: {}", fp, e))?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    /// The intro without a template: `images` faded in and out in turn, then the heroes
+    pub fn from_images(images: &[String]) -> Self {
+        let slide = |content| Slide {
+            content,
+            duration: None,
+            transition: Transition::Fade,
+        };
+        let mut slides: Vec<Slide> = images
+            .iter()
+            .map(|image| {
+                slide(SlideContent::Image {
+                    image: image.clone(),
+                })
+            })
+            .collect();
+        slides.push(slide(SlideContent::Heroes { overlay: None }));
+        Template { slides }
+    }
+}
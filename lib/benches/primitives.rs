@@ -0,0 +1,105 @@
+//! Compositing primitives run on every frame of the overlay
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use lib::{
+    fade::{remove_color, remove_white_corners},
+    relative_roi::RelativeRoi,
+    rotate::rotate_image,
+    text::center_text_at_rel,
+};
+use opencv::{
+    core::{Rect, Scalar, Size, UMat, CV_8UC3},
+    imgproc::{self, FONT_HERSHEY_SCRIPT_COMPLEX, LINE_8},
+};
+
+const FRAME_SIZE: Size = Size::new(1920, 1080);
+const CARD_SIZE: Size = Size::new(450, 628);
+const GREEN: Scalar = Scalar::new(0.0, 255.0, 0.0, 0.0);
+const WHITE: Scalar = Scalar::new(255.0, 255.0, 255.0, 0.0);
+
+fn filled(size: Size, color: Scalar) -> UMat {
+    UMat::new_size_with_default_def(size, CV_8UC3, color).unwrap()
+}
+
+/// A white card with a colored inside, like a card image with its white corners
+fn card() -> UMat {
+    let mut card = filled(CARD_SIZE, WHITE);
+    imgproc::rectangle(
+        &mut card,
+        Rect::new(20, 20, CARD_SIZE.width - 40, CARD_SIZE.height - 40),
+        Scalar::new(40.0, 90.0, 160.0, 0.0),
+        -1,
+        LINE_8,
+        0,
+    )
+    .unwrap();
+    card
+}
+
+fn remove_color_benchmark(c: &mut Criterion) {
+    let background = filled(CARD_SIZE, Scalar::new(30.0, 30.0, 30.0, 0.0));
+    // A card turned partway, with green either side of it
+    let mut foreground = filled(CARD_SIZE, GREEN);
+    imgproc::rectangle(
+        &mut foreground,
+        Rect::new(100, 0, CARD_SIZE.width - 200, CARD_SIZE.height),
+        WHITE,
+        -1,
+        LINE_8,
+        0,
+    )
+    .unwrap();
+    c.bench_function("remove_color", |b| {
+        b.iter(|| remove_color(&background, &foreground, &GREEN).unwrap())
+    });
+}
+
+fn remove_white_corners_benchmark(c: &mut Criterion) {
+    let background = filled(CARD_SIZE, GREEN);
+    let card = card();
+    c.bench_function("remove_white_corners", |b| {
+        b.iter(|| remove_white_corners(&background, &card).unwrap())
+    });
+}
+
+fn rotate_image_benchmark(c: &mut Criterion) {
+    let card = card();
+    let mut group = c.benchmark_group("rotate_image");
+    for percentage in [0.0, 0.5, 0.9] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(percentage),
+            &percentage,
+            |b, &percentage| b.iter(|| rotate_image(&card, percentage, true).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+fn center_text_at_rel_benchmark(c: &mut Criterion) {
+    let rel_roi = RelativeRoi::build_def(0.47, 0.0, 0.13, 0.125, None, None).unwrap();
+    let mut frame = filled(FRAME_SIZE, Scalar::all(0.0));
+    c.bench_function("center_text_at_rel", |b| {
+        b.iter(|| {
+            center_text_at_rel(
+                &mut frame,
+                "40",
+                FONT_HERSHEY_SCRIPT_COMPLEX,
+                10.0,
+                WHITE,
+                10,
+                rel_roi,
+                20,
+            )
+            .unwrap()
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    remove_color_benchmark,
+    remove_white_corners_benchmark,
+    rotate_image_benchmark,
+    center_text_at_rel_benchmark
+);
+criterion_main!(benches);
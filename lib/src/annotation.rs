@@ -0,0 +1,1154 @@
+use std::{
+    cmp::Ordering,
+    io::{Read, Write},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::time_tick::TimeTick;
+
+/// Columns of the version 1 annotation file, in order
+const V1_HEADERS: [&str; 7] = [
+    "sec",
+    "milli",
+    "name",
+    "pitch",
+    "player1_life",
+    "player2_life",
+    "update_type",
+];
+
+/// First line of a version 2 file, followed by the version number
+const VERSION_TAG: &str = "#annotation";
+/// Prefix of the header lines in a version 2 file
+const HEADER_PREFIX: char = '#';
+
+// Header keys
+const FIRST: &str = "first";
+const FORMAT: &str = "format";
+const EVENT_NAME: &str = "event";
+const ROUND: &str = "round";
+const VIDEO_OFFSET: &str = "video_offset";
+// Player metadata keys, prefixed with the player, e.g. "player1_team"
+const TEAM: &str = "team";
+const COUNTRY: &str = "country";
+const PRONOUNS: &str = "pronouns";
+const SEED: &str = "seed";
+
+// Update types
+const PLAYER1: &str = "player1";
+const PLAYER2: &str = "player2";
+const HERO1: &str = "hero1";
+const HERO2: &str = "hero2";
+const CARD: &str = "card";
+const BLOCK: &str = "block";
+const PAIR: &str = "pair";
+const LIFE: &str = "life";
+const TURN: &str = "turn";
+const WIN1: &str = "win1";
+const WIN2: &str = "win2";
+const ZOOM: &str = "zoom";
+const UNZOOM: &str = "unzoom";
+const GAME: &str = "game";
+const LOWER_THIRD1: &str = "lowerthird1";
+const LOWER_THIRD2: &str = "lowerthird2";
+const NOTE: &str = "note";
+const TIME: &str = "time";
+const TOKEN1: &str = "token1";
+const TOKEN2: &str = "token2";
+
+#[derive(Debug)]
+pub enum AnnotationError {
+    Io(std::io::Error),
+    Csv(csv::Error),
+    UnsupportedVersion(String),
+    UnknownUpdateType { line: usize, update_type: String },
+    MissingField { line: usize, field: &'static str },
+    InvalidHeader { line: usize, reason: String },
+    Json(serde_json::Error),
+    Sqlite(rusqlite::Error),
+    MissingSetup,
+}
+
+impl std::error::Error for AnnotationError {}
+
+impl std::fmt::Display for AnnotationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnnotationError::Io(err) => write!(f, "Could not read annotation file: {}", err),
+            AnnotationError::Csv(err) => write!(f, "Invalid annotation row: {}", err),
+            AnnotationError::UnsupportedVersion(header) => {
+                write!(f, "Unrecognized annotation header '{}'", header)
+            }
+            AnnotationError::UnknownUpdateType { line, update_type } => {
+                write!(f, "Unknown update type '{}' on line {}", update_type, line)
+            }
+            AnnotationError::MissingField { line, field } => {
+                write!(f, "Line {} is missing the `{}` field", line, field)
+            }
+            AnnotationError::InvalidHeader { line, reason } => {
+                write!(f, "Invalid header on line {}: {}", line, reason)
+            }
+            AnnotationError::Json(err) => write!(f, "Invalid JSON annotation: {}", err),
+            AnnotationError::Sqlite(err) => write!(f, "Invalid SQLite annotation: {}", err),
+            AnnotationError::MissingSetup => {
+                write!(f, "Annotation is missing the player and hero rows")
+            }
+        }
+    }
+}
+
+impl From<std::io::Error> for AnnotationError {
+    fn from(value: std::io::Error) -> Self {
+        AnnotationError::Io(value)
+    }
+}
+
+impl From<csv::Error> for AnnotationError {
+    fn from(value: csv::Error) -> Self {
+        AnnotationError::Csv(value)
+    }
+}
+
+impl From<serde_json::Error> for AnnotationError {
+    fn from(value: serde_json::Error) -> Self {
+        AnnotationError::Json(value)
+    }
+}
+
+impl From<rusqlite::Error> for AnnotationError {
+    fn from(value: rusqlite::Error) -> Self {
+        AnnotationError::Sqlite(value)
+    }
+}
+
+pub(crate) type Result<T> = std::result::Result<T, AnnotationError>;
+
+/// Annotation file formats that can be read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    /// Tab separated rows with players and heroes encoded as the first four rows
+    V1,
+    /// Header block with the match metadata, followed by the version 1 columns
+    V2,
+}
+
+impl Version {
+    /// Detects the version from the first line of the file
+    pub fn detect(header: &str) -> Result<Self> {
+        if let Some(version) = header.strip_prefix(VERSION_TAG) {
+            return match version.trim() {
+                "2" => Ok(Version::V2),
+                _ => Err(AnnotationError::UnsupportedVersion(header.trim_end().to_owned())),
+            };
+        }
+        let columns: Vec<&str> = header.trim_end().split('\t').map(|v| v.trim()).collect();
+        if columns == V1_HEADERS {
+            Ok(Version::V1)
+        } else {
+            Err(AnnotationError::UnsupportedVersion(header.trim_end().to_owned()))
+        }
+    }
+}
+
+/// Game format being played, and the defaults that go with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Format {
+    ClassicConstructed,
+    Blitz,
+    UltimatePitFight,
+}
+
+impl Format {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Format::ClassicConstructed => "CC",
+            Format::Blitz => "Blitz",
+            Format::UltimatePitFight => "UPF",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.trim().to_lowercase().as_str() {
+            "cc" | "classic constructed" => Some(Format::ClassicConstructed),
+            "blitz" => Some(Format::Blitz),
+            "upf" | "ultimate pit fight" => Some(Format::UltimatePitFight),
+            _ => None,
+        }
+    }
+
+    /// Full name, for labels
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::ClassicConstructed => "Classic Constructed",
+            Format::Blitz => "Blitz",
+            Format::UltimatePitFight => "Ultimate Pit Fight",
+        }
+    }
+
+    /// Whether the format is played with young heroes rather than adult ones
+    pub fn young_heroes(&self) -> bool {
+        match self {
+            Format::ClassicConstructed => false,
+            Format::Blitz | Format::UltimatePitFight => true,
+        }
+    }
+
+    /// Format a hero is played in when none is given
+    pub fn for_hero(young: bool) -> Self {
+        if young {
+            Format::Blitz
+        } else {
+            Format::ClassicConstructed
+        }
+    }
+
+    /// Starting life for a hero whose card doesn't give one
+    pub fn default_life(&self) -> u32 {
+        if self.young_heroes() {
+            20
+        } else {
+            40
+        }
+    }
+
+    /// Intellect for a hero whose card doesn't give one
+    pub fn default_intellect(&self) -> u32 {
+        4
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Format::from_code(s).ok_or_else(|| format!("Unknown game format '{}'", s.trim()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    Player1 {
+        name: String,
+    },
+    Player2 {
+        name: String,
+    },
+    /// `life` is the hero's starting life
+    Hero1 {
+        name: String,
+        life: String,
+    },
+    Hero2 {
+        name: String,
+        life: String,
+    },
+    Card {
+        name: String,
+        pitch: Option<u32>,
+    },
+    /// Card defending against the most recent card
+    Block {
+        name: String,
+        pitch: Option<u32>,
+    },
+    /// Card shown beside the card before it, e.g. an attack and its defense or two combo
+    /// pieces. Pairing is only how the cards are displayed, so it isn't counted as a play.
+    Pair {
+        name: String,
+        pitch: Option<u32>,
+    },
+    /// Life updates in the `LifeTracker` format, e.g. "-3"
+    Life {
+        player1: Option<String>,
+        player2: Option<String>,
+    },
+    Turn,
+    Win {
+        player: u8,
+    },
+    /// Zooms in on `name`, or on whatever card is displayed when `name` is `None`
+    Zoom {
+        name: Option<String>,
+        pitch: Option<u32>,
+    },
+    /// Ends the current zoom
+    Unzoom,
+    /// Ends the current game and starts the next one of the match
+    Game,
+    /// Shows the player's name plate
+    LowerThird {
+        player: u8,
+    },
+    /// Shows `text` on a plate, e.g. a caster's remark
+    Note {
+        text: String,
+    },
+    /// Time is called on the round, so the turns that follow are extra turns
+    TimeCalled,
+    /// Token, counter or other game object that isn't a card landing on the player's hero, e.g.
+    /// "Quicken" or "Aim counter"
+    Token {
+        player: u8,
+        name: String,
+    },
+}
+
+impl Event {
+    pub fn update_type(&self) -> &'static str {
+        match self {
+            Event::Player1 { .. } => PLAYER1,
+            Event::Player2 { .. } => PLAYER2,
+            Event::Hero1 { .. } => HERO1,
+            Event::Hero2 { .. } => HERO2,
+            Event::Card { .. } => CARD,
+            Event::Block { .. } => BLOCK,
+            Event::Pair { .. } => PAIR,
+            Event::Life { .. } => LIFE,
+            Event::Turn => TURN,
+            Event::Win { player: 1 } => WIN1,
+            Event::Win { .. } => WIN2,
+            Event::Zoom { .. } => ZOOM,
+            Event::Unzoom => UNZOOM,
+            Event::Game => GAME,
+            Event::LowerThird { player: 1 } => LOWER_THIRD1,
+            Event::LowerThird { .. } => LOWER_THIRD2,
+            Event::Note { .. } => NOTE,
+            Event::TimeCalled => TIME,
+            Event::Token { player: 1, .. } => TOKEN1,
+            Event::Token { .. } => TOKEN2,
+        }
+    }
+
+    /// Players and heroes describe the match rather than something that happens during it
+    pub fn is_setup(&self) -> bool {
+        matches!(
+            self,
+            Event::Player1 { .. } | Event::Player2 { .. } | Event::Hero1 { .. } | Event::Hero2 { .. }
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub time: TimeTick,
+    pub event: Event,
+}
+
+impl Annotation {
+    pub fn new(time: TimeTick, event: Event) -> Self {
+        Annotation { time, event }
+    }
+}
+
+#[derive(Deserialize)]
+struct RawRow {
+    sec: u64,
+    milli: f64,
+    name: String,
+    pitch: Option<u32>,
+    player1_life: Option<String>,
+    player2_life: Option<String>,
+    update_type: String,
+}
+
+impl RawRow {
+    fn into_annotation(self, line: usize) -> Result<Annotation> {
+        let time = TimeTick::build(self.sec, self.milli);
+        let life = |life: Option<String>, field| {
+            life.filter(|v| !v.trim().is_empty())
+                .ok_or(AnnotationError::MissingField { line, field })
+        };
+        let event = match self.update_type.trim() {
+            PLAYER1 => Event::Player1 { name: self.name },
+            PLAYER2 => Event::Player2 { name: self.name },
+            HERO1 => Event::Hero1 {
+                name: self.name,
+                life: life(self.player1_life, "player1_life")?,
+            },
+            HERO2 => Event::Hero2 {
+                name: self.name,
+                life: life(self.player2_life, "player2_life")?,
+            },
+            CARD => Event::Card {
+                name: self.name,
+                pitch: self.pitch,
+            },
+            BLOCK => Event::Block {
+                name: self.name,
+                pitch: self.pitch,
+            },
+            PAIR => Event::Pair {
+                name: self.name,
+                pitch: self.pitch,
+            },
+            LIFE => Event::Life {
+                player1: self.player1_life.filter(|v| !v.trim().is_empty()),
+                player2: self.player2_life.filter(|v| !v.trim().is_empty()),
+            },
+            TURN => Event::Turn,
+            WIN1 => Event::Win { player: 1 },
+            WIN2 => Event::Win { player: 2 },
+            ZOOM => Event::Zoom {
+                name: Some(self.name).filter(|name| !name.trim().is_empty()),
+                pitch: self.pitch,
+            },
+            UNZOOM => Event::Unzoom,
+            GAME => Event::Game,
+            LOWER_THIRD1 => Event::LowerThird { player: 1 },
+            LOWER_THIRD2 => Event::LowerThird { player: 2 },
+            NOTE => Event::Note { text: self.name },
+            TIME => Event::TimeCalled,
+            TOKEN1 => Event::Token {
+                player: 1,
+                name: self.name,
+            },
+            TOKEN2 => Event::Token {
+                player: 2,
+                name: self.name,
+            },
+            other => {
+                return Err(AnnotationError::UnknownUpdateType {
+                    line,
+                    update_type: other.to_owned(),
+                })
+            }
+        };
+        Ok(Annotation { time, event })
+    }
+}
+
+/// Parses a whole annotation file, detecting its version from the first line
+pub fn parse<R: Read>(mut reader: R) -> Result<AnnotationFile> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    let first = text.lines().next().unwrap_or("");
+    match Version::detect(first)? {
+        Version::V1 => parse_legacy(&text),
+        Version::V2 => parse_v2(&text),
+    }
+}
+
+/// Version 1 files carry the players and heroes as their first rows
+fn parse_legacy(text: &str) -> Result<AnnotationFile> {
+    let (setup, events) = MatchSetup::split(parse_rows(text, 0)?)?;
+    Ok(AnnotationFile {
+        header: Header::new(setup),
+        events,
+    })
+}
+
+fn parse_v2(text: &str) -> Result<AnnotationFile> {
+    let header_len = text
+        .lines()
+        .take_while(|line| line.starts_with(HEADER_PREFIX))
+        .count();
+    let header = Header::parse(text.lines().take(header_len))?;
+    let rows: String = text
+        .lines()
+        .skip(header_len)
+        .flat_map(|line| [line, "\n"])
+        .collect();
+    Ok(AnnotationFile {
+        header,
+        events: parse_rows(&rows, header_len)?,
+    })
+}
+
+/// Parses the tab separated rows. `offset` is the number of lines preceding the column header.
+fn parse_rows(text: &str, offset: usize) -> Result<Vec<Annotation>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b'\t')
+        .from_reader(text.as_bytes());
+    reader
+        .deserialize::<RawRow>()
+        .enumerate()
+        // the line after the offset is the column header
+        .map(|(idx, row)| row?.into_annotation(offset + idx + 2))
+        .collect()
+}
+
+pub fn headers() -> String {
+    format!("{}\n", V1_HEADERS.join("\t"))
+}
+
+/// Single line of the annotation file, including the trailing newline
+pub fn to_row(annotation: &Annotation) -> String {
+    let (name, pitch, player1_life, player2_life) = match &annotation.event {
+        Event::Player1 { name } | Event::Player2 { name } => (name.as_str(), None, None, None),
+        Event::Hero1 { name, life } => (name.as_str(), None, Some(life.as_str()), None),
+        Event::Hero2 { name, life } => (name.as_str(), None, None, Some(life.as_str())),
+        Event::Card { name, pitch }
+        | Event::Block { name, pitch }
+        | Event::Pair { name, pitch } => (name.as_str(), *pitch, None, None),
+        Event::Zoom { name, pitch } => (name.as_deref().unwrap_or(""), *pitch, None, None),
+        Event::Note { text } | Event::Token { name: text, .. } => (text.as_str(), None, None, None),
+        Event::Life { player1, player2 } => ("", None, player1.as_deref(), player2.as_deref()),
+        Event::Turn
+        | Event::Win { .. }
+        | Event::Unzoom
+        | Event::Game
+        | Event::LowerThird { .. }
+        | Event::TimeCalled => ("", None, None, None),
+    };
+    tsv_line([
+        annotation.time.sec().to_string().as_str(),
+        annotation.time.milli().to_string().as_str(),
+        name,
+        pitch.map_or("".to_string(), |v| v.to_string()).as_str(),
+        player1_life.unwrap_or(""),
+        player2_life.unwrap_or(""),
+        annotation.event.update_type(),
+    ])
+}
+
+/// Tab separated line, including the trailing newline, quoted the way `parse_rows` reads it so
+/// names with quotes or tabs survive
+fn tsv_line<'a>(fields: impl IntoIterator<Item = &'a str>) -> String {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(b'\t')
+        .from_writer(Vec::new());
+    writer
+        .write_record(fields)
+        .expect("writing to memory can't fail");
+    let line = writer.into_inner().expect("writing to memory can't fail");
+    String::from_utf8(line).expect("the fields are UTF-8")
+}
+
+/// Writes a version 2 file
+pub fn write<W: Write>(writer: &mut W, file: &AnnotationFile) -> std::io::Result<()> {
+    write!(writer, "{}", file.header.to_lines())?;
+    write!(writer, "{}", headers())?;
+    for annotation in file.events.iter() {
+        write!(writer, "{}", to_row(annotation))?;
+    }
+    Ok(())
+}
+
+/// Orders annotations by time. The sort is stable, so events at the same time keep their order.
+pub fn sort(annotations: &mut [Annotation]) {
+    annotations.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap_or(Ordering::Equal));
+}
+
+/// Players and heroes for a match
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MatchSetup {
+    pub player1: String,
+    pub player2: String,
+    pub hero1: String,
+    pub hero2: String,
+    pub hero1_life: String,
+    pub hero2_life: String,
+    #[serde(default)]
+    pub hero1_intellect: Option<u32>,
+    #[serde(default)]
+    pub hero2_intellect: Option<u32>,
+    /// Player that takes the first turn
+    pub first: u8,
+}
+
+impl MatchSetup {
+    /// Pulls the setup out of the rows of a version 1 annotation, returning the remaining events.
+    /// The first turn player is whichever hero row comes first.
+    pub fn split(annotations: Vec<Annotation>) -> Result<(Self, Vec<Annotation>)> {
+        let mut player1 = None;
+        let mut player2 = None;
+        let mut hero1 = None;
+        let mut hero2 = None;
+        let mut first = None;
+        let mut events = Vec::new();
+
+        for annotation in annotations {
+            match annotation.event {
+                Event::Player1 { name } => player1 = Some(name),
+                Event::Player2 { name } => player2 = Some(name),
+                Event::Hero1 { name, life } => {
+                    first.get_or_insert(1);
+                    hero1 = Some((name, life));
+                }
+                Event::Hero2 { name, life } => {
+                    first.get_or_insert(2);
+                    hero2 = Some((name, life));
+                }
+                event => events.push(Annotation::new(annotation.time, event)),
+            }
+        }
+
+        match (player1, player2, hero1, hero2, first) {
+            (Some(player1), Some(player2), Some(hero1), Some(hero2), Some(first)) => Ok((
+                MatchSetup {
+                    player1,
+                    player2,
+                    hero1: hero1.0,
+                    hero2: hero2.0,
+                    hero1_life: hero1.1,
+                    hero2_life: hero2.1,
+                    first,
+                    ..Default::default()
+                },
+                events,
+            )),
+            _ => Err(AnnotationError::MissingSetup),
+        }
+    }
+}
+
+/// Optional details about a player, for flags and lower thirds
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlayerInfo {
+    pub team: Option<String>,
+    /// Country code, e.g. "US"
+    pub country: Option<String>,
+    pub pronouns: Option<String>,
+    /// Seed or record, e.g. "3" or "5-1"
+    pub seed: Option<String>,
+}
+
+impl PlayerInfo {
+    fn fields(&self) -> [(&'static str, &Option<String>); 4] {
+        [
+            (TEAM, &self.team),
+            (COUNTRY, &self.country),
+            (PRONOUNS, &self.pronouns),
+            (SEED, &self.seed),
+        ]
+    }
+
+    fn field_mut(&mut self, key: &str) -> Option<&mut Option<String>> {
+        match key {
+            TEAM => Some(&mut self.team),
+            COUNTRY => Some(&mut self.country),
+            PRONOUNS => Some(&mut self.pronouns),
+            SEED => Some(&mut self.seed),
+            _ => None,
+        }
+    }
+}
+
+/// Metadata at the top of a version 2 file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Header {
+    pub setup: MatchSetup,
+    /// Player 1 and player 2
+    #[serde(default)]
+    pub players: [PlayerInfo; 2],
+    pub format: Option<Format>,
+    pub event_name: Option<String>,
+    pub round: Option<String>,
+    /// Seconds added to every event time to line the annotation up with the video
+    pub video_offset: f64,
+}
+
+impl Header {
+    pub fn new(setup: MatchSetup) -> Self {
+        Header {
+            setup,
+            players: Default::default(),
+            format: None,
+            event_name: None,
+            round: None,
+            video_offset: 0.0,
+        }
+    }
+
+    /// Header block, including the version line and the trailing newline
+    pub fn to_lines(&self) -> String {
+        let setup = &self.setup;
+        let mut lines = vec![
+            tsv_line([VERSION_TAG, "2"]),
+            header_line(PLAYER1, &[&setup.player1]),
+            header_line(PLAYER2, &[&setup.player2]),
+            hero_line(HERO1, &setup.hero1, &setup.hero1_life, setup.hero1_intellect),
+            hero_line(HERO2, &setup.hero2, &setup.hero2_life, setup.hero2_intellect),
+            header_line(FIRST, &[&setup.first.to_string()]),
+        ];
+        for (player, info) in [PLAYER1, PLAYER2].iter().zip(self.players.iter()) {
+            for (key, value) in info.fields() {
+                if let Some(value) = value {
+                    lines.push(header_line(&format!("{}_{}", player, key), &[value]));
+                }
+            }
+        }
+        if let Some(format) = self.format {
+            lines.push(header_line(FORMAT, &[format.code()]));
+        }
+        if let Some(event_name) = &self.event_name {
+            lines.push(header_line(EVENT_NAME, &[event_name]));
+        }
+        if let Some(round) = &self.round {
+            lines.push(header_line(ROUND, &[round]));
+        }
+        lines.push(header_line(VIDEO_OFFSET, &[&self.video_offset.to_string()]));
+        lines.concat()
+    }
+
+    /// Parses the `#` prefixed lines of a version 2 file, starting with the version line
+    fn parse<'a>(lines: impl Iterator<Item = &'a str>) -> Result<Self> {
+        let mut player1 = None;
+        let mut player2 = None;
+        let mut hero1 = None;
+        let mut hero2 = None;
+        let mut first = 1;
+        let mut header = Header::new(MatchSetup::default());
+
+        // line 1 is the version
+        for (idx, line) in lines.enumerate().skip(1) {
+            let line_number = idx + 1;
+            let invalid = |reason: &str| AnnotationError::InvalidHeader {
+                line: line_number,
+                reason: reason.to_owned(),
+            };
+            let mut values = header_fields(line.trim_start_matches(HEADER_PREFIX).trim_end())
+                .map_err(|e| invalid(&e.to_string()))?
+                .into_iter();
+            let key = values.next().unwrap_or_default();
+            let value = values.next().unwrap_or_default();
+            match key.as_str() {
+                PLAYER1 => player1 = Some(value),
+                PLAYER2 => player2 = Some(value),
+                HERO1 | HERO2 => {
+                    let life = values
+                        .next()
+                        .filter(|v| !v.is_empty())
+                        .ok_or_else(|| invalid("hero is missing its starting life"))?;
+                    let intellect = match values.next().filter(|v| !v.is_empty()) {
+                        Some(v) => Some(
+                            v.parse()
+                                .map_err(|_| invalid("hero intellect must be a number"))?,
+                        ),
+                        None => None,
+                    };
+                    if key == HERO1 {
+                        hero1 = Some((value, life, intellect));
+                    } else {
+                        hero2 = Some((value, life, intellect));
+                    }
+                }
+                FIRST => {
+                    first = match value.as_str() {
+                        "1" => 1,
+                        "2" => 2,
+                        _ => return Err(invalid("first player must be 1 or 2")),
+                    }
+                }
+                FORMAT => {
+                    header.format =
+                        Some(Format::from_code(&value).ok_or_else(|| invalid("unknown format"))?)
+                }
+                EVENT_NAME => header.event_name = Some(value).filter(|v| !v.is_empty()),
+                ROUND => header.round = Some(value).filter(|v| !v.is_empty()),
+                VIDEO_OFFSET => {
+                    header.video_offset = value
+                        .parse()
+                        .map_err(|_| invalid("video offset must be a number of seconds"))?
+                }
+                _ => {
+                    // unknown keys are skipped so newer files still load
+                    if let Some(field) = header.player_field(&key) {
+                        *field = Some(value).filter(|v| !v.is_empty());
+                    }
+                }
+            }
+        }
+
+        match (player1, player2, hero1, hero2) {
+            (Some(player1), Some(player2), Some(hero1), Some(hero2)) => {
+                header.setup = MatchSetup {
+                    player1,
+                    player2,
+                    hero1: hero1.0,
+                    hero2: hero2.0,
+                    hero1_life: hero1.1,
+                    hero2_life: hero2.1,
+                    hero1_intellect: hero1.2,
+                    hero2_intellect: hero2.2,
+                    first,
+                };
+                Ok(header)
+            }
+            _ => Err(AnnotationError::MissingSetup),
+        }
+    }
+
+    /// Field named by a player metadata key, e.g. "player2_country"
+    fn player_field(&mut self, key: &str) -> Option<&mut Option<String>> {
+        let (player, field) = key.split_once('_')?;
+        let info = match player {
+            PLAYER1 => &mut self.players[0],
+            PLAYER2 => &mut self.players[1],
+            _ => return None,
+        };
+        info.field_mut(field)
+    }
+}
+
+/// Header line of `key` and its values, e.g. "#first\t2\n"
+fn header_line(key: &str, values: &[&str]) -> String {
+    let key = format!("{}{}", HEADER_PREFIX, key);
+    tsv_line([key.as_str()].into_iter().chain(values.iter().copied()))
+}
+
+/// Hero header line, with the intellect only when it is known
+fn hero_line(key: &str, hero: &str, life: &str, intellect: Option<u32>) -> String {
+    match intellect {
+        Some(intellect) => header_line(key, &[hero, life, &intellect.to_string()]),
+        None => header_line(key, &[hero, life]),
+    }
+}
+
+/// Values of a header line without its prefix, unquoted the same way as the rows
+fn header_fields(line: &str) -> csv::Result<Vec<String>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b'\t')
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(line.as_bytes());
+    match reader.records().next() {
+        Some(record) => Ok(record?.iter().map(|v| v.trim().to_owned()).collect()),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// One game of a match. Games are separated by `Event::Game`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameSection<'a> {
+    /// Starts at 1
+    pub number: u32,
+    /// Player that takes the first turn, alternating each game
+    pub first: u8,
+    /// Events of the game, not including the `Event::Game` boundaries
+    pub events: &'a [Annotation],
+    pub winner: Option<u8>,
+}
+
+/// Contents of an annotation file
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnotationFile {
+    pub header: Header,
+    pub events: Vec<Annotation>,
+}
+
+impl AnnotationFile {
+    pub fn new(header: Header, events: Vec<Annotation>) -> Self {
+        AnnotationFile { header, events }
+    }
+
+    /// Events with the header's video offset applied
+    pub fn aligned_events(&self) -> Vec<Annotation> {
+        self.events
+            .iter()
+            .map(|annotation| {
+                let time =
+                    TimeTick::from_secs_f64(annotation.time.as_f64() + self.header.video_offset);
+                Annotation::new(time, annotation.event.clone())
+            })
+            .collect()
+    }
+
+    /// Splits the events into games. A file without `Event::Game` is a single game.
+    pub fn games(&self) -> Vec<GameSection> {
+        let mut first = self.header.setup.first;
+        self.events
+            .split(|annotation| annotation.event == Event::Game)
+            .enumerate()
+            .map(|(idx, events)| {
+                let winner = events.iter().find_map(|annotation| match annotation.event {
+                    Event::Win { player } => Some(player),
+                    _ => None,
+                });
+                let section = GameSection {
+                    number: idx as u32 + 1,
+                    first,
+                    events,
+                    winner,
+                };
+                first = if first == 1 { 2 } else { 1 };
+                section
+            })
+            .collect()
+    }
+
+    /// Games won by player 1 and player 2
+    pub fn score(&self) -> [u32; 2] {
+        let mut score = [0, 0];
+        for game in self.games() {
+            if let Some(player @ (1 | 2)) = game.winner {
+                score[player as usize - 1] += 1;
+            }
+        }
+        score
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn sample() -> Vec<Annotation> {
+        vec![
+            Annotation::new(TimeTick::new(), Event::Player2 { name: "Tom".to_string() }),
+            Annotation::new(TimeTick::new(), Event::Player1 { name: "Trevor".to_string() }),
+            Annotation::new(
+                TimeTick::new(),
+                Event::Hero2 {
+                    name: "Rhinar, Reckless Rampage".to_string(),
+                    life: "40".to_string(),
+                },
+            ),
+            Annotation::new(
+                TimeTick::new(),
+                Event::Hero1 {
+                    name: "Maxx 'The Hype' Nitro".to_string(),
+                    life: "40".to_string(),
+                },
+            ),
+            Annotation::new(
+                TimeTick::build(3, 250.0),
+                Event::Card {
+                    name: "Erase Face".to_string(),
+                    pitch: Some(1),
+                },
+            ),
+            Annotation::new(
+                TimeTick::build(3, 900.0),
+                Event::Block {
+                    name: "Sink Below".to_string(),
+                    pitch: Some(1),
+                },
+            ),
+            Annotation::new(
+                TimeTick::build(4, 0.0),
+                Event::Zoom {
+                    name: None,
+                    pitch: None,
+                },
+            ),
+            Annotation::new(
+                TimeTick::build(7, 0.0),
+                Event::Zoom {
+                    name: Some("Erase Face".to_string()),
+                    pitch: Some(1),
+                },
+            ),
+            Annotation::new(TimeTick::build(8, 500.0), Event::Unzoom),
+            Annotation::new(
+                TimeTick::build(9, 125.5),
+                Event::Life {
+                    player1: Some("-4".to_string()),
+                    player2: None,
+                },
+            ),
+            Annotation::new(TimeTick::build(12, 0.0), Event::Turn),
+            Annotation::new(TimeTick::build(60, 0.0), Event::Win { player: 2 }),
+        ]
+    }
+
+    fn sample_file() -> AnnotationFile {
+        let (setup, events) = MatchSetup::split(sample()).unwrap();
+        let mut header = Header::new(setup);
+        header.format = Some(Format::ClassicConstructed);
+        header.event_name = Some("Calling Indianapolis".to_string());
+        header.round = Some("Top 8".to_string());
+        header.video_offset = 1.5;
+        header.setup.hero2_intellect = Some(4);
+        header.players[0].team = Some("Team Covenant".to_string());
+        header.players[0].pronouns = Some("he/him".to_string());
+        header.players[1].seed = Some("5-1".to_string());
+        AnnotationFile::new(header, events)
+    }
+
+    #[test]
+    fn test_round_trip() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let file = sample_file();
+        let mut out = Vec::new();
+        write(&mut out, &file)?;
+
+        let parsed = parse(out.as_slice())?;
+        assert_eq!(parsed, file);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_legacy() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let mut text = headers();
+        for annotation in sample().iter() {
+            text.push_str(&to_row(annotation));
+        }
+        let parsed = parse(text.as_bytes())?;
+        let (setup, events) = MatchSetup::split(sample())?;
+        assert_eq!(parsed.header, Header::new(setup));
+        assert_eq!(parsed.events, events);
+        Ok(())
+    }
+
+    #[test]
+    fn test_legacy_without_setup() {
+        let text = format!("{}12\t500\t\t\t\t-3\tlife\n", headers());
+        assert!(matches!(
+            parse(text.as_bytes()),
+            Err(AnnotationError::MissingSetup)
+        ));
+    }
+
+    #[test]
+    fn test_unknown_update_type() {
+        let text = format!(
+            "{}{}1\t0\t\t\t\t\tbanish\n",
+            sample_file().header.to_lines(),
+            headers()
+        );
+        let header_len = sample_file().header.to_lines().lines().count();
+        assert!(matches!(
+            parse(text.as_bytes()),
+            Err(AnnotationError::UnknownUpdateType { line, .. }) if line == header_len + 2
+        ));
+    }
+
+    #[test]
+    fn test_format_presets() {
+        for format in [
+            Format::ClassicConstructed,
+            Format::Blitz,
+            Format::UltimatePitFight,
+        ] {
+            assert_eq!(Format::from_code(format.code()), Some(format));
+            assert_eq!(Format::from_code(format.name()), Some(format));
+        }
+        assert_eq!(Format::ClassicConstructed.default_life(), 40);
+        assert_eq!(Format::UltimatePitFight.default_life(), 20);
+        assert_eq!(Format::for_hero(true), Format::Blitz);
+    }
+
+    #[test]
+    fn test_aligned_events() {
+        let events = sample_file().aligned_events();
+        assert_eq!(events[0].time, TimeTick::build(4, 750.0));
+    }
+
+    #[test]
+    fn test_games() {
+        let mut file = sample_file();
+        file.events.extend([
+            Annotation::new(TimeTick::build(70, 0.0), Event::Game),
+            Annotation::new(TimeTick::build(80, 0.0), Event::Turn),
+            Annotation::new(TimeTick::build(90, 0.0), Event::Win { player: 2 }),
+            Annotation::new(TimeTick::build(95, 0.0), Event::Game),
+        ]);
+        let games = file.games();
+        assert_eq!(games.len(), 3);
+        assert_eq!(games[0].first, 2);
+        assert_eq!(games[1].first, 1);
+        assert_eq!(games[1].number, 2);
+        assert_eq!(games[1].events.len(), 2);
+        assert_eq!(games[2].winner, None);
+        assert_eq!(file.score(), [0, 2]);
+    }
+
+    #[test]
+    fn test_split_setup() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let (setup, events) = MatchSetup::split(sample())?;
+        assert_eq!(setup.player1, "Trevor");
+        assert_eq!(setup.hero2, "Rhinar, Reckless Rampage");
+        assert_eq!(setup.first, 2);
+        assert_eq!(events.len(), 8);
+        Ok(())
+    }
+
+    // Quotes, tabs and accents are in real card and player names, or in typos of them
+    const NAME: &str = "[A-Za-zÀ-ÿ가-힣\"][A-Za-zÀ-ÿ가-힣 \t,'\"]{0,20}";
+    const LIFE_UPDATE: &str = "[+=-][0-9]{1,3}";
+
+    fn event() -> impl Strategy<Value = Event> {
+        prop_oneof![
+            (NAME, prop::option::of(1..=3_u32))
+                .prop_map(|(name, pitch)| Event::Card { name, pitch }),
+            (NAME, prop::option::of(1..=3_u32))
+                .prop_map(|(name, pitch)| Event::Block { name, pitch }),
+            (NAME, prop::option::of(1..=3_u32))
+                .prop_map(|(name, pitch)| Event::Pair { name, pitch }),
+            (prop::option::of(NAME), prop::option::of(1..=3_u32))
+                .prop_map(|(name, pitch)| Event::Zoom { name, pitch }),
+            Just(Event::Unzoom),
+            (prop::option::of(LIFE_UPDATE), prop::option::of(LIFE_UPDATE))
+                .prop_map(|(player1, player2)| Event::Life { player1, player2 }),
+            Just(Event::Turn),
+            (1..=2_u8).prop_map(|player| Event::Win { player }),
+            Just(Event::Game),
+            (1..=2_u8).prop_map(|player| Event::LowerThird { player }),
+            NAME.prop_map(|text| Event::Note { text }),
+            Just(Event::TimeCalled),
+            (1..=2_u8, NAME).prop_map(|(player, name)| Event::Token { player, name }),
+        ]
+    }
+
+    fn annotation() -> impl Strategy<Value = Annotation> {
+        (0..100_000_u64, 0.0..1000.0, event())
+            .prop_map(|(sec, milli, event)| Annotation::new(TimeTick::build(sec, milli), event))
+    }
+
+    proptest! {
+        #[test]
+        fn prop_round_trip(events in prop::collection::vec(annotation(), 0..30)) {
+            let file = AnnotationFile::new(sample_file().header, events);
+            let mut out = Vec::new();
+            write(&mut out, &file).unwrap();
+            prop_assert_eq!(parse(out.as_slice()).map_err(|e| e.to_string()), Ok(file));
+        }
+
+        #[test]
+        fn prop_header_round_trip(player1 in NAME, event_name in prop::option::of(NAME)) {
+            // header values are trimmed when they're read
+            prop_assume!(player1.trim() == player1);
+            prop_assume!(event_name.iter().all(|name| name.trim() == name));
+            let mut header = sample_file().header;
+            header.setup.player1 = player1;
+            header.event_name = event_name;
+            let file = AnnotationFile::new(header, sample_file().events);
+            let mut out = Vec::new();
+            write(&mut out, &file).unwrap();
+            prop_assert_eq!(parse(out.as_slice()).map_err(|e| e.to_string()), Ok(file));
+        }
+
+        #[test]
+        fn prop_parse_never_panics(text in "\\PC*") {
+            let _ = parse(text.as_bytes());
+        }
+
+        #[test]
+        fn prop_malformed_rows_never_panic(
+            fields in prop::collection::vec("[^\t\n]*", 0..10)
+        ) {
+            let text = format!(
+                "{}{}{}\n",
+                sample_file().header.to_lines(),
+                headers(),
+                fields.join("\t")
+            );
+            let _ = parse(text.as_bytes());
+        }
+
+        #[test]
+        fn prop_unknown_update_type_rejected(update_type in "[a-z]{1,8}") {
+            prop_assume!(![
+                PLAYER1, PLAYER2, HERO1, HERO2, CARD, BLOCK, PAIR, LIFE, TURN, WIN1, WIN2, ZOOM,
+                UNZOOM, GAME, NOTE, TIME
+            ]
+            .contains(&update_type.as_str()));
+            let text = format!(
+                "{}{}1\t0\t\t\t\t\t{}\n",
+                sample_file().header.to_lines(),
+                headers(),
+                update_type
+            );
+            let is_unknown = matches!(
+                parse(text.as_bytes()),
+                Err(AnnotationError::UnknownUpdateType { .. })
+            );
+            prop_assert!(is_unknown);
+        }
+    }
+}
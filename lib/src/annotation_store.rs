@@ -0,0 +1,451 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Read, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    annotation::{self, Annotation, AnnotationError, AnnotationFile, Event, Header, Result},
+    time_tick::TimeTick,
+};
+
+/// Every SQLite database starts with this string
+const SQLITE_MAGIC: &[u8] = b"SQLite format 3\0";
+/// Schema version written into JSON and SQLite files
+const STORE_VERSION: u32 = 2;
+/// Added to the path of a file while it is being written
+const TMP_SUFFIX: &str = ".tmp";
+
+/// File formats annotations can be stored in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageFormat {
+    Tsv,
+    /// Header object on the first line, then one event object per line
+    JsonLines,
+    Sqlite,
+}
+
+impl StorageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            StorageFormat::Tsv => "tsv",
+            StorageFormat::JsonLines => "jsonl",
+            StorageFormat::Sqlite => "sqlite",
+        }
+    }
+
+    /// Sniffs the format from the start of a file
+    pub fn detect(start: &[u8]) -> Self {
+        if start.starts_with(SQLITE_MAGIC) {
+            StorageFormat::Sqlite
+        } else if start.iter().find(|c| !c.is_ascii_whitespace()) == Some(&b'{') {
+            StorageFormat::JsonLines
+        } else {
+            StorageFormat::Tsv
+        }
+    }
+
+    /// Sniffs the format of the file at `fp`
+    pub fn detect_path(fp: &str) -> Result<Self> {
+        let mut start = Vec::new();
+        File::open(fp)?
+            .take(SQLITE_MAGIC.len() as u64)
+            .read_to_end(&mut start)?;
+        Ok(Self::detect(&start))
+    }
+}
+
+impl FromStr for StorageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "tsv" => Ok(StorageFormat::Tsv),
+            "json" | "jsonl" => Ok(StorageFormat::JsonLines),
+            "sqlite" | "db" => Ok(StorageFormat::Sqlite),
+            other => Err(format!("Unknown annotation format '{}'", other)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonHeader {
+    version: u32,
+    header: Header,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonEvent {
+    sec: u64,
+    milli: f64,
+    event: Event,
+}
+
+impl From<&Annotation> for JsonEvent {
+    fn from(value: &Annotation) -> Self {
+        JsonEvent {
+            sec: value.time.sec(),
+            milli: value.time.milli(),
+            event: value.event.clone(),
+        }
+    }
+}
+
+impl From<JsonEvent> for Annotation {
+    fn from(value: JsonEvent) -> Self {
+        Annotation::new(TimeTick::build(value.sec, value.milli), value.event)
+    }
+}
+
+/// Reads an annotation file in any of the storage formats
+pub fn read_from_path(fp: &str) -> Result<AnnotationFile> {
+    match StorageFormat::detect_path(fp)? {
+        StorageFormat::Tsv => annotation::parse(File::open(fp)?),
+        StorageFormat::JsonLines => parse_json_lines(BufReader::new(File::open(fp)?)),
+        StorageFormat::Sqlite => read_sqlite(&Connection::open(fp)?),
+    }
+}
+
+/// Writes the annotation to `fp`, replacing anything already there. The file is written
+/// beside `fp` and renamed over it, so a crash never leaves it half written.
+pub fn write_to_path(fp: &str, file: &AnnotationFile, format: StorageFormat) -> Result<()> {
+    let tmp_fp = format!("{}{}", fp, TMP_SUFFIX);
+    if Path::new(&tmp_fp).exists() {
+        std::fs::remove_file(&tmp_fp)?;
+    }
+    match format {
+        StorageFormat::Tsv => {
+            let mut out = File::create(&tmp_fp)?;
+            annotation::write(&mut out, file)?;
+            out.sync_all()?;
+        }
+        StorageFormat::JsonLines => {
+            let mut out = File::create(&tmp_fp)?;
+            write_json_lines(&mut out, file)?;
+            out.sync_all()?;
+        }
+        StorageFormat::Sqlite => write_sqlite(&mut Connection::open(&tmp_fp)?, file)?,
+    }
+    std::fs::rename(&tmp_fp, fp)?;
+    Ok(())
+}
+
+pub fn parse_json_lines<R: BufRead>(reader: R) -> Result<AnnotationFile> {
+    let mut lines = reader.lines().filter(|line| {
+        line.as_ref()
+            .map_or(true, |line| !line.trim().is_empty())
+    });
+    let first = lines.next().ok_or(AnnotationError::MissingSetup)??;
+    let header: JsonHeader = serde_json::from_str(&first)?;
+    if header.version != STORE_VERSION {
+        return Err(AnnotationError::UnsupportedVersion(header.version.to_string()));
+    }
+    let events = lines
+        .map(|line| Ok(serde_json::from_str::<JsonEvent>(&line?)?.into()))
+        .collect::<Result<Vec<Annotation>>>()?;
+    Ok(AnnotationFile::new(header.header, events))
+}
+
+pub fn write_json_lines<W: Write>(writer: &mut W, file: &AnnotationFile) -> Result<()> {
+    let header = JsonHeader {
+        version: STORE_VERSION,
+        header: file.header.clone(),
+    };
+    writeln!(writer, "{}", serde_json::to_string(&header)?)?;
+    for annotation in file.events.iter() {
+        writeln!(writer, "{}", serde_json::to_string(&JsonEvent::from(annotation))?)?;
+    }
+    Ok(())
+}
+
+/// Events are stored as JSON alongside their time and update type, so they can be
+/// queried without decoding every row
+pub fn write_sqlite(conn: &mut Connection, file: &AnnotationFile) -> Result<()> {
+    let tx = conn.transaction()?;
+    tx.execute_batch(
+        "CREATE TABLE metadata (version INTEGER NOT NULL, header TEXT NOT NULL);
+         CREATE TABLE events (
+             id INTEGER PRIMARY KEY,
+             sec INTEGER NOT NULL,
+             milli REAL NOT NULL,
+             update_type TEXT NOT NULL,
+             event TEXT NOT NULL
+         );",
+    )?;
+    tx.execute(
+        "INSERT INTO metadata (version, header) VALUES (?1, ?2)",
+        params![STORE_VERSION, serde_json::to_string(&file.header)?],
+    )?;
+    {
+        let mut insert = tx.prepare(
+            "INSERT INTO events (sec, milli, update_type, event) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for annotation in file.events.iter() {
+            insert.execute(params![
+                annotation.time.sec() as i64,
+                annotation.time.milli(),
+                annotation.event.update_type(),
+                serde_json::to_string(&annotation.event)?,
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+pub fn read_sqlite(conn: &Connection) -> Result<AnnotationFile> {
+    let (version, header): (u32, String) =
+        conn.query_row("SELECT version, header FROM metadata", [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+    if version != STORE_VERSION {
+        return Err(AnnotationError::UnsupportedVersion(version.to_string()));
+    }
+    let header: Header = serde_json::from_str(&header)?;
+
+    let mut select = conn.prepare("SELECT sec, milli, event FROM events ORDER BY id")?;
+    let rows = select.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, f64>(1)?,
+            row.get::<_, String>(2)?,
+        ))
+    })?;
+    let mut events = Vec::new();
+    for row in rows {
+        let (sec, milli, event) = row?;
+        let event: Event = serde_json::from_str(&event)?;
+        events.push(Annotation::new(TimeTick::build(sec as u64, milli), event));
+    }
+    Ok(AnnotationFile::new(header, events))
+}
+
+/// Line of a session file after the header
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum SessionLine {
+    Event(JsonEvent),
+    /// Removes the most recent event
+    Undo { undo: bool },
+}
+
+/// Append-only log of an annotation in progress. Every line is flushed as soon as it is
+/// written, so a crash loses at most the line being written.
+pub struct SessionWriter {
+    file: File,
+    fp: String,
+}
+
+impl SessionWriter {
+    /// Starts a new session at `fp`, writing the header as the first line. Fails if `fp`
+    /// already exists, so a session left by a crash is never overwritten before it's resumed.
+    pub fn create(fp: &str, header: &Header) -> Result<Self> {
+        let file = OpenOptions::new().write(true).create_new(true).open(fp)?;
+        Self::start(fp, file, header)
+    }
+
+    fn start(fp: &str, file: File, header: &Header) -> Result<Self> {
+        let mut session = SessionWriter {
+            file,
+            fp: fp.to_owned(),
+        };
+        let header = JsonHeader {
+            version: STORE_VERSION,
+            header: header.clone(),
+        };
+        session.write_line(&serde_json::to_string(&header)?)?;
+        Ok(session)
+    }
+
+    /// Starts a session from an existing annotation, e.g. one replayed from a previous
+    /// session. Rewriting the file drops any line a crash left half written.
+    pub fn create_from(fp: &str, file: &AnnotationFile) -> Result<Self> {
+        let mut session = Self::start(fp, File::create(fp)?, &file.header)?;
+        for annotation in file.events.iter() {
+            session.push(annotation)?;
+        }
+        Ok(session)
+    }
+
+    pub fn push(&mut self, annotation: &Annotation) -> Result<()> {
+        let line = serde_json::to_string(&SessionLine::Event(annotation.into()))?;
+        self.write_line(&line)
+    }
+
+    pub fn undo(&mut self) -> Result<()> {
+        let line = serde_json::to_string(&SessionLine::Undo { undo: true })?;
+        self.write_line(&line)
+    }
+
+    /// Removes the session once the annotation it was recovering has been saved
+    pub fn finish(self) -> Result<()> {
+        std::fs::remove_file(&self.fp)?;
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<()> {
+        writeln!(self.file, "{}", line)?;
+        self.file.sync_data()?;
+        Ok(())
+    }
+}
+
+/// Replays a session file into the annotation it describes. A partially written last line
+/// is ignored, since that is what a crash mid-write leaves behind.
+pub fn read_session(fp: &str) -> Result<AnnotationFile> {
+    let mut lines = BufReader::new(File::open(fp)?).lines();
+    let first = lines.next().ok_or(AnnotationError::MissingSetup)??;
+    let header: JsonHeader = serde_json::from_str(&first)?;
+    if header.version != STORE_VERSION {
+        return Err(AnnotationError::UnsupportedVersion(header.version.to_string()));
+    }
+
+    let lines = lines.collect::<std::io::Result<Vec<String>>>()?;
+    let mut events = Vec::new();
+    for (idx, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<SessionLine>(line) {
+            Ok(SessionLine::Event(event)) => events.push(event.into()),
+            Ok(SessionLine::Undo { .. }) => {
+                events.pop();
+            }
+            Err(_) if idx == lines.len() - 1 => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(AnnotationFile::new(header.header, events))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::annotation::MatchSetup;
+
+    fn sample_file() -> AnnotationFile {
+        let setup = MatchSetup {
+            player1: "Trevor".to_string(),
+            player2: "Tom".to_string(),
+            hero1: "Maxx 'The Hype' Nitro".to_string(),
+            hero2: "Rhinar, Reckless Rampage".to_string(),
+            hero1_life: "40".to_string(),
+            hero2_life: "40".to_string(),
+            hero1_intellect: Some(4),
+            hero2_intellect: None,
+            first: 1,
+        };
+        let events = vec![
+            Annotation::new(
+                TimeTick::build(3, 250.0),
+                Event::Card {
+                    name: "Erase Face".to_string(),
+                    pitch: Some(1),
+                },
+            ),
+            Annotation::new(
+                TimeTick::build(9, 125.5),
+                Event::Life {
+                    player1: Some("-4".to_string()),
+                    player2: None,
+                },
+            ),
+            Annotation::new(TimeTick::build(60, 0.0), Event::Win { player: 2 }),
+        ];
+        AnnotationFile::new(Header::new(setup), events)
+    }
+
+    #[test]
+    fn test_json_lines_round_trip() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let file = sample_file();
+        let mut out = Vec::new();
+        write_json_lines(&mut out, &file)?;
+        assert_eq!(StorageFormat::detect(&out), StorageFormat::JsonLines);
+        assert_eq!(parse_json_lines(out.as_slice())?, file);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sqlite_round_trip() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let file = sample_file();
+        let mut conn = Connection::open_in_memory()?;
+        write_sqlite(&mut conn, &file)?;
+        assert_eq!(read_sqlite(&conn)?, file);
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_replay() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let file = sample_file();
+        let dir = tempfile::tempdir()?;
+        let fp = dir.path().join("match.session.jsonl");
+        let fp = fp.to_str().unwrap();
+
+        let mut session = SessionWriter::create(fp, &file.header)?;
+        session.push(&file.events[0])?;
+        session.push(&file.events[2])?;
+        session.undo()?;
+        session.push(&file.events[1])?;
+        session.push(&file.events[2])?;
+        drop(session);
+        // crash mid-write
+        OpenOptions::new()
+            .append(true)
+            .open(fp)?
+            .write_all(b"{\"sec\":61,\"mil")?;
+        assert_eq!(read_session(fp)?, file);
+
+        let mut session = SessionWriter::create_from(fp, &read_session(fp)?)?;
+        session.undo()?;
+        assert_eq!(read_session(fp)?.events, file.events[..2]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_not_overwritten() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let file = sample_file();
+        let dir = tempfile::tempdir()?;
+        let fp = dir.path().join("match.session.jsonl");
+        let fp = fp.to_str().unwrap();
+
+        let mut session = SessionWriter::create(fp, &file.header)?;
+        session.push(&file.events[0])?;
+        drop(session);
+        // starting over without resuming keeps the crashed session
+        assert!(matches!(
+            SessionWriter::create(fp, &file.header),
+            Err(AnnotationError::Io(err)) if err.kind() == std::io::ErrorKind::AlreadyExists
+        ));
+        assert_eq!(read_session(fp)?.events, file.events[..1]);
+
+        SessionWriter::create_from(fp, &read_session(fp)?)?.finish()?;
+        assert!(!Path::new(fp).exists());
+        SessionWriter::create(fp, &file.header)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_replaces() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let file = sample_file();
+        let dir = tempfile::tempdir()?;
+        let fp = dir.path().join("match.tsv");
+        let fp = fp.to_str().unwrap();
+
+        std::fs::write(fp, "old contents")?;
+        write_to_path(fp, &file, StorageFormat::Tsv)?;
+        assert_eq!(read_from_path(fp)?, file);
+        assert!(!Path::new(&format!("{}{}", fp, TMP_SUFFIX)).exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(StorageFormat::detect(SQLITE_MAGIC), StorageFormat::Sqlite);
+        assert_eq!(StorageFormat::detect(b"#annotation\t2"), StorageFormat::Tsv);
+        assert_eq!(StorageFormat::detect(b"sec\tmilli"), StorageFormat::Tsv);
+    }
+}
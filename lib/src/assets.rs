@@ -0,0 +1,35 @@
+//! Finds files in the data folder, like the card data, images and hero animations, under the
+//! configured `data_root`, so every tool reads and writes the same ones wherever it's run from.
+//! Paths in the data folder are kept relative, e.g. `data/card.csv`, and only resolved here
+//! when they're opened.
+
+use std::path::Path;
+
+use crate::config::Config;
+
+/// `fp`, relative to the data root like `data/card.csv`, as a path that can be opened
+pub fn path(fp: &str) -> String {
+    resolve(&Config::get().data_root, fp)
+}
+
+fn resolve(root: &str, fp: &str) -> String {
+    if root == "." || Path::new(fp).is_absolute() {
+        return fp.to_owned();
+    }
+    Path::new(root).join(fp).to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(resolve(".", "data/card.csv"), "data/card.csv");
+        assert_eq!(
+            resolve("/srv/go-to-one", "data/card.csv"),
+            "/srv/go-to-one/data/card.csv"
+        );
+        assert_eq!(resolve("/srv/go-to-one", "/tmp/logo.png"), "/tmp/logo.png");
+    }
+}
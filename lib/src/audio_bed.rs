@@ -0,0 +1,234 @@
+//! Music played under an intro, or looped under a whole match. It's mixed in by ffmpeg when the
+//! audio is added to the rendered video, so a finished video doesn't need editing just to add
+//! music.
+
+use std::path::Path;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Seconds the music fades out over, at the end of the intro
+const FADE_DURATION: f64 = 2.0;
+// Defaults for a `MusicBed`
+pub const MUSIC_BED_VOLUME: f64 = 0.3;
+pub const DUCK_THRESHOLD: f64 = 0.05;
+pub const DUCK_RATIO: f64 = 8.0;
+/// Milliseconds the music takes to duck once the match gets loud, and to come back up after
+const DUCK_ATTACK: f64 = 20.0;
+const DUCK_RELEASE: f64 = 500.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioBed {
+    pub fp: String,
+    /// Seconds the music plays for, including the fade out
+    pub duration: f64,
+    /// 1 leaves the music as it is
+    pub volume: f64,
+}
+
+impl AudioBed {
+    pub fn build(fp: &str, duration: f64, volume: f64) -> Result<Self> {
+        if !Path::new(fp).is_file() {
+            return Err(format!("Couldn't find music {}", fp).into());
+        }
+        Ok(AudioBed {
+            fp: fp.to_owned(),
+            duration,
+            volume,
+        })
+    }
+
+    /// Seconds into the video the music starts fading out
+    fn fade_start(&self) -> f64 {
+        (self.duration - FADE_DURATION).max(0.0)
+    }
+
+    /// ffmpeg filter graph giving `[a]`. The music is input `bed_input`. With `audio_input`,
+    /// that input's audio is silent under the music and fades in as the music fades out.
+    pub fn filter(&self, bed_input: usize, audio_input: Option<usize>) -> String {
+        let fade = self.duration - self.fade_start();
+        let bed = format!(
+            "[{}:a]atrim=0:{},volume={},afade=t=out:st={}:d={}",
+            bed_input,
+            self.duration,
+            self.volume,
+            self.fade_start(),
+            fade
+        );
+        match audio_input {
+            Some(audio_input) => format!(
+                "{}[bed];[{}:a]volume=0:enable='lt(t,{})',afade=t=in:st={}:d={}[main];\
+                 [main][bed]amix=inputs=2:duration=first:normalize=0[a]",
+                bed,
+                audio_input,
+                self.fade_start(),
+                self.fade_start(),
+                fade
+            ),
+            None => format!("{}[a]", bed),
+        }
+    }
+
+    /// Arguments for an ffmpeg command whose video is input 0 and which already has
+    /// `bed_input` inputs. Adds the music as an input and maps the video and mixed audio.
+    pub fn ffmpeg_args(&self, bed_input: usize, audio_input: Option<usize>) -> Vec<String> {
+        [
+            "-i",
+            &self.fp,
+            "-filter_complex",
+            &self.filter(bed_input, audio_input),
+            "-map",
+            "0:v",
+            "-map",
+            "[a]",
+            "-c:v",
+            "copy",
+            "-c:a",
+            "aac",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect()
+    }
+}
+
+/// Music looped under the match audio for as long as the video runs. It ducks while the match
+/// audio is loud, measured by its RMS, so commentary stays on top.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MusicBed {
+    pub fp: String,
+    /// Volume of the music while the match is quiet, where 1 leaves it as it is
+    pub volume: f64,
+    /// Volume of the match audio, which can be 0 to leave only the music
+    pub match_volume: f64,
+    /// Loudness of the match audio, from 0 to 1, above which the music ducks
+    pub threshold: f64,
+    /// How hard the music ducks above the threshold, e.g. 8 for 8:1
+    pub ratio: f64,
+}
+
+impl MusicBed {
+    pub fn build(
+        fp: &str,
+        volume: f64,
+        match_volume: f64,
+        threshold: f64,
+        ratio: f64,
+    ) -> Result<Self> {
+        if !Path::new(fp).is_file() {
+            return Err(format!("Couldn't find music {}", fp).into());
+        }
+        // ffmpeg's sidechaincompress limits
+        if !(0.001..=1.0).contains(&threshold) {
+            return Err(format!("Duck threshold {} isn't between 0.001 and 1", threshold).into());
+        }
+        if !(1.0..=20.0).contains(&ratio) {
+            return Err(format!("Duck ratio {} isn't between 1 and 20", ratio).into());
+        }
+        Ok(MusicBed {
+            fp: fp.to_owned(),
+            volume,
+            match_volume,
+            threshold,
+            ratio,
+        })
+    }
+
+    /// ffmpeg filter graph giving `[a]`. The music is input `bed_input` and the match audio is
+    /// input `audio_input`, which also drives the ducking.
+    pub fn filter(&self, bed_input: usize, audio_input: usize) -> String {
+        format!(
+            "[{}:a]volume={},asplit=2[main][key];[{}:a]volume={}[music];\
+             [music][key]sidechaincompress=threshold={}:ratio={}:attack={}:release={}:\
+             detection=rms[ducked];[main][ducked]amix=inputs=2:duration=first:normalize=0[a]",
+            audio_input,
+            self.match_volume,
+            bed_input,
+            self.volume,
+            self.threshold,
+            self.ratio,
+            DUCK_ATTACK,
+            DUCK_RELEASE
+        )
+    }
+
+    /// Arguments for an ffmpeg command whose video is input 0 and which already has
+    /// `bed_input` inputs. Adds the music as an endlessly looping input, which stops with the
+    /// match audio, and maps the video and mixed audio.
+    pub fn ffmpeg_args(&self, bed_input: usize, audio_input: usize) -> Vec<String> {
+        [
+            "-stream_loop",
+            "-1",
+            "-i",
+            &self.fp,
+            "-filter_complex",
+            &self.filter(bed_input, audio_input),
+            "-map",
+            "0:v",
+            "-map",
+            "[a]",
+            "-c:v",
+            "copy",
+            "-c:a",
+            "aac",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn bed() -> AudioBed {
+        AudioBed {
+            fp: "music.mp3".to_string(),
+            duration: 8.0,
+            volume: 0.5,
+        }
+    }
+
+    #[test]
+    fn test_filter() {
+        assert_eq!(
+            bed().filter(1, None),
+            "[1:a]atrim=0:8,volume=0.5,afade=t=out:st=6:d=2[a]"
+        );
+        let filter = bed().filter(2, Some(1));
+        assert!(filter.starts_with("[2:a]atrim=0:8"));
+        assert!(filter.contains("[1:a]volume=0:enable='lt(t,6)',afade=t=in:st=6:d=2[main]"));
+        assert!(filter.ends_with("[main][bed]amix=inputs=2:duration=first:normalize=0[a]"));
+    }
+
+    #[test]
+    fn test_music_bed_filter() {
+        let bed = MusicBed {
+            fp: "music.mp3".to_string(),
+            volume: 0.3,
+            match_volume: 1.0,
+            threshold: 0.05,
+            ratio: 8.0,
+        };
+        let filter = bed.filter(2, 1);
+        assert!(filter.starts_with("[1:a]volume=1,asplit=2[main][key];[2:a]volume=0.3[music];"));
+        assert!(filter.contains(
+            "[music][key]sidechaincompress=threshold=0.05:ratio=8:attack=20:release=500:\
+             detection=rms[ducked]"
+        ));
+        assert!(filter.ends_with("[main][ducked]amix=inputs=2:duration=first:normalize=0[a]"));
+        assert_eq!(
+            &bed.ffmpeg_args(2, 1)[..4],
+            ["-stream_loop", "-1", "-i", "music.mp3"]
+        );
+    }
+
+    #[test]
+    fn test_short_intro() {
+        let bed = AudioBed {
+            duration: 1.0,
+            ..bed()
+        };
+        assert!(bed.filter(1, None).contains("afade=t=out:st=0:d=1"));
+    }
+}
@@ -0,0 +1,207 @@
+//! Lines up two exports of the same footage by their audio, for moving an annotation made
+//! against one onto the other. The audio is boiled down to an onset envelope, how suddenly it
+//! gets louder every hundredth of a second, so steady noise like venue hum or a different mix
+//! level doesn't matter. The envelopes are then cross-correlated, or for a clap or shuffle at
+//! the start, their first big onsets are matched.
+
+use std::process::Command;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Samples per second the audio is decoded at, plenty for finding onsets
+const SAMPLE_RATE: u32 = 8000;
+/// Envelope values per second
+pub const ENVELOPE_RATE: f64 = 100.0;
+/// Keeps silence from going to negative infinity in the log energy
+const SILENCE: f64 = 1e-6;
+/// Fraction of the loudest onset a tap has to reach to count
+const TAP_THRESHOLD: f64 = 0.5;
+/// Fraction of the reference that has to overlap the target for a lag to be scored
+const MIN_OVERLAP: f64 = 0.5;
+
+/// Mono audio of `fp` from `start` for `duration` seconds, at `SAMPLE_RATE`
+pub fn decode_audio(fp: &str, start: f64, duration: f64) -> Result<Vec<f32>> {
+    let output = Command::new("ffmpeg")
+        .args([
+            "-v",
+            "error",
+            "-ss",
+            &start.to_string(),
+            "-t",
+            &duration.to_string(),
+        ])
+        .args(["-i", fp, "-vn", "-ac", "1", "-ar", &SAMPLE_RATE.to_string()])
+        .args(["-f", "f32le", "-"])
+        .output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "ffmpeg couldn't read the audio of {}: {}",
+            fp,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(output
+        .stdout
+        .chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect())
+}
+
+/// How much louder each frame of `samples` is than the one before, in log energy. Frames that
+/// get quieter count as 0.
+pub fn onset_envelope(samples: &[f32], sample_rate: u32) -> Vec<f64> {
+    let frame = ((sample_rate as f64 / ENVELOPE_RATE) as usize).max(1);
+    let energy: Vec<f64> = samples
+        .chunks(frame)
+        .map(|chunk| {
+            let power = chunk.iter().map(|v| (*v as f64).powi(2)).sum::<f64>() / chunk.len() as f64;
+            (power.sqrt() + SILENCE).ln()
+        })
+        .collect();
+    std::iter::once(0.0)
+        .chain(energy.windows(2).map(|pair| (pair[1] - pair[0]).max(0.0)))
+        .collect()
+}
+
+/// Scaled to a mean of 0 and a standard deviation of 1, or all 0 when it's flat
+fn standardize(values: &[f64]) -> Vec<f64> {
+    let mean = values.iter().sum::<f64>() / values.len().max(1) as f64;
+    let variance =
+        values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len().max(1) as f64;
+    let deviation = variance.sqrt();
+    if deviation == 0.0 {
+        return vec![0.0; values.len()];
+    }
+    values.iter().map(|v| (v - mean) / deviation).collect()
+}
+
+/// Where `reference` sits in `target`, in envelope frames from the start of `target`, and how
+/// well it matches there from -1 to 1. It can hang off either end by up to half its length.
+/// `None` when either is empty.
+pub fn best_lag(reference: &[f64], target: &[f64]) -> Option<(isize, f64)> {
+    if reference.is_empty() || target.is_empty() {
+        return None;
+    }
+    let reference = standardize(reference);
+    let target = standardize(target);
+    let min_overlap = ((reference.len() as f64 * MIN_OVERLAP) as usize).max(1);
+    let first = min_overlap as isize - reference.len() as isize;
+    let last = target.len() as isize - min_overlap as isize;
+    (first..=last)
+        .map(|lag| {
+            let (mut sum, mut count) = (0.0, 0);
+            for (i, value) in reference.iter().enumerate() {
+                let j = lag + i as isize;
+                if j >= 0 && (j as usize) < target.len() {
+                    sum += value * target[j as usize];
+                    count += 1;
+                }
+            }
+            (lag, if count == 0 { 0.0 } else { sum / count as f64 })
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// Frame of the first onset at least `TAP_THRESHOLD` of the loudest, e.g. a clap or shuffle
+/// made to sync the files
+pub fn first_tap(envelope: &[f64]) -> Option<usize> {
+    let loudest = envelope.iter().cloned().fold(0.0, f64::max);
+    if loudest <= 0.0 {
+        return None;
+    }
+    envelope
+        .iter()
+        .position(|onset| *onset >= loudest * TAP_THRESHOLD)
+}
+
+/// Seconds to add to a timestamp in the reference to find the same moment in the target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SyncOffset {
+    pub seconds: f64,
+    /// How well the audio matched, from -1 to 1. Taps always match.
+    pub score: f64,
+}
+
+impl SyncOffset {
+    /// Cross-correlates `window` seconds of the reference from `at` against the target, which
+    /// can be up to `max_offset` seconds either way
+    pub fn correlate(
+        reference_fp: &str,
+        target_fp: &str,
+        at: f64,
+        window: f64,
+        max_offset: f64,
+    ) -> Result<Self> {
+        let reference = onset_envelope(&decode_audio(reference_fp, at, window)?, SAMPLE_RATE);
+        let target_start = (at - max_offset).max(0.0);
+        let target_duration = at + window + max_offset - target_start;
+        let target = onset_envelope(
+            &decode_audio(target_fp, target_start, target_duration)?,
+            SAMPLE_RATE,
+        );
+        let (lag, score) = best_lag(&reference, &target).ok_or("One of the files has no audio")?;
+        Ok(SyncOffset {
+            seconds: target_start + lag as f64 / ENVELOPE_RATE - at,
+            score,
+        })
+    }
+
+    /// Matches the first tap in `window` seconds from `at` of each file
+    pub fn taps(reference_fp: &str, target_fp: &str, at: f64, window: f64) -> Result<Self> {
+        let tap = |fp: &str| -> Result<usize> {
+            let envelope = onset_envelope(&decode_audio(fp, at, window)?, SAMPLE_RATE);
+            first_tap(&envelope).ok_or_else(|| format!("Couldn't hear a tap in {}", fp).into())
+        };
+        let reference = tap(reference_fp)?;
+        let target = tap(target_fp)?;
+        Ok(SyncOffset {
+            seconds: (target as f64 - reference as f64) / ENVELOPE_RATE,
+            score: 1.0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Quiet noise with a burst wherever `bursts` says, in seconds
+    fn audio(seconds: f64, bursts: &[f64]) -> Vec<f32> {
+        let len = (seconds * SAMPLE_RATE as f64) as usize;
+        (0..len)
+            .map(|i| {
+                let t = i as f64 / SAMPLE_RATE as f64;
+                let hum = 0.01 * (i as f32 * 0.7).sin();
+                let burst = bursts
+                    .iter()
+                    .any(|start| (*start..start + 0.05).contains(&t));
+                if burst {
+                    hum + 0.8 * (i as f32 * 1.3).sin()
+                } else {
+                    hum
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_best_lag() {
+        let bursts = [0.5, 1.3, 1.7, 2.9, 3.4];
+        let reference = onset_envelope(&audio(4.0, &bursts), SAMPLE_RATE);
+        // The same sound 1.25s later
+        let shifted: Vec<f64> = bursts.iter().map(|t| t + 1.25).collect();
+        let target = onset_envelope(&audio(6.0, &shifted), SAMPLE_RATE);
+        let (lag, score) = best_lag(&reference, &target).unwrap();
+        assert_eq!(lag, 125);
+        assert!(score > 0.9);
+        assert_eq!(best_lag(&[], &target), None);
+    }
+
+    #[test]
+    fn test_first_tap() {
+        let envelope = onset_envelope(&audio(3.0, &[1.2, 2.0]), SAMPLE_RATE);
+        assert_eq!(first_tap(&envelope), Some(120));
+        assert_eq!(first_tap(&[0.0; 10]), None);
+    }
+}
@@ -24,47 +24,125 @@ pub fn autocomplete<'a, T: Named>(values: &'a [T], text: &str) -> Vec<&'a T> {
 }
 
 pub fn autocomplete_index<T: Named>(values: &[T], text: &str) -> VecDeque<usize> {
+    autocomplete_index_with(values, text, name_matches)
+}
+
+/// Like `autocomplete_index`, but with a custom test for whether an item matches the text
+pub fn autocomplete_index_with<T>(
+    values: &[T],
+    text: &str,
+    matcher: fn(&T, &str) -> bool,
+) -> VecDeque<usize> {
     values
         .iter()
         .enumerate()
-        .filter_map(|(idx, item)| {
-            if item
-                .get_name()
-                .to_lowercase()
-                .starts_with(&text.to_lowercase())
-            {
-                Some(idx)
-            } else {
-                None
-            }
-        })
+        .filter_map(|(idx, item)| if matcher(item, text) { Some(idx) } else { None })
         .collect()
 }
 
+/// Case insensitive prefix match on the item's name
+pub fn name_matches<T: Named>(item: &T, text: &str) -> bool {
+    item.get_name()
+        .to_lowercase()
+        .starts_with(&text.to_lowercase())
+}
+
 pub struct AutocompleteSuggestionManager<T: Named> {
     values: Vec<T>,
     suggestions: VecDeque<usize>,
+    matcher: fn(&T, &str) -> bool,
+    /// Sort key for each value, lowest first. `None` hides the value.
+    ranks: Vec<Option<u32>>,
+    /// Lowercased nicknames and the names of the values they stand for
+    aliases: Vec<(String, String)>,
 }
 
 impl<T: Named> AutocompleteSuggestionManager<T> {
     pub fn build(values: Vec<T>) -> Self {
+        Self::build_with_matcher(values, name_matches)
+    }
+
+    /// # Arguments
+    /// * `values` - Items to suggest
+    /// * `matcher` - Whether an item matches the text typed so far
+    pub fn build_with_matcher(values: Vec<T>, matcher: fn(&T, &str) -> bool) -> Self {
+        let ranks = vec![Some(0); values.len()];
         AutocompleteSuggestionManager {
             values,
             suggestions: VecDeque::new(),
+            matcher,
+            ranks,
+            aliases: Vec::new(),
         }
     }
 
+    /// Text that starts an alias also suggests the value named by it, e.g. "cn" suggests
+    /// Command and Conquer through "CnC"
+    pub fn set_aliases<'a>(&mut self, aliases: impl IntoIterator<Item = (&'a str, &'a str)>) {
+        self.aliases = aliases
+            .into_iter()
+            .map(|(alias, name)| (alias.to_lowercase(), name.to_string()))
+            .collect();
+    }
+
+    fn matching(&self, text: &str) -> VecDeque<usize> {
+        let lower = text.to_lowercase();
+        let aliased: Vec<&str> = self
+            .aliases
+            .iter()
+            .filter(|(alias, _)| alias.starts_with(&lower))
+            .map(|(_, name)| name.as_str())
+            .collect();
+        self.values
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| {
+                (self.matcher)(value, text)
+                    || aliased
+                        .iter()
+                        .any(|name| name.eq_ignore_ascii_case(value.get_name()))
+            })
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Reorders suggestions by `rank`, lowest first. Values ranked `None` are never suggested.
+    /// Values with the same rank keep their original order.
+    pub fn prioritize(&mut self, rank: impl Fn(&T) -> Option<u32>) {
+        self.ranks = self.values.iter().map(rank).collect();
+    }
+
     pub fn reset(&mut self) {
         self.suggestions = VecDeque::new();
     }
 
+    /// Suggests the values ranked `Some` without anything typed, lowest rank first, e.g. cards
+    /// recognised on screen
+    pub fn suggest(&mut self, rank: impl Fn(&T) -> Option<u32>) {
+        let mut suggestions: Vec<(u32, usize)> = self
+            .values
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, value)| rank(value).map(|rank| (rank, idx)))
+            .collect();
+        suggestions.sort_by_key(|(rank, _)| *rank);
+        self.suggestions = suggestions.into_iter().map(|(_, idx)| idx).collect();
+    }
+
     pub fn get_user_input_for_autocomplete(&mut self, text: &str, key: KeyEvent) -> String {
         let mut new_text = text.to_owned();
         match key.code {
             KeyCode::Char(c) => {
                 // Add character to current text and update suggestions
                 new_text.push(c);
-                let new_suggestions = autocomplete_index(&self.values, &new_text);
+                let mut new_suggestions: VecDeque<usize> = self
+                    .matching(&new_text)
+                    .into_iter()
+                    .filter(|idx| self.ranks[*idx].is_some())
+                    .collect();
+                new_suggestions
+                    .make_contiguous()
+                    .sort_by_key(|idx| self.ranks[*idx]);
 
                 // Ignore character if no matches
                 if new_suggestions.len() == 0 {
@@ -102,6 +180,11 @@ impl<T: Named> AutocompleteSuggestionManager<T> {
     pub fn current_suggestion(&self) -> Option<&T> {
         self.suggestions.front().map(|idx| &self.values[*idx])
     }
+
+    /// Every current suggestion, starting with the current one
+    pub fn suggestions(&self) -> impl Iterator<Item = &T> {
+        self.suggestions.iter().map(|idx| &self.values[*idx])
+    }
 }
 
 pub enum AutocompleteResult<'a, T: Named> {
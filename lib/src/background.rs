@@ -0,0 +1,239 @@
+//! The animated background behind the video: a gradient under a grid of hexagon outlines that
+//! drifts and pulses. Rendered directly, so its colors can match an event's.
+
+use std::{f64::consts::PI, fmt};
+
+use opencv::{
+    core::{add_weighted, Point, Rect, Scalar, Size, UMat, UMatTraitConst, Vector, CV_8UC3},
+    imgproc::{self, LINE_AA},
+};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Hexagon size, from the center to a corner, relative to the frame height
+const HEX_RADIUS_RATIO: f64 = 1.0 / 10.0;
+const HEX_LINE_THICKNESS: i32 = 2;
+/// Seconds for the grid to drift one hexagon across and down
+const DRIFT_PERIOD: f64 = 12.0;
+/// Seconds for the lines to brighten and dim again
+const PULSE_PERIOD: f64 = 4.0;
+/// Least and most visible the lines get
+const PULSE_MIN: f64 = 0.5;
+const PULSE_MAX: f64 = 1.0;
+
+/// Colors of the background, as "top,bottom,lines" in hex RGB, e.g. "2a2a2a,0a0a0a,5a5a5a"
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    /// Top of the gradient
+    pub top: Scalar,
+    /// Bottom of the gradient
+    pub bottom: Scalar,
+    /// Hexagon outlines
+    pub lines: Scalar,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            top: Scalar::new(235.0, 235.0, 235.0, 0.0),
+            bottom: Scalar::new(190.0, 190.0, 190.0, 0.0),
+            lines: Scalar::new(120.0, 120.0, 120.0, 0.0),
+        }
+    }
+}
+
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{}",
+            hex_color(self.top),
+            hex_color(self.bottom),
+            hex_color(self.lines)
+        )
+    }
+}
+
+/// An opencv BGR color as hex RGB, e.g. "ff8800"
+pub fn hex_color(color: Scalar) -> String {
+    format!(
+        "{:02x}{:02x}{:02x}",
+        color[2] as u8, color[1] as u8, color[0] as u8
+    )
+}
+
+/// "ff8800" or "#ff8800" as an opencv BGR color
+pub fn parse_hex_color(text: &str) -> std::result::Result<Scalar, String> {
+    let hex = text.trim().trim_start_matches('#');
+    let channel = |i: usize| {
+        hex.get(i..i + 2)
+            .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+            .map(f64::from)
+    };
+    match (hex.len(), channel(0), channel(2), channel(4)) {
+        (6, Some(r), Some(g), Some(b)) => Ok(Scalar::new(b, g, r, 0.0)),
+        _ => Err(format!("'{}' isn't a hex color like ff8800", text)),
+    }
+}
+
+/// Parses a `Palette`, for clap
+pub fn parse_palette(text: &str) -> std::result::Result<Palette, String> {
+    let colors = text
+        .split(',')
+        .map(parse_hex_color)
+        .collect::<std::result::Result<Vec<Scalar>, String>>()?;
+    match colors[..] {
+        [top, bottom, lines] => Ok(Palette { top, bottom, lines }),
+        _ => Err(format!(
+            "'{}' isn't three colors like top,bottom,lines",
+            text
+        )),
+    }
+}
+
+pub struct Background {
+    size: Size,
+    fps: f64,
+    frame_index: u64,
+    gradient: UMat,
+    /// Hexagon outlines on black, one grid period bigger than the frame each way so the grid can
+    /// drift by cropping it
+    grid: UMat,
+    /// Distance the grid repeats over, across and down
+    period: Point,
+}
+
+impl Background {
+    pub fn build(size: Size, palette: Palette, fps: f64) -> Result<Self> {
+        let radius = size.height as f64 * HEX_RADIUS_RATIO;
+        // Flat topped hexagons, with every other column half a row down
+        let column_width = 1.5 * radius;
+        let row_height = 3f64.sqrt() * radius;
+        let period = Point::new((2.0 * column_width) as i32, row_height as i32);
+
+        let mut gradient = UMat::new_size_with_default_def(size, CV_8UC3, palette.top)?;
+        for y in 0..size.height {
+            let t = y as f64 / size.height.max(1) as f64;
+            let mix = |i: usize| palette.top[i] * (1.0 - t) + palette.bottom[i] * t;
+            let color = Scalar::new(mix(0), mix(1), mix(2), 0.0);
+            imgproc::rectangle(
+                &mut gradient,
+                Rect::new(0, y, size.width, 1),
+                color,
+                -1,
+                imgproc::LINE_8,
+                0,
+            )?;
+        }
+
+        let grid_size = Size::new(size.width + period.x, size.height + period.y);
+        let mut grid = UMat::new_size_with_default_def(grid_size, CV_8UC3, Scalar::all(0.0))?;
+        let mut hexagons: Vector<Vector<Point>> = Vector::new();
+        let columns = (grid_size.width as f64 / column_width).ceil() as i32 + 1;
+        let rows = (grid_size.height as f64 / row_height).ceil() as i32 + 1;
+        for column in 0..columns {
+            for row in 0..rows {
+                let offset = if column % 2 == 1 {
+                    row_height / 2.0
+                } else {
+                    0.0
+                };
+                let center = (
+                    column as f64 * column_width,
+                    row as f64 * row_height + offset,
+                );
+                hexagons.push(hexagon(center, radius));
+            }
+        }
+        imgproc::polylines(
+            &mut grid,
+            &hexagons,
+            true,
+            palette.lines,
+            HEX_LINE_THICKNESS,
+            LINE_AA,
+            0,
+        )?;
+
+        Ok(Background {
+            size,
+            fps,
+            frame_index: 0,
+            gradient,
+            grid,
+            period,
+        })
+    }
+
+    /// Draws the background `seconds` into the animation to `frame`
+    pub fn draw_at(&self, seconds: f64, frame: &mut UMat) -> Result<()> {
+        let drift = (seconds / DRIFT_PERIOD).fract();
+        let offset = Point::new(
+            (drift * self.period.x as f64) as i32 % self.period.x.max(1),
+            (drift * self.period.y as f64) as i32 % self.period.y.max(1),
+        );
+        let grid = self.grid.roi(Rect::new(
+            offset.x,
+            offset.y,
+            self.size.width,
+            self.size.height,
+        ))?;
+        add_weighted(&self.gradient, 1.0, &grid, pulse(seconds), 0.0, frame, -1)?;
+        Ok(())
+    }
+
+    /// Draws the next frame of the animation, as a video at the background's frame rate, to
+    /// `frame`
+    pub fn read(&mut self, frame: &mut UMat) -> Result<()> {
+        self.draw_at(self.frame_index as f64 / self.fps, frame)?;
+        self.frame_index += 1;
+        Ok(())
+    }
+}
+
+/// How visible the lines are at `seconds`
+fn pulse(seconds: f64) -> f64 {
+    let wave = (1.0 - (2.0 * PI * seconds / PULSE_PERIOD).cos()) / 2.0;
+    PULSE_MIN + (PULSE_MAX - PULSE_MIN) * wave
+}
+
+/// Corners of a flat topped hexagon
+fn hexagon(center: (f64, f64), radius: f64) -> Vector<Point> {
+    (0..6)
+        .map(|i| {
+            let angle = PI / 3.0 * i as f64;
+            Point::new(
+                (center.0 + radius * angle.cos()).round() as i32,
+                (center.1 + radius * angle.sin()).round() as i32,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_palette() {
+        let palette = parse_palette("#ff8800,000000,0a0b0c").unwrap();
+        assert_eq!(palette.top, Scalar::new(0.0, 136.0, 255.0, 0.0));
+        assert_eq!(palette.lines, Scalar::new(12.0, 11.0, 10.0, 0.0));
+        assert!(parse_palette("ff8800,000000").is_err());
+        assert!(parse_palette("ff88zz,000000,000000").is_err());
+        assert_eq!(parse_palette(&palette.to_string()), Ok(palette));
+    }
+
+    #[test]
+    fn test_frame() -> Result<()> {
+        let size = Size::new(320, 180);
+        let mut background = Background::build(size, Palette::default(), 30.0)?;
+        let mut frame = UMat::new_def();
+        background.read(&mut frame)?;
+        assert_eq!(frame.size()?, size);
+        assert_eq!(frame.typ(), CV_8UC3);
+        assert_eq!(pulse(0.0), PULSE_MIN);
+        assert_eq!(pulse(PULSE_PERIOD / 2.0), PULSE_MAX);
+        Ok(())
+    }
+}
@@ -0,0 +1,286 @@
+//! Top cut brackets and Swiss standings, drawn as slides for the intro. Definitions are JSON,
+//! from a file or a URL, e.g.
+//! `{"type": "bracket", "title": "Top 8", "rounds": [{"name": "Finals", "matches":
+//! [{"player1": "Ada", "player2": "Bo", "score1": 2, "score2": 1}]}]}` or
+//! `{"type": "standings", "title": "After round 7", "rows": [{"player": "Ada", "record": "6-1"}]}`.
+
+use std::fs::File;
+
+use opencv::{
+    core::{Point, Rect, Scalar, UMat, UMatTraitConst},
+    imgproc::{self, FONT_HERSHEY_SIMPLEX, LINE_8},
+};
+use serde::Deserialize;
+
+use crate::text::center_text_at_rect;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const FONT_FACE: i32 = FONT_HERSHEY_SIMPLEX;
+const TITLE_FONT_SCALE: f64 = 3.0;
+const TITLE_FONT_THICKNESS: i32 = 6;
+const FONT_SCALE: f64 = 1.5;
+const FONT_THICKNESS: i32 = 3;
+const FONT_BUFFER: i32 = 20;
+const WHITE: Scalar = Scalar::new(255.0, 255.0, 255.0, 0.0);
+/// Gold, for whoever won a match
+const WINNER_COLOR: Scalar = Scalar::new(0.0, 215.0, 255.0, 0.0);
+const BOX_THICKNESS: i32 = 3;
+/// The title takes this much of the top of the frame, the bracket or table the rest
+const TITLE_HEIGHT_RATIO: f64 = 1.0 / 6.0;
+/// Space around the bracket or table, relative to the frame
+const MARGIN_RATIO: f64 = 1.0 / 20.0;
+/// Most rows of standings that fit on a slide
+pub const MAX_STANDINGS_ROWS: usize = 16;
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Match {
+    pub player1: String,
+    pub player2: String,
+    pub score1: Option<u32>,
+    pub score2: Option<u32>,
+}
+
+impl Match {
+    /// Player 1 or 2 once the scores say who won
+    pub fn winner(&self) -> Option<u8> {
+        match (self.score1?, self.score2?) {
+            (a, b) if a > b => Some(1),
+            (a, b) if b > a => Some(2),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Round {
+    /// e.g. "Quarterfinals"
+    pub name: String,
+    pub matches: Vec<Match>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Standing {
+    pub player: String,
+    pub hero: Option<String>,
+    /// e.g. "5-1"
+    pub record: String,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Bracket {
+    /// Single elimination, the first round on the left
+    Bracket { title: String, rounds: Vec<Round> },
+    /// Swiss standings, best first
+    Standings { title: String, rows: Vec<Standing> },
+}
+
+impl Bracket {
+    /// From a local JSON file, or a URL that serves the same JSON
+    pub fn load(source: &str) -> Result<Self> {
+        if source.starts_with("http://") || source.starts_with("https://") {
+            let text = reqwest::blocking::get(source)?.error_for_status()?.text()?;
+            return Ok(serde_json::from_str(&text)?);
+        }
+        let file =
+            File::open(source).map_err(|e| format!("Couldn't open bracket {}: {}", source, e))?;
+        Ok(serde_json::from_reader(file)?)
+    }
+
+    pub fn title(&self) -> &str {
+        match self {
+            Bracket::Bracket { title, .. } | Bracket::Standings { title, .. } => title,
+        }
+    }
+
+    /// Draws the title and the bracket or standings over `frame`
+    pub fn draw(&self, frame: &mut UMat) -> Result<()> {
+        let size = frame.size()?;
+        let margin_x = (size.width as f64 * MARGIN_RATIO) as i32;
+        let margin_y = (size.height as f64 * MARGIN_RATIO) as i32;
+        let title_height = (size.height as f64 * TITLE_HEIGHT_RATIO) as i32;
+        center_text_at_rect(
+            frame,
+            self.title(),
+            FONT_FACE,
+            TITLE_FONT_SCALE,
+            WHITE,
+            TITLE_FONT_THICKNESS,
+            Rect::new(
+                size.width.div_euclid(5),
+                margin_y,
+                3 * size.width.div_euclid(5),
+                title_height - margin_y,
+            ),
+            FONT_BUFFER,
+        )?;
+        let area = Rect::new(
+            margin_x,
+            title_height + margin_y,
+            size.width - 2 * margin_x,
+            size.height - title_height - 2 * margin_y,
+        );
+        match self {
+            Bracket::Bracket { rounds, .. } => draw_rounds(frame, rounds, area),
+            Bracket::Standings { rows, .. } => draw_standings(frame, rows, area),
+        }
+    }
+}
+
+/// One column per round, each round's matches spread evenly down it, so a match sits level with
+/// the two that fed it as in a printed bracket
+fn draw_rounds(frame: &mut UMat, rounds: &[Round], area: Rect) -> Result<()> {
+    if rounds.is_empty() {
+        return Ok(());
+    }
+    let column_width = area.width / rounds.len() as i32;
+    let header_height = area.height / 10;
+    let matches_height = area.height - header_height;
+    let box_width = 4 * column_width / 5;
+    let most_matches = rounds
+        .iter()
+        .map(|round| round.matches.len())
+        .max()
+        .unwrap_or(1);
+    let box_height = (3 * matches_height / (4 * most_matches.max(1) as i32)).min(area.height / 4);
+    for (column, round) in rounds.iter().enumerate() {
+        let x = area.x + column as i32 * column_width + (column_width - box_width) / 2;
+        center_text_at_rect(
+            frame,
+            &round.name,
+            FONT_FACE,
+            FONT_SCALE,
+            WHITE,
+            FONT_THICKNESS,
+            Rect::new(x, area.y, box_width, header_height),
+            FONT_BUFFER,
+        )?;
+        let span = matches_height / round.matches.len().max(1) as i32;
+        for (i, game) in round.matches.iter().enumerate() {
+            let y = area.y + header_height + i as i32 * span + (span - box_height) / 2;
+            draw_match(frame, game, Rect::new(x, y, box_width, box_height))?;
+        }
+    }
+    Ok(())
+}
+
+/// A box with player 1 over player 2, each with their score. The winner is in gold.
+fn draw_match(frame: &mut UMat, game: &Match, rect: Rect) -> Result<()> {
+    imgproc::rectangle(frame, rect, WHITE, BOX_THICKNESS, LINE_8, 0)?;
+    let line_height = rect.height / 2;
+    let lines = [
+        (&game.player1, game.score1, 1),
+        (&game.player2, game.score2, 2),
+    ];
+    for (i, (player, score, number)) in lines.into_iter().enumerate() {
+        let text = match score {
+            Some(score) => format!("{}  {}", player, score),
+            None => player.clone(),
+        };
+        let color = if game.winner() == Some(number) {
+            WINNER_COLOR
+        } else {
+            WHITE
+        };
+        center_text_at_rect(
+            frame,
+            &text,
+            FONT_FACE,
+            FONT_SCALE,
+            color,
+            FONT_THICKNESS,
+            Rect::new(
+                rect.x + BOX_THICKNESS,
+                rect.y + i as i32 * line_height + BOX_THICKNESS,
+                rect.width - 2 * BOX_THICKNESS,
+                line_height - 2 * BOX_THICKNESS,
+            ),
+            FONT_BUFFER,
+        )?;
+    }
+    Ok(())
+}
+
+/// A numbered row per player, split between two columns past half of `MAX_STANDINGS_ROWS`
+fn draw_standings(frame: &mut UMat, rows: &[Standing], area: Rect) -> Result<()> {
+    let rows = &rows[..rows.len().min(MAX_STANDINGS_ROWS)];
+    let per_column = MAX_STANDINGS_ROWS / 2;
+    let columns = rows.len().div_ceil(per_column).max(1) as i32;
+    let column_width = area.width / columns;
+    let row_height = area.height / per_column.min(rows.len()).max(1) as i32;
+    let row_height = row_height.min(area.height / 6);
+    for (i, standing) in rows.iter().enumerate() {
+        let column = (i / per_column) as i32;
+        let row = (i % per_column) as i32;
+        let mut text = format!("{}. {}", i + 1, standing.player);
+        if let Some(hero) = &standing.hero {
+            text.push_str(&format!(" ({})", hero));
+        }
+        text.push_str(&format!("  {}", standing.record));
+        let rect = Rect::new(
+            area.x + column * column_width,
+            area.y + row * row_height,
+            column_width,
+            row_height,
+        );
+        center_text_at_rect(
+            frame,
+            &text,
+            FONT_FACE,
+            FONT_SCALE,
+            WHITE,
+            FONT_THICKNESS,
+            rect,
+            FONT_BUFFER,
+        )?;
+        imgproc::line(
+            frame,
+            Point::new(rect.x, rect.y + rect.height),
+            Point::new(rect.x + rect.width, rect.y + rect.height),
+            WHITE,
+            1,
+            LINE_8,
+            0,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let bracket: Bracket = serde_json::from_str(
+            r#"{"type": "bracket", "title": "Top 8", "rounds": [{"name": "Finals", "matches":
+            [{"player1": "Ada", "player2": "Bo", "score1": 1, "score2": 2}]}]}"#,
+        )
+        .unwrap();
+        let Bracket::Bracket { title, rounds } = bracket else {
+            panic!("not a bracket")
+        };
+        assert_eq!(title, "Top 8");
+        assert_eq!(rounds[0].matches[0].winner(), Some(2));
+
+        let standings: Bracket = serde_json::from_str(
+            r#"{"type": "standings", "title": "Swiss", "rows": [{"player": "Ada", "record": "6-1"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(standings.title(), "Swiss");
+    }
+
+    #[test]
+    fn test_winner() {
+        let game = |score1, score2| Match {
+            player1: "Ada".to_string(),
+            player2: "Bo".to_string(),
+            score1,
+            score2,
+        };
+        assert_eq!(game(Some(2), Some(0)).winner(), Some(1));
+        assert_eq!(game(Some(1), Some(1)).winner(), None);
+        assert_eq!(game(None, Some(2)).winner(), None);
+    }
+}
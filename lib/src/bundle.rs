@@ -0,0 +1,92 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use tracing::warn;
+
+use crate::{
+    assets,
+    card::{CardImageDB, CARD_IMAGE_DIR},
+    image::FullArtHeroManager,
+};
+
+/// Files the overlay reads for every match, relative to the data root. Missing ones are
+/// skipped, e.g. the card store when it hasn't been built.
+const BUNDLE_FILES: [&str; 9] = [
+    "data/card.csv",
+    "data/card_data.csv",
+    "data/cards.sqlite",
+    "data/printings.json",
+    "data/full_art_hero_map.json",
+    "data/asset_link.json",
+    "data/cardback.png",
+    "data/image.png",
+    "data/life.png",
+];
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// What went into a bundle
+#[derive(Debug, Clone, Default)]
+pub struct BundleReport {
+    pub files: Vec<PathBuf>,
+    /// Cards, heroes and files that couldn't be added, with the reason
+    pub missing: Vec<String>,
+}
+
+/// Copies everything needed to render `cards` and `heroes` into `dir`, laid out like the
+/// data root so the overlay can be run from `dir` without a network connection.
+/// Card images are downloaded into `CARD_IMAGE_DIR`, which is read before the network.
+pub async fn export(
+    dir: &Path,
+    cards: &[(String, Option<u32>)],
+    heroes: &[String],
+) -> Result<BundleReport> {
+    let mut report = BundleReport::default();
+    let copy = |fp: &str, report: &mut BundleReport| -> Result<()> {
+        let target = dir.join(fp);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(assets::path(fp), &target)?;
+        report.files.push(target);
+        Ok(())
+    };
+
+    for fp in BUNDLE_FILES {
+        if Path::new(&assets::path(fp)).exists() {
+            copy(fp, &mut report)?;
+        }
+    }
+
+    let heroes_manager = FullArtHeroManager::new();
+    for hero in heroes {
+        match heroes_manager.hero_art_files(hero) {
+            Ok(animations) => {
+                for fp in animations.iter() {
+                    if let Err(e) = copy(fp, &mut report) {
+                        report.missing.push(format!("{}: {}", fp, e));
+                    }
+                }
+            }
+            Err(e) => report.missing.push(e.to_string()),
+        }
+    }
+
+    let image_dir = dir.join(CARD_IMAGE_DIR);
+    fs::create_dir_all(&image_dir)?;
+    let card_db = CardImageDB::init();
+    for (name, pitch) in cards {
+        match card_db.save_card_image(name, *pitch, &image_dir).await {
+            Ok(fp) => report.files.push(fp),
+            Err(e) => {
+                warn!("Couldn't bundle {} ({:?}): {}", name, pitch, e);
+                report
+                    .missing
+                    .push(format!("{} ({:?}): {}", name, pitch, e));
+            }
+        }
+    }
+    Ok(report)
+}
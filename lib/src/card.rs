@@ -1,27 +1,116 @@
 use csv::StringRecord;
-use log::warn;
+use tracing::warn;
 use opencv::{
-    core::{MatTraitConst, UMat, UMatTraitConst, Vector, CV_16U, CV_8U, CV_8UC3},
+    core::{MatTraitConst, Scalar, Size, UMat, UMatTraitConst, Vector, CV_16U, CV_8U, CV_8UC3},
     imgcodecs::{imdecode, IMREAD_COLOR, IMREAD_UNCHANGED},
     imgproc::{cvt_color_def, COLOR_RGBA2RGB},
 };
 
-/// This may need to be replaced with an actual DB at some point
-use std::{collections::HashMap, fs::File};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
 
-use crate::{autocomplete::Named, fade::convert_alpha_to_white};
+use crate::{
+    annotation::{Format, MatchSetup},
+    assets,
+    autocomplete::Named,
+    card_alias::CardAliases,
+    card_store::{CardStore, CARD_STORE_FILE},
+    download::{DownloadConfig, Downloader},
+    fade::convert_alpha_to_white,
+    image::load_image,
+    printing::{Printing, PrintingPolicy},
+};
 
-const URL_FILE: &'static str = "data/card_data.csv";
-const CARD_FILE: &'static str = "data/card.csv";
+pub(crate) const URL_FILE: &'static str = "data/card_data.csv";
+pub(crate) const CARD_FILE: &'static str = "data/card.csv";
+/// Downloaded card images, named after the last part of their url
+pub const CARD_IMAGE_DIR: &'static str = "data/card_images";
+/// Shown in place of a card whose image can't be loaded
+const CARD_PLACEHOLDER_FILE: &'static str = "data/cardback.png";
+/// Size of the blank card shown when even the placeholder can't be loaded
+const PLACEHOLDER_WIDTH: i32 = 450;
+const PLACEHOLDER_HEIGHT: i32 = 628;
+/// Legality columns of the card file. Cards are legal unless marked "No".
+const LEGALITY_COLUMNS: [(&str, Format); 3] = [
+    ("CC Legal", Format::ClassicConstructed),
+    ("Blitz Legal", Format::Blitz),
+    ("UPF Legal", Format::UltimatePitFight),
+];
+/// Symbols in the rules text and the words they stand for
+const RULES_SYMBOLS: [(char, &str); 7] = [
+    ('r', "resource"),
+    ('p', "power"),
+    ('d', "defense"),
+    ('i', "intellect"),
+    ('h', "life"),
+    ('t', "tap"),
+    ('u', "untap"),
+];
+/// Types that are a class, lowercased like `CardData::types`
+const CLASSES: [&str; 18] = [
+    "adjudicator",
+    "assassin",
+    "bard",
+    "brute",
+    "generic",
+    "guardian",
+    "illusionist",
+    "mechanologist",
+    "merchant",
+    "necromancer",
+    "ninja",
+    "pirate",
+    "ranger",
+    "runeblade",
+    "shapeshifter",
+    "thief",
+    "warrior",
+    "wizard",
+];
+/// Types that are a talent, lowercased like `CardData::types`
+const TALENTS: [&str; 12] = [
+    "chaos",
+    "draconic",
+    "earth",
+    "elemental",
+    "ice",
+    "light",
+    "lightning",
+    "mystic",
+    "revered",
+    "reviled",
+    "royal",
+    "shadow",
+];
 
 #[derive(Debug, Clone)]
 pub struct CardData {
     pub name: String,
     pub pitch: Option<u32>,
     pub life: Option<u32>,
+    pub intellect: Option<u32>,
+    pub cost: Option<u32>,
+    /// `None` for cards without power and variable power like X
+    pub power: Option<u32>,
+    pub defense: Option<u32>,
     pub display: String,
     pub uuid: String,
+    /// Every type, class and talent on the type line, lowercased
     pub types: Vec<String>,
+    /// The types that are classes, e.g. "ninja" or "generic"
+    pub classes: Vec<String>,
+    /// The types that are talents, e.g. "draconic"
+    pub talents: Vec<String>,
+    /// Keywords printed on the card, lowercased, e.g. "go again"
+    pub keywords: Vec<String>,
+    /// Formats the card can be played in
+    pub legal: Vec<Format>,
+    /// Rules text as printed in the card file, with `**` around bold words and symbols like
+    /// `{r}`
+    pub text: String,
 }
 
 impl CardData {
@@ -29,6 +118,84 @@ impl CardData {
         self.pitch.map(|v| v.to_string()).unwrap_or("".to_string())
     }
 
+    pub fn is_legal(&self, format: Format) -> bool {
+        self.legal.contains(&format)
+    }
+
+    pub fn has_keyword(&self, keyword: &str) -> bool {
+        self.keywords
+            .iter()
+            .any(|k| k.eq_ignore_ascii_case(keyword))
+    }
+
+    /// Rules text for reading on screen, without the bold markers and with the symbols spelled
+    /// out, e.g. "{r}{r}: Draw" as "2 resource: Draw" and "+1{p}" as "+1 power"
+    pub fn rules_text(&self) -> String {
+        let text = self.text.replace("**", "");
+        let mut plain = String::new();
+        let mut rest = text.as_str();
+        while let Some(c) = rest.chars().next() {
+            let symbol = RULES_SYMBOLS
+                .iter()
+                .map(|(letter, word)| (format!("{{{}}}", letter), word))
+                .find(|(symbol, _)| rest.starts_with(symbol.as_str()));
+            let Some((symbol, word)) = symbol else {
+                plain.push(c);
+                rest = &rest[c.len_utf8()..];
+                continue;
+            };
+            // Count the run of the same symbol, e.g. "{r}{r}{r}"
+            let mut count = 0;
+            while let Some(after) = rest.strip_prefix(symbol.as_str()) {
+                count += 1;
+                rest = after;
+            }
+            // A number already in front, like "+1{p}", is the amount
+            if count == 1 && plain.ends_with(|c: char| c.is_ascii_digit()) {
+                plain.push_str(&format!(" {}", word));
+            } else {
+                plain.push_str(&format!("{} {}", count, word));
+            }
+        }
+        plain
+    }
+
+    /// Splits a type line into its classes and talents
+    pub(crate) fn classes_and_talents(types: &[String]) -> (Vec<String>, Vec<String>) {
+        let pick = |kinds: &[&str]| -> Vec<String> {
+            types
+                .iter()
+                .filter(|t| kinds.contains(&t.as_str()))
+                .cloned()
+                .collect()
+        };
+        (pick(&CLASSES), pick(&TALENTS))
+    }
+
+    /// Young heroes are played in Blitz and Ultimate Pit Fight, adult heroes in Classic
+    /// Constructed
+    pub fn is_young(&self) -> bool {
+        self.types.iter().any(|t| t == "young")
+    }
+
+    /// Format whose defaults apply to this hero: `format` when given, otherwise the one its
+    /// age is played in
+    fn preset(&self, format: Option<Format>) -> Format {
+        format.unwrap_or(Format::for_hero(self.is_young()))
+    }
+
+    /// Life the hero starts with, from its card or else the format's default
+    pub fn starting_life(&self, format: Option<Format>) -> u32 {
+        self.life
+            .unwrap_or_else(|| self.preset(format).default_life())
+    }
+
+    /// Intellect of the hero, from its card or else the format's default
+    pub fn starting_intellect(&self, format: Option<Format>) -> u32 {
+        self.intellect
+            .unwrap_or_else(|| self.preset(format).default_intellect())
+    }
+
     fn build_from_record(headers: &HashMap<String, usize>, record: StringRecord) -> Option<Self> {
         if !headers.contains_key("Name") {
             warn!("Card file missing key {}", "Name");
@@ -41,19 +208,59 @@ impl CardData {
             "3" => " (B)".to_string(),
             _ => "".to_string(),
         };
+        let number = |column: &str| {
+            headers
+                .get(column)
+                .and_then(|idx| record.get(*idx))
+                .and_then(|v| v.parse::<u32>().ok())
+        };
+        let list = |column: &str| -> Vec<String> {
+            headers
+                .get(column)
+                .and_then(|idx| record.get(*idx))
+                .unwrap_or("")
+                .split(",")
+                .map(|v| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty())
+                .collect()
+        };
+        let types: Vec<String> = record
+            .get(headers["Types"])
+            .unwrap_or("")
+            .to_string()
+            .split(",")
+            .map(|v| v.trim().to_lowercase())
+            .collect();
+        let (classes, talents) = CardData::classes_and_talents(&types);
         Some(CardData {
             name: name.clone(),
             pitch: record[headers["Pitch"]].parse::<u32>().ok(),
             life: record[headers["Health"]].parse::<u32>().ok(),
+            intellect: number("Intelligence"),
+            cost: number("Cost"),
+            power: number("Power"),
+            defense: number("Defense"),
             display: format!("{}{}", name, pitch),
             uuid: record[headers["Unique ID"]].to_string(),
-            types: record
-                .get(headers["Types"])
-                .unwrap_or("")
-                .to_string()
-                .split(",")
-                .map(|v| v.trim().to_lowercase())
+            types,
+            classes,
+            talents,
+            keywords: list("Card Keywords"),
+            legal: LEGALITY_COLUMNS
+                .iter()
+                .filter(|(column, _)| {
+                    headers
+                        .get(*column)
+                        .and_then(|idx| record.get(*idx))
+                        .map_or(true, |v| !v.trim().eq_ignore_ascii_case("no"))
+                })
+                .map(|(_, format)| *format)
                 .collect(),
+            text: headers
+                .get("Functional Text")
+                .and_then(|idx| record.get(*idx))
+                .unwrap_or("")
+                .to_string(),
         })
     }
 }
@@ -63,9 +270,25 @@ pub struct CardDB {
 }
 
 impl CardDB {
+    /// Loads from the card store when it's up to date, which is much faster than parsing the
+    /// CSV
     pub fn init() -> Self {
+        let store_fp = assets::path(CARD_STORE_FILE);
+        if CardStore::is_fresh(&store_fp) {
+            match CardStore::open(&store_fp).and_then(|store| store.cards()) {
+                Ok(cards) => return CardDB { cards },
+                Err(e) => warn!(
+                    "Couldn't read {}, falling back to {}: {}",
+                    CARD_STORE_FILE, CARD_FILE, e
+                ),
+            }
+        }
+        Self::from_csv(&assets::path(CARD_FILE))
+    }
+
+    pub fn from_csv(fp: &str) -> Self {
         // Load card data
-        let file = File::open(CARD_FILE).expect(&format!("Could not find {}", CARD_FILE));
+        let file = File::open(fp).expect(&format!("Could not find {}", fp));
         let mut reader = csv::ReaderBuilder::new().delimiter(b'\t').from_reader(file);
         let headers = reader.headers().expect("Headers not found").to_owned();
         let headers =
@@ -89,6 +312,49 @@ impl CardDB {
             .collect()
     }
 
+    /// Heroes that can be played in `format`: young heroes for Blitz and Ultimate Pit Fight,
+    /// adult heroes for Classic Constructed
+    pub fn heroes_for(&self, format: Format) -> Vec<&CardData> {
+        self.heroes()
+            .into_iter()
+            .filter(|c| c.is_young() == format.young_heroes() && c.is_legal(format))
+            .collect()
+    }
+
+    /// Hero named `name`, ignoring case
+    pub fn hero(&self, name: &str) -> Option<&CardData> {
+        self.heroes()
+            .into_iter()
+            .find(|c| c.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Fills in the starting life and intellect the setup doesn't have from the heroes' cards,
+    /// e.g. for annotations made before intellect was recorded
+    pub fn complete_setup(&self, setup: &mut MatchSetup, format: Option<Format>) {
+        let heroes = [
+            (
+                &setup.hero1,
+                &mut setup.hero1_life,
+                &mut setup.hero1_intellect,
+            ),
+            (
+                &setup.hero2,
+                &mut setup.hero2_life,
+                &mut setup.hero2_intellect,
+            ),
+        ];
+        for (name, life, intellect) in heroes {
+            let Some(hero) = self.hero(name) else {
+                warn!("{} isn't a hero in the card db", name);
+                continue;
+            };
+            if life.trim().parse::<u32>().is_err() {
+                *life = hero.starting_life(format).to_string();
+            }
+            intellect.get_or_insert(hero.starting_intellect(format));
+        }
+    }
+
     pub fn find(&self, name: &str, pitch: Option<u32>) -> Option<&CardData> {
         self.cards
             .iter()
@@ -109,83 +375,415 @@ impl Named for &CardData {
     }
 }
 
+/// Where image urls are looked up
+enum ImageSource {
+    Map(HashMap<(String, Option<u32>), String>),
+    Store(CardStore),
+}
+
 pub struct CardImageDB {
-    uuid_card_map: HashMap<(String, Option<u32>), String>,
+    source: ImageSource,
+    /// Tried when a card isn't found under its own name
+    aliases: CardAliases,
+    downloader: Downloader,
+}
+
+#[derive(Debug)]
+pub enum CardImageError {
+    NotFound { name: String, pitch: Option<u32> },
+    Download(reqwest::Error),
+    Decode(String),
+}
+
+impl std::error::Error for CardImageError {}
+
+impl std::fmt::Display for CardImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CardImageError::NotFound { name, pitch } => {
+                write!(f, "{:?} not found in card image db", (name, pitch))
+            }
+            CardImageError::Download(err) => write!(f, "Could not download card image: {}", err),
+            CardImageError::Decode(err) => write!(f, "Could not read card image: {}", err),
+        }
+    }
+}
+
+impl From<reqwest::Error> for CardImageError {
+    fn from(err: reqwest::Error) -> Self {
+        CardImageError::Download(err)
+    }
 }
 
 impl CardImageDB {
     pub fn build(fp: &str) -> Self {
-        let mut map: HashMap<(String, Option<u32>), String> = HashMap::new();
+        Self::build_with_policy(fp, &PrintingPolicy::default())
+    }
+
+    /// Picks the printing `policy` ranks best for each card
+    pub fn build_with_policy(fp: &str, policy: &PrintingPolicy) -> Self {
+        let mut map = HashMap::new();
         let file = File::open(fp).expect(&format!("Could not find {}", URL_FILE));
 
         let mut reader = csv::ReaderBuilder::new().delimiter(b'\t').from_reader(file);
         let headers = reader.headers().expect("Headers not found").to_owned();
         let headers: HashMap<String, usize> =
             HashMap::from_iter(headers.iter().enumerate().map(|(e, v)| (v.to_owned(), e)));
+        let column = |row: &StringRecord, name: &str| -> String {
+            headers
+                .get(name)
+                .and_then(|idx| row.get(*idx))
+                .unwrap_or("")
+                .to_string()
+        };
 
         for row in reader.into_records() {
             let row = row.unwrap();
             let name = row[headers["Card Name"]].to_string();
-            let set = row[headers["Set ID"]].to_string();
             let pitch = row[headers["Card Pitch"]].parse::<u32>().ok();
-            // Skip HP1 and promo cards
-            if ["HP1", "FAB", "HER", "WIN", "JDG"].contains(&&set[0..=2]) {
-                continue;
-            }
-            let art_variations = row[headers["Art Variations"]].to_string();
-            // Skip art variations if possible
-            // Some cards only have art variations, though
-            if !art_variations.trim().is_empty() && map.contains_key(&(name.clone(), pitch)) {
+            let (card_id, foiling) = (column(&row, "Card ID"), column(&row, "Foiling"));
+            let printing = Printing {
+                card_id: &card_id,
+                set: &row[headers["Set ID"]],
+                foiling: &foiling,
+                art_variations: &row[headers["Art Variations"]],
+            };
+            let rank = policy.rank(&name, pitch, &printing);
+            let key = (name, pitch);
+            if map.get(&key).is_some_and(|(best, _)| rank > *best) {
                 continue;
             }
-            map.insert((name, pitch), row[headers["Image URL"]].to_string());
+            map.insert(key, (rank, row[headers["Image URL"]].to_string()));
         }
 
-        Self { uuid_card_map: map }
+        Self::from_urls(map.into_iter().map(|(key, (_, url))| (key, url)))
+    }
+
+    pub fn from_urls(urls: impl IntoIterator<Item = ((String, Option<u32>), String)>) -> Self {
+        Self {
+            source: ImageSource::Map(urls.into_iter().collect()),
+            aliases: CardAliases::default(),
+            downloader: Downloader::default(),
+        }
     }
 
+    /// Looks urls up in the card store when it's up to date instead of holding them all in
+    /// memory. Printings are picked by the policy in `PRINTING_POLICY_FILE`.
     pub fn init() -> Self {
-        Self::build(&URL_FILE)
+        let store_fp = assets::path(CARD_STORE_FILE);
+        if CardStore::is_fresh(&store_fp) {
+            match CardStore::open(&store_fp) {
+                Ok(store) => {
+                    return Self {
+                        source: ImageSource::Store(store),
+                        aliases: CardAliases::init(),
+                        downloader: Downloader::default(),
+                    }
+                }
+                Err(e) => warn!(
+                    "Couldn't open {}, falling back to {}: {}",
+                    CARD_STORE_FILE, URL_FILE, e
+                ),
+            }
+        }
+        Self {
+            aliases: CardAliases::init(),
+            ..Self::build_with_policy(&assets::path(URL_FILE), &PrintingPolicy::init())
+        }
     }
 
-    pub fn load_card_image(&self, name: &str, pitch: &Option<u32>) -> UMat {
-        let key = (name.to_string(), pitch.to_owned());
+    /// Name and pitch of every card with an image
+    pub fn cards(&self) -> Vec<(String, Option<u32>)> {
+        match &self.source {
+            ImageSource::Map(map) => map.keys().cloned().collect(),
+            ImageSource::Store(store) => store.image_cards().unwrap_or_else(|e| {
+                warn!("Couldn't list card images: {}", e);
+                Vec::new()
+            }),
+        }
+    }
+
+    /// Every url when they are held in memory, for building the card store
+    pub(crate) fn urls(&self) -> Vec<(&(String, Option<u32>), &String)> {
+        match &self.source {
+            ImageSource::Map(map) => map.iter().collect(),
+            ImageSource::Store(_) => Vec::new(),
+        }
+    }
+
+    /// Downloads images with `config` instead of the defaults
+    pub fn with_downloads(mut self, config: DownloadConfig) -> Self {
+        self.downloader = Downloader::new(config);
+        self
+    }
+
+    /// Falls back to the card `name` is an alias of
+    pub fn url(&self, name: &str, pitch: Option<u32>) -> Option<String> {
+        self.lookup(name, pitch).or_else(|| {
+            self.aliases
+                .resolve(name)
+                .and_then(|canonical| self.lookup(canonical, pitch))
+        })
+    }
+
+    fn lookup(&self, name: &str, pitch: Option<u32>) -> Option<String> {
+        match &self.source {
+            ImageSource::Map(map) => map.get(&(name.to_string(), pitch)).cloned(),
+            ImageSource::Store(store) => store.image_url(name, pitch).ok().flatten(),
+        }
+    }
+
+    /// Where the image at `url` is looked for before downloading it, so cards can be shown
+    /// offline
+    pub fn cached_image_fp(url: &str) -> PathBuf {
+        let file_name = url.rsplit('/').next().unwrap_or(url);
+        Path::new(&assets::path(CARD_IMAGE_DIR)).join(file_name)
+    }
+
+    /// Saves a downloaded image where `cached_image_fp` looks, so it's only downloaded once.
+    /// Only called once the image has decoded, so an error page is never cached. The image is
+    /// still used if it can't be saved.
+    fn cache_download(url: &str, bytes: &[u8]) {
+        let fp = Self::cached_image_fp(url);
+        let saved = fp
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&fp, bytes));
+        if let Err(e) = saved {
+            warn!("Couldn't cache {} in {}: {}", url, fp.display(), e);
+        }
+    }
+
+    /// Like `cache_download`, without blocking an async runtime on the write
+    async fn cache_download_async(url: &str, bytes: &[u8]) {
+        let fp = Self::cached_image_fp(url);
+        let saved = match fp.parent() {
+            Some(dir) => tokio::fs::create_dir_all(dir).await,
+            None => Ok(()),
+        };
+        if let Err(e) = saved.and(tokio::fs::write(&fp, bytes).await) {
+            warn!("Couldn't cache {} in {}: {}", url, fp.display(), e);
+        }
+    }
+
+    /// Downloads the image of a card into `dir`, under the name `load_card_image` looks for in
+    /// `CARD_IMAGE_DIR`. Returns the path written.
+    pub async fn save_card_image(
+        &self,
+        name: &str,
+        pitch: Option<u32>,
+        dir: &Path,
+    ) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let url = self
+            .url(name, pitch)
+            .ok_or_else(|| format!("{:?} not found in card image db", (name, pitch)))?;
+        let file_name = Self::cached_image_fp(&url)
+            .file_name()
+            .map(|f| f.to_owned())
+            .ok_or_else(|| format!("No file name in {}", url))?;
+        let fp = dir.join(file_name);
+        let cached = Self::cached_image_fp(&url);
+        if !fp.exists() {
+            if cached.exists() {
+                fs::copy(&cached, &fp)?;
+            } else {
+                fs::write(&fp, self.downloader.get_async(&url).await?)?;
+            }
+        }
+        Ok(fp)
+    }
+
+    pub fn try_load_card_image(
+        &self,
+        name: &str,
+        pitch: &Option<u32>,
+    ) -> Result<UMat, CardImageError> {
+        let url = self
+            .url(name, *pitch)
+            .ok_or_else(|| CardImageError::NotFound {
+                name: name.to_string(),
+                pitch: *pitch,
+            })?;
+        if let Ok(bytes) = fs::read(Self::cached_image_fp(&url)) {
+            return Self::decode(&bytes).map_err(|e| CardImageError::Decode(e.to_string()));
+        }
+        let bytes = self.downloader.get(&url)?;
+        let image = Self::decode(&bytes).map_err(|e| CardImageError::Decode(e.to_string()))?;
+        Self::cache_download(&url, &bytes);
+        Ok(image)
+    }
+
+    /// Like `try_load_card_image`, without blocking an async runtime on the download
+    pub async fn try_load_card_image_async(
+        &self,
+        name: &str,
+        pitch: &Option<u32>,
+    ) -> Result<UMat, CardImageError> {
         let url = self
-            .uuid_card_map
-            .get(&key)
-            .expect(&format!("{:?} not found in card image db", key));
+            .url(name, *pitch)
+            .ok_or_else(|| CardImageError::NotFound {
+                name: name.to_string(),
+                pitch: *pitch,
+            })?;
+        if let Ok(bytes) = tokio::fs::read(Self::cached_image_fp(&url)).await {
+            return Self::decode(&bytes).map_err(|e| CardImageError::Decode(e.to_string()));
+        }
+        let bytes = self.downloader.get_async(&url).await?;
+        let image = Self::decode(&bytes).map_err(|e| CardImageError::Decode(e.to_string()))?;
+        Self::cache_download_async(&url, &bytes).await;
+        Ok(image)
+    }
+
+    /// Shows the placeholder card when the image can't be loaded, so a flaky connection
+    /// doesn't stop a render
+    pub fn load_card_image(&self, name: &str, pitch: &Option<u32>) -> UMat {
+        self.try_load_card_image(name, pitch).unwrap_or_else(|e| {
+            warn!("{}, showing the placeholder card", e);
+            Self::placeholder()
+        })
+    }
+
+    fn placeholder() -> UMat {
+        load_image(&assets::path(CARD_PLACEHOLDER_FILE))
+            .or_else(|_| {
+                UMat::new_size_with_default_def(
+                    Size::new(PLACEHOLDER_WIDTH, PLACEHOLDER_HEIGHT),
+                    CV_8UC3,
+                    Scalar::all(255.0),
+                )
+            })
+            .expect("Couldn't create the placeholder card")
+    }
 
+    fn decode(bytes: &[u8]) -> Result<UMat, Box<dyn std::error::Error>> {
         let mut image_mat = UMat::new_def();
-        let img_vec = reqwest::blocking::get(url)
-            .unwrap()
-            .bytes()
-            .unwrap()
-            .to_vec();
-        let img_vec: Vector<u8> = Vector::from_iter(img_vec);
-        let img = imdecode(&img_vec, IMREAD_UNCHANGED).unwrap();
+        let img_vec: Vector<u8> = Vector::from_slice(bytes);
+        let img = imdecode(&img_vec, IMREAD_UNCHANGED)?;
+        // Anything that isn't an image, like an error page, decodes to nothing
+        if img.empty() {
+            return Err("Not an image".into());
+        }
 
-        img.copy_to(&mut image_mat).unwrap();
+        img.copy_to(&mut image_mat)?;
         // I don't totally understand this, but Splatter Skull had a depth of 2 whereas every other
         // image has a depth of 0, so this catches that case
         if image_mat.depth() > 0 {
             image_mat
                 .clone()
-                .convert_to(&mut image_mat, CV_8U, 1.0 / 256.0, 0.0)
-                .unwrap();
+                .convert_to(&mut image_mat, CV_8U, 1.0 / 256.0, 0.0)?;
         }
 
-        let img = convert_alpha_to_white(&image_mat).unwrap();
-        cvt_color_def(&img, &mut image_mat, COLOR_RGBA2RGB).unwrap();
+        let img = convert_alpha_to_white(&image_mat)?;
+        cvt_color_def(&img, &mut image_mat, COLOR_RGBA2RGB)?;
 
-        return image_mat;
+        Ok(image_mat)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::CardImageDB;
+    use super::{CardDB, CardData, CardImageDB};
+    use crate::annotation::{Format, MatchSetup};
     use opencv::highgui;
 
+    fn hero(name: &str, types: &[&str], legal: &[Format]) -> CardData {
+        CardData {
+            name: name.to_string(),
+            pitch: None,
+            life: Some(20),
+            intellect: Some(4),
+            cost: None,
+            power: None,
+            defense: None,
+            display: name.to_string(),
+            uuid: String::new(),
+            types: types.iter().map(|t| t.to_string()).collect(),
+            classes: Vec::new(),
+            talents: Vec::new(),
+            keywords: Vec::new(),
+            legal: legal.to_vec(),
+            text: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_heroes_for() {
+        let both = [Format::ClassicConstructed, Format::Blitz];
+        let upf = [Format::UltimatePitFight];
+        let db = CardDB {
+            cards: vec![
+                hero("Katsu", &["ninja", "hero", "young"], &both),
+                hero("Katsu, the Wanderer", &["ninja", "hero"], &both),
+                hero("Betsy", &["guardian", "hero", "young"], &[Format::ClassicConstructed]),
+                hero("Harmonized Kodachi", &["ninja", "weapon"], &both),
+                hero("Dash", &["mechanologist", "hero", "young"], &upf),
+            ],
+        };
+        let names = |heroes: Vec<&CardData>| -> Vec<String> {
+            heroes.iter().map(|c| c.name.clone()).collect()
+        };
+        assert_eq!(names(db.heroes_for(Format::Blitz)), vec!["Katsu"]);
+        assert_eq!(names(db.heroes_for(Format::UltimatePitFight)), vec!["Dash"]);
+        assert_eq!(
+            names(db.heroes_for(Format::ClassicConstructed)),
+            vec!["Katsu, the Wanderer"]
+        );
+    }
+
+    #[test]
+    fn test_complete_setup() {
+        let mut young = hero("Dash", &["mechanologist", "hero", "young"], &[]);
+        young.life = None;
+        young.intellect = None;
+        let db = CardDB {
+            cards: vec![hero("Katsu, the Wanderer", &["ninja", "hero"], &[]), young],
+        };
+        let mut setup = MatchSetup {
+            hero1: "katsu, the wanderer".to_string(),
+            hero2: "Dash".to_string(),
+            hero2_life: "35".to_string(),
+            ..Default::default()
+        };
+        db.complete_setup(&mut setup, None);
+        // From the card
+        assert_eq!(setup.hero1_life, "20");
+        assert_eq!(setup.hero1_intellect, Some(4));
+        // Kept from the annotation
+        assert_eq!(setup.hero2_life, "35");
+        // Blitz's default for a young hero without one on its card
+        assert_eq!(setup.hero2_intellect, Some(4));
+
+        let mut setup = MatchSetup {
+            hero1: "Dash".to_string(),
+            ..Default::default()
+        };
+        db.complete_setup(&mut setup, Some(Format::ClassicConstructed));
+        assert_eq!(setup.hero1_life, "40");
+    }
+
+    #[test]
+    fn test_classes_and_talents() {
+        let types: Vec<String> = ["draconic", "ninja", "warrior", "action", "attack"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        let (classes, talents) = CardData::classes_and_talents(&types);
+        assert_eq!(classes, vec!["ninja", "warrior"]);
+        assert_eq!(talents, vec!["draconic"]);
+    }
+
+    #[test]
+    fn test_rules_text() {
+        let mut card = hero("Cold Snap", &["ice", "action"], &[]);
+        card.text = "Target hero may pay {r}{r}{r}. This gets +1{p}.\n\n**Go again**".to_string();
+        assert_eq!(
+            card.rules_text(),
+            "Target hero may pay 3 resource. This gets +1 power.\n\nGo again"
+        );
+        card.text = "Pay {r}: gain {x} and {h".to_string();
+        assert_eq!(card.rules_text(), "Pay 1 resource: gain {x} and {h");
+    }
+
     #[test]
     fn test_load_image() -> Result<(), Box<dyn std::error::Error>> {
         let url_file = std::env::current_dir()?
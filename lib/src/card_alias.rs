@@ -0,0 +1,81 @@
+use std::{collections::HashMap, fs::File};
+
+use tracing::warn;
+
+use crate::assets;
+
+/// Community nicknames for cards, e.g. `{"CnC": "Command and Conquer"}`
+pub const CARD_ALIAS_FILE: &'static str = "data/card_aliases.json";
+
+/// Resolves nicknames to card names, ignoring case
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CardAliases {
+    /// Lowercased alias to card name
+    map: HashMap<String, String>,
+}
+
+impl CardAliases {
+    pub fn build(aliases: impl IntoIterator<Item = (String, String)>) -> Self {
+        CardAliases {
+            map: aliases
+                .into_iter()
+                .map(|(alias, name)| (alias.trim().to_lowercase(), name))
+                .collect(),
+        }
+    }
+
+    /// Reads `CARD_ALIAS_FILE`. Without one no aliases are resolved.
+    pub fn init() -> Self {
+        let Ok(file) = File::open(assets::path(CARD_ALIAS_FILE)) else {
+            return Self::default();
+        };
+        match serde_json::from_reader::<_, HashMap<String, String>>(file) {
+            Ok(aliases) => Self::build(aliases),
+            Err(e) => {
+                warn!("Ignoring invalid {}: {}", CARD_ALIAS_FILE, e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn resolve(&self, alias: &str) -> Option<&str> {
+        self.map
+            .get(&alias.trim().to_lowercase())
+            .map(|name| name.as_str())
+    }
+
+    /// The card name for `name` when it is an alias, and `name` otherwise
+    pub fn canonical<'a>(&'a self, name: &'a str) -> &'a str {
+        self.resolve(name).unwrap_or(name)
+    }
+
+    /// Card names with an alias starting with `text`, for suggesting cards while an alias is
+    /// being typed
+    pub fn names_for_prefix(&self, text: &str) -> Vec<&str> {
+        let text = text.trim().to_lowercase();
+        self.iter()
+            .filter(|(alias, _)| alias.starts_with(&text))
+            .map(|(_, name)| name)
+            .collect()
+    }
+
+    /// Lowercased aliases and the card names they resolve to
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.map
+            .iter()
+            .map(|(alias, name)| (alias.as_str(), name.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let aliases = CardAliases::build([("CnC".to_string(), "Command and Conquer".to_string())]);
+        assert_eq!(aliases.resolve("cnc"), Some("Command and Conquer"));
+        assert_eq!(aliases.resolve(" CNC "), Some("Command and Conquer"));
+        assert_eq!(aliases.canonical("Head Jab"), "Head Jab");
+    }
+}
@@ -0,0 +1,651 @@
+//! The card panel, where a card back turns over to show each card played and turns back once
+//! nothing else is waiting, and zooms that lift a card out of it over the video. How cards
+//! arrive and leave is an `Animation`, so a new reveal style doesn't touch the phases.
+
+use clap::ValueEnum;
+use opencv::{
+    core::{self, Point, Rect, Size, UMat, UMatTrait, UMatTraitConst, CV_8UC3},
+    imgproc,
+};
+
+use crate::{
+    fade::{remove_color, remove_white_corners},
+    movement::{
+        place_umat, relocate_umat, resize_umat, safe_scale, straight_line, MoveFunction,
+        Reparameterization,
+    },
+    rotate::{rotate_image, REMOVAL_COLOR},
+    time_tick::TimeTick,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Seconds a card is shown before a queued card replaces it
+pub const DISPLAY_DURATION: f64 = 6.0;
+/// Shortest a card is shown for with `QueuePolicy::Shorten`
+pub const MIN_DISPLAY_DURATION: f64 = 1.5;
+/// Cards waiting before a `QueuePolicy` steps in
+pub const QUEUE_LIMIT: usize = 3;
+/// Cards shown together by `QueuePolicy::Grid`, in rows of two
+pub const GRID_CARDS: usize = 4;
+/// Seconds a card stays up with nothing queued before the card back returns
+pub const EXTENDED_DISPLAY_DURATION: f64 = 12.0;
+/// Seconds a card takes to arrive or leave
+pub const TRANSITION_TIME: f64 = 0.75;
+/// Seconds the second card of a pair arrives and leaves after the first
+pub const PAIR_STAGGER: f64 = 0.4;
+// Zoom defaults
+pub const ZOOM_TIME: f64 = 2.0;
+pub const ZOOM_DISPLAY: f64 = 3.0;
+pub const ZOOM_SCALE: f64 = 1.5;
+
+/// Draws a card arriving in or leaving its place
+pub trait Animation {
+    /// Draws `card` at `rect`, `t` of the way through the animation. Its corners can be white,
+    /// or already keyed out in `REMOVAL_COLOR`.
+    fn draw(&self, frame: &mut UMat, card: &UMat, rect: Rect, t: f64, entering: bool)
+        -> Result<()>;
+}
+
+/// Copy of `card` with its white corners in `REMOVAL_COLOR`
+fn keyed(card: &UMat) -> Result<UMat> {
+    let key = UMat::new_size_with_default_def(card.size()?, card.typ(), REMOVAL_COLOR)?;
+    remove_white_corners(&key, card)
+}
+
+/// Turns the card edge on, as though it's being flipped over
+pub struct Rotate;
+
+impl Animation for Rotate {
+    fn draw(
+        &self,
+        frame: &mut UMat,
+        card: &UMat,
+        rect: Rect,
+        t: f64,
+        entering: bool,
+    ) -> Result<()> {
+        let rotated = rotate_image(&keyed(card)?, t as f32, !entering)?;
+        let rotated_rect = Rect::new(
+            rect.x,
+            rect.y - (rotated.rows() - rect.height).div_euclid(2),
+            rotated.cols(),
+            rotated.rows(),
+        );
+        let mut roi = frame.roi_mut(rotated_rect)?;
+        let rotation = remove_color(&roi, &rotated, &REMOVAL_COLOR)?;
+        rotation.copy_to(&mut roi)?;
+        Ok(())
+    }
+}
+
+/// Rises into place from the bottom of its place, and sinks back out of it
+pub struct Slide;
+
+impl Animation for Slide {
+    fn draw(
+        &self,
+        frame: &mut UMat,
+        card: &UMat,
+        rect: Rect,
+        t: f64,
+        entering: bool,
+    ) -> Result<()> {
+        let eased = Reparameterization::SCurve.apply(t);
+        let shown = if entering { eased } else { 1.0 - eased };
+        let visible = (card.rows() as f64 * shown) as i32;
+        if visible <= 0 {
+            return Ok(());
+        }
+        let mut top = UMat::new_def();
+        keyed(card)?
+            .roi(Rect::new(0, 0, card.cols(), visible))?
+            .copy_to(&mut top)?;
+        let dest = Rect::new(rect.x, rect.y + card.rows() - visible, card.cols(), visible);
+        let roi = frame.roi(dest)?;
+        let top = remove_color(&roi, &top, &REMOVAL_COLOR)?;
+        place_umat(&top, frame, dest)?;
+        Ok(())
+    }
+}
+
+/// Fades in over whatever is behind it, and back out
+pub struct Fade;
+
+impl Animation for Fade {
+    fn draw(
+        &self,
+        frame: &mut UMat,
+        card: &UMat,
+        rect: Rect,
+        t: f64,
+        entering: bool,
+    ) -> Result<()> {
+        let eased = Reparameterization::SCurve.apply(t);
+        let alpha = if entering { eased } else { 1.0 - eased };
+        let dest = Rect::new(rect.x, rect.y, card.cols(), card.rows());
+        let roi = frame.roi(dest)?;
+        let over = remove_color(&roi, &keyed(card)?, &REMOVAL_COLOR)?;
+        let mut blended = UMat::new_def();
+        core::add_weighted(&roi, 1.0 - alpha, &over, alpha, 0.0, &mut blended, -1)?;
+        place_umat(&blended, frame, dest)?;
+        Ok(())
+    }
+}
+
+/// The built in animations, for choosing on the command line
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Reveal {
+    #[default]
+    Rotate,
+    Slide,
+    Fade,
+}
+
+impl Reveal {
+    pub fn animation(&self) -> Box<dyn Animation> {
+        match self {
+            Reveal::Rotate => Box::new(Rotate),
+            Reveal::Slide => Box::new(Slide),
+            Reveal::Fade => Box::new(Fade),
+        }
+    }
+}
+
+/// What happens when cards are played faster than the panel can show them
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueuePolicy {
+    /// Every card is shown for the full time, however far behind the game the panel falls
+    #[default]
+    Wait,
+    /// Only the latest cards up to the limit wait, older ones are skipped
+    Cap,
+    /// Cards are shown for less time the further past the limit the queue is
+    Shorten,
+    /// Once more cards than the limit are waiting, the latest are shown together in a grid
+    Grid,
+}
+
+impl QueuePolicy {
+    /// Seconds each card is shown for with `queued` cards waiting behind it
+    pub fn display_duration(&self, queued: usize, limit: usize) -> f64 {
+        match self {
+            QueuePolicy::Shorten if queued > limit => {
+                (DISPLAY_DURATION * limit.max(1) as f64 / queued as f64).max(MIN_DISPLAY_DURATION)
+            }
+            _ => DISPLAY_DURATION,
+        }
+    }
+}
+
+/// Up to the last `GRID_CARDS` of `cards` tiled two to a row in an image of `size`, with the
+/// gaps and corners keyed out in `REMOVAL_COLOR`
+pub fn card_grid(cards: &[UMat], size: Size) -> Result<UMat> {
+    let first = cards.len().saturating_sub(GRID_CARDS);
+    let mut grid = UMat::new_size_with_default_def(size, CV_8UC3, REMOVAL_COLOR)?;
+    let cell = Size::new(size.width / 2, size.height / 2);
+    for (i, card) in cards[first..].iter().enumerate() {
+        let mut small = UMat::new_def();
+        imgproc::resize(card, &mut small, cell, 0.0, 0.0, imgproc::INTER_AREA)?;
+        let rect = Rect::new(
+            (i % 2) as i32 * cell.width,
+            (i / 2) as i32 * cell.height,
+            cell.width,
+            cell.height,
+        );
+        keyed(&small)?.copy_to(&mut grid.roi_mut(rect)?)?;
+    }
+    Ok(grid)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanelPhase {
+    /// The card back is up, waiting for a card
+    Sleep,
+    BackOut,
+    FrontIn,
+    Display,
+    /// Nothing is queued, so the card stays up longer
+    Extended,
+    FrontOut,
+    BackIn,
+}
+
+/// Where cards are shown as they're played. Cards are handed over with `show` when `update`
+/// asks for one.
+pub struct CardPanel {
+    rect: Rect,
+    /// Corners keyed out in `REMOVAL_COLOR`
+    card_back: UMat,
+    card: Option<UMat>,
+    /// Card shown beside `card`, each taking half of the panel
+    second: Option<UMat>,
+    enter: Box<dyn Animation>,
+    exit: Box<dyn Animation>,
+    phase: PanelPhase,
+    timer: TimeTick,
+    /// Seconds a card is shown before a queued card replaces it
+    display_duration: f64,
+}
+
+impl CardPanel {
+    /// `card_back` is the size of `rect`, with its corners keyed out in `REMOVAL_COLOR`
+    pub fn new(rect: Rect, card_back: &UMat, time_tick: TimeTick) -> Self {
+        CardPanel {
+            rect,
+            card_back: card_back.clone(),
+            card: None,
+            second: None,
+            enter: Box::new(Rotate),
+            exit: Box::new(Rotate),
+            phase: PanelPhase::Sleep,
+            timer: time_tick,
+            display_duration: DISPLAY_DURATION,
+        }
+    }
+
+    /// Cards, and the card back, arrive with `enter` and leave with `exit`
+    pub fn with_animations(mut self, enter: Box<dyn Animation>, exit: Box<dyn Animation>) -> Self {
+        self.enter = enter;
+        self.exit = exit;
+        self
+    }
+
+    pub fn phase(&self) -> PanelPhase {
+        self.phase
+    }
+
+    /// The card shown, or last shown
+    pub fn card(&self) -> Option<&UMat> {
+        self.card.as_ref()
+    }
+
+    /// Whether the card is up, rather than arriving, leaving or behind the card back
+    pub fn is_showing(&self) -> bool {
+        matches!(self.phase, PanelPhase::Display | PanelPhase::Extended)
+    }
+
+    /// Takes effect on the card already up, so a card that has been up long enough makes way
+    /// for the queue at the next `update`
+    pub fn set_display_duration(&mut self, seconds: f64) {
+        self.display_duration = seconds;
+    }
+
+    /// Replaces the card, which should be the size of the panel. Its corners can be white, or
+    /// already keyed out in `REMOVAL_COLOR`.
+    pub fn show(&mut self, card: UMat) {
+        self.card = Some(card);
+        self.second = None;
+    }
+
+    /// Shows two cards side by side, each `pair_size`. They arrive and leave one after the
+    /// other.
+    pub fn show_pair(&mut self, card: UMat, second: UMat) {
+        self.card = Some(card);
+        self.second = Some(second);
+    }
+
+    /// Size of each card of a pair, half the panel's so both fit across it
+    pub fn pair_size(&self) -> Size {
+        Size::new(self.rect.width / 2, self.rect.height / 2)
+    }
+
+    /// Where each card of a pair goes, centered top to bottom
+    fn pair_rects(&self) -> [Rect; 2] {
+        let size = self.pair_size();
+        let y = self.rect.y + (self.rect.height - size.height) / 2;
+        [
+            Rect::new(self.rect.x, y, size.width, size.height),
+            Rect::new(self.rect.x + size.width, y, size.width, size.height),
+        ]
+    }
+
+    /// Seconds the cards take to arrive or leave, longer for a pair so the second can follow
+    fn front_transition_time(&self) -> f64 {
+        match self.second {
+            Some(_) => TRANSITION_TIME + PAIR_STAGGER,
+            None => TRANSITION_TIME,
+        }
+    }
+
+    /// Moves to the next phase once the current one is over. `waiting` is whether a card is
+    /// queued. Returns true when the panel is ready for that card, which should be handed over
+    /// with `show` before drawing.
+    pub fn update(&mut self, time_tick: TimeTick, waiting: bool) -> bool {
+        let elapsed_time = (time_tick - self.timer).as_f64();
+        let front_time = self.front_transition_time();
+        let (next, ready) = match self.phase {
+            PanelPhase::Sleep if waiting => (PanelPhase::BackOut, true),
+            PanelPhase::BackOut if elapsed_time >= TRANSITION_TIME => (PanelPhase::FrontIn, false),
+            PanelPhase::FrontIn if elapsed_time >= front_time => (PanelPhase::Display, false),
+            PanelPhase::Display if elapsed_time >= self.display_duration && waiting => {
+                (PanelPhase::FrontOut, false)
+            }
+            PanelPhase::Display if elapsed_time >= DISPLAY_DURATION => {
+                (PanelPhase::Extended, false)
+            }
+            PanelPhase::Extended if waiting || elapsed_time >= EXTENDED_DISPLAY_DURATION => {
+                (PanelPhase::FrontOut, false)
+            }
+            PanelPhase::FrontOut if elapsed_time >= front_time && waiting => {
+                (PanelPhase::FrontIn, true)
+            }
+            PanelPhase::FrontOut if elapsed_time >= front_time => (PanelPhase::BackIn, false),
+            PanelPhase::BackIn if elapsed_time >= TRANSITION_TIME => (PanelPhase::Sleep, false),
+            _ => return false,
+        };
+        self.phase = next;
+        self.timer = time_tick;
+        ready
+    }
+
+    pub fn draw(&self, frame: &mut UMat, time_tick: TimeTick) -> Result<()> {
+        let elapsed_time = (time_tick - self.timer).as_f64();
+        if let (Some(card), Some(second)) = (&self.card, &self.second) {
+            if !matches!(
+                self.phase,
+                PanelPhase::Sleep | PanelPhase::BackOut | PanelPhase::BackIn
+            ) {
+                return self.draw_pair([card, second], frame, elapsed_time);
+            }
+        }
+        let t = (elapsed_time / TRANSITION_TIME).clamp(0.0, 1.0);
+        match (self.phase, &self.card) {
+            (PanelPhase::Sleep, _) => {
+                let roi = frame.roi(self.rect)?;
+                let card_back = remove_color(&roi, &self.card_back, &REMOVAL_COLOR)?;
+                place_umat(&card_back, frame, self.rect)?;
+                Ok(())
+            }
+            (PanelPhase::BackOut, _) => self.exit.draw(frame, &self.card_back, self.rect, t, false),
+            (PanelPhase::BackIn, _) => self.enter.draw(frame, &self.card_back, self.rect, t, true),
+            (PanelPhase::FrontIn, Some(card)) => self.enter.draw(frame, card, self.rect, t, true),
+            (PanelPhase::FrontOut, Some(card)) => self.exit.draw(frame, card, self.rect, t, false),
+            (PanelPhase::Display | PanelPhase::Extended, Some(card)) => {
+                let roi = frame.roi(self.rect)?;
+                let card = remove_color(&roi, &keyed(card)?, &REMOVAL_COLOR)?;
+                place_umat(&card, frame, self.rect)?;
+                Ok(())
+            }
+            (_, None) => Ok(()),
+        }
+    }
+
+    /// Each card of a pair runs its own animation, the second `PAIR_STAGGER` behind the first
+    fn draw_pair(&self, cards: [&UMat; 2], frame: &mut UMat, elapsed_time: f64) -> Result<()> {
+        for (i, (card, rect)) in cards.into_iter().zip(self.pair_rects()).enumerate() {
+            let t = (elapsed_time - i as f64 * PAIR_STAGGER) / TRANSITION_TIME;
+            match self.phase {
+                // Not arrived yet, or already gone
+                PanelPhase::FrontIn if t <= 0.0 => {}
+                PanelPhase::FrontOut if t >= 1.0 => {}
+                PanelPhase::FrontIn => self.enter.draw(frame, card, rect, t.min(1.0), true)?,
+                PanelPhase::FrontOut => self.exit.draw(frame, card, rect, t.max(0.0), false)?,
+                _ => {
+                    let roi = frame.roi(rect)?;
+                    let card = remove_color(&roi, &keyed(card)?, &REMOVAL_COLOR)?;
+                    place_umat(&card, frame, rect)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How zooms look
+#[derive(Debug, Clone, Copy)]
+pub struct ZoomSettings {
+    /// Size of the held zoom, relative to the card panel's card
+    pub scale: f64,
+    /// Seconds to zoom in, and again to zoom out
+    pub duration: f64,
+    /// Seconds the zoom is held before it zooms out on its own
+    pub hold: f64,
+    /// Where the center of the zoomed card goes, as fractions of the video's width and height
+    pub target: ZoomTarget,
+}
+
+impl Default for ZoomSettings {
+    fn default() -> Self {
+        ZoomSettings {
+            scale: ZOOM_SCALE,
+            duration: ZOOM_TIME,
+            hold: ZOOM_DISPLAY,
+            target: ZoomTarget::default(),
+        }
+    }
+}
+
+impl ZoomSettings {
+    /// Top left of the card once it has moved to the target, before it's scaled. Kept inside
+    /// `frame_rect`.
+    fn goal(&self, card_rect: &Rect, frame_rect: &Rect) -> Point {
+        let center_x = frame_rect.x as f64 + self.target.x * frame_rect.width as f64;
+        let center_y = frame_rect.y as f64 + self.target.y * frame_rect.height as f64;
+        let x = (center_x - card_rect.width as f64 / 2.0) as i32;
+        let y = (center_y - card_rect.height as f64 / 2.0) as i32;
+        Point::new(
+            x.clamp(
+                frame_rect.x,
+                frame_rect.x + (frame_rect.width - card_rect.width).max(0),
+            ),
+            y.clamp(
+                frame_rect.y,
+                frame_rect.y + (frame_rect.height - card_rect.height).max(0),
+            ),
+        )
+    }
+}
+
+/// Center of a zoomed card relative to the video, "center" or "X,Y" like "0.7,0.5"
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZoomTarget {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Default for ZoomTarget {
+    fn default() -> Self {
+        ZoomTarget { x: 0.5, y: 0.5 }
+    }
+}
+
+impl std::fmt::Display for ZoomTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{}", self.x, self.y)
+    }
+}
+
+impl ZoomTarget {
+    pub fn parse(value: &str) -> std::result::Result<Self, String> {
+        if value.trim().eq_ignore_ascii_case("center") {
+            return Ok(ZoomTarget::default());
+        }
+        let invalid = || format!("'{}' isn't \"center\" or X,Y between 0 and 1", value);
+        let (x, y) = value.split_once(',').ok_or_else(invalid)?;
+        let fraction = |v: &str| {
+            v.trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|v| (0.0..=1.0).contains(v))
+                .ok_or_else(invalid)
+        };
+        Ok(ZoomTarget {
+            x: fraction(x)?,
+            y: fraction(y)?,
+        })
+    }
+}
+
+enum ZoomPhase {
+    In,
+    Hold,
+    Out,
+    Done,
+}
+
+/// A card zoomed out of the card panel. It animates on its own, so the panel can carry on
+/// with the next card while it's zoomed.
+pub struct Zoom {
+    card: UMat,
+    phase: ZoomPhase,
+    timer: TimeTick,
+}
+
+impl Zoom {
+    pub fn new(card: UMat, time_tick: TimeTick) -> Self {
+        Zoom {
+            card,
+            phase: ZoomPhase::In,
+            timer: time_tick,
+        }
+    }
+
+    /// Zooms out from wherever the zoom in got to
+    pub fn end(&mut self, time_tick: TimeTick, settings: &ZoomSettings) {
+        let elapsed_time = (time_tick - self.timer).as_f64();
+        match self.phase {
+            ZoomPhase::In => {
+                self.timer = time_tick
+                    .saturating_sub(TimeTick::from_secs_f64(settings.duration - elapsed_time));
+                self.phase = ZoomPhase::Out;
+            }
+            ZoomPhase::Hold => {
+                self.timer = time_tick;
+                self.phase = ZoomPhase::Out;
+            }
+            ZoomPhase::Out | ZoomPhase::Done => {}
+        }
+    }
+
+    pub fn is_zoomed(&self) -> bool {
+        matches!(self.phase, ZoomPhase::In | ZoomPhase::Hold)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self.phase, ZoomPhase::Done)
+    }
+
+    /// Draws the card between `card_rect` and its target in `frame_rect`. Returns where the
+    /// card is while the zoom is held.
+    pub fn tick(
+        &mut self,
+        time_tick: TimeTick,
+        frame: &mut UMat,
+        frame_rect: &Rect,
+        card_rect: &Rect,
+        settings: &ZoomSettings,
+    ) -> Result<Option<Rect>> {
+        let elapsed_time = (time_tick - self.timer).as_f64();
+        let percentage = match self.phase {
+            ZoomPhase::In if elapsed_time >= settings.duration => {
+                self.timer = time_tick;
+                self.phase = ZoomPhase::Hold;
+                1.0
+            }
+            ZoomPhase::In => elapsed_time / settings.duration,
+            ZoomPhase::Hold if elapsed_time >= settings.hold => {
+                self.timer = time_tick;
+                self.phase = ZoomPhase::Out;
+                1.0
+            }
+            ZoomPhase::Hold => 1.0,
+            ZoomPhase::Out if elapsed_time >= settings.duration => {
+                self.phase = ZoomPhase::Done;
+                return Ok(None);
+            }
+            ZoomPhase::Out => 1.0 - elapsed_time / settings.duration,
+            ZoomPhase::Done => return Ok(None),
+        };
+        let scale_percentage = Reparameterization::SCurve.apply(percentage);
+
+        let relocation = relocate_umat(
+            &Point::new(card_rect.x, card_rect.y),
+            &settings.goal(card_rect, frame_rect),
+            &self.card,
+            frame,
+            percentage,
+            MoveFunction::SlowFastSlowCurve,
+        )?;
+        let resized = safe_scale(
+            &relocation,
+            &frame.size()?,
+            straight_line(1.0, settings.scale, scale_percentage),
+        )?;
+        let sized_img = resize_umat(&self.card, &resized.size())?;
+        let roi = frame.roi(resized)?;
+        let sized_img = remove_white_corners(&roi, &sized_img)?;
+        place_umat(&sized_img, frame, resized)?;
+        Ok(matches!(self.phase, ZoomPhase::Hold).then_some(resized))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_panel_phases() {
+        let at = TimeTick::from_secs_f64;
+        let mut panel = CardPanel::new(Rect::new(0, 0, 10, 14), &UMat::new_def(), at(0.0));
+        assert!(!panel.update(at(1.0), false));
+        assert_eq!(panel.phase(), PanelPhase::Sleep);
+
+        assert!(panel.update(at(1.0), true));
+        assert_eq!(panel.phase(), PanelPhase::BackOut);
+        panel.update(at(1.0 + TRANSITION_TIME), false);
+        panel.update(at(1.0 + 2.0 * TRANSITION_TIME), false);
+        assert!(panel.is_showing());
+
+        // A waiting card goes straight from one card to the next
+        let shown = 1.0 + 2.0 * TRANSITION_TIME;
+        assert!(!panel.update(at(shown + DISPLAY_DURATION), true));
+        assert_eq!(panel.phase(), PanelPhase::FrontOut);
+        assert!(panel.update(at(shown + DISPLAY_DURATION + TRANSITION_TIME), true));
+        assert_eq!(panel.phase(), PanelPhase::FrontIn);
+    }
+
+    #[test]
+    fn test_pair_follows() {
+        let at = TimeTick::from_secs_f64;
+        let mut panel = CardPanel::new(Rect::new(0, 0, 10, 14), &UMat::new_def(), at(0.0));
+        assert_eq!(panel.pair_size(), Size::new(5, 7));
+        panel.update(at(0.0), true);
+        panel.show_pair(UMat::new_def(), UMat::new_def());
+        panel.update(at(TRANSITION_TIME), false);
+        assert_eq!(panel.phase(), PanelPhase::FrontIn);
+        // The second card is still arriving
+        panel.update(at(2.0 * TRANSITION_TIME), false);
+        assert_eq!(panel.phase(), PanelPhase::FrontIn);
+        panel.update(at(2.0 * TRANSITION_TIME + PAIR_STAGGER), false);
+        assert!(panel.is_showing());
+    }
+
+    #[test]
+    fn test_shorten_display() {
+        assert_eq!(QueuePolicy::Wait.display_duration(10, 3), DISPLAY_DURATION);
+        assert_eq!(
+            QueuePolicy::Shorten.display_duration(3, 3),
+            DISPLAY_DURATION
+        );
+        assert_eq!(
+            QueuePolicy::Shorten.display_duration(4, 3),
+            DISPLAY_DURATION * 0.75
+        );
+        assert_eq!(
+            QueuePolicy::Shorten.display_duration(100, 3),
+            MIN_DISPLAY_DURATION
+        );
+    }
+
+    #[test]
+    fn test_zoom_target() {
+        assert_eq!(ZoomTarget::parse("Center"), Ok(ZoomTarget::default()));
+        assert_eq!(
+            ZoomTarget::parse("0.7, 0.25"),
+            Ok(ZoomTarget { x: 0.7, y: 0.25 })
+        );
+        assert!(ZoomTarget::parse("1.5,0.5").is_err());
+        assert!(ZoomTarget::parse("0.5").is_err());
+    }
+}
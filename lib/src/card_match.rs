@@ -0,0 +1,169 @@
+use std::{fs, io::Write};
+
+use tracing::warn;
+use opencv::{
+    core::{Mat, MatTraitConst, Size, ToInputArray},
+    imgproc,
+};
+
+use crate::card::CardImageDB;
+
+pub const CARD_HASH_FILE: &str = "data/card_hashes.tsv";
+/// Hashes further than this many bits from the frame aren't suggested
+const MAX_DISTANCE: u32 = 20;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Difference hash of an image: each bit is whether a pixel of the 9x8 grayscale thumbnail is
+/// brighter than its right neighbour. Similar images have hashes a few bits apart, regardless of
+/// size or compression.
+pub fn dhash(image: &impl ToInputArray) -> Result<u64> {
+    let mut gray = Mat::default();
+    imgproc::cvt_color_def(image, &mut gray, imgproc::COLOR_BGR2GRAY)?;
+    let mut small = Mat::default();
+    imgproc::resize(
+        &gray,
+        &mut small,
+        Size::new(9, 8),
+        0.0,
+        0.0,
+        imgproc::INTER_AREA,
+    )?;
+
+    let mut hash = 0;
+    for row in 0..8 {
+        for col in 0..8 {
+            let left = *small.at_2d::<u8>(row, col)?;
+            let right = *small.at_2d::<u8>(row, col + 1)?;
+            hash = (hash << 1) | (left > right) as u64;
+        }
+    }
+    Ok(hash)
+}
+
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardHash {
+    pub name: String,
+    pub pitch: Option<u32>,
+    pub hash: u64,
+}
+
+/// Hashes of every card image, used to recognise cards held up to the camera
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CardHashIndex {
+    pub hashes: Vec<CardHash>,
+}
+
+impl CardHashIndex {
+    /// Downloads and hashes every image in `db`. This takes a while, so the result should be
+    /// written to `CARD_HASH_FILE`. Cards whose image can't be hashed are skipped.
+    pub fn build(db: &CardImageDB) -> Self {
+        let hashes = db
+            .cards()
+            .into_iter()
+            .filter_map(|(name, pitch)| {
+                let image = match db.try_load_card_image(&name, &pitch) {
+                    Ok(image) => image,
+                    Err(e) => {
+                        warn!("Couldn't hash {} ({:?}): {}", name, pitch, e);
+                        return None;
+                    }
+                };
+                match dhash(&image) {
+                    Ok(hash) => Some(CardHash { name, pitch, hash }),
+                    Err(e) => {
+                        warn!("Couldn't hash {} ({:?}): {}", name, pitch, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+        CardHashIndex { hashes }
+    }
+
+    /// One card per line: name, pitch (empty for none) and hash in hex, tab separated
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut hashes = Vec::new();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let mut columns = line.split('\t');
+            let (Some(name), Some(pitch), Some(hash)) =
+                (columns.next(), columns.next(), columns.next())
+            else {
+                return Err(format!("Malformed card hash line: {}", line).into());
+            };
+            hashes.push(CardHash {
+                name: name.to_owned(),
+                pitch: pitch.parse().ok(),
+                hash: u64::from_str_radix(hash.trim(), 16)?,
+            });
+        }
+        Ok(CardHashIndex { hashes })
+    }
+
+    pub fn read_from_path(fp: &str) -> Result<Self> {
+        Self::parse(&fs::read_to_string(fp)?)
+    }
+
+    pub fn write_to_path(&self, fp: &str) -> Result<()> {
+        let mut file = fs::File::create(fp)?;
+        for card in self.hashes.iter() {
+            let pitch = card.pitch.map(|p| p.to_string()).unwrap_or_default();
+            writeln!(file, "{}\t{}\t{:016x}", card.name, pitch, card.hash)?;
+        }
+        Ok(())
+    }
+
+    /// Up to `n` cards closest to `hash`, closest first
+    pub fn nearest(&self, hash: u64, n: usize) -> Vec<&CardHash> {
+        let mut cards: Vec<(u32, &CardHash)> = self
+            .hashes
+            .iter()
+            .map(|card| (distance(card.hash, hash), card))
+            .filter(|(distance, _)| *distance <= MAX_DISTANCE)
+            .collect();
+        cards.sort_by_key(|(distance, _)| *distance);
+        cards.into_iter().take(n).map(|(_, card)| card).collect()
+    }
+
+    /// Up to `n` cards closest to `image`, closest first
+    pub fn identify(&self, image: &impl ToInputArray, n: usize) -> Result<Vec<&CardHash>> {
+        Ok(self.nearest(dhash(image)?, n))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use opencv::core::Vec3b;
+
+    #[test]
+    fn test_nearest() -> Result<()> {
+        let index = CardHashIndex::parse(
+            "Head Jab\t1\tff00ff00ff00ff00\nHead Jab\t3\tff00ff00ff00ff0f\nNullrune Boots\t\t00ff00ff00ff00ff\n",
+        )?;
+        assert_eq!(index.hashes[2].pitch, None);
+
+        let nearest = index.nearest(0xff00ff00ff00ff01, 3);
+        assert_eq!(nearest.len(), 2);
+        assert_eq!(nearest[0].pitch, Some(1));
+        assert_eq!(nearest[1].pitch, Some(3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_dhash() -> Result<()> {
+        // Left to right gradient: every pixel is darker than its right neighbour
+        let gradient = Mat::from_slice_2d(
+            &(0..8)
+                .map(|_| (0..9).map(|col| Vec3b::all(col as u8 * 20)).collect::<Vec<_>>())
+                .collect::<Vec<_>>(),
+        )?;
+        assert_eq!(dhash(&gradient)?, 0);
+        assert_eq!(distance(0b1011, 0b0110), 3);
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+use crate::card::CardData;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessEqual,
+    Equal,
+    GreaterEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn test(&self, left: u32, right: u32) -> bool {
+        match self {
+            Comparison::Less => left < right,
+            Comparison::LessEqual => left <= right,
+            Comparison::Equal => left == right,
+            Comparison::GreaterEqual => left >= right,
+            Comparison::Greater => left > right,
+        }
+    }
+}
+
+/// Restriction on which cards can match, written before the name in the input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CardFilter {
+    /// `t:action`. Matches any of the card's types starting with the value.
+    Type(String),
+    /// `c:red`, `c:y` or `c:3`
+    Pitch(u32),
+    /// `cost<=2`, `cost=0`, `cost>1`...
+    Cost(Comparison, u32),
+}
+
+impl CardFilter {
+    /// Parses a single whitespace separated token. Returns `None` for anything that isn't a
+    /// complete filter, including filters that are still being typed.
+    pub fn parse(token: &str) -> Option<Self> {
+        let token = token.to_lowercase();
+        if let Some((key, value)) = token.split_once(':') {
+            return match key {
+                "t" | "type" if !value.is_empty() => Some(CardFilter::Type(value.to_owned())),
+                "c" | "color" => match value {
+                    "r" | "red" | "1" => Some(CardFilter::Pitch(1)),
+                    "y" | "yellow" | "2" => Some(CardFilter::Pitch(2)),
+                    "b" | "blue" | "3" => Some(CardFilter::Pitch(3)),
+                    _ => None,
+                },
+                "cost" => Some(CardFilter::Cost(Comparison::Equal, value.parse().ok()?)),
+                _ => None,
+            };
+        }
+
+        let value = token.strip_prefix("cost")?;
+        // Two character operators first so `<=` isn't read as `<`
+        let (comparison, value) = [
+            ("<=", Comparison::LessEqual),
+            (">=", Comparison::GreaterEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+            ("=", Comparison::Equal),
+        ]
+        .into_iter()
+        .find_map(|(op, comparison)| value.strip_prefix(op).map(|value| (comparison, value)))?;
+        Some(CardFilter::Cost(comparison, value.parse().ok()?))
+    }
+
+    pub fn matches(&self, card: &CardData) -> bool {
+        match self {
+            CardFilter::Type(value) => card.types.iter().any(|t| t.starts_with(value.as_str())),
+            CardFilter::Pitch(pitch) => card.pitch == Some(*pitch),
+            CardFilter::Cost(comparison, cost) => {
+                card.cost.map_or(false, |v| comparison.test(v, *cost))
+            }
+        }
+    }
+}
+
+/// Autocomplete input split into filters and the name being typed,
+/// e.g. "t:action c:red era" is two filters and the name "era"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardQuery {
+    pub filters: Vec<CardFilter>,
+    pub name: String,
+}
+
+impl CardQuery {
+    pub fn parse(text: &str) -> Self {
+        let mut filters = Vec::new();
+        let mut words = Vec::new();
+        for token in text.split_whitespace() {
+            match CardFilter::parse(token) {
+                Some(filter) if words.is_empty() => filters.push(filter),
+                // Partially typed filters are dropped rather than matched against names
+                None if words.is_empty() && Self::is_filter_prefix(token) => {}
+                _ => words.push(token),
+            }
+        }
+        let mut name = words.join(" ");
+        // Keep the trailing space so "erase " doesn't match "Erased"
+        if !name.is_empty() && text.ends_with(char::is_whitespace) {
+            name.push(' ');
+        }
+        CardQuery { filters, name }
+    }
+
+    /// Whether a token looks like the start of a filter
+    fn is_filter_prefix(token: &str) -> bool {
+        let token = token.to_lowercase();
+        token.contains(':') || token.starts_with("cost")
+    }
+
+    pub fn matches(&self, card: &CardData) -> bool {
+        self.filters.iter().all(|filter| filter.matches(card))
+            && card
+                .name
+                .to_lowercase()
+                .starts_with(&self.name.to_lowercase())
+    }
+}
+
+/// Matcher for `AutocompleteSuggestionManager::build_with_matcher`
+pub fn matches(card: &CardData, text: &str) -> bool {
+    CardQuery::parse(text).matches(card)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn card(name: &str, pitch: u32, cost: u32, types: &[&str]) -> CardData {
+        CardData {
+            name: name.to_string(),
+            pitch: Some(pitch),
+            life: None,
+            intellect: None,
+            cost: Some(cost),
+            power: None,
+            defense: None,
+            display: name.to_string(),
+            uuid: String::new(),
+            types: types.iter().map(|t| t.to_string()).collect(),
+            classes: Vec::new(),
+            talents: Vec::new(),
+            keywords: Vec::new(),
+            legal: Vec::new(),
+            text: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_filters() {
+        assert_eq!(
+            CardFilter::parse("T:Action"),
+            Some(CardFilter::Type("action".to_string()))
+        );
+        assert_eq!(CardFilter::parse("c:yellow"), Some(CardFilter::Pitch(2)));
+        assert_eq!(
+            CardFilter::parse("cost<=2"),
+            Some(CardFilter::Cost(Comparison::LessEqual, 2))
+        );
+        assert_eq!(
+            CardFilter::parse("cost:0"),
+            Some(CardFilter::Cost(Comparison::Equal, 0))
+        );
+        assert_eq!(CardFilter::parse("cost<"), None);
+        assert_eq!(CardFilter::parse("c:"), None);
+        assert_eq!(CardFilter::parse("erase"), None);
+    }
+
+    #[test]
+    fn test_query() {
+        let query = CardQuery::parse("t:attack c:red cost<3 head ");
+        assert_eq!(query.filters.len(), 3);
+        assert_eq!(query.name, "head ");
+
+        let jab = card("Head Jab", 1, 0, &["ninja", "action", "attack"]);
+        let blue_jab = card("Head Jab", 3, 0, &["ninja", "action", "attack"]);
+        let header = card("Headliner", 1, 0, &["action", "attack"]);
+        assert!(query.matches(&jab));
+        assert!(!query.matches(&blue_jab));
+        assert!(!query.matches(&header));
+        // still typing the filter
+        assert!(matches(&jab, "t:attack cost<"));
+    }
+}
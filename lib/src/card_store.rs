@@ -0,0 +1,267 @@
+use std::{fs, path::Path};
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::{
+    annotation::Format,
+    assets,
+    card::{CardDB, CardData, CardImageDB, CARD_FILE, URL_FILE},
+    printing::{PrintingPolicy, PRINTING_POLICY_FILE},
+};
+
+pub const CARD_STORE_FILE: &'static str = "data/cards.sqlite";
+/// Bumped whenever the tables change, so older stores are rebuilt instead of misread
+const STORE_VERSION: u32 = 3;
+/// Columns of `cards` in the order `card_from_row` reads them
+const CARD_COLUMNS: &str =
+    "name, pitch, life, intellect, cost, power, defense, display, uuid, types, keywords, legal, text";
+
+pub type Result<T> = rusqlite::Result<T>;
+
+/// Card data and image urls in one indexed SQLite file. Built once from the card CSVs so
+/// lookups don't need to parse and hold every card in memory.
+pub struct CardStore {
+    conn: Connection,
+}
+
+impl CardStore {
+    pub fn open(fp: &str) -> Result<Self> {
+        let conn = Connection::open(fp)?;
+        let version: u32 = conn.query_row("SELECT version FROM metadata", [], |row| row.get(0))?;
+        if version != STORE_VERSION {
+            return Err(rusqlite::Error::InvalidQuery);
+        }
+        Ok(CardStore { conn })
+    }
+
+    /// Writes `cards` and `images` into a new store at `fp`, replacing any existing one
+    pub fn create(fp: &str, cards: &CardDB, images: &CardImageDB) -> Result<Self> {
+        if Path::new(fp).exists() {
+            fs::remove_file(fp).map_err(|_| rusqlite::Error::InvalidPath(fp.into()))?;
+        }
+        Self::write(Connection::open(fp)?, cards, images)
+    }
+
+    /// Rebuilds the store at `CARD_STORE_FILE` from the card CSVs
+    pub fn create_from_csv() -> Result<Self> {
+        Self::create(
+            &assets::path(CARD_STORE_FILE),
+            &CardDB::from_csv(&assets::path(CARD_FILE)),
+            &CardImageDB::build_with_policy(&assets::path(URL_FILE), &PrintingPolicy::init()),
+        )
+    }
+
+    fn write(mut conn: Connection, cards: &CardDB, images: &CardImageDB) -> Result<Self> {
+        let tx = conn.transaction()?;
+        tx.execute_batch(
+            "CREATE TABLE metadata (version INTEGER NOT NULL);
+             CREATE TABLE cards (
+                 id INTEGER PRIMARY KEY,
+                 name TEXT NOT NULL,
+                 pitch INTEGER,
+                 life INTEGER,
+                 intellect INTEGER,
+                 cost INTEGER,
+                 power INTEGER,
+                 defense INTEGER,
+                 display TEXT NOT NULL,
+                 uuid TEXT NOT NULL,
+                 types TEXT NOT NULL,
+                 keywords TEXT NOT NULL,
+                 legal TEXT NOT NULL,
+                 text TEXT NOT NULL
+             );
+             CREATE INDEX cards_name_pitch ON cards (name, pitch);
+             CREATE INDEX cards_uuid ON cards (uuid);
+             CREATE TABLE images (name TEXT NOT NULL, pitch INTEGER, url TEXT NOT NULL);
+             CREATE INDEX images_name_pitch ON images (name, pitch);",
+        )?;
+        tx.execute(
+            "INSERT INTO metadata (version) VALUES (?1)",
+            params![STORE_VERSION],
+        )?;
+        {
+            let mut insert = tx.prepare(&format!(
+                "INSERT INTO cards ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                CARD_COLUMNS
+            ))?;
+            for card in cards.cards.iter() {
+                let legal: Vec<&str> = card.legal.iter().map(|format| format.code()).collect();
+                insert.execute(params![
+                    card.name,
+                    card.pitch,
+                    card.life,
+                    card.intellect,
+                    card.cost,
+                    card.power,
+                    card.defense,
+                    card.display,
+                    card.uuid,
+                    card.types.join(","),
+                    card.keywords.join(","),
+                    legal.join(","),
+                    card.text,
+                ])?;
+            }
+            let mut insert =
+                tx.prepare("INSERT INTO images (name, pitch, url) VALUES (?1, ?2, ?3)")?;
+            for ((name, pitch), url) in images.urls() {
+                insert.execute(params![name, pitch, url])?;
+            }
+        }
+        tx.commit()?;
+        Ok(CardStore { conn })
+    }
+
+    /// Whether the store at `fp` exists and was built after the card CSVs and printing policy
+    /// were last changed
+    pub fn is_fresh(fp: &str) -> bool {
+        let modified = |fp: &str| fs::metadata(fp).and_then(|m| m.modified()).ok();
+        let Some(built) = modified(fp) else {
+            return false;
+        };
+        [CARD_FILE, URL_FILE, PRINTING_POLICY_FILE]
+            .iter()
+            .all(|csv| modified(&assets::path(csv)).map_or(true, |changed| changed <= built))
+    }
+
+    fn card_from_row(row: &Row) -> Result<CardData> {
+        let types: Vec<String> = row
+            .get::<_, String>(9)?
+            .split(',')
+            .map(|t| t.to_string())
+            .collect();
+        let keywords: String = row.get(10)?;
+        let legal: String = row.get(11)?;
+        let (classes, talents) = CardData::classes_and_talents(&types);
+        Ok(CardData {
+            name: row.get(0)?,
+            pitch: row.get(1)?,
+            life: row.get(2)?,
+            intellect: row.get(3)?,
+            cost: row.get(4)?,
+            power: row.get(5)?,
+            defense: row.get(6)?,
+            display: row.get(7)?,
+            uuid: row.get(8)?,
+            types,
+            classes,
+            talents,
+            keywords: keywords
+                .split(',')
+                .filter(|k| !k.is_empty())
+                .map(|k| k.to_string())
+                .collect(),
+            legal: legal.split(',').filter_map(Format::from_code).collect(),
+            text: row.get(12)?,
+        })
+    }
+
+    pub fn cards(&self) -> Result<Vec<CardData>> {
+        let mut select = self
+            .conn
+            .prepare(&format!("SELECT {} FROM cards ORDER BY id", CARD_COLUMNS))?;
+        let rows = select.query_map([], Self::card_from_row)?;
+        rows.collect()
+    }
+
+    pub fn find(&self, name: &str, pitch: Option<u32>) -> Result<Option<CardData>> {
+        self.conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM cards WHERE name = ?1 AND pitch IS ?2 ORDER BY id LIMIT 1",
+                    CARD_COLUMNS
+                ),
+                params![name, pitch],
+                Self::card_from_row,
+            )
+            .optional()
+    }
+
+    pub fn find_uuid(&self, uuid: &str) -> Result<Option<CardData>> {
+        self.conn
+            .query_row(
+                &format!("SELECT {} FROM cards WHERE uuid = ?1 LIMIT 1", CARD_COLUMNS),
+                params![uuid],
+                Self::card_from_row,
+            )
+            .optional()
+    }
+
+    pub fn image_url(&self, name: &str, pitch: Option<u32>) -> Result<Option<String>> {
+        self.conn
+            .query_row(
+                "SELECT url FROM images WHERE name = ?1 AND pitch IS ?2",
+                params![name, pitch],
+                |row| row.get(0),
+            )
+            .optional()
+    }
+
+    /// Name and pitch of every card with an image
+    pub fn image_cards(&self) -> Result<Vec<(String, Option<u32>)>> {
+        let mut select = self.conn.prepare("SELECT name, pitch FROM images")?;
+        let rows = select.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn card(name: &str, pitch: Option<u32>, uuid: &str) -> CardData {
+        CardData {
+            name: name.to_string(),
+            pitch,
+            life: None,
+            intellect: None,
+            cost: Some(1),
+            power: Some(3),
+            defense: Some(3),
+            display: name.to_string(),
+            uuid: uuid.to_string(),
+            types: vec!["ninja".to_string(), "action".to_string()],
+            classes: vec!["ninja".to_string()],
+            talents: Vec::new(),
+            keywords: vec!["go again".to_string()],
+            legal: vec![Format::Blitz],
+            text: "**Go again**".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        let cards = CardDB {
+            cards: vec![
+                card("Head Jab", Some(1), "abc"),
+                card("Head Jab", Some(3), "def"),
+                card("Harmonized Kodachi", None, "ghi"),
+            ],
+        };
+        let images = CardImageDB::from_urls([(
+            ("Head Jab".to_string(), Some(3)),
+            "https://example.com/head-jab.png".to_string(),
+        )]);
+        let store = CardStore::write(Connection::open_in_memory()?, &cards, &images)?;
+
+        assert_eq!(store.cards()?.len(), 3);
+        let found = store.find("Head Jab", Some(3))?.unwrap();
+        assert_eq!(found.uuid, "def");
+        assert_eq!(found.types, vec!["ninja", "action"]);
+        assert_eq!(found.classes, vec!["ninja"]);
+        assert_eq!(found.keywords, vec!["go again"]);
+        assert_eq!(found.power, Some(3));
+        assert_eq!(found.legal, vec![Format::Blitz]);
+        assert_eq!(found.text, "**Go again**");
+        assert_eq!(store.find("Harmonized Kodachi", None)?.unwrap().uuid, "ghi");
+        assert!(store.find("Head Jab", Some(2))?.is_none());
+        assert_eq!(store.find_uuid("abc")?.unwrap().pitch, Some(1));
+        assert_eq!(
+            store.image_url("Head Jab", Some(3))?.as_deref(),
+            Some("https://example.com/head-jab.png")
+        );
+        assert!(store.image_url("Head Jab", Some(1))?.is_none());
+        Ok(())
+    }
+}
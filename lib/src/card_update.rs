@@ -0,0 +1,274 @@
+use std::{fs, path::Path};
+
+use tracing::info;
+use reqwest::{
+    header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets,
+    card::{CARD_FILE, URL_FILE},
+    card_store::{CardStore, CARD_STORE_FILE},
+    download,
+};
+
+const CARD_FILE_URL: &'static str =
+    "https://the-fab-cube.github.io/flesh-and-blood-cards/web/csvs/english/card.html";
+const URL_FILE_URL: &'static str =
+    "https://the-fab-cube.github.io/a58c5dbd-aac1-4de5-9ead-1787f64c5685";
+/// Columns `CardDB` reads. A download without them is rejected rather than replacing a working
+/// file.
+const CARD_FILE_COLUMNS: [&str; 5] = ["Name", "Pitch", "Health", "Unique ID", "Types"];
+/// Columns `CardImageDB` reads
+const URL_FILE_COLUMNS: [&str; 5] = [
+    "Card Name",
+    "Set ID",
+    "Card Pitch",
+    "Art Variations",
+    "Image URL",
+];
+/// Added to the path of a file to store the validators of the version on disk
+const CACHE_SUFFIX: &str = ".cache";
+/// Added to the path of a file to keep the version it replaced
+const BACKUP_SUFFIX: &str = ".bak";
+const TMP_SUFFIX: &str = ".tmp";
+
+#[derive(Debug)]
+pub enum UpdateError {
+    Http(reqwest::Error),
+    Status {
+        url: String,
+        status: StatusCode,
+    },
+    Io(std::io::Error),
+    /// The download doesn't look like the file it should replace
+    InvalidFile {
+        url: String,
+        reason: String,
+    },
+    Store(rusqlite::Error),
+}
+
+impl std::error::Error for UpdateError {}
+
+impl std::fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpdateError::Http(err) => write!(f, "Could not download card data: {}", err),
+            UpdateError::Status { url, status } => write!(f, "{} returned {}", url, status),
+            UpdateError::Io(err) => write!(f, "Could not save card data: {}", err),
+            UpdateError::InvalidFile { url, reason } => {
+                write!(f, "Unexpected card data from {}: {}", url, reason)
+            }
+            UpdateError::Store(err) => write!(f, "Could not build the card store: {}", err),
+        }
+    }
+}
+
+impl From<reqwest::Error> for UpdateError {
+    fn from(err: reqwest::Error) -> Self {
+        UpdateError::Http(err)
+    }
+}
+
+impl From<std::io::Error> for UpdateError {
+    fn from(err: std::io::Error) -> Self {
+        UpdateError::Io(err)
+    }
+}
+
+impl From<rusqlite::Error> for UpdateError {
+    fn from(err: rusqlite::Error) -> Self {
+        UpdateError::Store(err)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, UpdateError>;
+
+/// Downloads the card files that changed and rebuilds the card store from them when needed.
+/// Returns whether the card store was rebuilt.
+pub async fn update_card_db() -> Result<bool> {
+    let changed = CardDbUpdater::init().update().await?;
+    if !changed && CardStore::is_fresh(&assets::path(CARD_STORE_FILE)) {
+        return Ok(false);
+    }
+    tokio::task::spawn_blocking(CardStore::create_from_csv)
+        .await
+        .expect("Card store builder panicked")?;
+    Ok(true)
+}
+
+/// Validators the server sent with the version on disk
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct CacheInfo {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+/// A tab separated file downloaded from `url` to `fp`
+#[derive(Debug, Clone)]
+pub struct CardSource {
+    pub url: String,
+    pub fp: String,
+    pub columns: Vec<&'static str>,
+}
+
+impl CardSource {
+    fn cache_fp(&self) -> String {
+        format!("{}{}", self.fp, CACHE_SUFFIX)
+    }
+
+    fn read_cache(&self) -> CacheInfo {
+        fs::read_to_string(self.cache_fp())
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    /// Checks the header row has every column that is read
+    fn validate(&self, body: &[u8]) -> Result<()> {
+        let invalid = |reason: String| UpdateError::InvalidFile {
+            url: self.url.clone(),
+            reason,
+        };
+        let header = body.split(|&b| b == b'\n').next().unwrap_or_default();
+        let header = std::str::from_utf8(header).map_err(|e| invalid(e.to_string()))?;
+        let header: Vec<&str> = header.trim_end().split('\t').collect();
+        let missing: Vec<&str> = self
+            .columns
+            .iter()
+            .filter(|column| !header.contains(*column))
+            .copied()
+            .collect();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(invalid(format!("missing columns {}", missing.join(", "))))
+        }
+    }
+}
+
+/// Keeps the card CSVs up to date. Files are only downloaded when the server says they changed,
+/// and the previous version is kept beside the new one in case the upstream format breaks.
+pub struct CardDbUpdater {
+    sources: Vec<CardSource>,
+    client: reqwest::Client,
+}
+
+impl CardDbUpdater {
+    pub fn new(sources: Vec<CardSource>) -> Self {
+        CardDbUpdater {
+            sources,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Updates the files `CardDB` and `CardImageDB` read
+    pub fn init() -> Self {
+        Self::new(vec![
+            CardSource {
+                url: CARD_FILE_URL.to_string(),
+                fp: assets::path(CARD_FILE),
+                columns: CARD_FILE_COLUMNS.to_vec(),
+            },
+            CardSource {
+                url: URL_FILE_URL.to_string(),
+                fp: assets::path(URL_FILE),
+                columns: URL_FILE_COLUMNS.to_vec(),
+            },
+        ])
+    }
+
+    /// Returns whether any file changed
+    pub async fn update(&self) -> Result<bool> {
+        let mut changed = false;
+        for source in self.sources.iter() {
+            changed |= self.update_source(source).await?;
+        }
+        Ok(changed)
+    }
+
+    /// Blocking wrapper around `update` for code that isn't async. Must not be called from an
+    /// async task.
+    pub fn update_blocking(&self) -> Result<bool> {
+        download::block_on(self.update())
+    }
+
+    async fn update_source(&self, source: &CardSource) -> Result<bool> {
+        let exists = Path::new(&source.fp).exists();
+        let cache = if exists {
+            source.read_cache()
+        } else {
+            CacheInfo::default()
+        };
+
+        let mut request = self.client.get(&source.url);
+        if let Some(etag) = &cache.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &cache.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        let resp = request.send().await?;
+        if resp.status() == StatusCode::NOT_MODIFIED {
+            info!("{} is up to date", source.fp);
+            return Ok(false);
+        }
+        if !resp.status().is_success() {
+            return Err(UpdateError::Status {
+                url: source.url.clone(),
+                status: resp.status(),
+            });
+        }
+
+        let header = |name| {
+            resp.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+        let new_cache = CacheInfo {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        };
+        let body = resp.bytes().await?;
+        source.validate(&body)?;
+
+        let tmp_fp = format!("{}{}", source.fp, TMP_SUFFIX);
+        fs::write(&tmp_fp, &body)?;
+        if exists {
+            fs::copy(&source.fp, format!("{}{}", source.fp, BACKUP_SUFFIX))?;
+        }
+        fs::rename(&tmp_fp, &source.fp)?;
+        fs::write(
+            source.cache_fp(),
+            serde_json::to_string(&new_cache).expect("Cache info is always serializable"),
+        )?;
+        info!("Updated {}", source.fp);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let source = CardSource {
+            url: String::new(),
+            fp: String::new(),
+            columns: vec!["Name", "Pitch"],
+        };
+        assert!(source
+            .validate(b"Unique ID\tName\tPitch\r\nabc\tHead Jab\t1\n")
+            .is_ok());
+        assert!(source.validate(b"Unique ID\tName\tPitch").is_ok());
+        assert!(source
+            .validate(b"Unique ID\tName\nabc\tHead Jab\n")
+            .is_err());
+        assert!(source.validate(b"<html>Not found</html>").is_err());
+    }
+}
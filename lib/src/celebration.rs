@@ -0,0 +1,143 @@
+use opencv::{
+    core::{Point, Rect, Scalar, UMat, UMatTrait, UMatTraitConst},
+    imgproc,
+};
+
+use crate::fade::remove_color;
+
+const CELEBRATION_DURATION: f64 = 2.5;
+const PARTICLE_COUNT: usize = 120;
+const PARTICLE_MIN_SIZE: f64 = 3.0;
+const PARTICLE_MAX_SIZE: f64 = 8.0;
+// Velocities and gravity are relative to the size of the panel, per second
+const HORIZONTAL_SPREAD: f64 = 0.8;
+const MIN_LAUNCH_SPEED: f64 = 0.9;
+const MAX_LAUNCH_SPEED: f64 = 1.8;
+const GRAVITY: f64 = 1.6;
+// Background color of the particle canvas. Removed when compositing, so it must stay far away
+// from every color in the palette.
+const KEY_COLOR: Scalar = Scalar::new(255.0, 0.0, 255.0, 0.0);
+const PALETTE: [Scalar; 5] = [
+    Scalar::new(0.0, 215.0, 255.0, 0.0),
+    Scalar::new(0.0, 0.0, 255.0, 0.0),
+    Scalar::new(255.0, 128.0, 0.0, 0.0),
+    Scalar::new(0.0, 255.0, 0.0, 0.0),
+    Scalar::new(255.0, 255.0, 255.0, 0.0),
+];
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Small xorshift generator so the burst looks the same on every render
+struct Xorshift(u64);
+
+impl Xorshift {
+    fn new(seed: u64) -> Self {
+        // xorshift gets stuck on 0
+        Xorshift(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    /// Value between 0.0 (inclusive) and 1.0 (exclusive)
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1_u64 << 53) as f64
+    }
+
+    fn range(&mut self, low: f64, high: f64) -> f64 {
+        low + (high - low) * self.next_f64()
+    }
+}
+
+struct Particle {
+    velocity: (f64, f64),
+    size: i32,
+    color: Scalar,
+}
+
+/// Confetti burst fired from the bottom of a panel, e.g. the winner's hero
+pub struct Celebration {
+    rect: Rect,
+    start: f64,
+    particles: Vec<Particle>,
+}
+
+impl Celebration {
+    /// # Arguments
+    /// * `rect` - Region of the frame the confetti is drawn in
+    /// * `start` - Time in seconds the celebration begins
+    /// * `seed` - Seed for the particle layout
+    pub fn build(rect: Rect, start: f64, seed: u64) -> Self {
+        let mut rng = Xorshift::new(seed);
+        let particles = (0..PARTICLE_COUNT)
+            .map(|_| Particle {
+                velocity: (
+                    rng.range(-HORIZONTAL_SPREAD, HORIZONTAL_SPREAD) * rect.width as f64,
+                    -rng.range(MIN_LAUNCH_SPEED, MAX_LAUNCH_SPEED) * rect.height as f64,
+                ),
+                size: rng.range(PARTICLE_MIN_SIZE, PARTICLE_MAX_SIZE) as i32,
+                color: PALETTE[(rng.next_f64() * PALETTE.len() as f64) as usize],
+            })
+            .collect();
+        Celebration {
+            rect,
+            start,
+            particles,
+        }
+    }
+
+    pub fn is_finished(&self, seconds: f64) -> bool {
+        seconds - self.start >= CELEBRATION_DURATION
+    }
+
+    /// Draws the particles for time `seconds`. Does nothing before the start or after the end.
+    pub fn draw(&self, frame: &mut UMat, seconds: f64) -> Result<()> {
+        let elapsed = seconds - self.start;
+        if elapsed < 0.0 || self.is_finished(seconds) {
+            return Ok(());
+        }
+
+        let mut canvas =
+            UMat::new_size_with_default_def(self.rect.size(), frame.typ(), KEY_COLOR)?;
+        let origin = (self.rect.width as f64 / 2.0, self.rect.height as f64);
+        let gravity = GRAVITY * self.rect.height as f64;
+        for particle in self.particles.iter() {
+            let x = origin.0 + particle.velocity.0 * elapsed;
+            let y = origin.1 + particle.velocity.1 * elapsed + 0.5 * gravity * elapsed.powi(2);
+            if x < 0.0 || y < 0.0 || x >= self.rect.width as f64 || y >= self.rect.height as f64 {
+                continue;
+            }
+            imgproc::rectangle(
+                &mut canvas,
+                Rect::from_points(
+                    Point::new(x as i32, y as i32),
+                    Point::new(x as i32 + particle.size, y as i32 + particle.size),
+                ),
+                particle.color,
+                -1,
+                imgproc::LINE_8,
+                0,
+            )?;
+        }
+
+        let roi = frame.roi(self.rect)?;
+        let composite = remove_color(&roi, &canvas, &KEY_COLOR)?;
+        let mut roi = frame.roi_mut(self.rect)?;
+        composite.copy_to(&mut roi)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Xorshift;
+
+    #[test]
+    fn test_xorshift_range() {
+        let mut rng = Xorshift::new(0);
+        for _ in 0..1000 {
+            let value = rng.range(-2.0, 3.0);
+            assert!((-2.0..3.0).contains(&value));
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Settings every tool shares, read once at startup from `go-to-one.json` in the working
+//! directory, or from the file named by `GO_TO_ONE_CONFIG`:
+//!
+//! ```json
+//! {
+//!     "data_root": "/srv/go-to-one",
+//!     "render_dir": "/srv/videos"
+//! }
+//! ```
+//!
+//! Every setting is optional. Without a file, everything is relative to the working directory
+//! as before.
+
+use std::{fs::File, io::BufReader, path::Path, sync::OnceLock};
+
+use serde::Deserialize;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+pub const CONFIG_FILE: &str = "go-to-one.json";
+/// Environment variable naming a config file to read instead of `CONFIG_FILE`
+pub const CONFIG_ENV: &str = "GO_TO_ONE_CONFIG";
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Directory the `data` folder is in, so the tools can be run from anywhere. Paths given
+    /// on the command line are still relative to the working directory.
+    pub data_root: String,
+    /// Where renders without an output file are written
+    pub render_dir: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            data_root: ".".to_owned(),
+            render_dir: "output_videos".to_owned(),
+        }
+    }
+}
+
+impl Config {
+    pub fn read_from_path(fp: &str) -> Result<Self> {
+        let file = File::open(fp).map_err(|e| format!("Couldn't open {}: {}", fp, e))?;
+        serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("Invalid config {}: {}", fp, e).into())
+    }
+
+    /// Reads the file named by `CONFIG_ENV`, or `CONFIG_FILE` when there is one, and makes it
+    /// what `get` returns. Called by each binary before anything is read from the data folder.
+    pub fn init() -> Result<&'static Config> {
+        let config = match std::env::var(CONFIG_ENV) {
+            Ok(fp) => Self::read_from_path(&fp)?,
+            Err(_) if Path::new(CONFIG_FILE).exists() => Self::read_from_path(CONFIG_FILE)?,
+            Err(_) => Self::default(),
+        };
+        CONFIG
+            .set(config)
+            .map_err(|_| "The config was read after it was first used")?;
+        Ok(Self::get())
+    }
+
+    /// The config read by `init`, or the defaults before then, e.g. in tests
+    pub fn get() -> &'static Config {
+        CONFIG.get_or_init(Config::default)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let fp = dir.path().join("go-to-one.json");
+        let fp = fp.to_str().unwrap();
+
+        std::fs::write(fp, r#"{"data_root": "/srv/go-to-one"}"#).unwrap();
+        let config = Config::read_from_path(fp).unwrap();
+        assert_eq!(config.data_root, "/srv/go-to-one");
+        assert_eq!(config.render_dir, Config::default().render_dir);
+
+        // A misspelt setting would otherwise be silently ignored
+        std::fs::write(fp, r#"{"data_dir": "/srv/go-to-one"}"#).unwrap();
+        assert!(Config::read_from_path(fp).is_err());
+    }
+}
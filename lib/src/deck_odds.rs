@@ -0,0 +1,181 @@
+//! What's left to draw of a player's decklist, given the cards the annotation has seen them
+//! play or defend with. Cards can also leave a deck unseen, by being pitched, banished face
+//! down or sat in hand, so these are the odds of the cards not seen yet rather than of the deck.
+
+use std::fmt;
+
+use crate::{
+    annotation::{Annotation, Event, MatchSetup},
+    decklist::{DeckEntry, Decklist},
+    match_state::MatchState,
+};
+
+/// Names in the order of their pitch value
+const PITCH_NAMES: [&str; 3] = ["red", "yellow", "blue"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeckOdds {
+    /// Copies of each card not seen yet. Cards without a pitch, i.e. heroes, weapons and
+    /// equipment, aren't drawn so are left out.
+    pub unseen: Vec<DeckEntry>,
+}
+
+impl DeckOdds {
+    /// Odds before any card has been seen
+    pub fn build(deck: &Decklist) -> Self {
+        DeckOdds {
+            unseen: deck
+                .entries
+                .iter()
+                .filter(|entry| entry.pitch.is_some())
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Odds for `player` after the annotations. A new game starts from the full deck again.
+    pub fn replay(
+        deck: &Decklist,
+        player: u8,
+        setup: &MatchSetup,
+        annotations: &[Annotation],
+    ) -> Self {
+        let mut odds = Self::build(deck);
+        let mut state = MatchState::build(setup);
+        for annotation in annotations {
+            match &annotation.event {
+                Event::Card { name, pitch } if state.turn_player == player => {
+                    odds.seen(name, *pitch)
+                }
+                Event::Block { name, pitch } if state.turn_player != player => {
+                    odds.seen(name, *pitch)
+                }
+                Event::Game => odds = Self::build(deck),
+                _ => {}
+            }
+            state.apply(&annotation.event);
+        }
+        odds
+    }
+
+    /// Takes a copy of the card out. Cards that aren't in the deck, or have no copies left, are
+    /// ignored.
+    pub fn seen(&mut self, name: &str, pitch: Option<u32>) {
+        if let Some(entry) = self.unseen.iter_mut().find(|entry| {
+            entry.count > 0
+                && entry.name.eq_ignore_ascii_case(name)
+                && (pitch.is_none() || entry.pitch == pitch)
+        }) {
+            entry.count -= 1;
+        }
+    }
+
+    pub fn total(&self) -> u32 {
+        self.unseen.iter().map(|entry| entry.count).sum()
+    }
+
+    /// Unseen copies of `name`, of any pitch
+    pub fn copies(&self, name: &str) -> u32 {
+        self.unseen
+            .iter()
+            .filter(|entry| entry.name.eq_ignore_ascii_case(name))
+            .map(|entry| entry.count)
+            .sum()
+    }
+
+    /// Unseen cards that pitch for `pitch`
+    pub fn with_pitch(&self, pitch: u32) -> u32 {
+        self.unseen
+            .iter()
+            .filter(|entry| entry.pitch == Some(pitch))
+            .map(|entry| entry.count)
+            .sum()
+    }
+
+    /// Chance the next card drawn pitches for `pitch`, from 0 to 1. 0 once every card is seen.
+    pub fn chance(&self, pitch: u32) -> f64 {
+        match self.total() {
+            0 => 0.0,
+            total => self.with_pitch(pitch) as f64 / total as f64,
+        }
+    }
+}
+
+/// e.g. "25 unseen: red 40%, yellow 20%, blue 40%"
+impl fmt::Display for DeckOdds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} unseen", self.total())?;
+        for (i, name) in PITCH_NAMES.iter().enumerate() {
+            let separator = if i == 0 { ": " } else { ", " };
+            let chance = self.chance(i as u32 + 1) * 100.0;
+            write!(f, "{}{} {:.0}%", separator, name, chance)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::time_tick::TimeTick;
+
+    use super::*;
+
+    fn deck() -> Decklist {
+        Decklist::parse(
+            "Hero: Katsu, the Wanderer\n3x Command and Conquer (red)\n1x Head Jab (yellow)\n4x Sink Below (blue)\n",
+        )
+    }
+
+    #[test]
+    fn test_seen() {
+        let mut odds = DeckOdds::build(&deck());
+        assert_eq!(odds.total(), 8);
+        odds.seen("command and conquer", Some(1));
+        odds.seen("Head Jab", None);
+        odds.seen("Head Jab", None);
+        odds.seen("Snatch", Some(1));
+        assert_eq!(odds.copies("Command and Conquer"), 2);
+        assert_eq!(odds.copies("Head Jab"), 0);
+        assert_eq!(odds.chance(3), 4.0 / 6.0);
+        assert_eq!(odds.to_string(), "6 unseen: red 33%, yellow 0%, blue 67%");
+    }
+
+    #[test]
+    fn test_replay() {
+        let setup = MatchSetup {
+            first: 1,
+            ..Default::default()
+        };
+        let events = [
+            Event::Card {
+                name: "Command and Conquer".to_string(),
+                pitch: Some(1),
+            },
+            // Player 2 defending
+            Event::Block {
+                name: "Sink Below".to_string(),
+                pitch: Some(3),
+            },
+            Event::Turn,
+            Event::Block {
+                name: "Sink Below".to_string(),
+                pitch: Some(3),
+            },
+        ];
+        let annotations: Vec<Annotation> = events
+            .into_iter()
+            .map(|event| Annotation::new(TimeTick::new(), event))
+            .collect();
+
+        let odds = DeckOdds::replay(&deck(), 1, &setup, &annotations);
+        assert_eq!(odds.copies("Command and Conquer"), 2);
+        assert_eq!(odds.copies("Sink Below"), 3);
+
+        let mut annotations = annotations;
+        annotations.push(Annotation::new(TimeTick::new(), Event::Game));
+        assert_eq!(
+            DeckOdds::replay(&deck(), 1, &setup, &annotations).total(),
+            8
+        );
+    }
+}
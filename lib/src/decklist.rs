@@ -0,0 +1,253 @@
+use std::fs;
+
+use serde_json::Value;
+
+use crate::card::{CardDB, CardData};
+
+const FABDB_DECK_URL: &str = "fabdb.net/decks/";
+const FABDB_API_URL: &str = "https://api.fabdb.net/decks/";
+/// Line of a Fabrary text export naming the hero
+const FABRARY_HERO_PREFIX: &str = "Hero:";
+/// Lines of a Fabrary text export that list several cards, comma separated
+const FABRARY_LIST_PREFIXES: [&str; 2] = ["Weapons:", "Equipment:"];
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// A card in a deck. `pitch` is `None` when the list doesn't say, e.g. for equipment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeckEntry {
+    pub name: String,
+    pub pitch: Option<u32>,
+    pub count: u32,
+}
+
+impl DeckEntry {
+    pub fn matches(&self, card: &CardData) -> bool {
+        self.name.eq_ignore_ascii_case(&card.name)
+            && (self.pitch.is_none() || self.pitch == card.pitch)
+    }
+
+    /// Parses a line like "3x Head Jab (red)", "(3) Head Jab (red)", "Head Jab (R)" or
+    /// "1 Nullrune Boots"
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("//") {
+            return None;
+        }
+
+        let (count, rest) = match line.split_once(char::is_whitespace) {
+            Some((count, rest)) => match count
+                .trim_start_matches('(')
+                .trim_end_matches([')', 'x', 'X'])
+                .parse::<u32>()
+            {
+                Ok(count) => (count, rest.trim()),
+                Err(_) => (1, line),
+            },
+            None => (1, line),
+        };
+
+        let (name, pitch) = match rest.rsplit_once('(') {
+            Some((name, suffix)) => match parse_pitch(suffix.trim_end_matches(')')) {
+                Some(pitch) => (name.trim(), Some(pitch)),
+                None => (rest, None),
+            },
+            None => (rest, None),
+        };
+        Some(DeckEntry {
+            name: name.to_owned(),
+            pitch,
+            count,
+        })
+    }
+}
+
+fn parse_pitch(value: &str) -> Option<u32> {
+    match value.trim().to_lowercase().as_str() {
+        "r" | "red" | "1" => Some(1),
+        "y" | "yellow" | "2" => Some(2),
+        "b" | "blue" | "3" => Some(3),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Decklist {
+    pub entries: Vec<DeckEntry>,
+}
+
+impl Decklist {
+    /// One card per line. Blank lines and lines starting with `#` or `//` are skipped.
+    /// Fabrary text exports are also accepted: their `Hero:`, `Weapons:` and `Equipment:`
+    /// lines become cards and the other header lines are skipped.
+    pub fn parse(text: &str) -> Self {
+        let mut entries = Vec::new();
+        for line in text.lines().map(|line| line.trim()) {
+            if let Some(hero) = line.strip_prefix(FABRARY_HERO_PREFIX) {
+                // Hero names have commas in them, e.g. "Katsu, the Wanderer"
+                entries.extend(DeckEntry::parse(hero));
+            } else if let Some(list) = FABRARY_LIST_PREFIXES
+                .iter()
+                .find_map(|prefix| line.strip_prefix(prefix))
+            {
+                entries.extend(list.split(',').filter_map(DeckEntry::parse));
+            } else if Self::is_export_header(line) {
+                continue;
+            } else if let Some(entry) = DeckEntry::parse(line) {
+                entries.push(entry);
+            }
+        }
+        Decklist { entries }
+    }
+
+    /// Lines like "Class: Ninja" or "Deck build - via https://fabrary.net :"
+    fn is_export_header(line: &str) -> bool {
+        line.ends_with(':')
+            || line.contains("://")
+            || line
+                .split_once(": ")
+                .is_some_and(|(key, _)| !key.is_empty() && !key.contains(char::is_numeric))
+    }
+
+    pub fn read_from_path(fp: &str) -> Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(fp)?))
+    }
+
+    /// Loads a decklist from a fabdb.net deck URL, any URL serving a text export (e.g. a
+    /// Fabrary text export), or a local file
+    pub async fn load(source: &str) -> Result<Self> {
+        if !source.starts_with("http://") && !source.starts_with("https://") {
+            return Self::read_from_path(source);
+        }
+        if let Some((_, slug)) = source.split_once(FABDB_DECK_URL) {
+            let slug = slug.trim_end_matches('/');
+            let body = reqwest::get(format!("{}{}", FABDB_API_URL, slug))
+                .await?
+                .error_for_status()?
+                .text()
+                .await?;
+            return Self::parse_fabdb(&body);
+        }
+        let body = reqwest::get(source)
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        Ok(Self::parse(&body))
+    }
+
+    /// Parses the fabdb.net deck API response, which lists `cards` with their `name`,
+    /// `total` and pitch as `stats.resource`
+    pub fn parse_fabdb(body: &str) -> Result<Self> {
+        let deck: Value = serde_json::from_str(body)?;
+        let cards = deck["cards"]
+            .as_array()
+            .ok_or("fabdb deck has no cards")?;
+        let entries = cards
+            .iter()
+            .filter_map(|card| {
+                Some(DeckEntry {
+                    name: card["name"].as_str()?.to_owned(),
+                    pitch: card["stats"]["resource"]
+                        .as_u64()
+                        .or_else(|| card["stats"]["resource"].as_str()?.parse().ok())
+                        .map(|v| v as u32),
+                    count: card["total"].as_u64().unwrap_or(1) as u32,
+                })
+            })
+            .collect();
+        Ok(Decklist { entries })
+    }
+
+    pub fn contains(&self, card: &CardData) -> bool {
+        self.entries.iter().any(|entry| entry.matches(card))
+    }
+
+    /// Looks up every entry in `db`, returning the matched cards with their counts and the
+    /// entries that couldn't be found
+    pub fn resolve<'a>(&self, db: &'a CardDB) -> (Vec<(&'a CardData, u32)>, Vec<&DeckEntry>) {
+        let mut found = Vec::new();
+        let mut missing = Vec::new();
+        for entry in self.entries.iter() {
+            match db.cards.iter().find(|card| entry.matches(card)) {
+                Some(card) => found.push((card, entry.count)),
+                None => missing.push(entry),
+            }
+        }
+        (found, missing)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_fabrary_export() {
+        let deck = Decklist::parse(
+            "Deck build - via https://fabrary.net :\n\nClass: Ninja\nHero: Katsu, the Wanderer\nWeapons: Harmonized Kodachi, Harmonized Kodachi\nEquipment: Mask of Momentum\n\n(3) Head Jab (red)\n(2) Surging Strike (blue)\n",
+        );
+        let names: Vec<&str> = deck.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "Katsu, the Wanderer",
+                "Harmonized Kodachi",
+                "Harmonized Kodachi",
+                "Mask of Momentum",
+                "Head Jab",
+                "Surging Strike"
+            ]
+        );
+        assert_eq!(deck.entries[4].count, 3);
+        assert_eq!(deck.entries[5].pitch, Some(3));
+    }
+
+    #[test]
+    fn test_parse_fabdb() -> Result<()> {
+        let deck = Decklist::parse_fabdb(
+            r#"{"name": "Katsu", "cards": [{"name": "Head Jab", "total": 3, "stats": {"resource": 1}}]}"#,
+        )?;
+        assert_eq!(
+            deck.entries,
+            vec![DeckEntry {
+                name: "Head Jab".to_string(),
+                pitch: Some(1),
+                count: 3
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse() {
+        let deck = Decklist::parse(
+            "# Ninja\n3x Head Jab (red)\nHead Jab (B)\n\n1 Nullrune Boots\nCommand and Conquer (1)\n",
+        );
+        assert_eq!(
+            deck.entries,
+            vec![
+                DeckEntry {
+                    name: "Head Jab".to_string(),
+                    pitch: Some(1),
+                    count: 3
+                },
+                DeckEntry {
+                    name: "Head Jab".to_string(),
+                    pitch: Some(3),
+                    count: 1
+                },
+                DeckEntry {
+                    name: "Nullrune Boots".to_string(),
+                    pitch: None,
+                    count: 1
+                },
+                DeckEntry {
+                    name: "Command and Conquer".to_string(),
+                    pitch: Some(1),
+                    count: 1
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use std::{
+    future::Future,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use tracing::warn;
+use reqwest::{Client, StatusCode};
+use tokio::{runtime::Runtime, sync::Semaphore};
+
+/// How card images and other assets are downloaded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DownloadConfig {
+    /// For a whole request, including reading the body
+    pub timeout: Duration,
+    /// Extra attempts after a failure that might go away, e.g. a timeout or a 503
+    pub retries: u32,
+    /// Wait before the first retry. Doubles for each retry after that.
+    pub backoff: Duration,
+    /// Most downloads running at once, across threads and tasks
+    pub max_concurrent: usize,
+}
+
+impl Default for DownloadConfig {
+    fn default() -> Self {
+        DownloadConfig {
+            timeout: Duration::from_secs(10),
+            retries: 3,
+            backoff: Duration::from_millis(500),
+            max_concurrent: 4,
+        }
+    }
+}
+
+/// Runs `future` to completion from code that isn't async, e.g. the render loop. Must not be
+/// called from an async task.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    // Never dropped, as a runtime can't be dropped from an async context. It has a worker
+    // thread so connections opened on it keep working when used from other runtimes.
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME
+        .get_or_init(|| {
+            tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(1)
+                .enable_all()
+                .build()
+                .expect("Couldn't start the download runtime")
+        })
+        .block_on(future)
+}
+
+/// Whether trying the request again could work
+fn is_transient(err: &reqwest::Error) -> bool {
+    err.is_timeout()
+        || err.is_connect()
+        || err.status().is_some_and(|status| {
+            status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+        })
+}
+
+/// Downloads with a timeout, retries and a limit on how many run at once. `get_async` is the
+/// implementation and `get` is a blocking wrapper around it.
+pub struct Downloader {
+    config: DownloadConfig,
+    client: Client,
+    slots: Arc<Semaphore>,
+}
+
+impl Downloader {
+    pub fn new(config: DownloadConfig) -> Self {
+        Downloader {
+            config,
+            client: Client::builder()
+                .timeout(config.timeout)
+                .build()
+                .expect("Couldn't set up an HTTP client"),
+            slots: Arc::new(Semaphore::new(config.max_concurrent.max(1))),
+        }
+    }
+
+    pub fn config(&self) -> DownloadConfig {
+        self.config
+    }
+
+    pub async fn get_async(&self, url: &str) -> reqwest::Result<Vec<u8>> {
+        let _slot = self
+            .slots
+            .acquire()
+            .await
+            .expect("Download slots are never closed");
+        let mut attempt = 0;
+        loop {
+            let result = match self.client.get(url).send().await {
+                Ok(resp) => match resp.error_for_status() {
+                    Ok(resp) => resp.bytes().await,
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(bytes) => return Ok(bytes.to_vec()),
+                Err(e) if attempt < self.config.retries && is_transient(&e) => {
+                    let wait = self.config.backoff * 2u32.saturating_pow(attempt);
+                    warn!("Retrying {} in {:?}: {}", url, wait, e);
+                    tokio::time::sleep(wait).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Blocks until the download is done. Must not be called from an async task.
+    pub fn get(&self, url: &str) -> reqwest::Result<Vec<u8>> {
+        block_on(self.get_async(url))
+    }
+}
+
+impl Default for Downloader {
+    fn default() -> Self {
+        Self::new(DownloadConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_invalid_url() {
+        let downloader = Downloader::new(DownloadConfig {
+            max_concurrent: 1,
+            ..Default::default()
+        });
+        // Not retried, and the slot is given back for the next download
+        assert!(downloader.get("not a url").is_err());
+        assert!(downloader.get("not a url").is_err());
+        assert_eq!(downloader.slots.available_permits(), 1);
+    }
+}
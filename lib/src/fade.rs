@@ -1,8 +1,11 @@
 use opencv::{
     boxed_ref::BoxedRefMut,
     core::{
-        add_weighted, bitwise_and, bitwise_not, bitwise_not_def, bitwise_or, bitwise_or_def, in_range, no_array, Rect, Scalar, ToInputArray, UMat, UMatTrait, UMatTraitConst
+        add_weighted, bitwise_and, bitwise_not, bitwise_not_def, bitwise_or, bitwise_or_def,
+        in_range, no_array, pow, Rect, Scalar, Size, ToInputArray, UMat, UMatTrait, UMatTraitConst,
+        CV_32F,
     },
+    imgproc::{blend_linear, cvt_color_def, resize, COLOR_BGR2GRAY, INTER_AREA, INTER_NEAREST},
 };
 
 const COLOR_LENIENCY: f64 = 80.0;
@@ -207,18 +210,17 @@ pub fn remove_white_corners(
     let col_increment = out_mask.cols().div_euclid(25);
     let row_increment = out_mask.rows().div_euclid(30);
 
-    for x in 0..col_increment {
-        edge_mask.col_mut(x)?.set_to(&255.0, &mut no_array())?;
-        edge_mask
-            .col_mut(foreground.cols() - (x + 1))?
-            .set_to(&255.0, &mut no_array())?;
-    }
-
-    for x in 0..row_increment {
-        edge_mask.row_mut(x)?.set_to(&255.0, &mut no_array())?;
-        edge_mask
-            .row_mut(foreground.rows() - (x + 1))?
-            .set_to(&255.0, &mut no_array())?;
+    // One call per edge rather than per row and column keeps the work on the GPU in a few
+    // large kernels
+    let (cols, rows) = (foreground.cols(), foreground.rows());
+    let edges = [
+        Rect::new(0, 0, col_increment, rows),
+        Rect::new(cols - col_increment, 0, col_increment, rows),
+        Rect::new(0, 0, cols, row_increment),
+        Rect::new(0, rows - row_increment, cols, row_increment),
+    ];
+    for edge in edges.into_iter().filter(|edge| edge.area() > 0) {
+        edge_mask.roi_mut(edge)?.set_to(&255.0, &mut no_array())?;
     }
 
     bitwise_and(
@@ -269,6 +271,35 @@ pub fn convert_alpha_to_white(image: &UMat) -> Result<UMat, Box<dyn std::error::
     Ok(new)
 }
 
+/// Lays `overlay` over `background` with each part of it as opaque as it is bright, so the dark
+/// parts of e.g. a fire video show the background through them. Brightness is averaged over
+/// `block` pixel squares, and a `block` of 1 keys every pixel on its own.
+pub fn overlay_by_luminance(
+    background: &UMat,
+    overlay: &UMat,
+    block: i32,
+) -> Result<UMat, Box<dyn std::error::Error>> {
+    let size = overlay.size()?;
+    let mut gray = UMat::new_def();
+    cvt_color_def(overlay, &mut gray, COLOR_BGR2GRAY)?;
+    if block > 1 {
+        let blocks = Size::new((size.width / block).max(1), (size.height / block).max(1));
+        let mut averaged = UMat::new_def();
+        resize(&gray, &mut averaged, blocks, 0.0, 0.0, INTER_AREA)?;
+        resize(&averaged, &mut gray, size, 0.0, 0.0, INTER_NEAREST)?;
+    }
+
+    // The cube root keeps faint parts of the overlay visible
+    let mut weights = UMat::new_def();
+    gray.convert_to(&mut weights, CV_32F, 1.0 / 255.0, 0.0)?;
+    pow(&weights.clone(), 1.0 / 3.0, &mut weights)?;
+    let mut inverse = UMat::new_def();
+    weights.convert_to(&mut inverse, CV_32F, -1.0, 1.0)?;
+
+    let mut out = UMat::new_def();
+    blend_linear(overlay, background, &weights, &inverse, &mut out)?;
+    Ok(out)
+}
 
 #[cfg(test)]
 mod test {
@@ -276,7 +307,29 @@ mod test {
 
     use crate::{image::load_image, movement::resize_umat, rotate::rotate_image};
 
-    use super::{convert_alpha_to_white, remove_color, remove_white_corners};
+    use opencv::core::{no_array, norm2, Rect, UMatTrait, CV_8UC3, NORM_INF};
+
+    use super::{convert_alpha_to_white, overlay_by_luminance, remove_color, remove_white_corners};
+
+    #[test]
+    fn test_overlay_by_luminance() -> Result<(), Box<dyn std::error::Error>> {
+        let size = Size::new(40, 20);
+        let background =
+            UMat::new_size_with_default_def(size, CV_8UC3, Scalar::new(0.0, 0.0, 255.0, 0.0))?;
+        let mut overlay = UMat::new_size_with_default_def(size, CV_8UC3, Scalar::all(0.0))?;
+        overlay
+            .roi_mut(Rect::new(20, 0, 20, 20))?
+            .set_to_def(&Scalar::all(255.0))?;
+
+        let out = overlay_by_luminance(&background, &overlay, 10)?;
+        let diff = |rect: Rect, expected: &UMat| -> opencv::Result<f64> {
+            norm2(&out.roi(rect)?, &expected.roi(rect)?, NORM_INF, &no_array())
+        };
+        // Black shows the background and white covers it
+        assert_eq!(diff(Rect::new(0, 0, 20, 20), &background)?, 0.0);
+        assert_eq!(diff(Rect::new(20, 0, 20, 20), &overlay)?, 0.0);
+        Ok(())
+    }
 
     #[test]
     fn test_remove_color() -> Result<(), Box<dyn std::error::Error>> {
@@ -0,0 +1,246 @@
+//! Statistics for each game of an annotated match, for endcards and for creators writing up a
+//! match. Annotations don't record resources, so how much was floated isn't reported.
+
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::{
+    annotation::{Annotation, Event, MatchSetup},
+    match_state::MatchState,
+};
+
+/// What happened in one turn
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TurnStats {
+    /// Starts at 1 each game
+    pub turn: u32,
+    /// Player whose turn it was
+    pub player: u8,
+    /// Cards the turn player played
+    pub cards_played: u32,
+    /// Cards the other player defended with
+    pub blocks: u32,
+    /// Life the turn player took off the other while they had a chain link open
+    pub damage: i32,
+}
+
+/// A player's totals over a game, and per turn averages
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PlayerStats {
+    pub name: String,
+    pub turns: u32,
+    pub cards_played: u32,
+    pub blocks: u32,
+    pub damage: i32,
+    /// Per turn of their own
+    pub cards_per_turn: f64,
+    /// Per turn of their own
+    pub damage_per_turn: f64,
+    /// Per turn of the other player's
+    pub blocks_per_turn: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GameReport {
+    /// Starts at 1
+    pub game: u32,
+    pub winner: Option<u8>,
+    pub players: [PlayerStats; 2],
+    pub turns: Vec<TurnStats>,
+}
+
+impl GameReport {
+    /// One report per game of the match, in order. A game that's still going is reported too.
+    pub fn for_match(setup: &MatchSetup, annotations: &[Annotation]) -> Vec<Self> {
+        let mut reports = Vec::new();
+        let mut state = MatchState::build(setup);
+        let mut turns = vec![Self::first_turn(&state)];
+        for annotation in annotations {
+            let before = state.clone();
+            state.apply(&annotation.event);
+            let turn = turns.last_mut().unwrap();
+            match &annotation.event {
+                Event::Card { .. } => turn.cards_played += 1,
+                Event::Block { .. } => turn.blocks += 1,
+                Event::Life { .. } => {
+                    let attacker = turn.player as usize - 1;
+                    turn.damage += state.damage_dealt[attacker] - before.damage_dealt[attacker];
+                }
+                Event::Turn => turns.push(TurnStats {
+                    turn: state.turn,
+                    player: state.turn_player,
+                    ..Default::default()
+                }),
+                Event::Game => {
+                    let turns = std::mem::replace(&mut turns, vec![Self::first_turn(&state)]);
+                    reports.push(Self::build(setup, &before, turns));
+                }
+                _ => {}
+            }
+        }
+        reports.push(Self::build(setup, &state, turns));
+        reports
+    }
+
+    fn first_turn(state: &MatchState) -> TurnStats {
+        TurnStats {
+            turn: state.turn,
+            player: state.turn_player,
+            ..Default::default()
+        }
+    }
+
+    fn build(setup: &MatchSetup, state: &MatchState, turns: Vec<TurnStats>) -> Self {
+        let stats = |player: u8, name: &str| {
+            let own: Vec<&TurnStats> = turns.iter().filter(|t| t.player == player).collect();
+            let other_turns = turns.len() - own.len();
+            let cards_played = own.iter().map(|t| t.cards_played).sum();
+            let damage = own.iter().map(|t| t.damage).sum();
+            let blocks = turns
+                .iter()
+                .filter(|t| t.player != player)
+                .map(|t| t.blocks)
+                .sum();
+            PlayerStats {
+                name: name.to_owned(),
+                turns: own.len() as u32,
+                cards_played,
+                blocks,
+                damage,
+                cards_per_turn: average(cards_played as f64, own.len()),
+                damage_per_turn: average(damage as f64, own.len()),
+                blocks_per_turn: average(blocks as f64, other_turns),
+            }
+        };
+        GameReport {
+            game: state.game,
+            winner: state.winner,
+            players: [stats(1, &setup.player1), stats(2, &setup.player2)],
+            turns,
+        }
+    }
+
+    /// A heading, a table of each player's totals and a table of every turn
+    pub fn to_markdown(&self) -> String {
+        let mut text = format!("## Game {}\n\n", self.game);
+        if let Some(winner) = self.winner {
+            let _ = writeln!(text, "Won by {}\n", self.players[winner as usize - 1].name);
+        }
+        text.push_str("| Player | Turns | Cards | Cards/turn | Damage | Damage/turn | Blocks | Blocks/turn |\n");
+        text.push_str("|---|---|---|---|---|---|---|---|\n");
+        for player in self.players.iter() {
+            let _ = writeln!(
+                text,
+                "| {} | {} | {} | {:.1} | {} | {:.1} | {} | {:.1} |",
+                player.name,
+                player.turns,
+                player.cards_played,
+                player.cards_per_turn,
+                player.damage,
+                player.damage_per_turn,
+                player.blocks,
+                player.blocks_per_turn
+            );
+        }
+        text.push_str("\n| Turn | Player | Cards | Blocks | Damage |\n");
+        text.push_str("|---|---|---|---|---|\n");
+        for turn in self.turns.iter() {
+            let _ = writeln!(
+                text,
+                "| {} | {} | {} | {} | {} |",
+                turn.turn,
+                self.players[turn.player as usize - 1].name,
+                turn.cards_played,
+                turn.blocks,
+                turn.damage
+            );
+        }
+        text
+    }
+}
+
+fn average(total: f64, turns: usize) -> f64 {
+    if turns == 0 {
+        0.0
+    } else {
+        total / turns as f64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::time_tick::TimeTick;
+
+    use super::*;
+
+    #[test]
+    fn test_for_match() {
+        let setup = MatchSetup {
+            player1: "Ada".to_string(),
+            player2: "Bo".to_string(),
+            hero1_life: "20".to_string(),
+            hero2_life: "20".to_string(),
+            first: 1,
+            ..Default::default()
+        };
+        let card = |name: &str| Event::Card {
+            name: name.to_string(),
+            pitch: Some(1),
+        };
+        let events = [
+            card("Head Jab"),
+            Event::Block {
+                name: "Sink Below".to_string(),
+                pitch: Some(3),
+            },
+            Event::Life {
+                player1: None,
+                player2: Some("-2".to_string()),
+            },
+            Event::Turn,
+            card("Snatch"),
+            Event::Life {
+                player1: Some("-4".to_string()),
+                player2: None,
+            },
+            Event::Turn,
+            card("Head Jab"),
+            card("Surging Strike"),
+            Event::Win { player: 1 },
+            Event::Game,
+            card("Snatch"),
+        ];
+        let annotations: Vec<Annotation> = events
+            .into_iter()
+            .map(|event| Annotation::new(TimeTick::new(), event))
+            .collect();
+
+        let reports = GameReport::for_match(&setup, &annotations);
+        assert_eq!(reports.len(), 2);
+        let report = &reports[0];
+        assert_eq!(report.winner, Some(1));
+        assert_eq!(report.turns.len(), 3);
+        assert_eq!(
+            report.turns[0],
+            TurnStats {
+                turn: 1,
+                player: 1,
+                cards_played: 1,
+                blocks: 1,
+                damage: 2,
+            }
+        );
+        assert_eq!(report.players[0].turns, 2);
+        assert_eq!(report.players[0].cards_per_turn, 1.5);
+        assert_eq!(report.players[0].damage_per_turn, 1.0);
+        assert_eq!(report.players[1].blocks_per_turn, 0.5);
+        assert_eq!(report.players[1].damage, 4);
+        assert!(report.to_markdown().contains("Won by Ada"));
+
+        // The other player goes first in the second game
+        assert_eq!(reports[1].game, 2);
+        assert_eq!(reports[1].turns[0].player, 2);
+        assert_eq!(reports[1].players[1].cards_played, 1);
+    }
+}
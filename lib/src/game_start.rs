@@ -0,0 +1,197 @@
+//! Finds where play starts in footage that begins with setup, shuffling or an empty table, so
+//! annotations can be aligned without trimming the video first. Frames are sampled every so
+//! often and the start is where the footage cuts or becomes busier than it was before.
+
+use opencv::{
+    core::{self, Mat, Size, Vector},
+    imgproc::{self, COLOR_BGR2GRAY, HISTCMP_CORREL, INTER_AREA},
+    prelude::*,
+    videoio::{self, VideoCapture},
+};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Samples are shrunk to this before being compared, which leaves out compression noise
+const SAMPLE_SIZE: Size = Size::new(64, 36);
+const HISTOGRAM_BINS: i32 = 32;
+/// Mean difference per pixel between samples for the table to count as active
+const MOTION_THRESHOLD: f64 = 6.0;
+/// How different the brightness histograms of consecutive samples are, from 0 to 2, for the
+/// footage to count as cutting
+const CUT_THRESHOLD: f64 = 0.5;
+/// How much a cut adds to a candidate's score
+const CUT_WEIGHT: f64 = 0.25;
+/// Fraction of samples after a start that have to be active
+const MIN_ACTIVITY: f64 = 0.3;
+const CANDIDATES: usize = 3;
+
+/// How a moment of the footage differs from the sample before it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActivitySample {
+    pub time: f64,
+    /// Mean difference per pixel, from 0 to 255
+    pub motion: f64,
+    /// Difference of the brightness histograms, from 0 to 2
+    pub scene_change: f64,
+}
+
+impl ActivitySample {
+    fn is_active(&self) -> bool {
+        self.motion > MOTION_THRESHOLD
+    }
+
+    fn is_cut(&self) -> bool {
+        self.scene_change > CUT_THRESHOLD
+    }
+}
+
+/// A possible start of play
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StartCandidate {
+    pub time: f64,
+    /// How much busier the footage is after `time` than before, plus a bonus for a cut
+    pub score: f64,
+    pub cut: bool,
+}
+
+/// Samples the video every `step` seconds
+pub fn sample_activity(video_fp: &str, step: f64) -> Result<Vec<ActivitySample>> {
+    let mut cap = VideoCapture::from_file(video_fp, videoio::CAP_ANY)?;
+    let fps = cap.get(videoio::CAP_PROP_FPS)?;
+    let duration = cap.get(videoio::CAP_PROP_FRAME_COUNT)? / fps;
+
+    let mut samples = Vec::new();
+    let mut previous: Option<(Mat, Mat)> = None;
+    let mut frame = Mat::default();
+    let mut time = 0.0;
+    while time < duration {
+        cap.set(videoio::CAP_PROP_POS_MSEC, time * 1000.0)?;
+        if !cap.read(&mut frame)? {
+            break;
+        }
+        let (small, histogram) = summarize(&frame)?;
+        if let Some((previous_small, previous_histogram)) = &previous {
+            let mut difference = Mat::default();
+            core::absdiff(&small, previous_small, &mut difference)?;
+            let correlation =
+                imgproc::compare_hist(&histogram, previous_histogram, HISTCMP_CORREL)?;
+            samples.push(ActivitySample {
+                time,
+                motion: core::mean_def(&difference)?[0],
+                scene_change: 1.0 - correlation,
+            });
+        }
+        previous = Some((small, histogram));
+        time += step;
+    }
+    Ok(samples)
+}
+
+/// Small grayscale copy of `frame` and its brightness histogram
+fn summarize(frame: &Mat) -> Result<(Mat, Mat)> {
+    let mut gray = Mat::default();
+    imgproc::cvt_color_def(frame, &mut gray, COLOR_BGR2GRAY)?;
+    let mut small = Mat::default();
+    imgproc::resize(&gray, &mut small, SAMPLE_SIZE, 0.0, 0.0, INTER_AREA)?;
+    let mut histogram = Mat::default();
+    imgproc::calc_hist_def(
+        &Vector::<Mat>::from_iter([small.clone()]),
+        &Vector::<i32>::from_slice(&[0]),
+        &core::no_array(),
+        &mut histogram,
+        &Vector::<i32>::from_slice(&[HISTOGRAM_BINS]),
+        &Vector::<f32>::from_slice(&[0.0, 256.0]),
+    )?;
+    Ok((small, histogram))
+}
+
+/// The likeliest starts of play, best first. Each is compared over `window` seconds either
+/// side, so none are in the first `window` seconds, and candidates are at least `window` apart.
+pub fn find_starts(samples: &[ActivitySample], window: f64) -> Vec<StartCandidate> {
+    let activity = |from: f64, to: f64| {
+        let in_range: Vec<_> = samples
+            .iter()
+            .filter(|sample| sample.time >= from && sample.time < to)
+            .collect();
+        if in_range.is_empty() {
+            return 0.0;
+        }
+        in_range.iter().filter(|sample| sample.is_active()).count() as f64 / in_range.len() as f64
+    };
+
+    let mut candidates: Vec<StartCandidate> = samples
+        .iter()
+        .filter(|sample| sample.time >= window)
+        .filter_map(|sample| {
+            let after = activity(sample.time, sample.time + window);
+            if after < MIN_ACTIVITY {
+                return None;
+            }
+            let before = activity(sample.time - window, sample.time);
+            let cut = sample.is_cut();
+            let score = after - before + if cut { CUT_WEIGHT } else { 0.0 };
+            Some(StartCandidate {
+                time: sample.time,
+                score,
+                cut,
+            })
+        })
+        .collect();
+    // Earlier wins a tie, as play is busy throughout once it starts
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.time.total_cmp(&b.time)));
+
+    let mut starts: Vec<StartCandidate> = Vec::new();
+    for candidate in candidates {
+        if starts
+            .iter()
+            .all(|start| (start.time - candidate.time).abs() >= window)
+        {
+            starts.push(candidate);
+        }
+        if starts.len() == CANDIDATES {
+            break;
+        }
+    }
+    starts
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn samples(motion: impl Fn(f64) -> f64, cut_at: Option<f64>) -> Vec<ActivitySample> {
+        (1..600)
+            .map(|time| {
+                let time = time as f64;
+                ActivitySample {
+                    time,
+                    motion: motion(time),
+                    scene_change: if cut_at == Some(time) { 1.0 } else { 0.05 },
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_quiet_then_busy() {
+        let samples = samples(|time| if time >= 200.0 { 12.0 } else { 1.0 }, None);
+        let starts = find_starts(&samples, 30.0);
+        assert_eq!(starts[0].time, 200.0);
+        assert!(!starts[0].cut);
+    }
+
+    #[test]
+    fn test_cut_to_the_table() {
+        // Setup is as busy as play, but the stream cuts to the table camera
+        let samples = samples(|_| 12.0, Some(300.0));
+        let starts = find_starts(&samples, 30.0);
+        assert_eq!(starts[0].time, 300.0);
+        assert!(starts[0].cut);
+    }
+
+    #[test]
+    fn test_still_footage_has_no_start() {
+        let samples = samples(|_| 1.0, None);
+        assert!(find_starts(&samples, 30.0).is_empty());
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use clap::ValueEnum;
+use opencv::{
+    core::{self, DeviceTraitConst, Rect, Scalar, Size, UMat, UMatTrait, UMatTraitConst, CV_8UC3},
+    imgproc,
+};
+
+use crate::fade::{remove_color, remove_white_corners};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Frames of sample work timed with and without OpenCL
+const SAMPLE_FRAMES: u32 = 30;
+const GREEN: Scalar = Scalar::new(0.0, 255.0, 0.0, 0.0);
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GpuMode {
+    /// Use OpenCL, failing if it isn't available
+    On,
+    /// Stay on the CPU
+    Off,
+    /// Use OpenCL when it's available and faster than the CPU
+    #[default]
+    Auto,
+}
+
+/// What `configure` found and chose
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuReport {
+    /// Name of the OpenCL device, if there is one
+    pub device: Option<String>,
+    pub in_use: bool,
+    /// How many times faster a sample of per frame work ran with OpenCL than without
+    pub speedup: Option<f64>,
+}
+
+/// Turns OpenCL on or off for every `UMat` operation after it. With a device available and
+/// OpenCL not turned off, a sample of per frame work is timed both ways to measure the speedup.
+pub fn configure(mode: GpuMode) -> Result<GpuReport> {
+    let device = opencl_device()?;
+    let speedup = match (mode, &device) {
+        (GpuMode::Off, _) | (_, None) => None,
+        _ => Some(time_sample(false)?.as_secs_f64() / time_sample(true)?.as_secs_f64()),
+    };
+    let use_opencl = match mode {
+        GpuMode::On if device.is_none() => {
+            return Err("--gpu on was given but no OpenCL device is available".into())
+        }
+        GpuMode::On => true,
+        GpuMode::Off => false,
+        GpuMode::Auto => speedup.is_some_and(|speedup| speedup > 1.0),
+    };
+    core::set_use_opencl(use_opencl)?;
+    Ok(GpuReport {
+        device,
+        in_use: core::use_opencl()?,
+        speedup,
+    })
+}
+
+/// OpenCL can be built in without a usable device, so it's turned on to check
+fn opencl_device() -> Result<Option<String>> {
+    if !core::have_opencl()? {
+        return Ok(None);
+    }
+    core::set_use_opencl(true)?;
+    if !core::use_opencl()? {
+        return Ok(None);
+    }
+    let device = core::Device::get_default()?;
+    Ok(Some(device.name()?))
+}
+
+fn time_sample(use_opencl: bool) -> Result<Duration> {
+    core::set_use_opencl(use_opencl)?;
+    let source = UMat::new_size_with_default_def(
+        Size::new(1280, 720),
+        CV_8UC3,
+        Scalar::new(40.0, 90.0, 160.0, 0.0),
+    )?;
+    let card = UMat::new_size_with_default_def(Size::new(365, 510), CV_8UC3, Scalar::all(255.0))?;
+    let mut frame = UMat::new_def();
+
+    // The first run compiles the OpenCL kernels
+    sample_frame(&source, &card, &mut frame)?;
+    core::finish()?;
+    let started = Instant::now();
+    for _ in 0..SAMPLE_FRAMES {
+        sample_frame(&source, &card, &mut frame)?;
+    }
+    core::finish()?;
+    Ok(started.elapsed())
+}
+
+/// The kinds of work the overlay does every frame: scaling the video, keying a card over it
+/// and shading part of it
+fn sample_frame(source: &UMat, card: &UMat, frame: &mut UMat) -> Result<()> {
+    imgproc::resize(
+        source,
+        frame,
+        Size::new(1920, 1080),
+        0.0,
+        0.0,
+        imgproc::INTER_AREA,
+    )?;
+    let rect = Rect::new(20, 560, card.cols(), card.rows());
+    let background = frame.roi(rect)?.try_clone()?;
+    let card = remove_white_corners(&background, card)?;
+    let card = remove_color(&background, &card, &GREEN)?;
+    card.copy_to(&mut frame.roi_mut(rect)?)?;
+    let mut shaded = UMat::new_def();
+    core::add_weighted(&*frame, 0.5, &*frame, 0.5, 0.0, &mut shaded, -1)?;
+    Ok(())
+}
+
+impl fmt::Display for GpuReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.device, self.in_use) {
+            (None, _) => write!(f, "OpenCL isn't available, rendering on the CPU")?,
+            (Some(device), true) => write!(f, "OpenCL is in use on {}", device)?,
+            (Some(device), false) => write!(f, "OpenCL is available on {} but not in use", device)?,
+        }
+        if let Some(speedup) = self.speedup {
+            write!(f, " ({:.2}x the speed of the CPU)", speedup)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let report = GpuReport {
+            device: Some("Iris".to_string()),
+            in_use: false,
+            speedup: Some(0.5),
+        };
+        assert_eq!(
+            report.to_string(),
+            "OpenCL is available on Iris but not in use (0.50x the speed of the CPU)"
+        );
+        let report = GpuReport {
+            device: None,
+            in_use: false,
+            speedup: None,
+        };
+        assert_eq!(
+            report.to_string(),
+            "OpenCL isn't available, rendering on the CPU"
+        );
+    }
+}
@@ -0,0 +1,137 @@
+//! Prepares raw full art hero animations for the overlay. Each is scaled and re-encoded at the
+//! render's frame rate, alongside the `cropped_` copy the overlay shows, and added to the full
+//! art hero map.
+
+use std::path::Path;
+
+use opencv::{
+    core::{Size, UMat, UMatTraitConst},
+    videoio::{
+        VideoCapture, VideoCaptureTrait, VideoCaptureTraitConst, VideoWriter, VideoWriterTrait,
+        CAP_PROP_FPS,
+    },
+};
+
+use crate::{
+    assets,
+    image::{FullArtHeroManager, FULL_ART_HERO_DIR},
+    movement::resize_umat,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Name of the prepared animation, from the raw animation's, e.g. "Kayo Raw.mov" is
+/// "kayo_raw.mp4"
+pub fn prepared_file_name(source_fp: &str) -> Result<String> {
+    let stem = Path::new(source_fp)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .filter(|stem| !stem.is_empty())
+        .ok_or_else(|| format!("No file name in {}", source_fp))?;
+    Ok(format!("{}.mp4", stem.to_lowercase().replace(' ', "_")))
+}
+
+/// `size` scaled to `width`, keeping its aspect ratio. Sizes are kept even for the encoder.
+fn scaled_size(size: Size, width: Option<i32>) -> Size {
+    let Some(width) = width else {
+        return size;
+    };
+    let height = (size.height as f64 * width as f64 / size.width as f64).round() as i32;
+    Size::new(width - width % 2, (height - height % 2).max(2))
+}
+
+/// How many times source frame `index` is written so the video plays at the same speed at
+/// `target_fps`. Frames are dropped when slowing down and repeated when speeding up.
+fn resampled_count(index: u64, source_fps: f64, target_fps: f64) -> u64 {
+    let ratio = target_fps / source_fps;
+    ((index + 1) as f64 * ratio).round() as u64 - (index as f64 * ratio).round() as u64
+}
+
+/// Writes the prepared and cropped animations of `source_fp` for `hero_name` and points the hero
+/// at them in `manager`. Without `width` or `fps`, the source's are kept. Returns the prepared
+/// file's name.
+pub fn prepare_hero(
+    manager: &mut FullArtHeroManager,
+    hero_name: &str,
+    source_fp: &str,
+    width: Option<i32>,
+    fps: Option<f64>,
+) -> Result<String> {
+    let mut cap = VideoCapture::from_file_def(source_fp)?;
+    if !cap.is_opened()? {
+        return Err(format!("Couldn't open {}", source_fp).into());
+    }
+    let source_fps = cap.get(CAP_PROP_FPS)?;
+    let target_fps = fps.unwrap_or(source_fps);
+
+    let mut frame = UMat::new_def();
+    if !cap.read(&mut frame)? {
+        return Err(format!("No frames in {}", source_fp).into());
+    }
+    let size = scaled_size(frame.size()?, width);
+    let cropped_size = FullArtHeroManager::crop_hero_img(&resize_umat(&frame, &size)?)?.size()?;
+
+    // Written under temporary names, so a source in the hero directory isn't overwritten as
+    // it's read
+    let file = prepared_file_name(source_fp)?;
+    let dir = assets::path(FULL_ART_HERO_DIR);
+    let fp = format!("{}/{}", dir, file);
+    let cropped_fp = format!("{}/cropped_{}", dir, file);
+    let tmp_fp = format!("{}/tmp_{}", dir, file);
+    let tmp_cropped_fp = format!("{}/tmp_cropped_{}", dir, file);
+    let fourcc = VideoWriter::fourcc('m', 'p', '4', 'v')?;
+    let mut writer = VideoWriter::new(&tmp_fp, fourcc, target_fps, size, true)?;
+    let mut cropped_writer =
+        VideoWriter::new(&tmp_cropped_fp, fourcc, target_fps, cropped_size, true)?;
+
+    let mut index = 0;
+    loop {
+        let resized = resize_umat(&frame, &size)?;
+        let cropped = FullArtHeroManager::crop_hero_img(&resized)?;
+        for _ in 0..resampled_count(index, source_fps, target_fps) {
+            writer.write(&resized)?;
+            cropped_writer.write(&cropped)?;
+        }
+        index += 1;
+        if !cap.read(&mut frame)? {
+            break;
+        }
+    }
+    writer.release()?;
+    cropped_writer.release()?;
+    std::fs::rename(&tmp_fp, &fp)?;
+    std::fs::rename(&tmp_cropped_fp, &cropped_fp)?;
+
+    manager.insert(hero_name, &file);
+    Ok(file)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resampled_count() {
+        let total = |source_fps, target_fps| {
+            (0..60)
+                .map(|i| resampled_count(i, source_fps, target_fps))
+                .sum::<u64>()
+        };
+        assert_eq!(total(30.0, 30.0), 60);
+        assert_eq!(total(30.0, 60.0), 120);
+        assert_eq!(total(60.0, 30.0), 30);
+        assert_eq!(total(24.0, 30.0), 75);
+    }
+
+    #[test]
+    fn test_prepared_file_name() {
+        assert_eq!(
+            prepared_file_name("raw/Kayo Raw.mov").unwrap(),
+            "kayo_raw.mp4"
+        );
+        assert_eq!(
+            scaled_size(Size::new(1080, 1920), Some(541)),
+            Size::new(540, 962)
+        );
+    }
+}
@@ -1,12 +1,25 @@
-use std::{borrow::BorrowMut, collections::HashMap};
+use std::{
+    borrow::BorrowMut,
+    collections::{BTreeMap, HashMap},
+};
 
 use opencv::{
-    core::{MatTraitConst, Rect, Size, UMat, UMatTrait, UMatTraitConst},
+    core::{MatTraitConst, Rect, Scalar, Size, UMat, UMatTrait, UMatTraitConst, Vector},
     imgcodecs, imgproc, Error,
 };
 
-use crate::err::RoiError;
+use crate::{
+    assets,
+    download::Downloader,
+    err::RoiError,
+    letterbox::fit_rect,
+    movement::{place_umat, resize_umat},
+};
 
+/// Full art hero animations, raw and cropped
+pub const FULL_ART_HERO_DIR: &str = "data/full_art_heroes";
+/// Hero names to their full art animation file in `FULL_ART_HERO_DIR`
+pub const FULL_ART_HERO_MAP_FP: &str = "data/full_art_hero_map.json";
 const ART_RATIO: f64 = 3.0 / 5.0;
 const BORDER_X_RATIO: f64 = 1.0 / 30.0;
 const BORDER_Y_RATIO: f64 = 1.0 / 36.0;
@@ -102,6 +115,26 @@ pub fn load_image(fp: &str) -> Result<UMat, opencv::error::Error> {
     Ok(umat)
 }
 
+/// Downloads an image, e.g. hero or asset art
+pub fn load_image_url(url: &str) -> Result<UMat, Box<dyn std::error::Error>> {
+    let bytes = Vector::<u8>::from_slice(&Downloader::default().get(url)?);
+    let img = imgcodecs::imdecode(&bytes, imgcodecs::IMREAD_COLOR)?;
+    if img.empty() {
+        return Err(format!("Couldn't decode image from {}", url).into());
+    }
+    let mut umat = UMat::new_def();
+    img.copy_to(&mut umat)?;
+    Ok(umat)
+}
+
+/// `img` scaled to fit `size` without stretching, centered on black
+pub fn resize_preserve_ratio(img: &UMat, size: &Size) -> Result<UMat, Box<dyn std::error::Error>> {
+    let mut frame = UMat::new_size_with_default_def(*size, img.typ(), Scalar::all(0.0))?;
+    let fit = fit_rect(img.size()?, *size);
+    place_umat(&resize_umat(img, &fit.size())?, &mut frame, fit)?;
+    Ok(frame)
+}
+
 pub fn load_image_unchanged(fp: &str) -> Result<UMat, opencv::error::Error> {
     let mut umat = UMat::new_def();
     // let img = imgcodecs::imread(fp, imgcodecs::IMREAD_COLOR)?;
@@ -127,11 +160,8 @@ impl FullArtHeroManager {
         &self,
         hero_name: &str,
     ) -> Result<String, Box<dyn std::error::Error>> {
-        if let Some(fp) = self.map.get(hero_name) {
-            Ok(format!("data/full_art_heroes/{}", fp))
-        } else {
-            Err(Box::new(Error::new(500, format!("Could not find full art animation for hero '{}' in the config file. An update is likely needed.", hero_name))))
-        }
+        let [fp, _] = self.hero_art_files(hero_name)?;
+        Ok(assets::path(&fp))
     }
 
     /// Loads the hero art animation for a given hero
@@ -139,13 +169,39 @@ impl FullArtHeroManager {
         &self,
         hero_name: &str,
     ) -> Result<String, Box<dyn std::error::Error>> {
+        let [_, fp] = self.hero_art_files(hero_name)?;
+        Ok(assets::path(&fp))
+    }
+
+    /// The hero's animation and its cropped top, relative to the data root like
+    /// `FULL_ART_HERO_DIR`
+    pub fn hero_art_files(
+        &self,
+        hero_name: &str,
+    ) -> Result<[String; 2], Box<dyn std::error::Error>> {
         if let Some(fp) = self.map.get(hero_name) {
-            Ok(format!("data/full_art_heroes/cropped_{}", fp))
+            Ok([
+                format!("{}/{}", FULL_ART_HERO_DIR, fp),
+                format!("{}/cropped_{}", FULL_ART_HERO_DIR, fp),
+            ])
         } else {
             Err(Box::new(Error::new(500, format!("Could not find full art animation for hero '{}' in the config file. An update is likely needed.", hero_name))))
         }
     }
 
+    /// Points `hero_name` at `file` in `FULL_ART_HERO_DIR`. Call `save` to keep the change.
+    pub fn insert(&mut self, hero_name: &str, file: &str) {
+        self.map.insert(hero_name.to_owned(), file.to_owned());
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let file = std::fs::File::create(assets::path(FULL_ART_HERO_MAP_FP))?;
+        // Sorted, so the file only changes where heroes do
+        let sorted: BTreeMap<&String, &String> = self.map.iter().collect();
+        serde_json::to_writer_pretty(file, &sorted)?;
+        Ok(())
+    }
+
     /// Loads only the top half fo the hero art animation
     pub fn crop_hero_img(hero_mat: &UMat) -> Result<UMat, Box<dyn std::error::Error>> {
         let roi = hero_mat.roi(Rect::new(
@@ -162,7 +218,7 @@ impl FullArtHeroManager {
 }
 
 fn load_full_art_hero_map() -> HashMap<String, String> {
-    let file = std::fs::File::open("data/full_art_hero_map.json")
+    let file = std::fs::File::open(assets::path(FULL_ART_HERO_MAP_FP))
         .expect("Can't find full art hero json file.");
     let json: HashMap<String, String> =
         serde_json::from_reader(file).expect("Full art json file incorrectly formatted.");
@@ -193,3 +249,25 @@ pub fn crop(img: &UMat, roi: &Rect) -> Result<UMat, Box<dyn std::error::Error>>
     crop_roi.copy_to(&mut cropped)?;
     Ok(cropped)
 }
+
+#[cfg(test)]
+mod test {
+    use opencv::core::{mean_def, Rect, Scalar, Size, UMat, UMatTraitConst, CV_8UC3};
+
+    use super::resize_preserve_ratio;
+
+    #[test]
+    fn test_resize_preserve_ratio() -> Result<(), Box<dyn std::error::Error>> {
+        let img =
+            UMat::new_size_with_default_def(Size::new(100, 100), CV_8UC3, Scalar::all(255.0))?;
+        let out = resize_preserve_ratio(&img, &Size::new(200, 100))?;
+        assert_eq!(out.size()?, Size::new(200, 100));
+        let mean =
+            |x| -> opencv::Result<f64> { Ok(mean_def(&out.roi(Rect::new(x, 0, 50, 100))?)?[0]) };
+        // Black bars either side of the square
+        assert_eq!(mean(0)?, 0.0);
+        assert_eq!(mean(75)?, 255.0);
+        assert_eq!(mean(150)?, 0.0);
+        Ok(())
+    }
+}
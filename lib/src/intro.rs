@@ -1,25 +1,94 @@
 use opencv::{
-    core::{bitwise_not_def, flip, Rect, Scalar, Size, UMat, UMatTraitConst},
-    imgproc::{cvt_color_def, resize_def, COLOR_BGR2GRAY, COLOR_GRAY2RGB, FONT_HERSHEY_SIMPLEX},
+    core::{add_weighted, flip, Rect, Scalar, Size, UMat, UMatTraitConst},
+    imgproc::{resize_def, FONT_HERSHEY_SIMPLEX},
     videoio::{VideoCapture, VideoCaptureTrait, VideoCaptureTraitConst, VideoWriter, VideoWriterTrait, CAP_PROP_FRAME_COUNT, CAP_PROP_POS_FRAMES},
 };
 
+use tracing::debug;
+
 use crate::{
+    card::CardData,
+    fade::overlay_by_luminance,
     image::crop,
-    movement::{place_umat, Reparameterization},
+    lowerthird::{record_text, LowerThird},
+    movement::{place_umat, resize_umat, Reparameterization},
     relative_roi::center_offset,
     text::center_text_at_rect,
 };
 
 pub const INTRO_TIME: f64 = 8.0;
+pub const OUTRO_TIME: f64 = 8.0;
 const PLAYER_NAME_FONT_SCALE: f64 = 4.0;
 const PLAYER_NAME_FONT_FACE: i32 = FONT_HERSHEY_SIMPLEX;
 const PLAYER_NAME_FONT_THICKNESS: i32 = 6;
 const PLAYER_NAME_FONT_BUFFER: i32 = 20;
 const WHITE: Scalar = Scalar::new(255.0, 255.0, 255.0, 0.0);
+const STATS_FONT_SCALE: f64 = 2.0;
+const STATS_FONT_THICKNESS: i32 = 3;
+/// Gold, for the winner
+const WINNER_COLOR: Scalar = Scalar::new(0.0, 215.0, 255.0, 0.0);
+const CREDITS_FONT_SCALE: f64 = 1.5;
+const CREDITS_FONT_THICKNESS: i32 = 2;
+/// Tallest a credit line can be, relative to the frame
+const CREDITS_LINE_HEIGHT_RATIO: f64 = 1.0 / 12.0;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
+/// What's shown under a player's name on the VS screen
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HeroStats {
+    /// The hero's classes, e.g. "Ninja"
+    pub class: Option<String>,
+    pub young: Option<bool>,
+    pub life: Option<u32>,
+    /// Seed or record at the event, e.g. "3" or "5-1"
+    pub record: Option<String>,
+}
+
+impl HeroStats {
+    /// `hero` is the hero's card, when the card db has it. `life` is the setup's starting life.
+    pub fn build(hero: Option<&CardData>, life: &str, record: Option<&str>) -> Self {
+        let class = hero
+            .map(|hero| {
+                hero.classes
+                    .iter()
+                    .map(|class| capitalize(class))
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            })
+            .filter(|class| !class.is_empty());
+        HeroStats {
+            class,
+            young: hero.map(|hero| hero.is_young()),
+            life: life.trim().parse().ok(),
+            record: record
+                .map(|record| record.trim().to_owned())
+                .filter(|record| !record.is_empty()),
+        }
+    }
+
+    /// e.g. "Ninja | Young | 20 life | Record 5-1", or `None` when nothing is known
+    pub fn line(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        parts.extend(self.class.clone());
+        parts.extend(
+            self.young
+                .map(|young| if young { "Young" } else { "Adult" }.to_string()),
+        );
+        parts.extend(self.life.map(|life| format!("{} life", life)));
+        parts.extend(self.record.as_deref().map(record_text));
+        (!parts.is_empty()).then(|| parts.join(" | "))
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
 pub struct VideoCapLooper {
     fp: String,
     cap: VideoCapture,
@@ -44,28 +113,8 @@ impl VideoCapLooper {
 
         Ok(frame)
     }
-
-    // Hack until I make this file myself
-    pub fn background_read(&mut self) -> Result<UMat> {
-        let mut frame = UMat::new_def();
-        let got = self.cap.read(&mut frame)?;
-        if !got {
-            self.cap = VideoCapture::from_file_def(&self.fp)?;
-            self.cap.read(&mut frame)?;
-        }
-
-        // HACK
-        let mut inverted_frame = UMat::new_def();
-        bitwise_not_def(&frame, &mut inverted_frame)?;
-        cvt_color_def(&inverted_frame, &mut frame, COLOR_BGR2GRAY)?;
-        // cvt_color_def(&frame, &mut inverted_frame, COLOR_RGB2BGR)?;
-        cvt_color_def(&frame, &mut inverted_frame, COLOR_GRAY2RGB)?;
-
-        Ok(inverted_frame)
-    }
 }
 
-
 pub struct VideoCapLooperAdj {
     cap: VideoCapture,
     frames: f64,
@@ -97,6 +146,72 @@ impl VideoCapLooperAdj {
     }
 }
 
+/// `img` mixed with black, all black at 0 and all `img` at 1
+fn fade_from_black(img: &UMat, alpha: f64) -> Result<UMat> {
+    let black = UMat::new_size_with_default_def(img.size()?, img.typ(), Scalar::all(0.0))?;
+    let mut frame = UMat::new_def();
+    add_weighted(&black, 1.0 - alpha, img, alpha, 0.0, &mut frame, 0)?;
+    Ok(frame)
+}
+
+/// Writes `img` fading in from black, held, then fading out to black
+pub fn display_image(
+    writer: &mut VideoWriter,
+    img: &UMat,
+    fade_in_count: u32,
+    display_count: u32,
+    fade_out_count: u32,
+) -> Result<()> {
+    for i in 0..fade_in_count {
+        let alpha = Reparameterization::EaseIn.apply(i as f64 / fade_in_count as f64);
+        writer.write(&fade_from_black(img, alpha)?)?;
+    }
+    for _ in 0..display_count {
+        writer.write(img)?;
+    }
+    for i in 0..fade_out_count {
+        let alpha = Reparameterization::EaseIn.apply(i as f64 / fade_out_count as f64);
+        writer.write(&fade_from_black(img, 1.0 - alpha)?)?;
+    }
+    Ok(())
+}
+
+/// Writes the looping `overlay_fp` video over `img`, keyed by its brightness in `block` pixel
+/// squares. The overlay fades in over black first, then `img` fades in under it, is held, and
+/// fades out.
+pub fn overlay_video_with_fade(
+    writer: &mut VideoWriter,
+    overlay_fp: &str,
+    img: &UMat,
+    block: i32,
+    fade_in_count: u32,
+    display_count: u32,
+    fade_out_count: u32,
+) -> Result<()> {
+    let mut overlay_looper = VideoCapLooper::build(overlay_fp)?;
+    let count = 2 * fade_in_count + display_count + fade_out_count;
+    for i in 0..count {
+        debug!("{} /{}", i, count);
+        let percentage = if i < fade_in_count {
+            0.0
+        } else if i < 2 * fade_in_count {
+            (i - fade_in_count) as f64 / fade_in_count as f64
+        } else if i < 2 * fade_in_count + display_count {
+            1.0
+        } else {
+            1.0 - (i - 2 * fade_in_count - display_count) as f64 / fade_out_count as f64
+        };
+        let frame = fade_from_black(img, Reparameterization::EaseIn.apply(percentage))?;
+
+        let mut overlay = resize_umat(&overlay_looper.read()?, &img.size()?)?;
+        if i < fade_in_count {
+            overlay = fade_from_black(&overlay, i as f64 / fade_in_count as f64)?;
+        }
+        writer.write(&overlay_by_luminance(&frame, &overlay, block)?)?;
+    }
+    Ok(())
+}
+
 fn bounce_in(percentage: f64, img: &UMat, frame: &mut UMat, right: bool) -> Result<()> {
     let frame_size = frame.size()?;
 
@@ -139,11 +254,14 @@ fn bounce_in(percentage: f64, img: &UMat, frame: &mut UMat, right: bool) -> Resu
     Ok(())
 }
 
+/// Both heroes bounce in, then the players' names are shown with "VS". With `stats`, each
+/// hero's class, life and the player's record slide in on a plate in their half.
 pub fn generate_intro(
     hero1_fp: &str,
     player1: &str,
     hero2_fp: &str,
     player2: &str,
+    stats: Option<&[HeroStats; 2]>,
     frame_size: &Size,
     frame_typ: i32,
     fps: f64,
@@ -156,6 +274,23 @@ pub fn generate_intro(
 
     let mut hero1_img = hero1_looper.read()?;
     let mut hero2_img = hero2_looper.read()?;
+    // Plates come in with the names, in the last stage
+    let names_start = (3 * num_frames + 1) as f64 / fps;
+    let plates: Vec<LowerThird> = stats
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .filter_map(|(i, hero_stats)| {
+            let area = Rect::new(
+                0,
+                i as i32 * img_size.height,
+                img_size.width,
+                img_size.height,
+            );
+            let line = hero_stats.line()?;
+            Some(LowerThird::build(area, names_start, &line, None))
+        })
+        .collect();
     flip(&hero1_img.clone(), &mut hero1_img, 1)?;
     resize_def(&hero1_img.clone(), &mut hero1_img, img_size)?;
     resize_def(&hero2_img.clone(), &mut hero2_img, img_size)?;
@@ -252,6 +387,9 @@ pub fn generate_intro(
                 ),
                 PLAYER_NAME_FONT_BUFFER,
             )?;
+            for plate in plates.iter() {
+                plate.draw(&mut frame, (2 * num_frames + i) as f64 / fps)?;
+            }
             center_text_at_rect(
                 &mut frame,
                 "VS",
@@ -273,6 +411,125 @@ pub fn generate_intro(
     Ok(())
 }
 
+/// The winner's hero bounces in, then "WINNER" and their name are shown over it and the final
+/// lives and `credits`, e.g. commentators and social handles, are shown below
+pub fn generate_outro(
+    winner_fp: &str,
+    winner: u8,
+    players: [&str; 2],
+    final_lives: [i32; 2],
+    credits: &[String],
+    frame_size: &Size,
+    frame_typ: i32,
+    fps: f64,
+    writer: &mut VideoWriter,
+) -> Result<()> {
+    let num_frames = (fps * (OUTRO_TIME / 4.0)) as i32;
+    let img_size = Size::new(frame_size.width, frame_size.height.div_euclid(2));
+    let mut winner_looper = VideoCapLooper::build(winner_fp)?;
+    let winner_name = players[winner as usize - 1];
+    let lives = format!(
+        "{} {}  -  {} {}",
+        players[0], final_lives[0], final_lives[1], players[1]
+    );
+
+    // Text sits in the middle three fifths of the width
+    let text_x = center_offset(3 * img_size.width.div_euclid(5), img_size.width);
+    let text_width = 3 * img_size.width.div_euclid(5);
+    let lives_rect = Rect::new(
+        text_x,
+        img_size.height + img_size.height.div_euclid(10),
+        text_width,
+        img_size.height.div_euclid(5),
+    );
+    let credits_top = lives_rect.y + lives_rect.height + img_size.height.div_euclid(10);
+    let credit_height = ((frame_size.height - credits_top) / credits.len().max(1) as i32)
+        .min((frame_size.height as f64 * CREDITS_LINE_HEIGHT_RATIO) as i32);
+
+    for i in 0..(4 * num_frames) {
+        let mut frame = UMat::new_size_with_default_def(
+            *frame_size,
+            frame_typ,
+            Scalar::new(0.0, 0.0, 0.0, 0.0),
+        )?;
+        let mut winner_img = winner_looper.read()?;
+        resize_def(&winner_img.clone(), &mut winner_img, img_size)?;
+        if i < num_frames {
+            bounce_in(i as f64 / num_frames as f64, &winner_img, &mut frame, true)?;
+            writer.write(&frame)?;
+            continue;
+        }
+        place_umat(
+            &winner_img,
+            &mut frame,
+            Rect::new(0, 0, img_size.width, img_size.height),
+        )?;
+
+        center_text_at_rect(
+            &mut frame,
+            "WINNER",
+            PLAYER_NAME_FONT_FACE,
+            PLAYER_NAME_FONT_SCALE,
+            WINNER_COLOR,
+            PLAYER_NAME_FONT_THICKNESS,
+            Rect::new(
+                text_x,
+                img_size.height.div_euclid(10),
+                text_width,
+                2 * img_size.height.div_euclid(5),
+            ),
+            PLAYER_NAME_FONT_BUFFER,
+        )?;
+        center_text_at_rect(
+            &mut frame,
+            winner_name,
+            PLAYER_NAME_FONT_FACE,
+            PLAYER_NAME_FONT_SCALE,
+            WHITE,
+            PLAYER_NAME_FONT_THICKNESS,
+            Rect::new(
+                text_x,
+                3 * img_size.height.div_euclid(5),
+                text_width,
+                3 * img_size.height.div_euclid(10),
+            ),
+            PLAYER_NAME_FONT_BUFFER,
+        )?;
+
+        if i >= 2 * num_frames {
+            center_text_at_rect(
+                &mut frame,
+                &lives,
+                PLAYER_NAME_FONT_FACE,
+                STATS_FONT_SCALE,
+                WHITE,
+                STATS_FONT_THICKNESS,
+                lives_rect,
+                PLAYER_NAME_FONT_BUFFER,
+            )?;
+            for (line_index, credit) in credits.iter().enumerate() {
+                center_text_at_rect(
+                    &mut frame,
+                    credit,
+                    PLAYER_NAME_FONT_FACE,
+                    CREDITS_FONT_SCALE,
+                    WHITE,
+                    CREDITS_FONT_THICKNESS,
+                    Rect::new(
+                        text_x,
+                        credits_top + line_index as i32 * credit_height,
+                        text_width,
+                        credit_height,
+                    ),
+                    PLAYER_NAME_FONT_BUFFER,
+                )?;
+            }
+        }
+        writer.write(&frame)?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use opencv::{
@@ -280,7 +537,7 @@ mod test {
         videoio::VideoWriter,
     };
 
-    use super::generate_intro;
+    use super::{generate_intro, HeroStats};
 
     #[test]
     fn test_intro() -> Result<(), Box<dyn std::error::Error>> {
@@ -311,6 +568,7 @@ mod test {
             "Tom",
             &hero2_fp,
             "Trevor",
+            None,
             &frame_size,
             frame_type,
             fps,
@@ -318,4 +576,21 @@ mod test {
         )?;
         Ok(())
     }
+
+    #[test]
+    fn test_stats_line() {
+        let stats = HeroStats {
+            class: Some("Ninja".to_string()),
+            young: Some(true),
+            life: Some(20),
+            record: Some("5-1".to_string()),
+        };
+        assert_eq!(
+            stats.line().unwrap(),
+            "Ninja | Young | 20 life | Record 5-1"
+        );
+        let seeded = HeroStats::build(None, "40", Some(" 3 "));
+        assert_eq!(seeded.line().unwrap(), "40 life | Seed 3");
+        assert_eq!(HeroStats::build(None, "", Some("")).line(), None);
+    }
 }
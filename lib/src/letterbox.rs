@@ -0,0 +1,203 @@
+//! Fits footage of any aspect ratio inside a fixed area without stretching it, for sources that
+//! aren't 16:9 or have black bars baked in.
+
+use clap::ValueEnum;
+use opencv::{
+    core::{Rect, Scalar, Size, UMat, UMatTrait, UMatTraitConst},
+    imgproc::{self, COLOR_BGR2GRAY, INTER_AREA, INTER_LINEAR, THRESH_BINARY},
+    videoio::{
+        VideoCapture, VideoCaptureTrait, VideoCaptureTraitConst, CAP_PROP_FRAME_COUNT,
+        CAP_PROP_POS_FRAMES,
+    },
+};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Brightness a pixel has to be over to count as picture rather than a black bar. Compression
+/// leaves bars a little above 0.
+const BAR_THRESHOLD: f64 = 24.0;
+/// Frames sampled across a video to find its picture. Several are needed, as a dark scene would
+/// look like bars on its own.
+const SAMPLES: u32 = 8;
+/// The blurred fill is blurred at this fraction of its size, which is much cheaper and just as
+/// blurry
+const BLUR_SCALE: f64 = 1.0 / 8.0;
+const BLUR_KERNEL: i32 = 21;
+/// The blurred fill is darkened so the footage stands out from it
+const BLUR_BRIGHTNESS: f64 = 0.6;
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Fill {
+    /// Black bars
+    #[default]
+    Black,
+    /// The footage itself, scaled to cover the area and blurred
+    Blur,
+}
+
+/// Largest rect with the aspect ratio of `content` that fits in `area`, centered in it
+pub fn fit_rect(content: Size, area: Size) -> Rect {
+    let scale = f64::min(
+        area.width as f64 / content.width as f64,
+        area.height as f64 / content.height as f64,
+    );
+    let width = ((content.width as f64 * scale).round() as i32).clamp(1, area.width);
+    let height = ((content.height as f64 * scale).round() as i32).clamp(1, area.height);
+    Rect::new(
+        (area.width - width) / 2,
+        (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// Part of `frame` inside any black bars. A frame that's black throughout is all picture.
+pub fn detect_content(frame: &UMat) -> Result<Rect> {
+    let mut gray = UMat::new_def();
+    imgproc::cvt_color_def(frame, &mut gray, COLOR_BGR2GRAY)?;
+    let mut mask = UMat::new_def();
+    imgproc::threshold(&gray, &mut mask, BAR_THRESHOLD, 255.0, THRESH_BINARY)?;
+    let rect = imgproc::bounding_rect(&mask)?;
+    if rect.empty() {
+        return Ok(Rect::new(0, 0, frame.cols(), frame.rows()));
+    }
+    Ok(rect)
+}
+
+/// Picture area of a video, from frames sampled across it. The capture is left where it was.
+pub fn detect_video_content(cap: &mut VideoCapture) -> Result<Option<Rect>> {
+    let position = cap.get(CAP_PROP_POS_FRAMES)?;
+    let frame_count = cap.get(CAP_PROP_FRAME_COUNT)?;
+    let mut frame = UMat::new_def();
+    let mut content: Option<Rect> = None;
+    for i in 0..SAMPLES {
+        cap.set(
+            CAP_PROP_POS_FRAMES,
+            (frame_count * (i as f64 + 0.5) / SAMPLES as f64).floor(),
+        )?;
+        if !cap.read(&mut frame)? {
+            continue;
+        }
+        let rect = detect_content(&frame)?;
+        content = Some(match content {
+            Some(content) => union(content, rect),
+            None => rect,
+        });
+    }
+    cap.set(CAP_PROP_POS_FRAMES, position)?;
+    Ok(content)
+}
+
+fn union(a: Rect, b: Rect) -> Rect {
+    let x = a.x.min(b.x);
+    let y = a.y.min(b.y);
+    let right = (a.x + a.width).max(b.x + b.width);
+    let bottom = (a.y + a.height).max(b.y + b.height);
+    Rect::new(x, y, right - x, bottom - y)
+}
+
+/// Draws `source` into `output` at `size`, as large as it fits without changing its aspect
+/// ratio, with `fill` either side of it
+pub fn letterbox(source: &UMat, size: Size, fill: Fill, output: &mut UMat) -> Result<()> {
+    match fill {
+        Fill::Black => {
+            *output = UMat::new_size_with_default_def(size, source.typ(), Scalar::all(0.0))?;
+        }
+        Fill::Blur => blurred_cover(source, size, output)?,
+    }
+    let rect = fit_rect(source.size()?, size);
+    let mut fitted = UMat::new_def();
+    imgproc::resize(source, &mut fitted, rect.size(), 0.0, 0.0, INTER_AREA)?;
+    fitted.copy_to(&mut output.roi_mut(rect)?)?;
+    Ok(())
+}
+
+/// `source` scaled up to cover `size`, cropped to it, then blurred and darkened
+fn blurred_cover(source: &UMat, size: Size, output: &mut UMat) -> Result<()> {
+    let small = Size::new(
+        ((size.width as f64 * BLUR_SCALE) as i32).max(1),
+        ((size.height as f64 * BLUR_SCALE) as i32).max(1),
+    );
+    // Crop the middle of the source to the aspect ratio of the area
+    let crop = fit_rect(size, source.size()?);
+    let mut shrunk = UMat::new_def();
+    imgproc::resize(&source.roi(crop)?, &mut shrunk, small, 0.0, 0.0, INTER_AREA)?;
+    let mut blurred = UMat::new_def();
+    imgproc::gaussian_blur_def(
+        &shrunk,
+        &mut blurred,
+        Size::new(BLUR_KERNEL, BLUR_KERNEL),
+        0.0,
+    )?;
+    let mut darkened = UMat::new_def();
+    blurred.convert_to(&mut darkened, -1, BLUR_BRIGHTNESS, 0.0)?;
+    imgproc::resize(&darkened, output, size, 0.0, 0.0, INTER_LINEAR)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use opencv::core::{Mat, MatTraitConst, Vec3b, CV_8UC3};
+
+    use super::*;
+
+    #[test]
+    fn test_fit_rect() {
+        // 4:3 in 16:9 is pillarboxed
+        assert_eq!(
+            fit_rect(Size::new(640, 480), Size::new(1600, 900)),
+            Rect::new(200, 0, 1200, 900)
+        );
+        // 21:9 in 16:9 is letterboxed
+        assert_eq!(
+            fit_rect(Size::new(2560, 1080), Size::new(1600, 900)),
+            Rect::new(0, 112, 1600, 675)
+        );
+        // Portrait phone footage
+        assert_eq!(
+            fit_rect(Size::new(1080, 1920), Size::new(1600, 900)),
+            Rect::new(547, 0, 506, 900)
+        );
+    }
+
+    #[test]
+    fn test_detect_content() -> Result<()> {
+        // 4:3 picture with bars baked into a 16:9 frame
+        let mut frame =
+            UMat::new_size_with_default_def(Size::new(320, 180), CV_8UC3, Scalar::all(8.0))?;
+        let picture = Rect::new(40, 0, 240, 180);
+        frame
+            .roi_mut(picture)?
+            .set_to_def(&Scalar::new(20.0, 120.0, 200.0, 0.0))?;
+        assert_eq!(detect_content(&frame)?, picture);
+
+        let black =
+            UMat::new_size_with_default_def(Size::new(320, 180), CV_8UC3, Scalar::all(0.0))?;
+        assert_eq!(detect_content(&black)?, Rect::new(0, 0, 320, 180));
+        Ok(())
+    }
+
+    #[test]
+    fn pixel(img: &UMat, row: i32, col: i32) -> Result<Vec3b> {
+        let mut mat = Mat::default();
+        img.copy_to(&mut mat)?;
+        Ok(*mat.at_2d::<Vec3b>(row, col)?)
+    }
+
+    #[test]
+    fn test_letterbox() -> Result<()> {
+        let source =
+            UMat::new_size_with_default_def(Size::new(40, 30), CV_8UC3, Scalar::all(200.0))?;
+        let mut output = UMat::new_def();
+        letterbox(&source, Size::new(160, 90), Fill::Black, &mut output)?;
+        assert_eq!(output.size()?, Size::new(160, 90));
+        assert_eq!(pixel(&output, 45, 10)?, Vec3b::all(0));
+        assert_eq!(pixel(&output, 45, 80)?, Vec3b::all(200));
+
+        let mut output = UMat::new_def();
+        letterbox(&source, Size::new(160, 90), Fill::Blur, &mut output)?;
+        // The fill is the footage darkened
+        assert_eq!(pixel(&output, 45, 10)?, Vec3b::all(120));
+        Ok(())
+    }
+}
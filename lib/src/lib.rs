@@ -1,13 +1,52 @@
+pub mod annotation;
+pub mod annotation_store;
+pub mod assets;
+pub mod audio_bed;
+pub mod audio_sync;
 pub mod autocomplete;
+pub mod background;
+pub mod bracket;
+pub mod bundle;
 pub mod card;
+pub mod card_alias;
+pub mod card_display;
+pub mod card_match;
+pub mod card_query;
+pub mod card_store;
+pub mod card_update;
+pub mod celebration;
 pub mod commands;
+pub mod config;
 pub mod coord;
+pub mod deck_odds;
+pub mod decklist;
+pub mod download;
 pub mod err;
 pub mod fade;
+pub mod game_report;
+pub mod game_start;
+pub mod gpu;
+pub mod hero_art;
 pub mod image;
 pub mod intro;
+pub mod letterbox;
 pub mod life_tracker;
+pub mod logging;
+pub mod lowerthird;
+pub mod match_state;
+pub mod memory;
+pub mod merge;
 pub mod movement;
+pub mod ocr;
+pub mod perspective;
+pub mod printing;
+pub mod profile;
 pub mod relative_roi;
+pub mod retime;
 pub mod rotate;
+pub mod starting_soon;
 pub mod text;
+pub mod time_tick;
+pub mod token;
+pub mod turn_indicator;
+pub mod video_format;
@@ -1,5 +1,8 @@
 use core::panic;
 
+use crate::movement::Reparameterization;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Operation {
     Add,
     Sub,
@@ -17,11 +20,53 @@ impl Operation {
     }
 }
 
+/// A single applied life update
+#[derive(Debug, Clone)]
+pub struct LifeUpdate {
+    /// Seconds into the video the update happened
+    pub time: f64,
+    pub operation: Operation,
+    pub value: i32,
+    pub before: i32,
+    pub after: i32,
+}
+
+impl LifeUpdate {
+    /// Signed change in life; negative for damage
+    pub fn delta(&self) -> i32 {
+        self.after - self.before
+    }
+}
+
+/// How the displayed life catches up to the actual life
+pub enum TickMode {
+    /// One point every `tick_rate`, no matter how large the change is
+    Linear,
+    /// The whole change animates along `curve`. Small changes take as long as they would when
+    /// linear, large ones are capped at `max_duration`.
+    Eased {
+        max_duration: f64,
+        curve: Reparameterization,
+    },
+}
+
+struct LifeAnimation {
+    from: i32,
+    to: i32,
+    elapsed: f64,
+    duration: f64,
+}
+
 pub struct LifeTracker {
     current: i32,
     display: i32,
     ticker: u32,
     ticker_max: u32,
+    tick_rate: f64,
+    increment: f64,
+    mode: TickMode,
+    animation: Option<LifeAnimation>,
+    history: Vec<LifeUpdate>,
 }
 
 impl LifeTracker {
@@ -30,6 +75,39 @@ impl LifeTracker {
     /// * `tick_rate` - How often the tracker should be updated
     /// * `increment` - How much time elapses each frame
     pub fn build(starting_life: &str, tick_rate: f64, increment: f64) -> Self {
+        Self::build_with_mode(starting_life, tick_rate, increment, TickMode::Linear)
+    }
+
+    /// # Arguments
+    /// * `starting_life` - String rep of the heros starting life
+    /// * `tick_rate` - How long a single point of change takes to display
+    /// * `increment` - How much time elapses each frame
+    /// * `max_duration` - Longest any single change can take to display
+    /// * `curve` - Easing applied to the change
+    pub fn build_eased(
+        starting_life: &str,
+        tick_rate: f64,
+        increment: f64,
+        max_duration: f64,
+        curve: Reparameterization,
+    ) -> Self {
+        Self::build_with_mode(
+            starting_life,
+            tick_rate,
+            increment,
+            TickMode::Eased {
+                max_duration,
+                curve,
+            },
+        )
+    }
+
+    pub fn build_with_mode(
+        starting_life: &str,
+        tick_rate: f64,
+        increment: f64,
+        mode: TickMode,
+    ) -> Self {
         let value = starting_life
             .parse::<i32>()
             .expect("Starting life is not a number");
@@ -39,6 +117,11 @@ impl LifeTracker {
             display: value,
             ticker: 0,
             ticker_max,
+            tick_rate,
+            increment,
+            mode,
+            animation: None,
+            history: Vec::new(),
         }
     }
 
@@ -47,16 +130,22 @@ impl LifeTracker {
         let operation =
             Operation::from_char(&operation_char).ok_or("Update does not have valid operation")?;
 
+        // Parsed unsigned so a second sign, e.g. "+-3", is rejected
         let val = update
             .get(1..)
-            .expect("Update missing value")
+            .ok_or("Update missing value")?
             .parse::<u32>()
             .map_err(|_| "Update value is not an integer")?;
+        let val = i32::try_from(val).map_err(|_| "Update value is too large")?;
 
-        Ok((operation, val as i32))
+        Ok((operation, val))
     }
 
-    pub fn update(&mut self, update: &str) {
+    /// Applies `update` and records it in the history
+    /// # Arguments
+    /// * `update` - String rep of the update, e.g. "-3", "+1", "=20"
+    /// * `time` - Seconds into the video the update happened
+    pub fn update(&mut self, update: &str, time: f64) {
         let update = Self::parse_update(update);
         if let Err(err) = update {
             panic!("{}", err);
@@ -64,20 +153,81 @@ impl LifeTracker {
         let (operation, val) = update.unwrap();
         let new_value = {
             match operation {
-                Operation::Add => self.current + val,
-                Operation::Sub => self.current - val,
+                Operation::Add => self.current.saturating_add(val),
+                Operation::Sub => self.current.saturating_sub(val),
                 Operation::Equal => val,
             }
         };
+        self.history.push(LifeUpdate {
+            time,
+            operation,
+            value: val,
+            before: self.current,
+            after: new_value,
+        });
         self.current = new_value;
+
+        if let TickMode::Eased { max_duration, .. } = self.mode {
+            let difference = (self.current - self.display).abs() as f64;
+            self.animation.replace(LifeAnimation {
+                from: self.display,
+                to: self.current,
+                elapsed: 0.0,
+                duration: (difference * self.tick_rate).min(max_duration),
+            });
+        }
+    }
+
+    /// Every update applied so far, in the order they were applied
+    pub fn history(&self) -> impl Iterator<Item = &LifeUpdate> {
+        self.history.iter()
+    }
+
+    /// Total life lost, i.e. the damage dealt to this player
+    pub fn damage_taken(&self) -> u32 {
+        self.history
+            .iter()
+            .filter(|u| u.delta() < 0)
+            .map(|u| u.delta().unsigned_abs())
+            .sum()
+    }
+
+    /// Total life gained
+    pub fn healed(&self) -> u32 {
+        self.history
+            .iter()
+            .filter(|u| u.delta() > 0)
+            .map(|u| u.delta() as u32)
+            .sum()
+    }
+
+    pub fn current(&self) -> i32 {
+        self.current
     }
 
     /// Ticks display life by one increment
     pub fn tick_display(&mut self) {
-        self.ticker += 1;
-        if self.ticker == self.ticker_max {
-            self.ticker = 0;
-            self.display += (self.current - self.display).signum();
+        match &self.mode {
+            TickMode::Linear => {
+                self.ticker += 1;
+                if self.ticker == self.ticker_max {
+                    self.ticker = 0;
+                    self.display += (self.current - self.display).signum();
+                }
+            }
+            TickMode::Eased { curve, .. } => {
+                if let Some(animation) = self.animation.as_mut() {
+                    animation.elapsed += self.increment;
+                    if animation.elapsed >= animation.duration {
+                        self.display = animation.to;
+                        self.animation = None;
+                    } else {
+                        let percentage = curve.apply(animation.elapsed / animation.duration);
+                        let change = (animation.to - animation.from) as f64 * percentage;
+                        self.display = animation.from + change.round() as i32;
+                    }
+                }
+            }
         }
     }
 
@@ -85,3 +235,79 @@ impl LifeTracker {
         self.display.to_string()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::{LifeTracker, Operation};
+    use crate::movement::Reparameterization;
+
+    #[test]
+    fn test_history_and_totals() {
+        let mut tracker = LifeTracker::build("20", 250.0, 1000.0 / 60.0);
+        tracker.update("-5", 10.0);
+        tracker.update("+2", 12.5);
+        tracker.update("=10", 20.0);
+
+        let history: Vec<_> = tracker.history().collect();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].operation, Operation::Sub);
+        assert_eq!((history[0].before, history[0].after), (20, 15));
+        assert_eq!(history[1].time, 12.5);
+        assert_eq!(history[2].delta(), -7);
+
+        assert_eq!(tracker.damage_taken(), 12);
+        assert_eq!(tracker.healed(), 2);
+        assert_eq!(tracker.current(), 10);
+    }
+
+    #[test]
+    fn test_eased_finishes_within_max_duration() {
+        let increment = 1000.0 / 60.0;
+        let mut tracker =
+            LifeTracker::build_eased("40", 250.0, increment, 1500.0, Reparameterization::SCurve);
+        tracker.update("-15", 0.0);
+
+        let mut displayed = vec![];
+        for _ in 0..(1500.0 / increment).ceil() as u32 {
+            tracker.tick_display();
+            displayed.push(tracker.display().parse::<i32>().unwrap());
+        }
+        assert_eq!(*displayed.last().unwrap(), 25);
+        assert!(displayed.windows(2).all(|w| w[1] <= w[0]));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_parse_update_never_panics(update in "\\PC*") {
+            let _ = LifeTracker::parse_update(&update);
+        }
+
+        #[test]
+        fn prop_parse_update_round_trip(op in "[+=-]", value in 0..=i32::MAX) {
+            let (operation, parsed) = LifeTracker::parse_update(&format!("{}{}", op, value))
+                .map_err(TestCaseError::fail)?;
+            prop_assert_eq!(parsed, value);
+            prop_assert_eq!(Some(operation), Operation::from_char(&op.chars().next().unwrap()));
+        }
+
+        #[test]
+        fn prop_parse_update_rejects_large(value in (i32::MAX as u64 + 1)..) {
+            prop_assert!(LifeTracker::parse_update(&format!("-{}", value)).is_err());
+        }
+
+        #[test]
+        fn prop_updates_never_panic(
+            updates in prop::collection::vec(("[+=-]", 0..1_000_000_i32), 0..50)
+        ) {
+            let mut tracker =
+                LifeTracker::build_eased("40", 250.0, 1000.0 / 60.0, 1500.0, Reparameterization::SCurve);
+            for (op, value) in updates {
+                tracker.update(&format!("{}{}", op, value), 0.0);
+                tracker.tick_display();
+            }
+            prop_assert_eq!(tracker.history().count(), tracker.history.len());
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use std::{fs::File, sync::Mutex};
+
+use clap::Args;
+use tracing_subscriber::{fmt, EnvFilter};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Logging flags for every binary, added with `#[command(flatten)]`
+#[derive(Args, Debug, Clone)]
+pub struct LogArgs {
+    /// Lowest level logged, e.g. "debug", optionally per module, e.g. "info,lib::card=debug"
+    #[arg(long, global = true, default_value = "info")]
+    pub log_level: String,
+
+    /// Log one JSON object per line
+    #[arg(long, global = true, action)]
+    pub log_json: bool,
+
+    /// Write logs to this file instead of stderr
+    #[arg(long, global = true)]
+    pub log_file: Option<String>,
+}
+
+impl LogArgs {
+    /// Sets up the global subscriber. Records from crates using `log` are included.
+    pub fn init(&self) -> Result<()> {
+        let builder = fmt()
+            .with_env_filter(EnvFilter::try_new(&self.log_level)?)
+            .with_writer(std::io::stderr);
+        match (&self.log_file, self.log_json) {
+            (Some(fp), true) => builder
+                .json()
+                .with_writer(Mutex::new(File::create(fp)?))
+                .try_init()?,
+            (Some(fp), false) => builder
+                .with_ansi(false)
+                .with_writer(Mutex::new(File::create(fp)?))
+                .try_init()?,
+            (None, true) => builder.json().try_init()?,
+            (None, false) => builder.try_init()?,
+        }
+        Ok(())
+    }
+}
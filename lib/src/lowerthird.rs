@@ -0,0 +1,209 @@
+//! Name plates that slide in at the bottom left of a region, hold, then slide back out. Used for
+//! players in the intro and for `lowerthird` and `note` events during a match.
+
+use opencv::{
+    core::{Rect, Scalar, Size, UMat, UMatTraitConst},
+    imgproc::{self, FONT_HERSHEY_SIMPLEX, LINE_8},
+};
+
+use crate::{
+    annotation::PlayerInfo,
+    image::crop,
+    movement::{place_umat, Reparameterization},
+    text::center_text_at_rect,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Seconds a plate is on screen, including sliding in and out
+pub const LOWER_THIRD_DURATION: f64 = 6.0;
+const SLIDE_DURATION: f64 = 0.5;
+const FONT_FACE: i32 = FONT_HERSHEY_SIMPLEX;
+const TITLE_FONT_SCALE: f64 = 2.0;
+const TITLE_FONT_THICKNESS: i32 = 4;
+const SUBTITLE_FONT_SCALE: f64 = 1.2;
+const SUBTITLE_FONT_THICKNESS: i32 = 2;
+const FONT_BUFFER: i32 = 10;
+const WHITE: Scalar = Scalar::new(255.0, 255.0, 255.0, 0.0);
+const PLATE_COLOR: Scalar = Scalar::new(40.0, 40.0, 40.0, 0.0);
+/// Gold stripe down the left of the plate
+const ACCENT_COLOR: Scalar = Scalar::new(0.0, 215.0, 255.0, 0.0);
+// Relative to the region the plate is in
+const PLATE_WIDTH_RATIO: f64 = 2.0 / 5.0;
+const PLATE_HEIGHT_RATIO: f64 = 1.0 / 8.0;
+const MARGIN_RATIO: f64 = 1.0 / 30.0;
+// Relative to the plate
+const ACCENT_WIDTH_RATIO: f64 = 1.0 / 40.0;
+const TITLE_HEIGHT_RATIO: f64 = 0.6;
+
+/// "Seed 3" for a seed, "Record 5-1" for a record
+pub fn record_text(record: &str) -> String {
+    if record.contains('-') {
+        format!("Record {}", record)
+    } else {
+        format!("Seed {}", record)
+    }
+}
+
+pub struct LowerThird {
+    /// Region of the frame the plate sits at the bottom left of
+    area: Rect,
+    /// Time in seconds the plate starts sliding in
+    start: f64,
+    title: String,
+    subtitle: Option<String>,
+}
+
+impl LowerThird {
+    pub fn build(area: Rect, start: f64, title: &str, subtitle: Option<&str>) -> Self {
+        LowerThird {
+            area,
+            start,
+            title: title.to_owned(),
+            subtitle: subtitle
+                .map(|subtitle| subtitle.to_owned())
+                .filter(|subtitle| !subtitle.trim().is_empty()),
+        }
+    }
+
+    /// The player's name over their hero, team and seed or record
+    pub fn for_player(area: Rect, start: f64, name: &str, hero: &str, info: &PlayerInfo) -> Self {
+        let mut details = vec![hero.to_owned()];
+        details.extend(info.team.clone());
+        details.extend(info.seed.as_deref().map(record_text));
+        let subtitle = details
+            .into_iter()
+            .filter(|detail| !detail.trim().is_empty())
+            .collect::<Vec<String>>()
+            .join(" | ");
+        Self::build(area, start, name, Some(&subtitle))
+    }
+
+    pub fn subtitle(&self) -> Option<&str> {
+        self.subtitle.as_deref()
+    }
+
+    pub fn is_finished(&self, seconds: f64) -> bool {
+        seconds - self.start >= LOWER_THIRD_DURATION
+    }
+
+    /// Fraction of the plate showing at time `seconds`
+    fn shown(&self, seconds: f64) -> f64 {
+        let elapsed = seconds - self.start;
+        if elapsed < 0.0 || self.is_finished(seconds) {
+            return 0.0;
+        }
+        let sliding_in = elapsed / SLIDE_DURATION;
+        let sliding_out = (LOWER_THIRD_DURATION - elapsed) / SLIDE_DURATION;
+        match sliding_in.min(sliding_out) {
+            sliding if sliding >= 1.0 => 1.0,
+            sliding => Reparameterization::SCurve.apply(sliding),
+        }
+    }
+
+    /// Draws the plate for time `seconds`. Does nothing before the start or after the end.
+    pub fn draw(&self, frame: &mut UMat, seconds: f64) -> Result<()> {
+        let plate_width = (self.area.width as f64 * PLATE_WIDTH_RATIO) as i32;
+        let plate_height = (self.area.height as f64 * PLATE_HEIGHT_RATIO) as i32;
+        let shown = (plate_width as f64 * self.shown(seconds)) as i32;
+        if shown <= 0 || plate_height <= 0 {
+            return Ok(());
+        }
+
+        let mut plate = UMat::new_size_with_default_def(
+            Size::new(plate_width, plate_height),
+            frame.typ(),
+            PLATE_COLOR,
+        )?;
+        let accent_width = ((plate_width as f64 * ACCENT_WIDTH_RATIO) as i32).max(1);
+        imgproc::rectangle(
+            &mut plate,
+            Rect::new(0, 0, accent_width, plate_height),
+            ACCENT_COLOR,
+            -1,
+            LINE_8,
+            0,
+        )?;
+        let text_x = 2 * accent_width;
+        let text_width = plate_width - 3 * accent_width;
+        let title_height = match self.subtitle {
+            Some(_) => (plate_height as f64 * TITLE_HEIGHT_RATIO) as i32,
+            None => plate_height,
+        };
+        center_text_at_rect(
+            &mut plate,
+            &self.title,
+            FONT_FACE,
+            TITLE_FONT_SCALE,
+            WHITE,
+            TITLE_FONT_THICKNESS,
+            Rect::new(text_x, 0, text_width, title_height),
+            FONT_BUFFER,
+        )?;
+        if let Some(subtitle) = &self.subtitle {
+            center_text_at_rect(
+                &mut plate,
+                subtitle,
+                FONT_FACE,
+                SUBTITLE_FONT_SCALE,
+                WHITE,
+                SUBTITLE_FONT_THICKNESS,
+                Rect::new(
+                    text_x,
+                    title_height,
+                    text_width,
+                    plate_height - title_height,
+                ),
+                FONT_BUFFER,
+            )?;
+        }
+
+        // The plate's right edge leads as it slides in from the left of the region
+        let margin = (self.area.width.min(self.area.height) as f64 * MARGIN_RATIO) as i32;
+        let visible = crop(
+            &plate,
+            &Rect::new(plate_width - shown, 0, shown, plate_height),
+        )?;
+        place_umat(
+            &visible,
+            frame,
+            Rect::new(
+                self.area.x + margin,
+                self.area.y + self.area.height - plate_height - margin,
+                shown,
+                plate_height,
+            ),
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_shown() {
+        let plate = LowerThird::build(Rect::new(0, 0, 1920, 1080), 10.0, "Ada", None);
+        assert_eq!(plate.shown(9.0), 0.0);
+        assert!(plate.shown(10.1) < 0.5);
+        assert_eq!(plate.shown(13.0), 1.0);
+        assert!(plate.shown(15.9) < 0.5);
+        assert_eq!(plate.shown(16.0), 0.0);
+        assert!(plate.is_finished(16.0));
+    }
+
+    #[test]
+    fn test_for_player() {
+        let info = PlayerInfo {
+            team: Some("Team Go Again".to_string()),
+            seed: Some("5-1".to_string()),
+            ..Default::default()
+        };
+        let area = Rect::new(0, 0, 1920, 1080);
+        let plate = LowerThird::for_player(area, 0.0, "Ada", "Katsu", &info);
+        assert_eq!(plate.subtitle(), Some("Katsu | Team Go Again | Record 5-1"));
+        let plate = LowerThird::for_player(area, 0.0, "Bo", "", &PlayerInfo::default());
+        assert_eq!(plate.subtitle(), None);
+    }
+}
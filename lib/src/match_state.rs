@@ -0,0 +1,291 @@
+use crate::{
+    annotation::{Annotation, Event, MatchSetup},
+    life_tracker::{LifeTracker, Operation},
+};
+
+/// A card played on the combat chain and what was played to defend it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainLink {
+    /// Player who played the card
+    pub attacker: u8,
+    pub attack: String,
+    pub defenders: Vec<String>,
+    /// Life the defending player lost while this was the newest link
+    pub damage: i32,
+}
+
+/// Game state at a point in an annotation, built by replaying its events
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchState {
+    pub player1_life: i32,
+    pub player2_life: i32,
+    /// Starts at 1 with the first player's turn
+    pub turn: u32,
+    /// Player whose turn it is
+    pub turn_player: u8,
+    pub last_card: Option<String>,
+    /// Winner of the current game
+    pub winner: Option<u8>,
+    /// Starts at 1
+    pub game: u32,
+    /// Games won by player 1 and player 2
+    pub score: [u32; 2],
+    /// Links of the current turn's combat chain, oldest first. Every card the turn player plays
+    /// opens a link, as annotations don't say which cards are attacks, and a turn closes it.
+    pub chain: Vec<ChainLink>,
+    /// Cards played or defended with by player 1 and player 2 over the match
+    pub cards_used: [u32; 2],
+    /// Life player 1 and player 2 took off the other while they had a link open
+    pub damage_dealt: [i32; 2],
+    /// Player that took the first turn of the current game
+    first: u8,
+    starting_life: (i32, i32),
+}
+
+impl MatchState {
+    /// State before anything has happened. Unparseable starting lives are treated as 0.
+    pub fn build(setup: &MatchSetup) -> Self {
+        let starting_life = (
+            setup.hero1_life.trim().parse().unwrap_or(0),
+            setup.hero2_life.trim().parse().unwrap_or(0),
+        );
+        MatchState {
+            player1_life: starting_life.0,
+            player2_life: starting_life.1,
+            turn: 1,
+            turn_player: setup.first,
+            last_card: None,
+            winner: None,
+            game: 1,
+            score: [0, 0],
+            chain: Vec::new(),
+            cards_used: [0, 0],
+            damage_dealt: [0, 0],
+            first: setup.first,
+            starting_life,
+        }
+    }
+
+    /// State after every annotation has been applied in order
+    pub fn replay(setup: &MatchSetup, annotations: &[Annotation]) -> Self {
+        let mut state = Self::build(setup);
+        for annotation in annotations {
+            state.apply(&annotation.event);
+        }
+        state
+    }
+
+    /// Applies a single event. Invalid life updates are ignored.
+    pub fn apply(&mut self, event: &Event) {
+        match event {
+            Event::Card { name, .. } => {
+                self.last_card = Some(name.to_owned());
+                self.cards_used[self.turn_player as usize - 1] += 1;
+                self.chain.push(ChainLink {
+                    attacker: self.turn_player,
+                    attack: name.to_owned(),
+                    defenders: Vec::new(),
+                    damage: 0,
+                });
+            }
+            Event::Block { name, .. } => {
+                self.cards_used[other(self.turn_player) as usize - 1] += 1;
+                if let Some(link) = self.chain.last_mut() {
+                    link.defenders.push(name.to_owned());
+                }
+            }
+            Event::Life { player1, player2 } => {
+                if let Some(update) = player1 {
+                    let lost = Self::apply_life(&mut self.player1_life, update);
+                    self.record_damage(1, lost);
+                }
+                if let Some(update) = player2 {
+                    let lost = Self::apply_life(&mut self.player2_life, update);
+                    self.record_damage(2, lost);
+                }
+            }
+            Event::Turn => {
+                self.turn += 1;
+                self.turn_player = other(self.turn_player);
+                self.chain.clear();
+            }
+            Event::Win { player } => {
+                // Only the first win of a game counts towards the score
+                if self.winner.is_none() && matches!(player, 1 | 2) {
+                    self.score[*player as usize - 1] += 1;
+                }
+                self.winner = Some(*player);
+            }
+            Event::Game => self.next_game(),
+            _ => {}
+        }
+    }
+
+    /// Resets lives and turns, with the other player going first
+    fn next_game(&mut self) {
+        self.game += 1;
+        self.first = if self.first == 1 { 2 } else { 1 };
+        self.player1_life = self.starting_life.0;
+        self.player2_life = self.starting_life.1;
+        self.turn = 1;
+        self.turn_player = self.first;
+        self.last_card = None;
+        self.winner = None;
+        self.chain.clear();
+    }
+
+    /// Applies a life update, returning how much life it took away
+    fn apply_life(life: &mut i32, update: &str) -> i32 {
+        let before = *life;
+        if let Ok((operation, value)) = LifeTracker::parse_update(update) {
+            *life = match operation {
+                Operation::Add => *life + value,
+                Operation::Sub => *life - value,
+                Operation::Equal => value,
+            };
+        }
+        (before - *life).max(0)
+    }
+
+    /// Puts life `player` lost on the newest link, when the other player opened it
+    fn record_damage(&mut self, player: u8, lost: i32) {
+        if lost == 0 {
+            return;
+        }
+        if let Some(link) = self.chain.last_mut().filter(|link| link.attacker != player) {
+            link.damage += lost;
+            self.damage_dealt[link.attacker as usize - 1] += lost;
+        }
+    }
+}
+
+fn other(player: u8) -> u8 {
+    if player == 1 {
+        2
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::time_tick::TimeTick;
+
+    #[test]
+    fn test_replay() {
+        let setup = MatchSetup {
+            hero1_life: "40".to_string(),
+            hero2_life: "40".to_string(),
+            first: 2,
+            ..Default::default()
+        };
+        let events = [
+            Event::Card {
+                name: "Erase Face".to_string(),
+                pitch: Some(1),
+            },
+            Event::Life {
+                player1: Some("-4".to_string()),
+                player2: Some("+1".to_string()),
+            },
+            Event::Turn,
+            Event::Life {
+                player1: Some("=20".to_string()),
+                player2: None,
+            },
+        ];
+        let annotations: Vec<Annotation> = events
+            .into_iter()
+            .map(|event| Annotation::new(TimeTick::new(), event))
+            .collect();
+
+        let state = MatchState::replay(&setup, &annotations);
+        assert_eq!(state.player1_life, 20);
+        assert_eq!(state.player2_life, 41);
+        assert_eq!(state.turn, 2);
+        assert_eq!(state.turn_player, 1);
+        assert_eq!(state.last_card.as_deref(), Some("Erase Face"));
+        assert_eq!(state.winner, None);
+    }
+
+    #[test]
+    fn test_combat_chain() {
+        let setup = MatchSetup {
+            hero1_life: "20".to_string(),
+            hero2_life: "20".to_string(),
+            first: 1,
+            ..Default::default()
+        };
+        let card = |name: &str| Event::Card {
+            name: name.to_string(),
+            pitch: Some(1),
+        };
+        let block = |name: &str| Event::Block {
+            name: name.to_string(),
+            pitch: Some(3),
+        };
+        let life = |player1: Option<&str>, player2: Option<&str>| Event::Life {
+            player1: player1.map(str::to_string),
+            player2: player2.map(str::to_string),
+        };
+        let events = [
+            card("Mugenshi: RELEASE"),
+            card("Head Jab"),
+            block("Sink Below"),
+            life(None, Some("-1")),
+            card("Surging Strike"),
+            // Gaining life isn't damage
+            life(Some("+1"), Some("-4")),
+        ];
+        let annotations: Vec<Annotation> = events
+            .into_iter()
+            .map(|event| Annotation::new(TimeTick::new(), event))
+            .collect();
+
+        let state = MatchState::replay(&setup, &annotations);
+        assert_eq!(state.chain.len(), 3);
+        assert_eq!(state.chain[1].defenders, vec!["Sink Below"]);
+        assert_eq!(state.chain[1].damage, 1);
+        assert_eq!(state.chain[2].damage, 4);
+        assert_eq!(state.cards_used, [3, 1]);
+        assert_eq!(state.damage_dealt, [5, 0]);
+
+        // The turn ends the chain but not the totals
+        let mut state = state;
+        state.apply(&Event::Turn);
+        assert!(state.chain.is_empty());
+        assert_eq!(state.damage_dealt, [5, 0]);
+    }
+
+    #[test]
+    fn test_next_game() {
+        let setup = MatchSetup {
+            hero1_life: "20".to_string(),
+            hero2_life: "20".to_string(),
+            first: 1,
+            ..Default::default()
+        };
+        let events = [
+            Event::Turn,
+            Event::Life {
+                player1: Some("-20".to_string()),
+                player2: None,
+            },
+            Event::Win { player: 2 },
+            Event::Game,
+        ];
+        let annotations: Vec<Annotation> = events
+            .into_iter()
+            .map(|event| Annotation::new(TimeTick::new(), event))
+            .collect();
+
+        let state = MatchState::replay(&setup, &annotations);
+        assert_eq!(state.game, 2);
+        assert_eq!(state.score, [0, 1]);
+        assert_eq!(state.player1_life, 20);
+        assert_eq!(state.turn, 1);
+        assert_eq!(state.turn_player, 2);
+        assert_eq!(state.winner, None);
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::VecDeque;
+
+use opencv::core::{UMat, UMatTraitConst};
+use tracing::{info, warn};
+
+const KIB: u64 = 1024;
+const MIB: u64 = 1024 * KIB;
+const GIB: u64 = 1024 * MIB;
+
+/// Resident memory of this process, from /proc. `None` where that isn't available.
+pub fn resident_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * KIB)
+}
+
+/// Parses a size such as "512M", "4G" or "4GiB". A bare number is in bytes.
+pub fn parse_bytes(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("\"{}\" isn't a size, e.g. \"4G\"", text))?;
+    let unit = match unit.trim().to_ascii_uppercase().trim_end_matches("IB") {
+        "" | "B" => 1,
+        "K" => KIB,
+        "M" => MIB,
+        "G" => GIB,
+        other => return Err(format!("Unknown size unit \"{}\"", other)),
+    };
+    Ok((number * unit as f64) as u64)
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    if bytes >= GIB {
+        format!("{:.2} GiB", bytes as f64 / GIB as f64)
+    } else {
+        format!("{:.1} MiB", bytes as f64 / MIB as f64)
+    }
+}
+
+/// Bytes held by the pixels of an image
+pub fn umat_bytes(img: &UMat) -> u64 {
+    (img.total() * img.elem_size().unwrap_or(0)) as u64
+}
+
+/// Images kept for reuse up to a total size, dropping the least recently used first
+pub struct ImageCache<K> {
+    budget: u64,
+    used: u64,
+    // Least recently used first
+    entries: VecDeque<(K, UMat)>,
+}
+
+impl<K: PartialEq> ImageCache<K> {
+    pub fn new(budget: u64) -> Self {
+        Self {
+            budget,
+            used: 0,
+            entries: VecDeque::new(),
+        }
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<&UMat> {
+        let idx = self.entries.iter().position(|(k, _)| k == key)?;
+        let entry = self.entries.remove(idx)?;
+        self.entries.push_back(entry);
+        self.entries.back().map(|(_, img)| img)
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.entries.iter().any(|(k, _)| k == key)
+    }
+
+    /// Keeps `img` under `key`. An image larger than the whole budget isn't kept.
+    pub fn insert(&mut self, key: K, img: UMat) {
+        if let Some(idx) = self.entries.iter().position(|(k, _)| *k == key) {
+            if let Some((_, old)) = self.entries.remove(idx) {
+                self.used -= umat_bytes(&old);
+            }
+        }
+        let bytes = umat_bytes(&img);
+        if bytes > self.budget {
+            return;
+        }
+        while self.used + bytes > self.budget {
+            match self.entries.pop_front() {
+                Some((_, old)) => self.used -= umat_bytes(&old),
+                None => break,
+            }
+        }
+        self.used += bytes;
+        self.entries.push_back((key, img));
+    }
+
+    pub fn used(&self) -> u64 {
+        self.used
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Logs resident memory every `interval` frames, warning when it's over the budget
+pub struct MemoryMonitor {
+    budget: u64,
+    interval: u64,
+    frames: u64,
+    peak: u64,
+}
+
+impl MemoryMonitor {
+    pub fn new(budget: u64, interval: u64) -> Self {
+        Self {
+            budget,
+            interval: interval.max(1),
+            frames: 0,
+            peak: 0,
+        }
+    }
+
+    /// Counts a frame, checking memory when the interval is up
+    pub fn tick(&mut self) {
+        self.frames += 1;
+        if self.frames % self.interval != 0 {
+            return;
+        }
+        let Some(resident) = self.sample() else {
+            return;
+        };
+        if resident > self.budget {
+            warn!(
+                "Resident memory is {}, over the budget of {}",
+                format_bytes(resident),
+                format_bytes(self.budget)
+            );
+        } else {
+            info!(
+                "Resident memory is {} of {} after {} frames",
+                format_bytes(resident),
+                format_bytes(self.budget),
+                self.frames
+            );
+        }
+    }
+
+    /// Reads resident memory now, keeping track of the highest seen
+    pub fn sample(&mut self) -> Option<u64> {
+        let resident = resident_bytes()?;
+        self.peak = self.peak.max(resident);
+        Some(resident)
+    }
+
+    /// Highest resident memory seen by `tick` or `sample`
+    pub fn peak(&self) -> u64 {
+        self.peak
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use opencv::core::{Scalar, Size, CV_8UC3};
+
+    use super::*;
+
+    fn image(width: i32) -> UMat {
+        UMat::new_size_with_default_def(Size::new(width, 10), CV_8UC3, Scalar::all(0.0)).unwrap()
+    }
+
+    #[test]
+    fn test_parse_bytes() {
+        assert_eq!(parse_bytes("512M"), Ok(512 * MIB));
+        assert_eq!(parse_bytes("4G"), Ok(4 * GIB));
+        assert_eq!(parse_bytes("1.5GiB"), Ok(GIB + GIB / 2));
+        assert_eq!(parse_bytes("100"), Ok(100));
+        assert!(parse_bytes("lots").is_err());
+        assert!(parse_bytes("4T").is_err());
+    }
+
+    #[test]
+    fn test_image_cache_evicts_least_recent() {
+        // Room for two 10x10 images
+        let mut cache = ImageCache::new(2 * 300);
+        cache.insert("a", image(10));
+        cache.insert("b", image(10));
+        assert!(cache.get(&"a").is_some());
+        cache.insert("c", image(10));
+
+        assert!(cache.contains(&"a"));
+        assert!(!cache.contains(&"b"));
+        assert!(cache.contains(&"c"));
+        assert_eq!(cache.used(), 600);
+
+        // Too big to keep at all
+        cache.insert("d", image(30));
+        assert!(!cache.contains(&"d"));
+        assert_eq!(cache.len(), 2);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{
+    annotation::{self, Annotation, AnnotationFile, Event},
+    retime::Retime,
+};
+
+/// What happened to the second file's events in a merge
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MergeReport {
+    /// Events of the second file already in the first, which were dropped
+    pub duplicates: Vec<Annotation>,
+    /// Events of the first and second file at the same moment that disagree. Both are kept.
+    pub conflicts: Vec<(Annotation, Annotation)>,
+    /// The files describe different players or heroes. The first file's header is kept.
+    pub setup_differs: bool,
+}
+
+/// Whether two events at the same moment can't both be right, e.g. two different cards
+fn conflicts(a: &Event, b: &Event) -> bool {
+    match (a, b) {
+        (
+            Event::Card { name, pitch },
+            Event::Card {
+                name: other_name,
+                pitch: other_pitch,
+            },
+        )
+        | (
+            Event::Block { name, pitch },
+            Event::Block {
+                name: other_name,
+                pitch: other_pitch,
+            },
+        )
+        | (
+            Event::Pair { name, pitch },
+            Event::Pair {
+                name: other_name,
+                pitch: other_pitch,
+            },
+        ) => name != other_name || pitch != other_pitch,
+        (
+            Event::Life { player1, player2 },
+            Event::Life {
+                player1: other1,
+                player2: other2,
+            },
+        ) => {
+            // One operator logging each player isn't a disagreement
+            let differs = |a: &Option<String>, b: &Option<String>| {
+                a.is_some() && b.is_some() && a != b
+            };
+            differs(player1, other1) || differs(player2, other2)
+        }
+        (Event::Win { player }, Event::Win { player: other }) => player != other,
+        _ => false,
+    }
+}
+
+/// Merges two annotations of the same video, e.g. one operator logging cards and another life
+/// totals. Events of the second file are moved by the difference in `video_offset` so both
+/// line up with the video. Events within `tolerance` seconds of each other count as the same
+/// moment: identical ones are kept once and disagreeing ones are reported.
+pub fn merge(
+    first: &AnnotationFile,
+    second: &AnnotationFile,
+    tolerance: f64,
+) -> (AnnotationFile, MergeReport) {
+    let mut report = MergeReport {
+        setup_differs: first.header.setup != second.header.setup,
+        ..Default::default()
+    };
+    let mut incoming = second.events.clone();
+    Retime::offset(second.header.video_offset - first.header.video_offset)
+        .apply_all(&mut incoming);
+
+    let mut events = first.events.clone();
+    // Each event of the first file can only absorb one duplicate
+    let mut matched = vec![false; first.events.len()];
+    for event in incoming {
+        let near = |other: &Annotation| {
+            (other.time.as_f64() - event.time.as_f64()).abs() <= tolerance
+        };
+        if let Some(idx) = (0..first.events.len()).find(|&idx| {
+            !matched[idx] && near(&first.events[idx]) && first.events[idx].event == event.event
+        }) {
+            matched[idx] = true;
+            report.duplicates.push(event);
+            continue;
+        }
+        if let Some(other) = first
+            .events
+            .iter()
+            .find(|other| near(other) && conflicts(&other.event, &event.event))
+        {
+            report.conflicts.push((other.clone(), event.clone()));
+        }
+        events.push(event);
+    }
+    annotation::sort(&mut events);
+    (AnnotationFile::new(first.header.clone(), events), report)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        annotation::{Header, MatchSetup},
+        time_tick::TimeTick,
+    };
+
+    fn file(events: Vec<(f64, Event)>) -> AnnotationFile {
+        AnnotationFile::new(
+            Header::new(MatchSetup::default()),
+            events
+                .into_iter()
+                .map(|(time, event)| Annotation::new(TimeTick::from_secs_f64(time), event))
+                .collect(),
+        )
+    }
+
+    fn card(name: &str) -> Event {
+        Event::Card {
+            name: name.to_string(),
+            pitch: Some(1),
+        }
+    }
+
+    fn life(player1: Option<&str>, player2: Option<&str>) -> Event {
+        Event::Life {
+            player1: player1.map(|u| u.to_string()),
+            player2: player2.map(|u| u.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let cards = file(vec![
+            (1.0, card("Head Jab")),
+            (5.0, card("Surging Strike")),
+            (9.0, Event::Turn),
+        ]);
+        let lives = file(vec![
+            (1.5, card("Head Jab")),
+            (3.0, life(Some("-3"), None)),
+            (5.5, card("Hurricane Technique")),
+            (9.5, Event::Turn),
+        ]);
+
+        let (merged, report) = merge(&cards, &lives, 0.5);
+        let times: Vec<f64> = merged.events.iter().map(|e| e.time.as_f64()).collect();
+        assert_eq!(times, vec![1.0, 3.0, 5.0, 5.5, 9.0]);
+        assert_eq!(report.duplicates.len(), 2);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].1.event, card("Hurricane Technique"));
+        assert!(!report.setup_differs);
+    }
+
+    #[test]
+    fn test_conflicts() {
+        assert!(!conflicts(&life(Some("-3"), None), &life(None, Some("-2"))));
+        assert!(conflicts(&life(Some("-3"), None), &life(Some("-4"), None)));
+        assert!(conflicts(&Event::Win { player: 1 }, &Event::Win { player: 2 }));
+        assert!(!conflicts(&card("Head Jab"), &Event::Turn));
+    }
+
+    #[test]
+    fn test_merge_video_offset() {
+        let first = file(vec![(10.0, Event::Turn)]);
+        let mut second = file(vec![(8.0, Event::Turn)]);
+        second.header.video_offset = 2.0;
+
+        let (merged, report) = merge(&first, &second, 0.1);
+        assert_eq!(merged.events.len(), 1);
+        assert_eq!(report.duplicates.len(), 1);
+    }
+}
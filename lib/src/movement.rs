@@ -21,6 +21,11 @@ fn s_curve(percentage: f64) -> f64 {
     (1.0 + E.powf(-t)).recip()
 }
 
+/// e^x, normalized. Slow then fast, for fading in from black.
+fn ease_in(percentage: f64) -> f64 {
+    (E.powf(2.0 * percentage) - 1.0) / (E.powi(2) - 1.0)
+}
+
 fn arctan_ish(percentage: f64) -> f64 {
     let left = -5.0;
     let right = 10.0;
@@ -48,6 +53,7 @@ pub enum Reparameterization {
     ArcTan,
     SCurve,
     Bounce,
+    EaseIn,
 }
 
 impl Reparameterization {
@@ -57,6 +63,7 @@ impl Reparameterization {
             Reparameterization::ArcTan => arctan_ish(percentage),
             Reparameterization::SCurve => s_curve(percentage),
             Reparameterization::Bounce => bounce(percentage),
+            Reparameterization::EaseIn => ease_in(percentage),
         }
     }
 }
@@ -0,0 +1,87 @@
+use std::{fs, process::Command};
+
+use opencv::{
+    core::{Mat, Rect, Size},
+    imgcodecs, imgproc,
+};
+
+/// Tesseract binary, which must be on the PATH
+const TESSERACT: &str = "tesseract";
+/// Tesseract struggles with small digits, so regions are scaled up before reading
+const UPSCALE: f64 = 3.0;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Reads the life total shown in `roi` of `frame`, e.g. on a digital life tracker held up to the
+/// camera. Returns `None` when tesseract doesn't find a number.
+pub fn read_life(frame: &Mat, roi: Rect) -> Result<Option<i32>> {
+    let region = Mat::roi(frame, roi)?;
+    let mut gray = Mat::default();
+    imgproc::cvt_color_def(&*region, &mut gray, imgproc::COLOR_BGR2GRAY)?;
+    let mut scaled = Mat::default();
+    imgproc::resize(
+        &gray,
+        &mut scaled,
+        Size::default(),
+        UPSCALE,
+        UPSCALE,
+        imgproc::INTER_CUBIC,
+    )?;
+    let mut binary = Mat::default();
+    imgproc::threshold(
+        &scaled,
+        &mut binary,
+        0.0,
+        255.0,
+        imgproc::THRESH_BINARY | imgproc::THRESH_OTSU,
+    )?;
+
+    let fp = std::env::temp_dir().join(format!("life_ocr_{}.png", std::process::id()));
+    let fp = fp.to_str().ok_or("temp path is not valid unicode")?;
+    imgcodecs::imwrite_def(fp, &binary)?;
+    // single line of digits
+    let output = Command::new(TESSERACT)
+        .args([fp, "stdout", "--psm", "7"])
+        .args(["-c", "tessedit_char_whitelist=0123456789"])
+        .output();
+    let _ = fs::remove_file(fp);
+    let output = output?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).into_owned().into());
+    }
+    Ok(parse_life(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_life(text: &str) -> Option<i32> {
+    let digits: String = text.chars().filter(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+/// Life update in the `LifeTracker` format taking `current` to `read`, e.g. "-3", or `None`
+/// when they already match
+pub fn suggest_update(current: i32, read: i32) -> Option<String> {
+    match read - current {
+        0 => None,
+        delta if delta > 0 => Some(format!("+{}", delta)),
+        delta => Some(delta.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_life() {
+        assert_eq!(parse_life("18\n\x0c"), Some(18));
+        assert_eq!(parse_life(" 4 0\n"), Some(40));
+        assert_eq!(parse_life("\n"), None);
+    }
+
+    #[test]
+    fn test_suggest_update() {
+        assert_eq!(suggest_update(20, 17).as_deref(), Some("-3"));
+        assert_eq!(suggest_update(17, 19).as_deref(), Some("+2"));
+        assert_eq!(suggest_update(20, 20), None);
+    }
+}
@@ -0,0 +1,190 @@
+//! Straightens footage from a camera mounted at an angle to the table. The four corners of the
+//! play area are picked once with `annotate calibrate`, then every frame is warped so they
+//! become a rectangle.
+
+use std::{fs::File, io::BufReader, path::Path};
+
+use opencv::{
+    core::{Mat, Point2f, Scalar, Size, UMat},
+    imgproc::INTER_LINEAR,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::rotate::{quad_homography, warp};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Corners of the play area, as fractions of the frame's width and height so they still fit
+/// after the video is rescaled
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Perspective {
+    /// Top left, top right, bottom right and bottom left, as [x, y]
+    pub corners: [[f32; 2]; 4],
+}
+
+impl Perspective {
+    pub fn load(fp: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(fp.as_ref())?;
+        let perspective: Self = serde_json::from_reader(BufReader::new(file))?;
+        perspective.validate()?;
+        Ok(perspective)
+    }
+
+    pub fn save(&self, fp: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(fp, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Builds from corners picked in pixels on a frame of `frame_size`
+    pub fn from_pixels(corners: [Point2f; 4], frame_size: Size) -> Result<Self> {
+        let perspective = Self {
+            corners: corners.map(|corner| {
+                [
+                    corner.x / frame_size.width as f32,
+                    corner.y / frame_size.height as f32,
+                ]
+            }),
+        };
+        perspective.validate()?;
+        Ok(perspective)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let in_frame = self
+            .corners
+            .iter()
+            .flatten()
+            .all(|value| (0.0..=1.0).contains(value));
+        if !in_frame {
+            return Err("Perspective corners have to be within the frame".into());
+        }
+        if self.area() <= 0.0 {
+            return Err(
+                "Perspective corners have to go clockwise from the top left and not cross".into(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Signed area of the corners, as a fraction of the frame. Positive when they go clockwise
+    /// on screen.
+    fn area(&self) -> f32 {
+        let mut area = 0.0;
+        for i in 0..4 {
+            let [x1, y1] = self.corners[i];
+            let [x2, y2] = self.corners[(i + 1) % 4];
+            area += x1 * y2 - x2 * y1;
+        }
+        area / 2.0
+    }
+
+    fn pixel_corners(&self, frame_size: Size) -> [Point2f; 4] {
+        self.corners
+            .map(|[x, y]| Point2f::new(x * frame_size.width as f32, y * frame_size.height as f32))
+    }
+
+    /// The warp for frames of `frame_size`. The output is as wide and high as the play area's
+    /// edges are on average, so it keeps about the same resolution.
+    pub fn correction(&self, frame_size: Size) -> Result<PerspectiveCorrection> {
+        let [top_left, top_right, bottom_right, bottom_left] = self.pixel_corners(frame_size);
+        let distance = |a: Point2f, b: Point2f| ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt();
+        let width = (distance(top_left, top_right) + distance(bottom_left, bottom_right)) / 2.0;
+        let height = (distance(top_left, bottom_left) + distance(top_right, bottom_right)) / 2.0;
+        let size = Size::new(
+            (width.round() as i32).max(1),
+            (height.round() as i32).max(1),
+        );
+        let (width, height) = (size.width as f32, size.height as f32);
+        let homography = quad_homography(
+            [top_left, top_right, bottom_right, bottom_left],
+            [
+                Point2f::new(0.0, 0.0),
+                Point2f::new(width, 0.0),
+                Point2f::new(width, height),
+                Point2f::new(0.0, height),
+            ],
+        )?;
+        Ok(PerspectiveCorrection { homography, size })
+    }
+}
+
+/// A `Perspective` worked out for one frame size, so the homography is found once
+pub struct PerspectiveCorrection {
+    homography: Mat,
+    size: Size,
+}
+
+impl PerspectiveCorrection {
+    /// Size of corrected frames
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    pub fn apply(&self, frame: &UMat, output: &mut UMat) -> Result<()> {
+        warp(
+            frame,
+            output,
+            &self.homography,
+            self.size,
+            INTER_LINEAR,
+            Scalar::all(0.0),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use opencv::core::{MatTraitConst, Rect, UMatTrait, UMatTraitConst, Vec3b, CV_8UC3};
+
+    use super::*;
+
+    const SQUARE: Perspective = Perspective {
+        corners: [[0.25, 0.25], [0.75, 0.25], [0.75, 0.75], [0.25, 0.75]],
+    };
+
+    #[test]
+    fn test_validate() {
+        assert!(SQUARE.validate().is_ok());
+        // Counterclockwise
+        let mut corners = SQUARE.corners;
+        corners.swap(1, 3);
+        assert!(Perspective { corners }.validate().is_err());
+        // Outside the frame
+        let mut corners = SQUARE.corners;
+        corners[2] = [1.2, 0.75];
+        assert!(Perspective { corners }.validate().is_err());
+    }
+
+    #[test]
+    fn test_correction_crops_a_rectangle() -> Result<()> {
+        let frame_size = Size::new(200, 100);
+        let correction = SQUARE.correction(frame_size)?;
+        assert_eq!(correction.size(), Size::new(100, 50));
+
+        let mut frame = UMat::new_size_with_default_def(frame_size, CV_8UC3, Scalar::all(0.0))?;
+        frame
+            .roi_mut(Rect::new(50, 25, 100, 50))?
+            .set_to_def(&Scalar::all(200.0))?;
+        let mut output = UMat::new_def();
+        correction.apply(&frame, &mut output)?;
+        let mut mat = Mat::default();
+        output.copy_to(&mut mat)?;
+        assert_eq!(*mat.at_2d::<Vec3b>(25, 50)?, Vec3b::all(200));
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_pixels() -> Result<()> {
+        let corners = [
+            Point2f::new(50.0, 25.0),
+            Point2f::new(150.0, 25.0),
+            Point2f::new(150.0, 75.0),
+            Point2f::new(50.0, 75.0),
+        ];
+        assert_eq!(
+            Perspective::from_pixels(corners, Size::new(200, 100))?,
+            SQUARE
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+use std::{collections::HashMap, fs::File, io::BufReader};
+
+use tracing::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::assets;
+
+/// Read by `PrintingPolicy::init` when present, e.g. to match the printings played at an event
+pub const PRINTING_POLICY_FILE: &'static str = "data/printings.json";
+/// Sets that are skipped unless preferred: HP1, promos and judge cards
+const SKIPPED_SETS: [&str; 5] = ["HP1", "FAB", "HER", "WIN", "JDG"];
+
+/// One printing of a card, as listed in the card image file
+#[derive(Debug, Clone, Default)]
+pub struct Printing<'a> {
+    /// e.g. "WTR001"
+    pub card_id: &'a str,
+    pub set: &'a str,
+    /// S (standard), R (rainbow foil), C (cold foil) or G (gold cold foil)
+    pub foiling: &'a str,
+    /// Comma separated, e.g. "EA" for extended art. Empty for the regular art.
+    pub art_variations: &'a str,
+}
+
+/// Which printing's image is shown for a card
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrintingPolicy {
+    /// Set prefixes, best first. Printings of other sets come after these.
+    pub preferred_sets: Vec<String>,
+    /// Set prefixes that are never used unless nothing else is printed
+    pub skipped_sets: Vec<String>,
+    /// Art variations as good as the regular art, e.g. "EA" or "FA"
+    pub allowed_art: Vec<String>,
+    /// Foiling to pick when a card has several, e.g. "C" for cold foil scans
+    pub foiling: Option<String>,
+    /// Card id of the exact printing to show, by card name and then pitch (empty for none),
+    /// e.g. `{"Head Jab": {"1": "WTR081"}}`
+    pub overrides: HashMap<String, HashMap<String, String>>,
+}
+
+impl Default for PrintingPolicy {
+    fn default() -> Self {
+        PrintingPolicy {
+            preferred_sets: Vec::new(),
+            skipped_sets: SKIPPED_SETS.iter().map(|s| s.to_string()).collect(),
+            allowed_art: Vec::new(),
+            foiling: None,
+            overrides: HashMap::new(),
+        }
+    }
+}
+
+impl PrintingPolicy {
+    /// Reads `PRINTING_POLICY_FILE`, or the default policy when there isn't one
+    pub fn init() -> Self {
+        let Ok(file) = File::open(assets::path(PRINTING_POLICY_FILE)) else {
+            return Self::default();
+        };
+        serde_json::from_reader(BufReader::new(file)).unwrap_or_else(|e| {
+            warn!("Ignoring invalid {}: {}", PRINTING_POLICY_FILE, e);
+            Self::default()
+        })
+    }
+
+    fn is_override(&self, name: &str, pitch: Option<u32>, printing: &Printing) -> bool {
+        let pitch = pitch.map(|p| p.to_string()).unwrap_or_default();
+        self.overrides
+            .get(name)
+            .and_then(|pitches| pitches.get(&pitch))
+            .is_some_and(|card_id| card_id.eq_ignore_ascii_case(printing.card_id))
+    }
+
+    /// Lower is better. `CardImageDB` keeps the later printing of two with the same rank.
+    pub fn rank(&self, name: &str, pitch: Option<u32>, printing: &Printing) -> impl Ord {
+        let starts = |prefix: &String| printing.set.starts_with(prefix.as_str());
+        let set = self
+            .preferred_sets
+            .iter()
+            .position(starts)
+            .unwrap_or(self.preferred_sets.len());
+        let regular_art = printing
+            .art_variations
+            .split(',')
+            .map(|art| art.trim())
+            .filter(|art| !art.is_empty())
+            .all(|art| self.allowed_art.iter().any(|allowed| allowed == art));
+        let foiling = self
+            .foiling
+            .as_ref()
+            .map_or(true, |foiling| foiling == printing.foiling);
+        (
+            !self.is_override(name, pitch, printing),
+            self.skipped_sets.iter().any(starts),
+            set,
+            !regular_art,
+            !foiling,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn printing<'a>(card_id: &'a str, foiling: &'a str, art_variations: &'a str) -> Printing<'a> {
+        Printing {
+            card_id,
+            set: &card_id[0..3],
+            foiling,
+            art_variations,
+        }
+    }
+
+    /// Later printings win ties, like in `CardImageDB`
+    fn best<'a>(policy: &PrintingPolicy, printings: &[Printing<'a>]) -> &'a str {
+        printings
+            .iter()
+            .rev()
+            .min_by_key(|p| policy.rank("Head Jab", Some(1), p))
+            .unwrap()
+            .card_id
+    }
+
+    #[test]
+    fn test_rank() {
+        let printings = [
+            printing("1HP081", "S", ""),
+            printing("FAB001", "S", ""),
+            printing("WTR081", "S", "EA"),
+            printing("WTR081", "S", ""),
+        ];
+        let mut policy = PrintingPolicy::default();
+        assert_eq!(best(&policy, &printings), "WTR081");
+
+        policy.preferred_sets = vec!["1HP".to_string()];
+        assert_eq!(best(&policy, &printings), "1HP081");
+
+        policy.overrides = HashMap::from([(
+            "Head Jab".to_string(),
+            HashMap::from([("1".to_string(), "fab001".to_string())]),
+        )]);
+        assert_eq!(best(&policy, &printings), "FAB001");
+    }
+
+    #[test]
+    fn test_rank_foiling() {
+        let printings = [printing("WTR081", "S", ""), printing("WTR081", "C", "EA")];
+        let mut policy = PrintingPolicy::default();
+        let rank =
+            |policy: &PrintingPolicy, idx: usize| policy.rank("Head Jab", Some(1), &printings[idx]);
+        assert!(rank(&policy, 0) < rank(&policy, 1));
+
+        policy.allowed_art = vec!["EA".to_string()];
+        policy.foiling = Some("C".to_string());
+        assert!(rank(&policy, 1) < rank(&policy, 0));
+    }
+}
@@ -0,0 +1,119 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// Timings for one stage of the render loop
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StageStats {
+    pub count: u32,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl StageStats {
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            self.total / self.count
+        }
+    }
+}
+
+/// Adds up how long each named stage takes across every frame. Stages are reported in the
+/// order they were first recorded.
+#[derive(Debug, Clone, Default)]
+pub struct Profiler {
+    stages: Vec<(&'static str, StageStats)>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the time since `started` against `stage`, returning now so the next stage can
+    /// start from it
+    pub fn record(&mut self, stage: &'static str, started: Instant) -> Instant {
+        let now = Instant::now();
+        self.add(stage, now - started);
+        now
+    }
+
+    pub fn add(&mut self, stage: &'static str, elapsed: Duration) {
+        let idx = match self.stages.iter().position(|(name, _)| *name == stage) {
+            Some(idx) => idx,
+            None => {
+                self.stages.push((stage, StageStats::default()));
+                self.stages.len() - 1
+            }
+        };
+        let stats = &mut self.stages[idx].1;
+        stats.count += 1;
+        stats.total += elapsed;
+        stats.max = stats.max.max(elapsed);
+    }
+
+    pub fn stage(&self, stage: &str) -> Option<StageStats> {
+        self.stages
+            .iter()
+            .find(|(name, _)| *name == stage)
+            .map(|(_, stats)| *stats)
+    }
+
+    pub fn total(&self) -> Duration {
+        self.stages.iter().map(|(_, stats)| stats.total).sum()
+    }
+}
+
+/// A table of every stage's total, mean and max time, and share of the total
+impl fmt::Display for Profiler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.total().as_secs_f64();
+        writeln!(
+            f,
+            "{:<16} {:>8} {:>10} {:>10} {:>10} {:>6}",
+            "stage", "count", "total", "mean", "max", "share"
+        )?;
+        for (name, stats) in self.stages.iter() {
+            let share = if total > 0.0 {
+                100.0 * stats.total.as_secs_f64() / total
+            } else {
+                0.0
+            };
+            writeln!(
+                f,
+                "{:<16} {:>8} {:>9.2}s {:>8.2}ms {:>8.2}ms {:>5.1}%",
+                name,
+                stats.count,
+                stats.total.as_secs_f64(),
+                stats.mean().as_secs_f64() * 1_000.0,
+                stats.max.as_secs_f64() * 1_000.0,
+                share
+            )?;
+        }
+        write!(f, "{:<16} {:>8} {:>9.2}s", "all", "", total)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_add() {
+        let mut profiler = Profiler::new();
+        profiler.add("encode", Duration::from_millis(30));
+        profiler.add("read", Duration::from_millis(5));
+        profiler.add("encode", Duration::from_millis(10));
+
+        let encode = profiler.stage("encode").unwrap();
+        assert_eq!(encode.count, 2);
+        assert_eq!(encode.mean(), Duration::from_millis(20));
+        assert_eq!(encode.max, Duration::from_millis(30));
+        assert_eq!(profiler.total(), Duration::from_millis(45));
+        assert!(profiler.to_string().starts_with("stage"));
+        assert_eq!(profiler.stage("text"), None);
+    }
+}
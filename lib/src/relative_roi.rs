@@ -250,6 +250,16 @@ impl RelativeRoi {
         })
     }
 
+    /// The same region flipped left to right across the frame, with its buffers swapped to match
+    pub fn mirrored(&self) -> Self {
+        Self {
+            x: (1.0 - self.x - self.width).max(0.0),
+            left_horizontal_buffer: self.right_horizontal_buffer,
+            right_horizontal_buffer: self.left_horizontal_buffer,
+            ..*self
+        }
+    }
+
     /// Generates rect given full frame size
     pub fn generate_roi(&self, region_size: &Size, umat: &UMat) -> Rect {
         // calculate ratio
@@ -430,3 +440,21 @@ impl VerticalPartition {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use opencv::core::{Rect, Size};
+
+    use super::RelativeRoi;
+
+    #[test]
+    fn test_mirrored() {
+        let roi = RelativeRoi::build(0.1, 0.0, 0.2, 0.5, 0.01, 0.0, 0.0, 0.0).unwrap();
+        let size = Size::new(1000, 100);
+        assert_eq!(roi.generate_roi_raw(&size), Rect::new(110, 0, 190, 50));
+        assert_eq!(
+            roi.mirrored().generate_roi_raw(&size),
+            Rect::new(700, 0, 190, 50)
+        );
+    }
+}
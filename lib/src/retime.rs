@@ -0,0 +1,79 @@
+use crate::{annotation::Annotation, time_tick::TimeTick};
+
+/// Linear change to every timestamp of an annotation, for when the video it was made against
+/// was trimmed or re-encoded differently than the final footage
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Retime {
+    scale: f64,
+    offset: f64,
+}
+
+impl Retime {
+    /// Moves every timestamp by `seconds`, earlier when negative
+    pub fn offset(seconds: f64) -> Self {
+        Retime {
+            scale: 1.0,
+            offset: seconds,
+        }
+    }
+
+    /// Moves `from.0` to `to.0` and `from.1` to `to.1`, stretching everything else to match.
+    /// Returns `None` when the `from` anchors are the same time.
+    pub fn anchors(from: (f64, f64), to: (f64, f64)) -> Option<Self> {
+        let span = from.1 - from.0;
+        if span == 0.0 || !span.is_finite() {
+            return None;
+        }
+        let scale = (to.1 - to.0) / span;
+        Some(Retime {
+            scale,
+            offset: to.0 - from.0 * scale,
+        })
+    }
+
+    /// Timestamps that would land before the start of the video are clamped to it
+    pub fn apply(&self, time: TimeTick) -> TimeTick {
+        TimeTick::from_secs_f64(time.as_f64() * self.scale + self.offset)
+    }
+
+    pub fn apply_all(&self, annotations: &mut [Annotation]) {
+        for annotation in annotations.iter_mut() {
+            annotation.time = self.apply(annotation.time);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn secs(seconds: f64) -> TimeTick {
+        TimeTick::from_secs_f64(seconds)
+    }
+
+    fn assert_near(time: TimeTick, seconds: f64) {
+        assert!(
+            (time.as_f64() - seconds).abs() < 1e-6,
+            "{} != {}",
+            time.as_f64(),
+            seconds
+        );
+    }
+
+    #[test]
+    fn test_offset() {
+        let retime = Retime::offset(-12.5);
+        assert_eq!(retime.apply(secs(20.0)).as_f64(), 7.5);
+        assert_eq!(retime.apply(secs(10.0)).as_f64(), 0.0);
+    }
+
+    #[test]
+    fn test_anchors() {
+        // The final video starts 5s later and runs 10% slower
+        let retime = Retime::anchors((10.0, 110.0), (15.0, 125.0)).unwrap();
+        assert_near(retime.apply(secs(10.0)), 15.0);
+        assert_near(retime.apply(secs(60.0)), 70.0);
+        assert_near(retime.apply(secs(110.0)), 125.0);
+        assert!(Retime::anchors((10.0, 10.0), (0.0, 1.0)).is_none());
+    }
+}
@@ -1,6 +1,6 @@
 use opencv::{
     calib3d::{ find_homography_def},
-    core::{no_array, Point2f, Scalar, Size, UMat, Vector, BORDER_CONSTANT},
+    core::{no_array, Mat, Point2f, Scalar, Size, UMat, Vector, BORDER_CONSTANT},
     imgproc::{cvt_color_def, warp_perspective, COLOR_RGBA2RGB, INTER_NEAREST},
     prelude::*,
 };
@@ -92,17 +92,45 @@ pub fn rotate_image(
 
     // Warp the image for the current frame
     let mut warped_frame = UMat::new(opencv::core::UMatUsageFlags::USAGE_DEFAULT);
-    warp_perspective(
+    warp(
         &image,
         &mut warped_frame,
         &homography,
         output_size,
         INTER_NEAREST,
-        BORDER_CONSTANT,
-        REMOVAL_COLOR
+        REMOVAL_COLOR,
     )?;
 
     cvt_color_def(&warped_frame.clone(), &mut warped_frame, COLOR_RGBA2RGB)?;
 
     Ok(warped_frame)
 }
+
+/// Homography taking each of the four `src` corners to the matching `dst` corner
+pub fn quad_homography(src: [Point2f; 4], dst: [Point2f; 4]) -> Result<Mat, Box<dyn Error>> {
+    let src = Vector::<Point2f>::from_slice(&src);
+    let dst = Vector::<Point2f>::from_slice(&dst);
+    Ok(find_homography_def(&src, &dst, &mut no_array())?)
+}
+
+/// Warps `image` by `homography` into `output` of `size`, filling anywhere outside the image
+/// with `border`
+pub fn warp(
+    image: &UMat,
+    output: &mut UMat,
+    homography: &Mat,
+    size: Size,
+    interpolation: i32,
+    border: Scalar,
+) -> Result<(), Box<dyn Error>> {
+    warp_perspective(
+        image,
+        output,
+        homography,
+        size,
+        interpolation,
+        BORDER_CONSTANT,
+        border,
+    )?;
+    Ok(())
+}
@@ -0,0 +1,196 @@
+//! A "Starting Soon" screen to show before a stream: the overlay's animated background, a logo,
+//! both heroes and a countdown.
+
+use opencv::{
+    core::{Rect, Scalar, Size, UMat, UMatTraitConst},
+    imgproc::FONT_HERSHEY_SIMPLEX,
+    videoio::{VideoWriter, VideoWriterTrait},
+};
+
+use crate::{
+    assets,
+    background::{Background, Palette},
+    image::{load_image, FullArtHeroManager},
+    intro::VideoCapLooper,
+    letterbox::fit_rect,
+    movement::{place_umat, resize_umat},
+    text::center_text_at_rect,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const LOGO_FP: &str = "data/image.png";
+const FRAME_WIDTH: i32 = 1920;
+const FRAME_HEIGHT: i32 = 1080;
+const FONT_FACE: i32 = FONT_HERSHEY_SIMPLEX;
+const TITLE_FONT_SCALE: f64 = 3.0;
+const TITLE_FONT_THICKNESS: i32 = 6;
+const COUNTDOWN_FONT_SCALE: f64 = 6.0;
+const COUNTDOWN_FONT_THICKNESS: i32 = 10;
+const FONT_BUFFER: i32 = 20;
+const WHITE: Scalar = Scalar::new(255.0, 255.0, 255.0, 0.0);
+
+/// Space around the heroes, relative to the frame width
+const MARGIN_RATIO: f64 = 1.0 / 20.0;
+const HERO_WIDTH_RATIO: f64 = 3.0 / 10.0;
+const HERO_TOP_RATIO: f64 = 1.0 / 4.0;
+const HERO_HEIGHT_RATIO: f64 = 3.0 / 5.0;
+const LOGO_TOP_RATIO: f64 = 1.0 / 20.0;
+const LOGO_SIZE_RATIO: f64 = 1.0 / 5.0;
+const TITLE_TOP_RATIO: f64 = 2.0 / 5.0;
+const TITLE_HEIGHT_RATIO: f64 = 1.0 / 8.0;
+const COUNTDOWN_TOP_RATIO: f64 = 11.0 / 20.0;
+const COUNTDOWN_HEIGHT_RATIO: f64 = 1.0 / 5.0;
+
+pub struct StartingSoon {
+    /// Cropped full art animations of the heroes, left then right
+    pub hero_fps: [String; 2],
+    /// Defaults to the go-to-one logo
+    pub logo_fp: String,
+    /// Shown above the countdown
+    pub title: String,
+    /// Seconds counted down. The video is this long.
+    pub countdown: f64,
+    pub palette: Palette,
+}
+
+impl StartingSoon {
+    /// `heroes` are hero names, as in the full art hero map
+    pub fn build(heroes: [&str; 2], countdown: f64) -> Result<Self> {
+        let full_art_manager = FullArtHeroManager::new();
+        Ok(StartingSoon {
+            hero_fps: [
+                full_art_manager.get_cropped_hero_art_animation_fp(heroes[0])?,
+                full_art_manager.get_cropped_hero_art_animation_fp(heroes[1])?,
+            ],
+            logo_fp: assets::path(LOGO_FP),
+            title: "Starting Soon".to_owned(),
+            countdown,
+            palette: Palette::default(),
+        })
+    }
+
+    pub fn render(&self, output_fp: &str, fps: f64) -> Result<()> {
+        let frame_size = Size::new(FRAME_WIDTH, FRAME_HEIGHT);
+        let mut writer = VideoWriter::new(
+            output_fp,
+            VideoWriter::fourcc('m', 'p', '4', 'v')?,
+            fps,
+            frame_size,
+            true,
+        )?;
+        let mut background = Background::build(frame_size, self.palette, fps)?;
+        let mut hero_loops = [
+            VideoCapLooper::build(&self.hero_fps[0])?,
+            VideoCapLooper::build(&self.hero_fps[1])?,
+        ];
+        let logo = load_image(&self.logo_fp)?;
+        if logo.empty() {
+            return Err(format!("Couldn't load logo {}", self.logo_fp).into());
+        }
+
+        let rel = |x: f64, y: f64, width: f64, height: f64| {
+            Rect::new(
+                (x * FRAME_WIDTH as f64) as i32,
+                (y * FRAME_HEIGHT as f64) as i32,
+                (width * FRAME_WIDTH as f64) as i32,
+                (height * FRAME_HEIGHT as f64) as i32,
+            )
+        };
+        let logo_rect = rel(
+            (1.0 - LOGO_SIZE_RATIO) / 2.0,
+            LOGO_TOP_RATIO,
+            LOGO_SIZE_RATIO,
+            LOGO_SIZE_RATIO,
+        );
+        let hero_rects = [
+            rel(
+                MARGIN_RATIO,
+                HERO_TOP_RATIO,
+                HERO_WIDTH_RATIO,
+                HERO_HEIGHT_RATIO,
+            ),
+            rel(
+                1.0 - MARGIN_RATIO - HERO_WIDTH_RATIO,
+                HERO_TOP_RATIO,
+                HERO_WIDTH_RATIO,
+                HERO_HEIGHT_RATIO,
+            ),
+        ];
+        // Text goes in the column between the heroes
+        let column_x = MARGIN_RATIO + HERO_WIDTH_RATIO;
+        let column_width = 1.0 - 2.0 * column_x;
+        let title_rect = rel(column_x, TITLE_TOP_RATIO, column_width, TITLE_HEIGHT_RATIO);
+        let countdown_rect = rel(
+            column_x,
+            COUNTDOWN_TOP_RATIO,
+            column_width,
+            COUNTDOWN_HEIGHT_RATIO,
+        );
+
+        let num_frames = (self.countdown * fps).round() as u64;
+        for i in 0..num_frames {
+            let mut frame = UMat::new_def();
+            background.read(&mut frame)?;
+            fit_into(&logo, &mut frame, logo_rect)?;
+            for (hero_loop, rect) in hero_loops.iter_mut().zip(hero_rects) {
+                fit_into(&hero_loop.read()?, &mut frame, rect)?;
+            }
+            center_text_at_rect(
+                &mut frame,
+                &self.title,
+                FONT_FACE,
+                TITLE_FONT_SCALE,
+                WHITE,
+                TITLE_FONT_THICKNESS,
+                title_rect,
+                FONT_BUFFER,
+            )?;
+            center_text_at_rect(
+                &mut frame,
+                &countdown_text(self.countdown - i as f64 / fps),
+                FONT_FACE,
+                COUNTDOWN_FONT_SCALE,
+                WHITE,
+                COUNTDOWN_FONT_THICKNESS,
+                countdown_rect,
+                FONT_BUFFER,
+            )?;
+            writer.write(&frame)?;
+        }
+        writer.release()?;
+        Ok(())
+    }
+}
+
+/// Resizes `img` to fit `area` of the frame without stretching it, centered in the area
+fn fit_into(img: &UMat, frame: &mut UMat, area: Rect) -> Result<()> {
+    let fit = fit_rect(img.size()?, area.size());
+    let resized = resize_umat(img, &fit.size())?;
+    place_umat(
+        &resized,
+        frame,
+        Rect::new(area.x + fit.x, area.y + fit.y, fit.width, fit.height),
+    )?;
+    Ok(())
+}
+
+/// Seconds left as "M:SS", rounded up so the countdown shows 0:00 only once it's over
+fn countdown_text(remaining: f64) -> String {
+    let seconds = remaining.max(0.0).ceil() as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_countdown_text() {
+        assert_eq!(countdown_text(300.0), "5:00");
+        assert_eq!(countdown_text(299.5), "5:00");
+        assert_eq!(countdown_text(61.0), "1:01");
+        assert_eq!(countdown_text(0.2), "0:01");
+        assert_eq!(countdown_text(-1.0), "0:00");
+    }
+}
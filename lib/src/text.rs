@@ -2,7 +2,7 @@ use std::borrow::BorrowMut;
 
 use opencv::{
     core::{Point, Rect, Scalar, Size, UMat, UMatTrait, UMatTraitConst, VecN},
-    imgproc::{get_text_size, put_text, resize_def, LINE_8},
+    imgproc::{get_text_size, put_text, resize_def, LINE_8, LINE_AA},
 };
 
 use crate::{
@@ -122,3 +122,93 @@ pub fn center_text_at_rect(
 
     Ok(())
 }
+
+/// Smallest font scale `put_text_block` shrinks text to
+const MIN_BLOCK_FONT_SCALE: f64 = 0.4;
+/// Space between the lines of a text block, relative to the line height
+const BLOCK_LINE_SPACING: f64 = 1.5;
+
+/// Splits `text` into lines no wider than `max_width`, breaking at spaces and newlines. Blank
+/// lines are kept as paragraph breaks.
+pub fn wrap_text(
+    text: &str,
+    font_face: i32,
+    font_scale: f64,
+    thickness: i32,
+    max_width: i32,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut baseline = 0;
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+            let width =
+                get_text_size(&candidate, font_face, font_scale, thickness, &mut baseline)?.width;
+            if width > max_width && !line.is_empty() {
+                lines.push(line);
+                line = word.to_string();
+            } else {
+                line = candidate;
+            }
+        }
+        lines.push(line);
+    }
+    Ok(lines)
+}
+
+/// Writes `text` from the top of `rect`, wrapped to its width. The font shrinks from
+/// `font_scale` until the text fits.
+pub fn put_text_block(
+    frame: &mut UMat,
+    text: &str,
+    font_face: i32,
+    font_scale: f64,
+    color: VecN<f64, 4>,
+    thickness: i32,
+    rect: Rect,
+    buffer: i32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let width = rect.width - buffer;
+    let mut scale = font_scale;
+    let (lines, line_height) = loop {
+        let mut baseline = 0;
+        let height = get_text_size("Tg", font_face, scale, thickness, &mut baseline)?.height;
+        let line_height = ((height + baseline) as f64 * BLOCK_LINE_SPACING) as i32;
+        let lines = wrap_text(text, font_face, scale, thickness, width)?;
+        let fits = line_height * lines.len() as i32 <= rect.height - buffer;
+        if fits || scale <= MIN_BLOCK_FONT_SCALE {
+            break (lines, line_height);
+        }
+        scale = (scale * 0.9).max(MIN_BLOCK_FONT_SCALE);
+    };
+
+    let thickness = if scale < font_scale {
+        ((thickness as f64 * scale / font_scale).round() as i32).max(1)
+    } else {
+        thickness
+    };
+    let mut y = rect.y + buffer.div_euclid(2);
+    for line in lines.iter() {
+        y += line_height;
+        if y > rect.y + rect.height {
+            break;
+        }
+        put_text(
+            frame,
+            line,
+            Point::new(rect.x + buffer.div_euclid(2), y),
+            font_face,
+            scale,
+            color,
+            thickness,
+            LINE_AA,
+            false,
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,222 @@
+use std::{
+    cmp::Ordering,
+    ops::{Add, Sub},
+    time::Duration,
+};
+
+const MILLI: f64 = 1_000.0;
+
+/// Position in a video as whole seconds plus milliseconds. `milli` is always kept in
+/// `0.0..1000.0`, and subtraction saturates at zero instead of underflowing.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TimeTick {
+    sec: u64,
+    milli: f64,
+}
+
+impl TimeTick {
+    pub fn new() -> Self {
+        TimeTick { sec: 0, milli: 0.0 }
+    }
+
+    /// Milliseconds past a full second are carried into `sec`
+    pub fn build(sec: u64, milli: f64) -> Self {
+        let mut tick = TimeTick { sec, milli: 0.0 };
+        tick.increment_milli(milli);
+        tick
+    }
+
+    pub fn from_secs_f64(seconds: f64) -> Self {
+        let seconds = seconds.max(0.0);
+        Self::build(seconds.trunc() as u64, seconds.fract() * MILLI)
+    }
+
+    /// Parses seconds, "mm:ss" or "h:mm:ss", each with optional fractional seconds, e.g.
+    /// "95", "12:34" or "1:02:03.5"
+    pub fn parse(text: &str) -> Option<Self> {
+        let parts: Vec<&str> = text.trim().split(':').collect();
+        if parts.len() > 3 {
+            return None;
+        }
+        let mut seconds = 0.0;
+        for part in parts {
+            let value: f64 = part.trim().parse().ok()?;
+            if value < 0.0 || !value.is_finite() {
+                return None;
+            }
+            seconds = seconds * 60.0 + value;
+        }
+        Some(Self::from_secs_f64(seconds))
+    }
+
+    pub fn sec(&self) -> u64 {
+        self.sec
+    }
+
+    pub fn milli(&self) -> f64 {
+        self.milli
+    }
+
+    /// Moves the tick forward by `increment` milliseconds. Negative increments are ignored.
+    pub fn increment_milli(&mut self, increment: f64) {
+        if increment <= 0.0 || !increment.is_finite() {
+            return;
+        }
+        let total = self.milli + increment;
+        self.sec = self.sec.saturating_add((total / MILLI).trunc() as u64);
+        self.milli = total % MILLI;
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        self.sec as f64 + (self.milli / MILLI)
+    }
+
+    /// Difference between the two ticks, or zero if `rhs` is later than `self`
+    pub fn saturating_sub(self, rhs: Self) -> Self {
+        if self <= rhs {
+            return Self::new();
+        }
+        if self.milli < rhs.milli {
+            // Built so a difference rounded up to a full second is carried
+            TimeTick::build((self.sec - 1) - rhs.sec, (self.milli + MILLI) - rhs.milli)
+        } else {
+            TimeTick {
+                sec: self.sec - rhs.sec,
+                milli: self.milli - rhs.milli,
+            }
+        }
+    }
+}
+
+impl Sub for TimeTick {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.saturating_sub(rhs)
+    }
+}
+
+impl Add for TimeTick {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        let mut tick = TimeTick {
+            sec: self.sec.saturating_add(rhs.sec),
+            milli: self.milli,
+        };
+        tick.increment_milli(rhs.milli);
+        tick
+    }
+}
+
+impl PartialEq for TimeTick {
+    fn eq(&self, other: &Self) -> bool {
+        (self.sec, self.milli) == (other.sec, other.milli)
+    }
+}
+
+impl PartialOrd for TimeTick {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (self.sec, self.milli).partial_cmp(&(other.sec, other.milli))
+    }
+}
+
+impl From<Duration> for TimeTick {
+    fn from(value: Duration) -> Self {
+        TimeTick::build(value.as_secs(), value.subsec_nanos() as f64 / 1_000_000.0)
+    }
+}
+
+impl From<TimeTick> for Duration {
+    fn from(value: TimeTick) -> Self {
+        Duration::from_secs(value.sec)
+            + Duration::from_nanos((value.milli * 1_000_000.0).round() as u64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use proptest::prelude::*;
+
+    use super::{TimeTick, MILLI};
+
+    fn tick(max_sec: u64) -> impl Strategy<Value = TimeTick> {
+        (0..=max_sec, 0.0..MILLI).prop_map(|(sec, milli)| TimeTick::build(sec, milli))
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(TimeTick::parse("95").map(|t| t.as_f64()), Some(95.0));
+        assert_eq!(TimeTick::parse("12:34").map(|t| t.as_f64()), Some(754.0));
+        assert_eq!(TimeTick::parse("1:02:03.5").map(|t| t.as_f64()), Some(3723.5));
+        assert_eq!(TimeTick::parse("-3"), None);
+        assert_eq!(TimeTick::parse("1:2:3:4"), None);
+        assert_eq!(TimeTick::parse("soon"), None);
+    }
+
+    #[test]
+    fn test_build_carries_milli() {
+        let tick = TimeTick::build(1, 2_500.0);
+        assert_eq!(tick.sec(), 3);
+        assert_eq!(tick.milli(), 500.0);
+    }
+
+    #[test]
+    fn test_increment_over_multiple_seconds() {
+        let mut tick = TimeTick::new();
+        tick.increment_milli(999.0);
+        tick.increment_milli(1.0);
+        assert_eq!(tick, TimeTick::build(1, 0.0));
+
+        tick.increment_milli(8_000.0 * 1.5);
+        assert_eq!(tick, TimeTick::build(13, 0.0));
+    }
+
+    #[test]
+    fn test_sub_saturates() {
+        let early = TimeTick::build(1, 900.0);
+        let late = TimeTick::build(2, 100.0);
+        assert_eq!(late - early, TimeTick::build(0, 200.0));
+        assert_eq!(early - late, TimeTick::new());
+    }
+
+    #[test]
+    fn test_add() {
+        let tick = TimeTick::build(1, 600.0) + TimeTick::build(2, 700.0);
+        assert_eq!(tick.sec(), 4);
+        assert!((tick.milli() - 300.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_duration_round_trip() {
+        let duration = Duration::from_millis(12_345);
+        let tick = TimeTick::from(duration);
+        assert_eq!(tick.sec(), 12);
+        assert!((tick.milli() - 345.0).abs() < 1e-9);
+        assert_eq!(Duration::from(tick).as_millis(), 12_345);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_arithmetic_never_panics(a in tick(u64::MAX), b in tick(u64::MAX)) {
+            for tick in [a + b, b + a, a - b, b - a] {
+                prop_assert!((0.0..MILLI).contains(&tick.milli()));
+            }
+            prop_assert!(a - b == TimeTick::new() || b - a == TimeTick::new());
+        }
+
+        #[test]
+        fn prop_sub_undoes_add(a in tick(1_000_000), b in tick(1_000_000)) {
+            let sum = a + b;
+            prop_assert!(sum >= a);
+            prop_assert!(((sum - b).as_f64() - a.as_f64()).abs() < 1e-6);
+        }
+
+        #[test]
+        fn prop_parse_never_panics(text in "\\PC*") {
+            let _ = TimeTick::parse(&text);
+        }
+    }
+}
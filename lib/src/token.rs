@@ -0,0 +1,298 @@
+//! Badges for game objects that aren't cards, like a Quicken token or an aim counter, popped up
+//! beside the hero they're on for `token` events. The icons are drawn rather than loaded, so
+//! they need no image files.
+
+use opencv::{
+    core::{self, Point, Rect, Scalar, Size, UMat, UMatTraitConst, Vector, CV_8UC3},
+    imgproc::{self, FONT_HERSHEY_SIMPLEX, LINE_8},
+};
+
+use crate::{
+    fade::remove_color,
+    movement::{place_umat, Reparameterization},
+    text::center_text_at_rect,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Seconds a badge is on screen, including popping in and fading out
+pub const TOKEN_DURATION: f64 = 3.0;
+const POP_DURATION: f64 = 0.3;
+const FADE_DURATION: f64 = 0.5;
+const FONT_FACE: i32 = FONT_HERSHEY_SIMPLEX;
+const FONT_THICKNESS: i32 = 2;
+const FONT_BUFFER: i32 = 6;
+// Background of the badge canvas, removed when compositing. Shapes are drawn without
+// antialiasing so their edges don't pick it up.
+const KEY_COLOR: Scalar = Scalar::new(255.0, 0.0, 255.0, 0.0);
+const BADGE_COLOR: Scalar = Scalar::new(40.0, 40.0, 40.0, 0.0);
+const WHITE: Scalar = Scalar::new(255.0, 255.0, 255.0, 0.0);
+// Relative to the hero's panel
+const BADGE_SIZE_RATIO: f64 = 0.3;
+const MARGIN_RATIO: f64 = 0.04;
+// Relative to the badge's icon
+const LABEL_HEIGHT_RATIO: f64 = 0.3;
+const RING_RATIO: f64 = 0.08;
+
+/// Game objects with their own icon. Anything else gets its initial.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Quicken,
+    Frostbite,
+    Ponder,
+    AimCounter,
+    Other,
+}
+
+impl TokenKind {
+    pub fn parse(name: &str) -> Self {
+        match name.trim().to_lowercase().as_str() {
+            "quicken" => TokenKind::Quicken,
+            "frostbite" => TokenKind::Frostbite,
+            "ponder" => TokenKind::Ponder,
+            "aim" | "aim counter" => TokenKind::AimCounter,
+            _ => TokenKind::Other,
+        }
+    }
+
+    fn label(&self) -> Option<&'static str> {
+        match self {
+            TokenKind::Quicken => Some("Quicken"),
+            TokenKind::Frostbite => Some("Frostbite"),
+            TokenKind::Ponder => Some("Ponder"),
+            TokenKind::AimCounter => Some("Aim"),
+            TokenKind::Other => None,
+        }
+    }
+
+    fn color(&self) -> Scalar {
+        match self {
+            TokenKind::Quicken => Scalar::new(0.0, 215.0, 255.0, 0.0),
+            TokenKind::Frostbite => Scalar::new(250.0, 210.0, 150.0, 0.0),
+            TokenKind::Ponder => Scalar::new(220.0, 120.0, 170.0, 0.0),
+            TokenKind::AimCounter => Scalar::new(40.0, 40.0, 220.0, 0.0),
+            TokenKind::Other => WHITE,
+        }
+    }
+
+    /// Draws the icon inside the badge's circle at `center`
+    fn draw_icon(&self, canvas: &mut UMat, center: Point, radius: i32, name: &str) -> Result<()> {
+        let color = self.color();
+        let at = |x: f64, y: f64| {
+            Point::new(
+                center.x + (x * radius as f64) as i32,
+                center.y + (y * radius as f64) as i32,
+            )
+        };
+        let thickness = (radius / 8).max(1);
+        match self {
+            TokenKind::Quicken => {
+                let bolt: Vector<Point> = [
+                    (0.15, -0.7),
+                    (-0.35, 0.1),
+                    (0.0, 0.1),
+                    (-0.15, 0.7),
+                    (0.35, -0.1),
+                    (0.0, -0.1),
+                ]
+                .into_iter()
+                .map(|(x, y)| at(x, y))
+                .collect();
+                let mut bolts: Vector<Vector<Point>> = Vector::new();
+                bolts.push(bolt);
+                imgproc::fill_poly(canvas, &bolts, color, LINE_8, 0, Point::default())?;
+            }
+            TokenKind::Frostbite => {
+                // Snowflake of three crossed strokes
+                for angle in [90.0_f64, 30.0, 150.0] {
+                    let (sin, cos) = angle.to_radians().sin_cos();
+                    imgproc::line(
+                        canvas,
+                        at(cos * 0.6, sin * 0.6),
+                        at(-cos * 0.6, -sin * 0.6),
+                        color,
+                        thickness,
+                        LINE_8,
+                        0,
+                    )?;
+                }
+            }
+            TokenKind::Ponder => {
+                imgproc::circle(canvas, center, radius / 2, color, -1, LINE_8, 0)?;
+                imgproc::circle(canvas, at(-0.15, -0.15), radius / 8, WHITE, -1, LINE_8, 0)?;
+            }
+            TokenKind::AimCounter => {
+                imgproc::circle(canvas, center, radius * 2 / 5, color, thickness, LINE_8, 0)?;
+                for (x, y) in [(0.0, 1.0), (0.0, -1.0), (1.0, 0.0), (-1.0, 0.0)] {
+                    imgproc::line(
+                        canvas,
+                        at(x * 0.2, y * 0.2),
+                        at(x * 0.65, y * 0.65),
+                        color,
+                        thickness,
+                        LINE_8,
+                        0,
+                    )?;
+                }
+            }
+            TokenKind::Other => {
+                let initial = name
+                    .chars()
+                    .next()
+                    .unwrap_or('?')
+                    .to_uppercase()
+                    .to_string();
+                let side = radius;
+                center_text_at_rect(
+                    canvas,
+                    &initial,
+                    FONT_FACE,
+                    2.0,
+                    color,
+                    FONT_THICKNESS,
+                    Rect::new(center.x - side / 2, center.y - side / 2, side, side),
+                    FONT_BUFFER,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where the `slot`th badge on a hero goes, down the hero's top right corner. Slots past the
+/// bottom of the hero start again at the top.
+pub fn badge_rect(hero_rect: Rect, slot: usize) -> Rect {
+    let size = (hero_rect.width.min(hero_rect.height) as f64 * BADGE_SIZE_RATIO) as i32;
+    let height = size + (size as f64 * LABEL_HEIGHT_RATIO) as i32;
+    let margin = (hero_rect.width.min(hero_rect.height) as f64 * MARGIN_RATIO) as i32;
+    let slots = ((hero_rect.height - margin) / (height + margin)).max(1) as usize;
+    Rect::new(
+        hero_rect.x + hero_rect.width - size - margin,
+        hero_rect.y + margin + (slot % slots) as i32 * (height + margin),
+        size,
+        height,
+    )
+}
+
+/// A token's badge, from when it pops in to when it has faded out
+pub struct TokenBadge {
+    rect: Rect,
+    start: f64,
+    /// Icon and label, on `KEY_COLOR`
+    canvas: UMat,
+}
+
+impl TokenBadge {
+    /// # Arguments
+    /// * `hero_rect` - Panel of the hero the token is on
+    /// * `slot` - Badges already showing on that hero, so this one goes below them
+    /// * `start` - Time in seconds the badge pops in
+    /// * `name` - Token as annotated, e.g. "Quicken"
+    pub fn build(hero_rect: Rect, slot: usize, start: f64, name: &str) -> Result<Self> {
+        let rect = badge_rect(hero_rect, slot);
+        let kind = TokenKind::parse(name);
+        let mut canvas = UMat::new_size_with_default_def(rect.size(), CV_8UC3, KEY_COLOR)?;
+        let radius = rect.width / 2;
+        let center = Point::new(radius, radius);
+        imgproc::circle(&mut canvas, center, radius, BADGE_COLOR, -1, LINE_8, 0)?;
+        let ring = ((radius as f64 * RING_RATIO) as i32).max(1);
+        imgproc::circle(
+            &mut canvas,
+            center,
+            radius - ring,
+            kind.color(),
+            ring,
+            LINE_8,
+            0,
+        )?;
+        kind.draw_icon(&mut canvas, center, radius, name)?;
+        let label = Rect::new(0, rect.width, rect.width, rect.height - rect.width);
+        if label.height > 0 {
+            center_text_at_rect(
+                &mut canvas,
+                kind.label().unwrap_or(name.trim()),
+                FONT_FACE,
+                1.0,
+                WHITE,
+                FONT_THICKNESS,
+                label,
+                FONT_BUFFER,
+            )?;
+        }
+        Ok(TokenBadge {
+            rect,
+            start,
+            canvas,
+        })
+    }
+
+    pub fn is_finished(&self, seconds: f64) -> bool {
+        seconds - self.start >= TOKEN_DURATION
+    }
+
+    /// Draws the badge for time `seconds`. Does nothing before the start or after the end.
+    pub fn draw(&self, frame: &mut UMat, seconds: f64) -> Result<()> {
+        let elapsed = seconds - self.start;
+        if elapsed < 0.0 || self.is_finished(seconds) {
+            return Ok(());
+        }
+
+        let grow = Reparameterization::SCurve.apply((elapsed / POP_DURATION).min(1.0));
+        let size = Size::new(
+            ((self.rect.width as f64 * grow) as i32).max(1),
+            ((self.rect.height as f64 * grow) as i32).max(1),
+        );
+        let dest = Rect::new(
+            self.rect.x + (self.rect.width - size.width) / 2,
+            self.rect.y + (self.rect.height - size.height) / 2,
+            size.width,
+            size.height,
+        );
+        // Nearest neighbour keeps the key color from blending into the badge's edge
+        let mut sized = UMat::new_def();
+        imgproc::resize(
+            &self.canvas,
+            &mut sized,
+            size,
+            0.0,
+            0.0,
+            imgproc::INTER_NEAREST,
+        )?;
+        let roi = frame.roi(dest)?;
+        let badge = remove_color(&roi, &sized, &KEY_COLOR)?;
+        let alpha = ((TOKEN_DURATION - elapsed) / FADE_DURATION).min(1.0);
+        let mut blended = UMat::new_def();
+        core::add_weighted(&roi, 1.0 - alpha, &badge, alpha, 0.0, &mut blended, -1)?;
+        place_umat(&blended, frame, dest)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_kind() {
+        assert_eq!(TokenKind::parse(" Quicken"), TokenKind::Quicken);
+        assert_eq!(TokenKind::parse("aim counter"), TokenKind::AimCounter);
+        assert_eq!(TokenKind::parse("Gold"), TokenKind::Other);
+    }
+
+    #[test]
+    fn test_badge_slots() {
+        let hero = Rect::new(100, 200, 300, 400);
+        let first = badge_rect(hero, 0);
+        assert_eq!(first.x + first.width, hero.x + hero.width - 12);
+        assert_eq!(first.y, hero.y + 12);
+        let second = badge_rect(hero, 1);
+        assert_eq!(second.x, first.x);
+        assert!(second.y > first.y + first.height);
+        // Slots wrap rather than running off the hero
+        let slots = (0..10)
+            .filter(|&slot| badge_rect(hero, slot).y == first.y)
+            .count();
+        assert!(slots > 1);
+        assert!((0..10).all(|slot| badge_rect(hero, slot).y + first.height <= hero.y + hero.height));
+    }
+}
@@ -0,0 +1,175 @@
+use std::f64::consts::PI;
+
+use opencv::{
+    core::{add_weighted, Point, Rect, Scalar, UMat, UMatTraitConst, Vector},
+    imgproc::{self, FONT_HERSHEY_SIMPLEX},
+};
+
+use crate::{movement::place_umat, text::center_text_at_rect};
+
+const PULSE_PERIOD: f64 = 1.5;
+const GLOW_LAYERS: i32 = 4;
+const GLOW_MAX_ALPHA: f64 = 0.6;
+const BADGE_WIDTH_RATIO: f64 = 1.0 / 4.0;
+const BADGE_HEIGHT_RATIO: f64 = 1.0 / 5.0;
+const BADGE_TEXT: &str = "TURN";
+const BADGE_FONT_FACE: i32 = FONT_HERSHEY_SIMPLEX;
+const BADGE_FONT_SCALE: f64 = 1.0;
+const BADGE_FONT_THICKNESS: i32 = 2;
+const BADGE_FONT_BUFFER: i32 = 10;
+const WHITE: Scalar = Scalar::new(255.0, 255.0, 255.0, 0.0);
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Side of the hero panel the badge sits on. The arrow on the badge points out of this side, so
+/// it should be the side facing the center of the scoreboard.
+#[derive(Copy, Clone)]
+pub enum BadgeSide {
+    Left,
+    Right,
+}
+
+/// Animated border and "TURN" badge drawn around the active player's hero panel
+pub struct TurnIndicator {
+    color: Scalar,
+    thickness: i32,
+    period: f64,
+}
+
+impl TurnIndicator {
+    /// # Arguments
+    /// * `color` - Color of the border, glow, and badge
+    /// * `thickness` - Thickness of the solid border; glow layers use the same thickness
+    /// * `period` - Seconds for one full pulse of the glow
+    pub fn build(color: Scalar, thickness: i32, period: f64) -> Self {
+        TurnIndicator {
+            color,
+            thickness,
+            period,
+        }
+    }
+
+    pub fn new(color: Scalar, thickness: i32) -> Self {
+        Self::build(color, thickness, PULSE_PERIOD)
+    }
+
+    /// Strength of the glow at `seconds`, between 0.0 and 1.0
+    pub fn pulse(&self, seconds: f64) -> f64 {
+        0.5 - 0.5 * (2.0 * PI * seconds / self.period).cos()
+    }
+
+    pub fn draw(&self, frame: &mut UMat, rect: Rect, seconds: f64, side: BadgeSide) -> Result<()> {
+        self.draw_glow(frame, rect, seconds)?;
+        imgproc::rectangle(
+            frame,
+            rect,
+            self.color,
+            self.thickness,
+            imgproc::LINE_8,
+            0,
+        )?;
+        self.draw_badge(frame, rect, side)
+    }
+
+    /// Layers of translucent borders inside of `rect`, fading out towards the center. Only
+    /// `rect` is blended, rather than the whole frame.
+    fn draw_glow(&self, frame: &mut UMat, rect: Rect, seconds: f64) -> Result<()> {
+        let pulse = self.pulse(seconds);
+        let mut region = UMat::new_def();
+        frame.roi(rect)?.copy_to(&mut region)?;
+        for layer in 1..=GLOW_LAYERS {
+            let inset = layer * self.thickness;
+            if 2 * inset >= rect.width || 2 * inset >= rect.height {
+                break;
+            }
+            let layer_rect = Rect::new(
+                inset,
+                inset,
+                rect.width - 2 * inset,
+                rect.height - 2 * inset,
+            );
+            let alpha = GLOW_MAX_ALPHA * pulse * (1.0 - layer as f64 / (GLOW_LAYERS + 1) as f64);
+
+            let mut overlay = region.try_clone()?;
+            imgproc::rectangle(
+                &mut overlay,
+                layer_rect,
+                self.color,
+                self.thickness,
+                imgproc::LINE_8,
+                0,
+            )?;
+            let mut blended = UMat::new_def();
+            add_weighted(&overlay, alpha, &region, 1.0 - alpha, 0., &mut blended, -1)?;
+            region = blended;
+        }
+        place_umat(&region, frame, rect)?;
+        Ok(())
+    }
+
+    fn draw_badge(&self, frame: &mut UMat, rect: Rect, side: BadgeSide) -> Result<()> {
+        let width = (rect.width as f64 * BADGE_WIDTH_RATIO) as i32;
+        let height = (rect.height as f64 * BADGE_HEIGHT_RATIO) as i32;
+        let arrow_width = height.div_euclid(2);
+
+        let (badge_rect, arrow) = match side {
+            BadgeSide::Left => {
+                let badge_rect = Rect::new(rect.x + arrow_width, rect.y, width, height);
+                let arrow = [
+                    Point::new(badge_rect.x, badge_rect.y),
+                    Point::new(rect.x, badge_rect.y + height.div_euclid(2)),
+                    Point::new(badge_rect.x, badge_rect.y + height),
+                ];
+                (badge_rect, arrow)
+            }
+            BadgeSide::Right => {
+                let badge_rect =
+                    Rect::new(rect.x + rect.width - arrow_width - width, rect.y, width, height);
+                let arrow = [
+                    Point::new(badge_rect.x + width, badge_rect.y),
+                    Point::new(rect.x + rect.width, badge_rect.y + height.div_euclid(2)),
+                    Point::new(badge_rect.x + width, badge_rect.y + height),
+                ];
+                (badge_rect, arrow)
+            }
+        };
+
+        imgproc::rectangle(frame, badge_rect, self.color, -1, imgproc::LINE_8, 0)?;
+        imgproc::fill_convex_poly(
+            frame,
+            &Vector::<Point>::from_slice(&arrow),
+            self.color,
+            imgproc::LINE_8,
+            0,
+        )?;
+        center_text_at_rect(
+            frame,
+            BADGE_TEXT,
+            BADGE_FONT_FACE,
+            BADGE_FONT_SCALE,
+            WHITE,
+            BADGE_FONT_THICKNESS,
+            badge_rect,
+            BADGE_FONT_BUFFER,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use opencv::core::Scalar;
+
+    use super::TurnIndicator;
+
+    #[test]
+    fn test_pulse_bounds() {
+        let indicator = TurnIndicator::build(Scalar::new(0.0, 100.0, 255.0, 0.0), 5, 2.0);
+        assert!(indicator.pulse(0.0).abs() < 1e-9);
+        assert!((indicator.pulse(1.0) - 1.0).abs() < 1e-9);
+        for i in 0..100 {
+            let pulse = indicator.pulse(i as f64 * 0.037);
+            assert!((0.0..=1.0).contains(&pulse));
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! Phone recordings can have a variable frame rate or be stored sideways with rotation
+//! metadata. The overlay advances time by a constant increment per frame, so such videos are
+//! transcoded to a constant frame rate, upright, before rendering.
+
+use std::{path::Path, process::Command};
+
+use serde_json::Value;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Rates videos are normalized to, whichever is nearest the average
+const STANDARD_FPS: [f64; 8] = [23.976, 24.0, 25.0, 29.97, 30.0, 50.0, 59.94, 60.0];
+/// How far the average rate can be from the nominal one, as a fraction, before a video
+/// counts as variable frame rate
+const VARIABLE_TOLERANCE: f64 = 0.01;
+/// Nominal rates above this are a time base rather than a frame rate, e.g. 90000
+const MAX_FPS: f64 = 240.0;
+
+/// Timing and orientation of a video's first video stream, from ffprobe
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoFormat {
+    /// The rate the container claims
+    pub nominal_fps: f64,
+    /// Frames over duration
+    pub average_fps: f64,
+    /// Degrees the video is turned when played
+    pub rotation: i32,
+}
+
+impl VideoFormat {
+    pub fn probe(fp: &str) -> Result<Self> {
+        let output = Command::new("ffprobe")
+            .args(["-v", "error", "-select_streams", "v:0", "-show_entries"])
+            .arg("stream=r_frame_rate,avg_frame_rate:stream_tags=rotate:stream_side_data=rotation")
+            .args(["-of", "json", fp])
+            .output()?;
+        if !output.status.success() {
+            return Err(format!(
+                "ffprobe couldn't read {}: {}",
+                fp,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
+        Self::parse(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Parses ffprobe's JSON output
+    pub fn parse(json: &str) -> Result<Self> {
+        let value: Value = serde_json::from_str(json)?;
+        let stream = value["streams"]
+            .get(0)
+            .ok_or("ffprobe found no video stream")?;
+        let rate = |key: &str| {
+            stream[key]
+                .as_str()
+                .and_then(parse_rate)
+                .ok_or(format!("ffprobe gave no {}", key))
+        };
+        let nominal_fps = rate("r_frame_rate")?;
+        let average_fps = rate("avg_frame_rate").unwrap_or(nominal_fps);
+
+        // Newer ffmpeg puts rotation in a display matrix, older in a tag
+        let side_data_rotation = stream["side_data_list"].as_array().and_then(|list| {
+            list.iter()
+                .find_map(|side_data| side_data["rotation"].as_f64())
+        });
+        let tag_rotation = stream["tags"]["rotate"]
+            .as_str()
+            .and_then(|rotate| rotate.parse::<f64>().ok());
+        let rotation = side_data_rotation.or(tag_rotation).unwrap_or(0.0) as i32;
+
+        Ok(VideoFormat {
+            nominal_fps,
+            average_fps,
+            rotation: rotation.rem_euclid(360),
+        })
+    }
+
+    pub fn is_variable(&self) -> bool {
+        (self.average_fps - self.nominal_fps).abs() > self.nominal_fps * VARIABLE_TOLERANCE
+    }
+
+    /// Whether the claimed rate can't be a frame rate, as with some VFR phone videos
+    pub fn is_odd_rate(&self) -> bool {
+        self.nominal_fps > MAX_FPS
+    }
+
+    pub fn needs_normalizing(&self) -> bool {
+        self.is_variable() || self.is_odd_rate() || self.rotation != 0
+    }
+
+    /// The nominal rate when it can be trusted, otherwise the standard rate nearest the average
+    pub fn target_fps(&self) -> f64 {
+        if !self.is_variable() && !self.is_odd_rate() {
+            return self.nominal_fps;
+        }
+        STANDARD_FPS
+            .iter()
+            .copied()
+            .min_by(|a, b| {
+                (a - self.average_fps)
+                    .abs()
+                    .total_cmp(&(b - self.average_fps).abs())
+            })
+            .unwrap()
+    }
+
+    /// Transcodes `fp` to `output_fp` at a constant `target_fps`, turned upright. The audio is
+    /// copied as it is.
+    pub fn normalize(&self, fp: &str, output_fp: &Path) -> Result<()> {
+        // ffmpeg applies rotation metadata itself when it re-encodes
+        let status = Command::new("ffmpeg")
+            .args(["-y", "-v", "error", "-i", fp])
+            .args(["-vf", &format!("fps={}", self.target_fps())])
+            .args(["-c:v", "libx264", "-preset", "veryfast", "-crf", "18"])
+            .args(["-c:a", "copy"])
+            .arg(output_fp)
+            .status()?;
+        if !status.success() {
+            return Err(format!("ffmpeg couldn't normalize {}", fp).into());
+        }
+        Ok(())
+    }
+}
+
+/// Parses a rate such as "30000/1001" or "25"
+fn parse_rate(rate: &str) -> Option<f64> {
+    let rate = match rate.split_once('/') {
+        Some((num, den)) => num.parse::<f64>().ok()? / den.parse::<f64>().ok()?,
+        None => rate.parse().ok()?,
+    };
+    (rate.is_finite() && rate > 0.0).then_some(rate)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_constant() -> Result<()> {
+        let format = VideoFormat::parse(
+            r#"{"streams": [{"r_frame_rate": "30000/1001", "avg_frame_rate": "30000/1001"}]}"#,
+        )?;
+        assert!((format.nominal_fps - 29.97).abs() < 0.01);
+        assert_eq!(format.rotation, 0);
+        assert!(!format.needs_normalizing());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unusual_constant_rate_is_kept() -> Result<()> {
+        let format = VideoFormat::parse(
+            r#"{"streams": [{"r_frame_rate": "10/1", "avg_frame_rate": "10/1"}]}"#,
+        )?;
+        assert!(!format.needs_normalizing());
+        assert_eq!(format.target_fps(), 10.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_phone_recording() -> Result<()> {
+        let format = VideoFormat::parse(
+            r#"{"streams": [{
+                "r_frame_rate": "90000/1",
+                "avg_frame_rate": "3573000/119137",
+                "side_data_list": [{"side_data_type": "Display Matrix", "rotation": -90}]
+            }]}"#,
+        )?;
+        assert!(format.is_variable());
+        assert!(format.is_odd_rate());
+        assert_eq!(format.rotation, 270);
+        assert_eq!(format.target_fps(), 30.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_rotate_tag() -> Result<()> {
+        let format = VideoFormat::parse(
+            r#"{"streams": [{"r_frame_rate": "25/1", "avg_frame_rate": "25/1", "tags": {"rotate": "90"}}]}"#,
+        )?;
+        assert_eq!(format.rotation, 90);
+        assert!(format.needs_normalizing());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_rate() {
+        assert_eq!(parse_rate("25/1"), Some(25.0));
+        assert_eq!(parse_rate("60"), Some(60.0));
+        assert_eq!(parse_rate("0/0"), None);
+    }
+}
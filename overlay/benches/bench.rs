@@ -1,12 +1,72 @@
 use std::time::Duration;
 
-use criterion::{criterion_group, criterion_main, Criterion};
-use lib::relative_roi::RelativeRoi;
-use opencv::core::{Size, UMat};
-use overlay::hero_display::DisplayHeroManager;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use lib::{annotation::Event, image::load_image, relative_roi::RelativeRoi, time_tick::TimeTick};
+use opencv::core::{Rect, Scalar, Size, UMat, CV_8UC3};
+use overlay::{hero_display::DisplayHeroManager, render::CardDisplayManager};
 
 const HERO1_NAME: &str = "Maxx 'The Hype' Nitro";
 const HERO2_NAME: &str = "Rhinar, Reckless Rampage";
+const CARD_BACK_FP: &str = "data/cardback.png";
+/// Not a real card, so the placeholder is shown rather than anything downloaded
+const CARD_NAME: &str = "Benchmark Card";
+/// Plenty for the one card, so it's only loaded once
+const CARD_CACHE_BUDGET: u64 = 64 << 20;
+
+/// Times at which the card display is ticked to reach a phase, then the time of the measured
+/// tick. A card is queued before the first tick and `zoom` is queued before the tick at
+/// `zoom_at`.
+struct PhaseSetup {
+    name: &'static str,
+    ticks: &'static [f64],
+    zoom_at: Option<f64>,
+    measured: f64,
+}
+
+const PHASES: [PhaseSetup; 7] = [
+    PhaseSetup {
+        name: "sleep",
+        ticks: &[],
+        zoom_at: None,
+        measured: 0.0,
+    },
+    PhaseSetup {
+        name: "card_back_rotate_out",
+        ticks: &[0.0],
+        zoom_at: None,
+        measured: 0.3,
+    },
+    PhaseSetup {
+        name: "card_front_rotate_in",
+        ticks: &[0.0, 0.8],
+        zoom_at: None,
+        measured: 1.1,
+    },
+    PhaseSetup {
+        name: "display",
+        ticks: &[0.0, 0.8, 1.6],
+        zoom_at: None,
+        measured: 2.0,
+    },
+    PhaseSetup {
+        name: "card_front_rotate_out",
+        ticks: &[0.0, 0.8, 1.6, 7.7, 19.8],
+        zoom_at: None,
+        measured: 20.0,
+    },
+    PhaseSetup {
+        name: "zoom_in",
+        ticks: &[0.0, 0.8, 1.6, 2.0],
+        zoom_at: Some(2.0),
+        measured: 3.0,
+    },
+    PhaseSetup {
+        name: "zoom_display",
+        ticks: &[0.0, 0.8, 1.6, 2.0, 4.1],
+        zoom_at: Some(2.0),
+        measured: 5.0,
+    },
+];
 
 fn display_heroes_for(frames: u64) {
     let mut dhm = DisplayHeroManager::new_def(HERO1_NAME, HERO2_NAME).unwrap();
@@ -27,5 +87,81 @@ pub fn display_heroes_benchmark(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, display_heroes_benchmark);
+fn card_display_in(phase: &PhaseSetup, card_back: &UMat, card_rect: &Rect) -> CardDisplayManager {
+    let mut frame = blank_frame();
+    let frame_rect = Rect::new(0, 0, 1920, 1080);
+    let mut manager =
+        CardDisplayManager::new(card_rect, card_back, &TimeTick::new(), CARD_CACHE_BUDGET);
+    if !phase.ticks.is_empty() {
+        manager.add_card_to_queue(Event::Card {
+            name: CARD_NAME.to_string(),
+            pitch: None,
+        });
+    }
+    for &t in phase.ticks {
+        if phase.zoom_at == Some(t) {
+            manager.queue_zoom(Event::Zoom {
+                name: None,
+                pitch: None,
+            });
+        }
+        manager
+            .tick(TimeTick::from_secs_f64(t), &mut frame, &frame_rect)
+            .unwrap();
+    }
+    manager
+}
+
+fn blank_frame() -> UMat {
+    UMat::new_size_with_default_def(Size::new(1920, 1080), CV_8UC3, Scalar::all(0.0)).unwrap()
+}
+
+pub fn card_display_benchmark(c: &mut Criterion) {
+    // Data files are found relative to the workspace root
+    std::env::set_current_dir(
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .unwrap(),
+    )
+    .unwrap();
+    let card_rect = Rect::new(19, 560, 365, 510);
+    let mut card_back = UMat::new_def();
+    opencv::imgproc::resize(
+        &load_image(CARD_BACK_FP).unwrap(),
+        &mut card_back,
+        card_rect.size(),
+        0.0,
+        0.0,
+        opencv::imgproc::INTER_AREA,
+    )
+    .unwrap();
+    let frame_rect = Rect::new(0, 0, 1920, 1080);
+
+    let mut group = c.benchmark_group("card_display_tick");
+    for phase in PHASES.iter() {
+        group.bench_function(phase.name, |b| {
+            b.iter_batched(
+                || {
+                    (
+                        card_display_in(phase, &card_back, &card_rect),
+                        blank_frame(),
+                    )
+                },
+                |(mut manager, mut frame)| {
+                    manager
+                        .tick(
+                            TimeTick::from_secs_f64(phase.measured),
+                            &mut frame,
+                            &frame_rect,
+                        )
+                        .unwrap()
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, display_heroes_benchmark, card_display_benchmark);
 criterion_main!(benches);
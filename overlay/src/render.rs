@@ -0,0 +1,2835 @@
+use clap::{Parser, ValueEnum};
+use indicatif::ProgressBar;
+use tracing::{debug, debug_span, info, warn};
+
+use lib::{
+    annotation::{Annotation, Event, Format, MatchSetup, PlayerInfo},
+    annotation_store, assets,
+    audio_bed::{AudioBed, MusicBed, DUCK_RATIO, DUCK_THRESHOLD, MUSIC_BED_VOLUME},
+    background::{hex_color, parse_hex_color, parse_palette, Background, Palette},
+    card::{CardDB, CardData, CardImageDB},
+    card_display::{
+        card_grid, CardPanel, QueuePolicy, Reveal, Zoom, ZoomSettings, ZoomTarget, GRID_CARDS,
+        QUEUE_LIMIT, ZOOM_DISPLAY, ZOOM_SCALE, ZOOM_TIME,
+    },
+    celebration::Celebration,
+    fade::{convert_alpha_to_white, remove_white_corners},
+    gpu::{self, GpuMode},
+    image::{load_image, load_image_unchanged, FullArtHeroManager},
+    intro::{generate_intro, generate_outro, HeroStats, VideoCapLooperAdj, INTRO_TIME},
+    letterbox::{detect_video_content, letterbox, Fill},
+    life_tracker::LifeTracker,
+    logging::LogArgs,
+    lowerthird::LowerThird,
+    match_state::MatchState,
+    memory::{format_bytes, parse_bytes, ImageCache, MemoryMonitor},
+    movement::{place_umat, Reparameterization},
+    perspective::Perspective,
+    profile::Profiler,
+    relative_roi::{center_offset, HorizontalPartition, RelativeRoi, VerticalPartition},
+    rotate::REMOVAL_COLOR,
+    text::{center_text_at_rect, center_text_at_rel, put_text_block},
+    time_tick::TimeTick,
+    token::TokenBadge,
+    turn_indicator::{BadgeSide, TurnIndicator},
+    video_format::VideoFormat,
+};
+use opencv::{
+    core::{
+        self, flip, Point, Rect, Scalar, Size, UMat, UMatTrait, UMatTraitConst, CV_8U, CV_8UC3,
+    },
+    imgproc::{
+        self, cvt_color_def, COLOR_RGBA2RGB, FONT_HERSHEY_SCRIPT_COMPLEX, FONT_HERSHEY_SIMPLEX,
+        LINE_8,
+    },
+    videoio::{
+        self, VideoCapture, VideoCaptureTrait, VideoCaptureTraitConst, VideoWriter,
+        VideoWriterTrait, CAP_PROP_FRAME_COUNT, CAP_PROP_POS_FRAMES,
+    },
+};
+use std::{
+    borrow::BorrowMut,
+    collections::VecDeque,
+    error,
+    path::Path,
+    process::{Command, Stdio},
+    time::Instant,
+};
+use tempfile::NamedTempFile;
+
+// Card display
+/// Size of a blocking card's thumbnail relative to the featured card
+const BLOCK_SCALE: f64 = 0.4;
+/// Height of the `--card-stats` strip, relative to the card
+const STATS_HEIGHT_RATIO: f64 = 1.0 / 10.0;
+const STATS_FONT_SCALE: f64 = 1.0;
+const STATS_FONT_THICKNESS: i32 = 2;
+/// Largest font of the `--rules-text` panel, which shrinks to fit long text
+const RULES_FONT_SCALE: f64 = 1.2;
+const RULES_FONT_THICKNESS: i32 = 2;
+/// How bright the video stays behind the `--rules-text` panel
+const RULES_PANEL_BRIGHTNESS: f64 = 0.25;
+/// Pips of red, yellow and blue cards, by pitch
+const PITCH_COLORS: [Scalar; 3] = [
+    Scalar::new(40.0, 40.0, 210.0, 0.0),
+    Scalar::new(0.0, 210.0, 250.0, 0.0),
+    Scalar::new(220.0, 130.0, 20.0, 0.0),
+];
+/// Queued cards whose images are loaded ahead of being shown
+const PREFETCH_CARDS: usize = 2;
+
+// Constants
+const MILLI: f64 = 1_000.0;
+const FRAME_WIDTH: i32 = 1920;
+const FRAME_HEIGHT: i32 = 1080;
+
+// Colors
+const WHITE: Scalar = Scalar::new(255.0, 255.0, 255.0, 0.0);
+
+// Frame dimensions
+const FRAME_HEIGHT_RATIO: f64 = 1.0 - (1.0 / 64.0);
+
+// Relative dimensions
+const TOP_PANEL_HEIGHT_RATIO: f64 = 1.0 / 8.0;
+const WIDTH_BUFFER_RATIO: f64 = 1.0 / 100.0;
+const HEIGHT_BUFFER_RATIO: f64 = 1.0 / 100.0;
+const SIDE_PANEL_WIDTH_RATIO: f64 = 1.0 / 5.0;
+const LIFE_SYMBOL_WIDTH_RATIO: f64 = 1.0 / 30.0;
+
+// Square dimensions
+const SQUARE_SIDE_PANEL_WIDTH_RATIO: f64 = 0.3;
+const SQUARE_LIFE_SYMBOL_WIDTH_RATIO: f64 = 1.0 / 16.0;
+
+// Portrait dimensions
+const PORTRAIT_TOP_PANEL_HEIGHT_RATIO: f64 = 1.0 / 10.0;
+const PORTRAIT_LIFE_SYMBOL_WIDTH_RATIO: f64 = 1.0 / 16.0;
+/// The inner frame starts below the player names
+const PORTRAIT_FRAME_TOP_RATIO: f64 = PORTRAIT_TOP_PANEL_HEIGHT_RATIO * 5.0 / 4.0;
+const PORTRAIT_FRAME_HEIGHT_RATIO: f64 = 0.45;
+const PORTRAIT_CARD_X_RATIO: f64 = 0.3;
+const PORTRAIT_CARD_WIDTH_RATIO: f64 = 0.45;
+const PORTRAIT_CARD_HEIGHT_RATIO: f64 = 0.35;
+
+// Fonts
+const SCORE_FONT_SCALE: f64 = 10.0;
+const SCORE_FONT_STYLE: i32 = FONT_HERSHEY_SCRIPT_COMPLEX;
+const SCORE_FONT_WIDTH: i32 = 10;
+
+const TURN_FONT_SCALE: f64 = 1.75;
+const TURN_FONT_FACE: i32 = FONT_HERSHEY_SIMPLEX;
+const TURN_FONT_THICKNESS: i32 = 3;
+/// Height of the turn counter's accent stripe, relative to the counter
+const TURN_ACCENT_HEIGHT_RATIO: f64 = 1.0 / 8.0;
+/// Plate and stripe of the turn counter once time is called
+const TIME_CALLED_BACKGROUND: Scalar = Scalar::new(0.0, 0.0, 140.0, 0.0);
+const TIME_CALLED_ACCENT: Scalar = Scalar::new(0.0, 0.0, 255.0, 0.0);
+
+// Heros
+// const HERO_OFFSET_RATIO: f64 = 1.0 / 256.0;
+const HERO_BORDER_THICKNESS: i32 = 5;
+const HERO_TURN_COLOR: Scalar = Scalar::new(0.0, 100.0, 255.0, 0.0);
+const HERO_WIN_COLOR: Scalar = Scalar::new(0.0, 255.0, 0.0, 0.0);
+const HERO_DEF_COLOR: Scalar = Scalar::new(0.0, 0.0, 0.0, 0.0);
+
+// Outro
+/// Seconds of video kept after the final win, so its celebration plays out before the outro
+const OUTRO_DELAY: f64 = 4.0;
+
+// Life
+const LIFE_TICK: f64 = 250.0;
+const LIFE_MAX_TICK_DURATION: f64 = 1_500.0;
+
+// Logo
+const LOGO_FP: &str = "data/image.png";
+const CARD_BACK_FP: &str = "data/cardback.png";
+const LIFE_FP: &'static str = "data/life.png";
+/// How far from white a pixel of the life icon can be and still show the scoreboard through it
+const LIFE_ICON_KEY_LENIENCY: f64 = 80.0;
+
+// Memory
+/// Share of the memory budget kept for card images, e.g. 8 for an eighth
+const CARD_CACHE_SHARE: u64 = 8;
+/// Seconds of video between resident memory reports
+const MEMORY_REPORT_SECS: f64 = 60.0;
+
+/// The card panel and zooms, fed the annotation's card events
+pub struct CardDisplayManager {
+    card_rect: Rect,
+    card_db: lib::card::CardImageDB,
+    panel: CardPanel,
+    /// Name and pitch of the panel's card
+    display_name: Option<(String, Option<u32>)>,
+    /// Thumbnails of the cards blocking the displayed card
+    blocks: Vec<UMat>,
+    /// Looks up the displayed card's numbers and rules text, when either is shown
+    card_data_db: Option<CardDB>,
+    /// Card data of the panel's card
+    display_data: Option<CardData>,
+    /// Show the strip of numbers under the displayed card
+    show_stats: bool,
+    /// Show the rules text beside the zoomed card
+    show_rules_text: bool,
+    /// Resized card images by name, pitch, width and height
+    cache: ImageCache<(String, Option<u32>, i32, i32)>,
+    queue: VecDeque<Event>,
+    queue_policy: QueuePolicy,
+    /// Cards that can wait before `queue_policy` steps in
+    queue_limit: usize,
+    /// A zoom is waiting for the displayed card to finish turning over
+    zoom: bool,
+    /// Zooms on screen, with the card data for their rules text when it's shown
+    zooms: Vec<(Zoom, Option<CardData>)>,
+    zoom_settings: ZoomSettings,
+}
+
+impl CardDisplayManager {
+    pub fn queue_zoom(&mut self, zoom: Event) {
+        self.queue.push_back(zoom);
+    }
+
+    pub fn queue_unzoom(&mut self) {
+        self.queue.push_back(Event::Unzoom);
+    }
+
+    pub fn add_card_to_queue(&mut self, card: Event) {
+        let mut queued_cards = self.queued_cards();
+        if self.queue_policy == QueuePolicy::Cap && queued_cards >= self.queue_limit.max(1) {
+            self.skip_oldest_card();
+            queued_cards -= 1;
+        }
+        // Cards further back are loaded when they come up, so prefetching stays bounded
+        if let (true, Event::Card { name, pitch }) = (queued_cards < PREFETCH_CARDS, &card) {
+            if let Err(e) = self.card_image(name, pitch, self.card_rect.size()) {
+                warn!("Couldn't prefetch {}: {}", name, e);
+            }
+        }
+        self.queue.push_back(card);
+    }
+
+    pub fn add_block_to_queue(&mut self, block: Event) {
+        self.queue.push_back(block);
+    }
+
+    pub fn add_pair_to_queue(&mut self, pair: Event) {
+        self.queue.push_back(pair);
+    }
+
+    fn queued_cards(&self) -> usize {
+        self.queue
+            .iter()
+            .filter(|event| matches!(event, Event::Card { .. }))
+            .count()
+    }
+
+    /// Drops the first queued card, along with the blocks and pair that would go with it
+    fn skip_oldest_card(&mut self) {
+        let Some(i) = self
+            .queue
+            .iter()
+            .position(|event| matches!(event, Event::Card { .. }))
+        else {
+            return;
+        };
+        self.queue.remove(i);
+        while let Some(Event::Block { .. } | Event::Pair { .. }) = self.queue.get(i) {
+            self.queue.remove(i);
+        }
+    }
+
+    /// Card images are cached up to `cache_budget` bytes
+    pub fn new(
+        card_rect: &Rect,
+        card_back: &UMat,
+        time_tick: &TimeTick,
+        cache_budget: u64,
+    ) -> Self {
+        let card_db = CardImageDB::init();
+        Self {
+            card_rect: card_rect.clone(),
+            card_db,
+            panel: CardPanel::new(*card_rect, card_back, *time_tick),
+            display_name: None,
+            blocks: Vec::new(),
+            card_data_db: None,
+            display_data: None,
+            show_stats: false,
+            show_rules_text: false,
+            cache: ImageCache::new(cache_budget),
+            queue: VecDeque::new(),
+            queue_policy: QueuePolicy::default(),
+            queue_limit: QUEUE_LIMIT,
+            zoom: false,
+            zooms: Vec::new(),
+            zoom_settings: ZoomSettings::default(),
+        }
+    }
+
+    /// Cards arrive in the panel with `enter` and leave with `exit`
+    fn with_reveal(mut self, enter: Reveal, exit: Reveal) -> Self {
+        self.panel = self
+            .panel
+            .with_animations(enter.animation(), exit.animation());
+        self
+    }
+
+    /// How the panel keeps up once more than `limit` cards are waiting
+    fn with_queue_policy(mut self, policy: QueuePolicy, limit: usize) -> Self {
+        self.queue_policy = policy;
+        self.queue_limit = limit;
+        self
+    }
+
+    fn with_zoom(mut self, settings: ZoomSettings) -> Self {
+        self.zoom_settings = settings;
+        self
+    }
+
+    /// Shows a strip with the displayed card's cost, pitch, power and defense under it when
+    /// `stats`, and its rules text beside it while it's zoomed when `rules_text`
+    fn with_card_data(mut self, card_db: CardDB, stats: bool, rules_text: bool) -> Self {
+        self.card_data_db = Some(card_db);
+        self.show_stats = stats;
+        self.show_rules_text = rules_text;
+        self
+    }
+
+    /// Draws the card panel, then any zooms over the video in `frame_rect`
+    pub fn tick(&mut self, time_tick: TimeTick, frame: &mut UMat, frame_rect: &Rect) -> Result<()> {
+        // Check for unzoom, which ends the latest zoom
+        if let Some(Event::Unzoom) = self.queue.front() {
+            self.queue.pop_front();
+            self.zoom = false;
+            if let Some((zoom, _)) = self
+                .zooms
+                .iter_mut()
+                .rev()
+                .find(|(zoom, _)| zoom.is_zoomed())
+            {
+                zoom.end(time_tick, &self.zoom_settings);
+            }
+        }
+
+        // Check for zoom
+        if let Some(Event::Zoom { name, pitch }) = self.queue.front().cloned() {
+            self.queue.pop_front();
+            match name {
+                // A card other than the displayed one is shown first, then zoomed
+                Some(name) if self.display_name != Some((name.clone(), pitch)) => {
+                    self.queue.push_front(Event::Zoom { name: None, pitch: None });
+                    self.queue.push_front(Event::Card { name, pitch });
+                }
+                // ignore zooms not attached to a card, or on a grid or pair
+                _ => {
+                    if self.display_name.is_some() {
+                        self.zoom = true;
+                    }
+                }
+            }
+        }
+
+        // Blocks attach to whichever card is displayed when they come up
+        while let Some(Event::Block { name, pitch }) = self.queue.front().cloned() {
+            self.queue.pop_front();
+            if self.panel.card().is_some() {
+                let size = core::Size::new(
+                    (self.card_rect.width as f64 * BLOCK_SCALE) as i32,
+                    (self.card_rect.height as f64 * BLOCK_SCALE) as i32,
+                );
+                let thumbnail = self.card_image(&name, &pitch, size)?;
+                self.blocks.push(thumbnail);
+            }
+        }
+        let queued_cards = self.queued_cards();
+        let duration = self
+            .queue_policy
+            .display_duration(queued_cards, self.queue_limit);
+        self.panel.set_display_duration(duration);
+        if self.panel.update(time_tick, !self.queue.is_empty()) {
+            if self.queue_policy == QueuePolicy::Grid && queued_cards > self.queue_limit {
+                self.load_card_grid()?;
+            } else {
+                let card = self.queue.pop_front().unwrap();
+                self.load_card_image(&card)?;
+            }
+        }
+        self.panel.draw(frame, time_tick)?;
+        if self.panel.is_showing() {
+            self.draw_blocks(frame)?;
+            self.draw_stats(frame)?;
+        }
+
+        // Zooms start once the card is showing, and carry on if the panel moves to the next card
+        if self.zoom && self.panel.is_showing() {
+            self.zoom = false;
+            if let Some(card) = self.panel.card() {
+                let rules = self.display_data.clone().filter(|_| self.show_rules_text);
+                let zoom = Zoom::new(card.try_clone()?, time_tick);
+                self.zooms.push((zoom, rules));
+            }
+        }
+        for (zoom, rules) in self.zooms.iter_mut() {
+            let held = zoom.tick(
+                time_tick,
+                frame,
+                frame_rect,
+                &self.card_rect,
+                &self.zoom_settings,
+            )?;
+            if let (Some(zoomed), Some(card)) = (held, rules.as_ref()) {
+                draw_rules_text(frame, frame_rect, zoomed, card)?;
+            }
+        }
+        self.zooms.retain(|(zoom, _)| !zoom.is_finished());
+        Ok(())
+    }
+
+    /// Stacks the block thumbnails beside the displayed card, on the right unless they
+    /// would run off the frame
+    fn draw_blocks(&self, frame: &mut UMat) -> Result<()> {
+        let mut y = self.card_rect.y;
+        for block in self.blocks.iter() {
+            let size = block.size()?;
+            if y + size.height > self.card_rect.y + self.card_rect.height {
+                break;
+            }
+            let right = self.card_rect.x + self.card_rect.width;
+            let x = if right + size.width <= frame.cols() {
+                right
+            } else {
+                self.card_rect.x - size.width
+            };
+            let rect = core::Rect::new(x, y, size.width, size.height);
+            let roi = frame.roi(rect)?;
+            let thumbnail = remove_white_corners(&roi, block)?;
+            place_umat(&thumbnail, frame, rect)?;
+            y += size.height;
+        }
+        Ok(())
+    }
+
+    /// Draws the cost, pitch pips, power and defense of the displayed card in a strip under it,
+    /// or over its bottom edge when the card reaches the bottom of the frame. Numbers the card
+    /// doesn't have are left out.
+    fn draw_stats(&self, frame: &mut UMat) -> Result<()> {
+        let Some(card) = self.display_data.as_ref().filter(|_| self.show_stats) else {
+            return Ok(());
+        };
+        let mut items = Vec::new();
+        if let Some(cost) = card.cost {
+            items.push(Some(format!("Cost {}", cost)));
+        }
+        if card.pitch.is_some() {
+            // pips are drawn in place of text
+            items.push(None);
+        }
+        if let Some(power) = card.power {
+            items.push(Some(format!("Pow {}", power)));
+        }
+        if let Some(defense) = card.defense {
+            items.push(Some(format!("Def {}", defense)));
+        }
+        if items.is_empty() {
+            return Ok(());
+        }
+
+        let height = ((self.card_rect.height as f64 * STATS_HEIGHT_RATIO) as i32).max(1);
+        let below = self.card_rect.y + self.card_rect.height;
+        let y = if below + height <= frame.rows() {
+            below
+        } else {
+            below - height
+        };
+        let strip = Rect::new(self.card_rect.x, y, self.card_rect.width, height);
+        imgproc::rectangle(frame, strip, Scalar::new(0.0, 0.0, 0.0, 0.0), -1, LINE_8, 0)?;
+
+        let width = strip.width / items.len() as i32;
+        for (i, item) in items.iter().enumerate() {
+            let cell = Rect::new(strip.x + i as i32 * width, strip.y, width, strip.height);
+            match item {
+                Some(text) => center_text_at_rect(
+                    frame,
+                    text,
+                    TURN_FONT_FACE,
+                    STATS_FONT_SCALE,
+                    WHITE,
+                    STATS_FONT_THICKNESS,
+                    cell,
+                    10,
+                )?,
+                None => Self::draw_pitch_pips(frame, cell, card.pitch.unwrap_or(0))?,
+            }
+        }
+        Ok(())
+    }
+
+    /// One pip per point of pitch, in the card's color
+    fn draw_pitch_pips(frame: &mut UMat, cell: Rect, pitch: u32) -> Result<()> {
+        let Some(color) = PITCH_COLORS.get(pitch.saturating_sub(1) as usize) else {
+            return Ok(());
+        };
+        let radius = (cell.height / 4)
+            .min(cell.width / (3 * pitch as i32 + 1))
+            .max(1);
+        let spacing = 3 * radius;
+        let first = cell.x + (cell.width - spacing * (pitch as i32 - 1)) / 2;
+        for pip in 0..pitch as i32 {
+            let center = Point::new(first + pip * spacing, cell.y + cell.height / 2);
+            imgproc::circle(frame, center, radius, *color, -1, imgproc::LINE_AA, 0)?;
+            imgproc::circle(frame, center, radius, WHITE, 1, imgproc::LINE_AA, 0)?;
+        }
+        Ok(())
+    }
+
+    fn load_card_image(&mut self, display_card: &Event) -> Result<()> {
+        let (name, pitch) = match display_card {
+            Event::Card { name, pitch } => (name.as_str(), pitch),
+            // The card a late pair goes with is already up, so it comes back beside it
+            Event::Pair { name, pitch } => match self.display_name.clone() {
+                Some((first, first_pitch)) => {
+                    return self.load_pair((first.as_str(), &first_pitch), (name, pitch))
+                }
+                None => (name.as_str(), pitch),
+            },
+            _ => return Ok(()),
+        };
+        if let Some(Event::Pair {
+            name: second,
+            pitch: second_pitch,
+        }) = self.queue.front().cloned()
+        {
+            self.queue.pop_front();
+            return self.load_pair((name, pitch), (second.as_str(), &second_pitch));
+        }
+        let img = self.card_image(name, pitch, self.card_rect.size())?;
+        self.panel.show(img);
+        self.display_name.replace((name.to_owned(), *pitch));
+        self.display_data = self
+            .card_data_db
+            .as_ref()
+            .and_then(|card_db| card_db.find(name, *pitch))
+            .cloned();
+        self.blocks.clear();
+        Ok(())
+    }
+
+    /// Shows two cards side by side in the panel
+    fn load_pair(
+        &mut self,
+        (name, pitch): (&str, &Option<u32>),
+        (second, second_pitch): (&str, &Option<u32>),
+    ) -> Result<()> {
+        let size = self.panel.pair_size();
+        let first_img = self.card_image(name, pitch, size)?;
+        let second_img = self.card_image(second, second_pitch, size)?;
+        self.panel.show_pair(first_img, second_img);
+        // Zooms, stats and blocks follow a single card
+        self.display_name = None;
+        self.display_data = None;
+        self.blocks.clear();
+        Ok(())
+    }
+
+    /// Shows the latest queued cards together, dropping the rest of the cards and blocks queued
+    fn load_card_grid(&mut self) -> Result<()> {
+        let mut cards = Vec::new();
+        self.queue.retain(|event| match event {
+            Event::Card { name, pitch } | Event::Pair { name, pitch } => {
+                cards.push((name.clone(), *pitch));
+                false
+            }
+            Event::Block { .. } => false,
+            _ => true,
+        });
+        let first = cards.len().saturating_sub(GRID_CARDS);
+        let images = cards[first..]
+            .iter()
+            .map(|(name, pitch)| self.card_image(name, pitch, self.card_rect.size()))
+            .collect::<Result<Vec<_>>>()?;
+        self.panel.show(card_grid(&images, self.card_rect.size())?);
+        self.display_name = None;
+        self.display_data = None;
+        self.blocks.clear();
+        Ok(())
+    }
+
+    /// Card image turned upright and resized to `size`
+    fn card_image(&mut self, name: &str, pitch: &Option<u32>, size: core::Size) -> Result<UMat> {
+        let key = (name.to_string(), *pitch, size.width, size.height);
+        if let Some(img) = self.cache.get(&key) {
+            return Ok(img.try_clone()?);
+        }
+        let mut img = self.card_db.load_card_image(name, pitch);
+        if img.cols() > img.rows() {
+            let mut rotated_card_image = UMat::new_def();
+            opencv::core::rotate(
+                &img,
+                &mut rotated_card_image,
+                opencv::core::ROTATE_90_CLOCKWISE,
+            )?;
+            img = rotated_card_image
+        }
+        opencv::imgproc::resize(
+            &img.clone(),
+            &mut img,
+            size,
+            0.0,
+            0.0,
+            opencv::imgproc::INTER_LINEAR,
+        )?;
+        self.cache.insert(key, img.try_clone()?);
+        Ok(img)
+    }
+}
+
+/// Writes `card`'s rules text on a panel beside `zoomed`, on whichever side of `frame_rect` has
+/// more room
+fn draw_rules_text(
+    frame: &mut UMat,
+    frame_rect: &Rect,
+    zoomed: Rect,
+    card: &CardData,
+) -> Result<()> {
+    let text = card.rules_text();
+    if text.trim().is_empty() {
+        return Ok(());
+    }
+    let margin = (frame_rect.width as f64 * WIDTH_BUFFER_RATIO) as i32;
+    let left = zoomed.x - frame_rect.x;
+    let right = frame_rect.x + frame_rect.width - (zoomed.x + zoomed.width);
+    let width = left.max(right) - 2 * margin;
+    if width <= 0 {
+        return Ok(());
+    }
+    let x = if right >= left {
+        zoomed.x + zoomed.width + margin
+    } else {
+        frame_rect.x + margin
+    };
+    let panel = Rect::new(x, zoomed.y, width, zoomed.height);
+
+    // Darken the video under the text rather than covering it
+    let roi = frame.roi(panel)?;
+    let mut dimmed = UMat::new_def();
+    roi.convert_to(&mut dimmed, -1, RULES_PANEL_BRIGHTNESS, 0.0)?;
+    dimmed.copy_to(&mut frame.roi_mut(panel)?)?;
+    put_text_block(
+        frame,
+        &format!("{}\n\n{}", card.display, text),
+        TURN_FONT_FACE,
+        RULES_FONT_SCALE,
+        WHITE,
+        RULES_FONT_THICKNESS,
+        panel,
+        2 * margin,
+    )?;
+    Ok(())
+}
+
+/// Shape of the rendered video and where everything goes in it
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Layout {
+    /// 16:9, with the card panel down the left
+    #[default]
+    Landscape,
+    /// 9:16 for shorts and reels, with the scoreboard on top and the card panel below the video
+    Portrait,
+    /// 1:1, laid out like landscape with a wider card panel
+    Square,
+}
+
+/// Which side of the frame the logo and card panel go on
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum SidePanel {
+    #[default]
+    Left,
+    Right,
+}
+
+/// Where the turn counter goes
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum TurnCounterSlot {
+    /// The top right corner of the video, beside the match score
+    #[default]
+    Frame,
+    /// Under the lives, level with the players' names
+    TopPanel,
+    Hidden,
+}
+
+/// Colors of the turn counter, as "background,text,accent" in hex RGB
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TurnCounterStyle {
+    background: Scalar,
+    text: Scalar,
+    /// Stripe along the bottom
+    accent: Scalar,
+}
+
+impl Default for TurnCounterStyle {
+    fn default() -> Self {
+        TurnCounterStyle {
+            background: Scalar::new(0.0, 0.0, 0.0, 0.0),
+            text: WHITE,
+            accent: HERO_TURN_COLOR,
+        }
+    }
+}
+
+impl std::fmt::Display for TurnCounterStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{},{},{}",
+            hex_color(self.background),
+            hex_color(self.text),
+            hex_color(self.accent)
+        )
+    }
+}
+
+impl TurnCounterStyle {
+    fn parse(value: &str) -> std::result::Result<Self, String> {
+        let colors = value
+            .split(',')
+            .map(parse_hex_color)
+            .collect::<std::result::Result<Vec<Scalar>, String>>()?;
+        match colors[..] {
+            [background, text, accent] => Ok(TurnCounterStyle {
+                background,
+                text,
+                accent,
+            }),
+            _ => Err(format!(
+                "'{}' isn't three colors like background,text,accent",
+                value
+            )),
+        }
+    }
+
+    /// Draws "Turn N", or "Turn N (+X)" tinted red once time is called, where `extra_turns` is X
+    fn draw(
+        &self,
+        frame: &mut UMat,
+        rect: Rect,
+        turn: u32,
+        extra_turns: Option<u32>,
+    ) -> Result<()> {
+        let (background, accent, text) = match extra_turns {
+            Some(extra) => (
+                TIME_CALLED_BACKGROUND,
+                TIME_CALLED_ACCENT,
+                format!("Turn {} (+{})", turn, extra),
+            ),
+            None => (self.background, self.accent, format!("Turn {}", turn)),
+        };
+        imgproc::rectangle(frame, rect, background, -1, imgproc::LINE_8, 0)?;
+        let accent_height = ((rect.height as f64 * TURN_ACCENT_HEIGHT_RATIO) as i32).max(1);
+        imgproc::rectangle(
+            frame,
+            Rect::new(
+                rect.x,
+                rect.y + rect.height - accent_height,
+                rect.width,
+                accent_height,
+            ),
+            accent,
+            -1,
+            imgproc::LINE_8,
+            0,
+        )?;
+        center_text_at_rect(
+            frame,
+            &text,
+            TURN_FONT_FACE,
+            TURN_FONT_SCALE,
+            self.text,
+            TURN_FONT_THICKNESS,
+            Rect::new(rect.x, rect.y, rect.width, rect.height - accent_height),
+            20,
+        )?;
+        Ok(())
+    }
+}
+
+/// Shapes of `--clip` for posting on social media
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum ClipPreset {
+    /// 9:16 for shorts and reels
+    #[default]
+    Shorts,
+    /// 1:1 for feeds
+    Square,
+}
+
+impl ClipPreset {
+    fn layout(&self) -> Layout {
+        match self {
+            ClipPreset::Shorts => Layout::Portrait,
+            ClipPreset::Square => Layout::Square,
+        }
+    }
+}
+
+/// Where each part of the overlay goes, relative to the frame
+struct Regions {
+    hero1: RelativeRoi,
+    hero2: RelativeRoi,
+    player1: RelativeRoi,
+    player2: RelativeRoi,
+    life1: RelativeRoi,
+    life2: RelativeRoi,
+    life_symbol: RelativeRoi,
+    innerframe: RelativeRoi,
+    logo: RelativeRoi,
+    card: RelativeRoi,
+    /// Turn counter slot in the top panel
+    turn_counter: RelativeRoi,
+    /// Player 1's hero, name and life are on the right of the scoreboard
+    player1_right: bool,
+}
+
+impl Regions {
+    /// Everything flipped left to right
+    fn mirrored(self) -> Self {
+        Regions {
+            hero1: self.hero1.mirrored(),
+            hero2: self.hero2.mirrored(),
+            player1: self.player1.mirrored(),
+            player2: self.player2.mirrored(),
+            life1: self.life1.mirrored(),
+            life2: self.life2.mirrored(),
+            life_symbol: self.life_symbol.mirrored(),
+            innerframe: self.innerframe.mirrored(),
+            logo: self.logo.mirrored(),
+            card: self.card.mirrored(),
+            turn_counter: self.turn_counter.mirrored(),
+            player1_right: !self.player1_right,
+        }
+    }
+
+    /// Player 1's hero, name and life swapped with player 2's
+    fn swap_players(self) -> Self {
+        Regions {
+            hero1: self.hero2,
+            hero2: self.hero1,
+            player1: self.player2,
+            player2: self.player1,
+            life1: self.life2,
+            life2: self.life1,
+            player1_right: !self.player1_right,
+            ..self
+        }
+    }
+}
+
+impl Layout {
+    fn frame_size(&self) -> Size {
+        match self {
+            Layout::Landscape => Size::new(FRAME_WIDTH, FRAME_HEIGHT),
+            Layout::Portrait => Size::new(FRAME_HEIGHT, FRAME_WIDTH),
+            Layout::Square => Size::new(FRAME_HEIGHT, FRAME_HEIGHT),
+        }
+    }
+
+    /// With the panel on the right, everything is mirrored but the players, who stay in order
+    /// unless `mirror_players`
+    fn regions(&self, side_panel: SidePanel, mirror_players: bool) -> Result<Regions> {
+        let regions = match self {
+            Layout::Landscape => Self::side_panel(
+                SIDE_PANEL_WIDTH_RATIO,
+                TOP_PANEL_HEIGHT_RATIO,
+                LIFE_SYMBOL_WIDTH_RATIO,
+            ),
+            Layout::Portrait => Self::portrait(),
+            Layout::Square => Self::side_panel(
+                SQUARE_SIDE_PANEL_WIDTH_RATIO,
+                TOP_PANEL_HEIGHT_RATIO,
+                SQUARE_LIFE_SYMBOL_WIDTH_RATIO,
+            ),
+        }?;
+        let regions = match side_panel {
+            SidePanel::Left => regions,
+            SidePanel::Right => regions.mirrored().swap_players(),
+        };
+        Ok(if mirror_players {
+            regions.swap_players()
+        } else {
+            regions
+        })
+    }
+
+    /// The scoreboard across the top, `top` high, and the logo and card panel down the left,
+    /// `side` wide
+    fn side_panel(side: f64, top: f64, life_symbol: f64) -> Result<Regions> {
+        // Top panel
+        let hero1_rel_roi = RelativeRoi::build(
+            side,
+            0.0,
+            (1.0 / 3.0) * (1.0 - side),
+            top,
+            WIDTH_BUFFER_RATIO,
+            0.0,
+            HEIGHT_BUFFER_RATIO,
+            0.0,
+        )?;
+        let hero2_rel_roi = RelativeRoi::build(
+            side + (2.0 / 3.0) * (1.0 - side),
+            0.0,
+            (1.0 / 3.0) * (1.0 - side),
+            top,
+            0.0,
+            WIDTH_BUFFER_RATIO,
+            HEIGHT_BUFFER_RATIO,
+            0.0,
+        )?;
+        let player1_rel_roi = RelativeRoi::build(
+            side,
+            top,
+            (1.0 / 3.0) * (1.0 - side),
+            top / 4.0,
+            WIDTH_BUFFER_RATIO,
+            0.0,
+            0.0,
+            0.0,
+        )?;
+        let player2_rel_roi = RelativeRoi::build(
+            side + (2.0 / 3.0) * (1.0 - side),
+            top,
+            (1.0 / 3.0) * (1.0 - side),
+            top / 4.0,
+            0.0,
+            WIDTH_BUFFER_RATIO,
+            0.0,
+            0.0,
+        )?;
+        let life1_rel_roi = RelativeRoi::build(
+            side + (1.0 / 3.0) * (1.0 - side),
+            0.0,
+            (1.0 / 6.0) * (1.0 - side),
+            top,
+            0.0,
+            WIDTH_BUFFER_RATIO,
+            HEIGHT_BUFFER_RATIO,
+            0.0,
+        )?;
+        let life2_rel_roi = RelativeRoi::build(
+            side + 0.5 * (1.0 - side),
+            0.0,
+            (1.0 / 6.0) * (1.0 - side),
+            top,
+            WIDTH_BUFFER_RATIO,
+            0.0,
+            HEIGHT_BUFFER_RATIO,
+            0.0,
+        )?;
+        let turn_counter_rel_roi = RelativeRoi::build(
+            side + (1.0 / 3.0) * (1.0 - side),
+            top,
+            (1.0 / 3.0) * (1.0 - side),
+            top / 4.0,
+            WIDTH_BUFFER_RATIO,
+            WIDTH_BUFFER_RATIO,
+            0.0,
+            0.0,
+        )?;
+        let life_symbol_rel_roi = RelativeRoi::build(
+            side + (1.0 - side) * 0.5 - life_symbol / 2.0,
+            0.0,
+            life_symbol,
+            top,
+            0.0,
+            0.0,
+            HEIGHT_BUFFER_RATIO,
+            0.0,
+        )?;
+
+        // Inner frame
+        let innerframe_rel_roi = RelativeRoi::build(
+            side,
+            top,
+            1.0 - side,
+            1.0 - top,
+            WIDTH_BUFFER_RATIO / 2.0,
+            WIDTH_BUFFER_RATIO,
+            HEIGHT_BUFFER_RATIO,
+            HEIGHT_BUFFER_RATIO,
+        )?;
+
+        // Side panel
+        let logo_rel_roi = RelativeRoi::build_as_partition(
+            0.0,
+            0.0,
+            side,
+            4.0 / 9.0,
+            Some(WIDTH_BUFFER_RATIO),
+            Some(2.0 * HEIGHT_BUFFER_RATIO),
+            Some(HorizontalPartition::Left),
+            Some(VerticalPartition::Top),
+        )?;
+        let card_rel_roi = RelativeRoi::build_as_partition(
+            0.0,
+            4.0 / 9.0,
+            side,
+            5.0 / 9.0,
+            Some(WIDTH_BUFFER_RATIO),
+            Some(2.0 * HEIGHT_BUFFER_RATIO),
+            Some(HorizontalPartition::Left),
+            Some(VerticalPartition::Bottom),
+        )?;
+
+        Ok(Regions {
+            hero1: hero1_rel_roi,
+            hero2: hero2_rel_roi,
+            player1: player1_rel_roi,
+            player2: player2_rel_roi,
+            life1: life1_rel_roi,
+            life2: life2_rel_roi,
+            life_symbol: life_symbol_rel_roi,
+            innerframe: innerframe_rel_roi,
+            logo: logo_rel_roi,
+            card: card_rel_roi,
+            turn_counter: turn_counter_rel_roi,
+            player1_right: false,
+        })
+    }
+
+    fn portrait() -> Result<Regions> {
+        // Top panel, across the whole width
+        let hero1_rel_roi = RelativeRoi::build(
+            0.0,
+            0.0,
+            1.0 / 3.0,
+            PORTRAIT_TOP_PANEL_HEIGHT_RATIO,
+            WIDTH_BUFFER_RATIO,
+            0.0,
+            HEIGHT_BUFFER_RATIO,
+            0.0,
+        )?;
+        let hero2_rel_roi = RelativeRoi::build(
+            2.0 / 3.0,
+            0.0,
+            1.0 / 3.0,
+            PORTRAIT_TOP_PANEL_HEIGHT_RATIO,
+            0.0,
+            WIDTH_BUFFER_RATIO,
+            HEIGHT_BUFFER_RATIO,
+            0.0,
+        )?;
+        let player1_rel_roi = RelativeRoi::build(
+            0.0,
+            PORTRAIT_TOP_PANEL_HEIGHT_RATIO,
+            1.0 / 3.0,
+            PORTRAIT_TOP_PANEL_HEIGHT_RATIO / 4.0,
+            WIDTH_BUFFER_RATIO,
+            0.0,
+            0.0,
+            0.0,
+        )?;
+        let player2_rel_roi = RelativeRoi::build(
+            2.0 / 3.0,
+            PORTRAIT_TOP_PANEL_HEIGHT_RATIO,
+            1.0 / 3.0,
+            PORTRAIT_TOP_PANEL_HEIGHT_RATIO / 4.0,
+            0.0,
+            WIDTH_BUFFER_RATIO,
+            0.0,
+            0.0,
+        )?;
+        let life1_rel_roi = RelativeRoi::build(
+            1.0 / 3.0,
+            0.0,
+            1.0 / 6.0,
+            PORTRAIT_TOP_PANEL_HEIGHT_RATIO,
+            0.0,
+            WIDTH_BUFFER_RATIO,
+            HEIGHT_BUFFER_RATIO,
+            0.0,
+        )?;
+        let life2_rel_roi = RelativeRoi::build(
+            0.5,
+            0.0,
+            1.0 / 6.0,
+            PORTRAIT_TOP_PANEL_HEIGHT_RATIO,
+            WIDTH_BUFFER_RATIO,
+            0.0,
+            HEIGHT_BUFFER_RATIO,
+            0.0,
+        )?;
+        let life_symbol_rel_roi = RelativeRoi::build(
+            0.5 - PORTRAIT_LIFE_SYMBOL_WIDTH_RATIO / 2.0,
+            0.0,
+            PORTRAIT_LIFE_SYMBOL_WIDTH_RATIO,
+            PORTRAIT_TOP_PANEL_HEIGHT_RATIO,
+            0.0,
+            0.0,
+            HEIGHT_BUFFER_RATIO,
+            0.0,
+        )?;
+
+        let turn_counter_rel_roi = RelativeRoi::build(
+            1.0 / 3.0,
+            PORTRAIT_TOP_PANEL_HEIGHT_RATIO,
+            1.0 / 3.0,
+            PORTRAIT_TOP_PANEL_HEIGHT_RATIO / 4.0,
+            WIDTH_BUFFER_RATIO,
+            WIDTH_BUFFER_RATIO,
+            0.0,
+            0.0,
+        )?;
+
+        // Inner frame, below the player names
+        let innerframe_rel_roi = RelativeRoi::build(
+            0.0,
+            PORTRAIT_FRAME_TOP_RATIO,
+            1.0,
+            PORTRAIT_FRAME_HEIGHT_RATIO,
+            WIDTH_BUFFER_RATIO,
+            WIDTH_BUFFER_RATIO,
+            HEIGHT_BUFFER_RATIO,
+            HEIGHT_BUFFER_RATIO,
+        )?;
+
+        // Bottom panel, with room right of the card for blocks and below it for the card
+        // turning over
+        let bottom = PORTRAIT_FRAME_TOP_RATIO + PORTRAIT_FRAME_HEIGHT_RATIO;
+        let logo_rel_roi = RelativeRoi::build_as_partition(
+            0.0,
+            bottom,
+            PORTRAIT_CARD_X_RATIO,
+            PORTRAIT_CARD_HEIGHT_RATIO,
+            Some(WIDTH_BUFFER_RATIO),
+            Some(2.0 * HEIGHT_BUFFER_RATIO),
+            Some(HorizontalPartition::Left),
+            Some(VerticalPartition::Top),
+        )?;
+        let card_rel_roi = RelativeRoi::build(
+            PORTRAIT_CARD_X_RATIO,
+            bottom,
+            PORTRAIT_CARD_WIDTH_RATIO,
+            PORTRAIT_CARD_HEIGHT_RATIO,
+            WIDTH_BUFFER_RATIO,
+            WIDTH_BUFFER_RATIO,
+            2.0 * HEIGHT_BUFFER_RATIO,
+            0.0,
+        )?;
+
+        Ok(Regions {
+            hero1: hero1_rel_roi,
+            hero2: hero2_rel_roi,
+            player1: player1_rel_roi,
+            player2: player2_rel_roi,
+            life1: life1_rel_roi,
+            life2: life2_rel_roi,
+            life_symbol: life_symbol_rel_roi,
+            innerframe: innerframe_rel_roi,
+            logo: logo_rel_roi,
+            card: card_rel_roi,
+            turn_counter: turn_counter_rel_roi,
+            player1_right: false,
+        })
+    }
+}
+
+// Change the alias to use `Box<dyn error::Error>`.
+type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
+
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct Cli {
+    #[arg(short, long)]
+    video_file: String,
+
+    #[arg(short, long)]
+    card_file: String,
+
+    #[arg(short, long)]
+    timeout: Option<u64>,
+
+    #[arg(short, long, action)]
+    debug: bool,
+
+    #[arg(long, action)]
+    skip_intro: bool,
+
+    /// Music mixed under the intro, fading out as the match audio comes in
+    #[arg(long, conflicts_with_all = ["skip_intro", "clip"])]
+    intro_music: Option<String>,
+
+    /// Volume of the --intro-music, where 1 leaves it as it is
+    #[arg(long, default_value_t = 1.0, requires = "intro_music")]
+    intro_music_volume: f64,
+
+    /// Music looped under the whole match, ducking while the match audio is loud. For footage
+    /// whose venue audio is unusable.
+    #[arg(long, conflicts_with = "intro_music")]
+    music_bed: Option<String>,
+
+    /// Volume of the --music-bed while the match is quiet, where 1 leaves it as it is
+    #[arg(long, default_value_t = MUSIC_BED_VOLUME, requires = "music_bed")]
+    music_bed_volume: f64,
+
+    /// Volume of the match audio under the --music-bed, 0 to leave only the music
+    #[arg(long, default_value_t = 1.0, requires = "music_bed")]
+    match_volume: f64,
+
+    /// Loudness of the match audio, from 0.001 to 1, above which the --music-bed ducks
+    #[arg(long, default_value_t = DUCK_THRESHOLD, requires = "music_bed")]
+    duck_threshold: f64,
+
+    /// How hard the --music-bed ducks above --duck-threshold, from 1 to 20, e.g. 8 for 8:1
+    #[arg(long, default_value_t = DUCK_RATIO, requires = "music_bed")]
+    duck_ratio: f64,
+
+    /// Show each hero's class and life, and each player's seed or record, in the intro
+    #[arg(long, action, conflicts_with_all = ["skip_intro", "clip"])]
+    intro_stats: bool,
+
+    /// Player 1's seed or record, e.g. "3" or "5-1". Defaults to the annotation's.
+    #[arg(long, requires = "intro_stats")]
+    record1: Option<String>,
+
+    /// Player 2's seed or record. Defaults to the annotation's.
+    #[arg(long, requires = "intro_stats")]
+    record2: Option<String>,
+
+    /// Keep the video running after the final win instead of ending with the outro
+    #[arg(long, action)]
+    skip_outro: bool,
+
+    /// Lines shown at the end of the outro, e.g. commentators and social handles
+    #[arg(long, conflicts_with = "skip_outro")]
+    credits: Vec<String>,
+
+    #[arg(long)]
+    crop_left: Option<f64>,
+
+    #[arg(long)]
+    crop_right: Option<f64>,
+
+    #[arg(long)]
+    crop_top: Option<f64>,
+
+    #[arg(long)]
+    crop_bottom: Option<f64>,
+
+    /// Find the picture inside any black bars and fit it in the frame without stretching it,
+    /// instead of cropping by the --crop options
+    #[arg(long, action, conflicts_with_all = ["crop_left", "crop_right", "crop_top", "crop_bottom"])]
+    auto_fit: bool,
+
+    /// What fills the space beside the footage with --auto-fit
+    #[arg(long, value_enum, default_value_t = Fill::Black)]
+    fit_fill: Fill,
+
+    /// Colors of the animated background as "top,bottom,lines" in hex RGB, e.g. to match an
+    /// event's, like "1a2b4c,0a1020,4f7fbf"
+    #[arg(long, value_parser = parse_palette, default_value_t = Palette::default())]
+    background_colors: Palette,
+
+    /// Put the logo and card panel on the right instead of the left
+    #[arg(long, value_enum, default_value_t = SidePanel::Left)]
+    side_panel: SidePanel,
+
+    /// Image shown between the life totals. White or transparent parts show the scoreboard
+    /// through. Defaults to data/life.png.
+    #[arg(long)]
+    life_icon: Option<String>,
+
+    /// Color the boxes behind the life totals are tinted, in hex RGB
+    #[arg(long, value_parser = parse_hex_color, default_value = "000000")]
+    life_box_color: Scalar,
+
+    /// How much the life boxes are tinted, from 0 for not at all to 1 for solid
+    #[arg(long, default_value_t = 0.5)]
+    life_box_opacity: f64,
+
+    /// Radius of the life boxes' corners, in pixels
+    #[arg(long, default_value_t = 0)]
+    life_box_radius: i32,
+
+    /// Show the featured card's cost, pitch, power and defense in a strip under it
+    #[arg(long, action)]
+    card_stats: bool,
+
+    /// Write out the zoomed card's rules text beside it, for when the card in the video is too
+    /// small to read
+    #[arg(long, action)]
+    rules_text: bool,
+
+    /// How cards arrive in the card panel
+    #[arg(long, value_enum, default_value_t = Reveal::Rotate)]
+    card_enter: Reveal,
+
+    /// How cards leave the card panel
+    #[arg(long, value_enum, default_value_t = Reveal::Rotate)]
+    card_exit: Reveal,
+
+    /// What happens when cards are entered faster than the card panel shows them
+    #[arg(long, value_enum, default_value_t = QueuePolicy::Wait)]
+    card_queue: QueuePolicy,
+
+    /// Cards that can wait for the card panel before --card-queue steps in
+    #[arg(long, default_value_t = QUEUE_LIMIT)]
+    card_queue_limit: usize,
+
+    /// How much bigger a zoomed card gets than the card panel's
+    #[arg(long, default_value_t = ZOOM_SCALE)]
+    zoom_scale: f64,
+
+    /// Seconds a zoom takes to zoom in, and again to zoom out
+    #[arg(long, default_value_t = ZOOM_TIME)]
+    zoom_time: f64,
+
+    /// Seconds a zoom is held before zooming out, unless an unzoom comes first
+    #[arg(long, default_value_t = ZOOM_DISPLAY)]
+    zoom_hold: f64,
+
+    /// Where zoomed cards go in the video: "center", or the card's center as X,Y fractions of
+    /// the video's width and height, e.g. "0.7,0.5"
+    #[arg(long, value_parser = ZoomTarget::parse, default_value_t = ZoomTarget::default())]
+    zoom_position: ZoomTarget,
+
+    /// Where the turn counter goes, or hidden
+    #[arg(long, value_enum, default_value_t = TurnCounterSlot::Frame)]
+    turn_counter: TurnCounterSlot,
+
+    /// Colors of the turn counter as "background,text,accent" in hex RGB
+    #[arg(long, value_parser = TurnCounterStyle::parse, default_value_t = TurnCounterStyle::default())]
+    turn_counter_colors: TurnCounterStyle,
+
+    /// Put player 1 on the right of the scoreboard and player 2 on the left
+    #[arg(long, action)]
+    mirror_players: bool,
+
+    /// Shape of the video: landscape, or portrait (9:16) for shorts and reels
+    #[arg(long, value_enum, default_value_t = Layout::Landscape)]
+    layout: Layout,
+
+    /// Format being played, e.g. blitz, labelled in the corner of the video. Defaults to the
+    /// annotation's.
+    #[arg(long)]
+    format: Option<Format>,
+
+    /// Corners of the play area saved by `annotate calibrate`. Frames are straightened so the
+    /// play area is a rectangle before they're cropped or fitted.
+    #[arg(long)]
+    perspective: Option<String>,
+
+    #[arg(long)]
+    output_file: Option<String>,
+
+    /// Only render from this many seconds into the video, without the intro or audio.
+    /// Used by --chunks for every chunk but the first.
+    #[arg(long)]
+    start: Option<f64>,
+
+    /// Stop rendering this many seconds into the video, without adding audio. Used by --chunks.
+    #[arg(long)]
+    end: Option<f64>,
+
+    /// Render only this part of the video, e.g. `--clip 12:30 13:05`, with just the lives and
+    /// cards drawn over it, for posting on its own
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["START", "END"],
+        value_parser = parse_time,
+        conflicts_with_all = ["start", "end", "chunks", "layout"]
+    )]
+    clip: Option<Vec<f64>>,
+
+    /// Shape of a --clip
+    #[arg(long, value_enum, default_value_t = ClipPreset::Shorts, requires = "clip")]
+    preset: ClipPreset,
+
+    /// Split the video into this many chunks rendered at once by separate processes, then
+    /// join them without re-encoding
+    #[arg(long)]
+    chunks: Option<usize>,
+
+    /// Print how long each stage of the render loop took once the video is done
+    #[arg(long, action)]
+    profile: bool,
+
+    /// Resident memory to stay within, e.g. "4G". An eighth of it is kept for card images.
+    #[arg(long, value_parser = parse_bytes, default_value = "4G")]
+    memory_budget: u64,
+
+    /// Whether to render with OpenCL. "auto" uses it when it's faster than the CPU.
+    #[arg(long, value_enum, default_value_t = GpuMode::Auto)]
+    gpu: GpuMode,
+
+    #[command(flatten)]
+    log: LogArgs,
+}
+
+impl Cli {
+    /// Logging to set up before `run`, which --debug turns up and writes to log.txt
+    pub fn log_args(&self) -> LogArgs {
+        let mut log = self.log.clone();
+        if self.debug {
+            log.log_level = "debug".to_string();
+            log.log_file.get_or_insert("log.txt".to_string());
+        }
+        log
+    }
+}
+
+fn parse_time(text: &str) -> std::result::Result<f64, String> {
+    TimeTick::parse(text)
+        .map(|time| time.as_f64())
+        .ok_or_else(|| format!("'{}' isn't a time like 95, 12:34 or 1:02:03.5", text))
+}
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum TurnPlayer {
+    None,
+    One,
+    Two,
+}
+
+impl TurnPlayer {
+    fn swap_update(&mut self, default: &Self) {
+        match &self {
+            Self::One => {
+                *self = Self::Two;
+            }
+            Self::Two => {
+                *self = Self::One;
+            }
+            Self::None => *self = default.clone(),
+        }
+    }
+}
+
+/// What the events have done to the match so far, drawn around the video
+struct Scoreboard {
+    player1_life_tracker: LifeTracker,
+    player2_life_tracker: LifeTracker,
+    turn_counter: u32,
+    /// Turn counter when time was called, if it has been this game
+    time_called: Option<u32>,
+    turn_player: TurnPlayer,
+    first_turn_player: TurnPlayer,
+    winner: Option<u8>,
+    // Games won by player 1 and player 2
+    score: [u32; 2],
+    celebration: Option<Celebration>,
+    /// Player 1 and player 2, for their name plates
+    players: [PlayerInfo; 2],
+    lower_third: Option<LowerThird>,
+    /// Token badges showing, with the player whose hero they're on
+    tokens: Vec<(u8, TokenBadge)>,
+}
+
+impl Scoreboard {
+    fn build(setup: &MatchSetup, players: &[PlayerInfo; 2], increment: f64) -> Self {
+        // Track what the players lives should be so we can tick them down
+        let life_tracker = |life: &str| {
+            LifeTracker::build_eased(
+                life,
+                LIFE_TICK,
+                increment,
+                LIFE_MAX_TICK_DURATION,
+                Reparameterization::SCurve,
+            )
+        };
+        Scoreboard {
+            player1_life_tracker: life_tracker(&setup.hero1_life),
+            player2_life_tracker: life_tracker(&setup.hero2_life),
+            turn_counter: 0,
+            time_called: None,
+            turn_player: TurnPlayer::None,
+            first_turn_player: if setup.first == 1 {
+                TurnPlayer::One
+            } else {
+                TurnPlayer::Two
+            },
+            winner: None,
+            score: [0, 0],
+            celebration: None,
+            players: players.clone(),
+            lower_third: None,
+            tokens: Vec::new(),
+        }
+    }
+
+    /// Turns taken since time was called
+    fn extra_turns(&self) -> Option<u32> {
+        self.time_called
+            .map(|called| self.turn_counter.saturating_sub(called))
+    }
+
+    /// Moves the displayed lives towards their new values
+    fn tick(&mut self) {
+        self.player1_life_tracker.tick_display();
+        self.player2_life_tracker.tick_display();
+    }
+
+    /// Applies one event, passing cards on to the card display. Name plates and notes go at the
+    /// bottom left of `innerframe_rect`.
+    fn apply(
+        &mut self,
+        row: Annotation,
+        time_tick: TimeTick,
+        hero_rects: [Rect; 2],
+        innerframe_rect: Rect,
+        setup: &MatchSetup,
+        card_display_manager: &mut CardDisplayManager,
+    ) {
+        match row.event {
+            Event::Card { .. } => card_display_manager.add_card_to_queue(row.event),
+            Event::Block { .. } => card_display_manager.add_block_to_queue(row.event),
+            Event::Pair { .. } => card_display_manager.add_pair_to_queue(row.event),
+            Event::Zoom { .. } => card_display_manager.queue_zoom(row.event),
+            Event::Unzoom => card_display_manager.queue_unzoom(),
+            Event::Turn => {
+                self.turn_counter += 1;
+                self.turn_player.swap_update(&self.first_turn_player);
+            }
+            Event::TimeCalled => {
+                self.time_called.get_or_insert(self.turn_counter);
+            }
+            Event::Life {
+                player1: player1_update,
+                player2: player2_update,
+            } => {
+                if let Some(update) = player1_update {
+                    self.player1_life_tracker.update(&update, row.time.as_f64());
+                }
+                if let Some(update) = player2_update {
+                    self.player2_life_tracker.update(&update, row.time.as_f64());
+                }
+            }
+            Event::Win { player } => {
+                if self.winner.is_none() && matches!(player, 1 | 2) {
+                    self.score[player as usize - 1] += 1;
+                }
+                let _ = self.winner.insert(player);
+                let winner_rect = if player == 1 {
+                    hero_rects[0]
+                } else {
+                    hero_rects[1]
+                };
+                self.celebration.replace(Celebration::build(
+                    winner_rect,
+                    time_tick.as_f64(),
+                    player as u64,
+                ));
+            }
+            Event::Game => {
+                // Lives go back to the heroes' starting life and the other player goes first
+                self.player1_life_tracker
+                    .update(&format!("={}", setup.hero1_life.trim()), row.time.as_f64());
+                self.player2_life_tracker
+                    .update(&format!("={}", setup.hero2_life.trim()), row.time.as_f64());
+                self.first_turn_player = if self.first_turn_player == TurnPlayer::One {
+                    TurnPlayer::Two
+                } else {
+                    TurnPlayer::One
+                };
+                self.turn_player = TurnPlayer::None;
+                self.turn_counter = 0;
+                self.time_called = None;
+                self.winner = None;
+                self.celebration = None;
+                self.tokens.clear();
+            }
+            Event::LowerThird { player } => {
+                let (name, hero) = if player == 1 {
+                    (&setup.player1, &setup.hero1)
+                } else {
+                    (&setup.player2, &setup.hero2)
+                };
+                let info = &self.players[if player == 1 { 0 } else { 1 }];
+                self.lower_third = Some(LowerThird::for_player(
+                    innerframe_rect,
+                    time_tick.as_f64(),
+                    name,
+                    hero,
+                    info,
+                ));
+            }
+            Event::Note { text } => {
+                self.lower_third = Some(LowerThird::build(
+                    innerframe_rect,
+                    time_tick.as_f64(),
+                    &text,
+                    None,
+                ));
+            }
+            Event::Token { player, name } if matches!(player, 1 | 2) => {
+                // New badges go below the ones still showing on the same hero
+                let seconds = time_tick.as_f64();
+                let slot = self
+                    .tokens
+                    .iter()
+                    .filter(|(on, badge)| *on == player && !badge.is_finished(seconds))
+                    .count();
+                let hero_rect = hero_rects[player as usize - 1];
+                match TokenBadge::build(hero_rect, slot, seconds, &name) {
+                    Ok(badge) => self.tokens.push((player, badge)),
+                    Err(e) => warn!("Couldn't draw the {} token: {}", name, e),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The end of the match: the video is cut `OUTRO_DELAY` after the final win and the outro added
+struct Outro {
+    /// Seconds into the video
+    start: f64,
+    winner: u8,
+    final_lives: [i32; 2],
+}
+
+impl Outro {
+    /// `None` when no one has won
+    fn find(setup: &MatchSetup, rows: &[Annotation]) -> Option<Self> {
+        let last_win = rows
+            .iter()
+            .rposition(|row| matches!(row.event, Event::Win { player: 1 | 2 }))?;
+        let mut state = MatchState::build(setup);
+        for row in rows[..=last_win].iter() {
+            state.apply(&row.event);
+        }
+        let Event::Win { player } = rows[last_win].event else {
+            unreachable!()
+        };
+        Some(Outro {
+            start: rows[last_win].time.as_f64() + OUTRO_DELAY,
+            winner: player,
+            final_lives: [state.player1_life, state.player2_life],
+        })
+    }
+
+    /// Frame the outro replaces the video from, or the end of a video that stops sooner
+    fn frame(&self, fps: f64, frame_count: f64) -> f64 {
+        (self.start * fps).round().min(frame_count)
+    }
+}
+
+/// The box behind a life total: `rect` of the frame tinted towards a color, with rounded
+/// corners. The fill and corner mask are made once, as the box never moves.
+struct LifeBox {
+    rect: Rect,
+    fill: UMat,
+    opacity: f64,
+    /// Pixels inside the rounded corners, or `None` for square corners
+    mask: Option<UMat>,
+}
+
+impl LifeBox {
+    fn build(rect: Rect, color: Scalar, opacity: f64, radius: i32) -> Result<Self> {
+        let fill = UMat::new_size_with_default_def(rect.size(), CV_8UC3, color)?;
+        let radius = radius.min(rect.width / 2).min(rect.height / 2);
+        let mask = if radius > 0 {
+            let mut mask = UMat::new_size_with_default_def(rect.size(), CV_8U, Scalar::all(0.0))?;
+            let white = Scalar::all(255.0);
+            for inset in [
+                Rect::new(radius, 0, rect.width - 2 * radius, rect.height),
+                Rect::new(0, radius, rect.width, rect.height - 2 * radius),
+            ] {
+                imgproc::rectangle(&mut mask, inset, white, -1, LINE_8, 0)?;
+            }
+            for (x, y) in [
+                (radius, radius),
+                (rect.width - radius - 1, radius),
+                (radius, rect.height - radius - 1),
+                (rect.width - radius - 1, rect.height - radius - 1),
+            ] {
+                imgproc::circle(&mut mask, Point::new(x, y), radius, white, -1, LINE_8, 0)?;
+            }
+            Some(mask)
+        } else {
+            None
+        };
+        Ok(LifeBox {
+            rect,
+            fill,
+            opacity,
+            mask,
+        })
+    }
+
+    /// Only the box's region is touched, so the whole frame stays where it is rather than
+    /// being copied for a blend
+    fn draw(&self, frame: &mut UMat) -> Result<()> {
+        let mut roi = frame.roi_mut(self.rect)?;
+        let region = roi.try_clone()?;
+        let mut tinted = UMat::new_def();
+        core::add_weighted(
+            &region,
+            1.0 - self.opacity,
+            &self.fill,
+            self.opacity,
+            0.0,
+            &mut tinted,
+            -1,
+        )?;
+        match &self.mask {
+            Some(mask) => tinted.copy_to_masked(&mut roi, mask)?,
+            None => tinted.copy_to(&mut roi)?,
+        }
+        Ok(())
+    }
+}
+
+/// The life symbol, keyed once: its white and transparent parts show the scoreboard through
+struct LifeIcon {
+    rect: Rect,
+    img: UMat,
+    mask: UMat,
+}
+
+impl LifeIcon {
+    fn build(fp: &str, rel_roi: &RelativeRoi, frame_size: &Size) -> Result<Self> {
+        let mut icon = load_image_unchanged(fp)?;
+        if icon.empty() {
+            return Err(format!("Couldn't load life icon {}", fp).into());
+        }
+        if icon.channels() == 4 {
+            icon = convert_alpha_to_white(&icon)?;
+            cvt_color_def(&icon.clone(), &mut icon, COLOR_RGBA2RGB)?;
+        }
+        let rect = rel_roi.generate_roi(frame_size, &icon);
+        let img = rel_roi.resize(frame_size, &icon)?;
+
+        let mut white = UMat::new_def();
+        core::in_range(
+            &img,
+            &Scalar::all(255.0 - LIFE_ICON_KEY_LENIENCY),
+            &Scalar::all(255.0),
+            &mut white,
+        )?;
+        let mut mask = UMat::new_def();
+        core::bitwise_not_def(&white, &mut mask)?;
+        Ok(LifeIcon { rect, img, mask })
+    }
+
+    fn draw(&self, frame: &mut UMat) -> Result<()> {
+        let mut roi = frame.roi_mut(self.rect)?;
+        self.img.copy_to_masked(&mut roi, &self.mask)?;
+        Ok(())
+    }
+}
+
+/// Part of a source frame shown in the overlay, after cropping
+fn crop_rect(args: &Cli, size: Size) -> Rect {
+    let crop_left = ((args.crop_left.unwrap_or(0.0) / 100.0) * size.width as f64) as i32;
+    let crop_right = ((args.crop_right.unwrap_or(0.0) / 100.0) * size.width as f64) as i32;
+    let crop_top = ((args.crop_top.unwrap_or(0.0) / 100.0) * size.height as f64) as i32;
+    let crop_bottom = ((args.crop_bottom.unwrap_or(0.0) / 100.0) * size.height as f64) as i32;
+    core::Rect::new(
+        crop_left,
+        crop_top,
+        size.width - (crop_left + crop_right),
+        ((size.height - (crop_top + crop_bottom)) as f64 * FRAME_HEIGHT_RATIO) as i32,
+    )
+}
+
+/// Renders `args.chunks` parts of the video in parallel by running this binary once per chunk
+/// with --start and --end, then joins the parts and adds the audio
+/// `use_opencl` is passed on so the chunks don't each time OpenCL against the CPU
+fn render_chunks(
+    args: &Cli,
+    subcommand: Option<&str>,
+    chunks: usize,
+    output_path: &str,
+    use_opencl: bool,
+    intro_music: Option<&AudioBed>,
+    music_bed: Option<&MusicBed>,
+    outro: Option<&Outro>,
+) -> Result<()> {
+    let cap = VideoCapture::from_file(&args.video_file, videoio::CAP_ANY)?;
+    let fps = cap.get(videoio::CAP_PROP_FPS)?;
+    let mut last_frame = cap.get(CAP_PROP_FRAME_COUNT)?;
+    // The last chunk ends with the outro
+    if let Some(outro) = outro {
+        last_frame = outro.frame(fps, last_frame);
+    }
+    if let Some(sec) = args.timeout {
+        last_frame = last_frame.min((sec + 1) as f64 * fps);
+    }
+    let first_frame = if args.skip_intro {
+        0.0
+    } else {
+        INTRO_TIME * fps
+    };
+    // Chunk boundaries are whole frames so no frame is rendered twice or skipped
+    let boundaries: Vec<f64> = (0..=chunks)
+        .map(|i| (first_frame + (last_frame - first_frame) * i as f64 / chunks as f64).round())
+        .collect();
+
+    let dir = tempfile::tempdir()?;
+    let exe = std::env::current_exe()?;
+    let shared_args = chunk_args(args, use_opencl, chunks);
+    let log_file = args.log_args().log_file;
+    info!("Rendering {} chunks...", chunks);
+    let mut workers = Vec::new();
+    for (i, range) in boundaries.windows(2).enumerate() {
+        let segment = dir.path().join(format!("chunk_{}.mp4", i));
+        let mut cmd = Command::new(&exe);
+        cmd.args(subcommand)
+            .args(&shared_args)
+            .arg("--output-file")
+            .arg(&segment)
+            .args(["--end", &(range[1] / fps).to_string()]);
+        // The first chunk starts from the beginning so it includes the intro
+        if i > 0 {
+            cmd.args(["--start", &(range[0] / fps).to_string()]);
+        }
+        if let Some(fp) = &log_file {
+            cmd.args(["--log-file", &chunk_log_file(fp, i)]);
+        }
+        let worker = cmd.stdout(Stdio::null()).stderr(Stdio::piped()).spawn()?;
+        workers.push((segment, worker));
+    }
+
+    let mut list = String::new();
+    for (i, (segment, worker)) in workers.into_iter().enumerate() {
+        let output = worker.wait_with_output()?;
+        if !output.status.success() {
+            return Err(format!(
+                "Chunk {} failed: {}",
+                i + 1,
+                String::from_utf8_lossy(&output.stderr)
+            )
+            .into());
+        }
+        info!("Chunk {} of {} rendered", i + 1, chunks);
+        list.push_str(&format!("file '{}'\n", segment.display()));
+    }
+    let list_path = dir.path().join("chunks.txt");
+    std::fs::write(&list_path, list)?;
+
+    info!("Joining chunks and adding audio...");
+    let mut cmd = Command::new("ffmpeg");
+    cmd.args(["-f", "concat", "-safe", "0", "-i"])
+        .arg(&list_path)
+        .args(["-i", &args.video_file]);
+    add_audio_args(&mut cmd, intro_music, music_bed);
+    let status = cmd.args(["-shortest", output_path, "-y"]).output()?.status;
+    if !status.success() {
+        return Err("Couldn't join the chunks with ffmpeg".into());
+    }
+    info!("Finished!");
+    Ok(())
+}
+
+/// Flags every chunk is rendered with: all of `args` but the chunk's range, output and log file,
+/// and the music, which is mixed in when the chunks are joined
+fn chunk_args(args: &Cli, use_opencl: bool, chunks: usize) -> Vec<String> {
+    let mut values = vec![
+        ("--video-file", args.video_file.clone()),
+        ("--card-file", args.card_file.clone()),
+        ("--fit-fill", value_name(args.fit_fill)),
+        ("--background-colors", args.background_colors.to_string()),
+        ("--side-panel", value_name(args.side_panel)),
+        ("--life-box-color", hex_color(args.life_box_color)),
+        ("--life-box-opacity", args.life_box_opacity.to_string()),
+        ("--life-box-radius", args.life_box_radius.to_string()),
+        ("--card-enter", value_name(args.card_enter)),
+        ("--card-exit", value_name(args.card_exit)),
+        ("--card-queue", value_name(args.card_queue)),
+        ("--card-queue-limit", args.card_queue_limit.to_string()),
+        ("--zoom-scale", args.zoom_scale.to_string()),
+        ("--zoom-time", args.zoom_time.to_string()),
+        ("--zoom-hold", args.zoom_hold.to_string()),
+        ("--zoom-position", args.zoom_position.to_string()),
+        ("--turn-counter", value_name(args.turn_counter)),
+        (
+            "--turn-counter-colors",
+            args.turn_counter_colors.to_string(),
+        ),
+        ("--layout", value_name(args.layout)),
+        ("--gpu", if use_opencl { "on" } else { "off" }.to_string()),
+        // The chunks render at once, so they share the budget
+        (
+            "--memory-budget",
+            (args.memory_budget / chunks as u64).to_string(),
+        ),
+        ("--log-level", args.log.log_level.clone()),
+    ];
+    let optional = [
+        ("--timeout", args.timeout.map(|sec| sec.to_string())),
+        ("--record1", args.record1.clone()),
+        ("--record2", args.record2.clone()),
+        ("--crop-left", args.crop_left.map(|crop| crop.to_string())),
+        ("--crop-right", args.crop_right.map(|crop| crop.to_string())),
+        ("--crop-top", args.crop_top.map(|crop| crop.to_string())),
+        (
+            "--crop-bottom",
+            args.crop_bottom.map(|crop| crop.to_string()),
+        ),
+        ("--life-icon", args.life_icon.clone()),
+        (
+            "--format",
+            args.format.map(|format| format.code().to_string()),
+        ),
+        ("--perspective", args.perspective.clone()),
+    ];
+    for (flag, value) in optional {
+        if let Some(value) = value {
+            values.push((flag, value));
+        }
+    }
+    for credit in args.credits.iter() {
+        values.push(("--credits", credit.clone()));
+    }
+    let switches = [
+        ("--debug", args.debug),
+        ("--skip-intro", args.skip_intro),
+        ("--intro-stats", args.intro_stats),
+        ("--skip-outro", args.skip_outro),
+        ("--auto-fit", args.auto_fit),
+        ("--card-stats", args.card_stats),
+        ("--rules-text", args.rules_text),
+        ("--mirror-players", args.mirror_players),
+        ("--profile", args.profile),
+        ("--log-json", args.log.log_json),
+    ];
+    let mut argv: Vec<String> = values
+        .into_iter()
+        .flat_map(|(flag, value)| [flag.to_string(), value])
+        .collect();
+    for (flag, on) in switches {
+        if on {
+            argv.push(flag.to_string());
+        }
+    }
+    argv
+}
+
+/// `value` as it's written on the command line, e.g. "top-panel"
+fn value_name(value: impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .expect("no values are skipped")
+        .get_name()
+        .to_owned()
+}
+
+/// Log file of chunk `i`, e.g. "log.chunk2.txt" beside "log.txt", so the chunks don't write over
+/// the render's own log
+fn chunk_log_file(fp: &str, i: usize) -> String {
+    let path = Path::new(fp);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{}.chunk{}.{}", stem, i + 1, ext.to_string_lossy()),
+        None => format!("{}.chunk{}", stem, i + 1),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+/// Maps the video from input 0 and the audio from input 1, with any music mixed in
+fn add_audio_args(cmd: &mut Command, intro_music: Option<&AudioBed>, music_bed: Option<&MusicBed>) {
+    match (intro_music, music_bed) {
+        (Some(music), _) => cmd.args(music.ffmpeg_args(2, Some(1))),
+        (None, Some(bed)) => cmd.args(bed.ffmpeg_args(2, 1)),
+        // Footage without sound still gets its video
+        (None, None) => cmd.args(["-c", "copy", "-map", "0:v", "-map", "1:a?"]),
+    };
+}
+
+/// Renders the overlay onto `args.video_file`. Logging is set up by the caller, from
+/// `Cli::log_args`. Chunks are rendered by running this binary again, with `subcommand`
+/// first when rendering is one of its subcommands.
+pub fn run(mut args: Cli, subcommand: Option<&str>) -> Result<()> {
+    let gpu_report = gpu::configure(args.gpu)?;
+    info!("{}", gpu_report);
+
+    // A clip is rendered like a chunk, but with its own layout and audio
+    if let Some(clip) = &args.clip {
+        if clip[1] <= clip[0] {
+            return Err("--clip has to end after it starts".into());
+        }
+        args.layout = args.preset.layout();
+        args.skip_intro = true;
+    }
+    let simple = args.clip.is_some();
+    let (start, end) = match &args.clip {
+        Some(clip) => (Some(clip[0]), Some(clip[1])),
+        None => (args.start, args.end),
+    };
+
+    // Variable frame rate or rotated videos are rendered from an upright, constant rate copy,
+    // kept until the end of main so chunks and the audio mux read it too
+    let _normalized = match VideoFormat::probe(&args.video_file) {
+        Ok(format) if format.needs_normalizing() => {
+            info!(
+                "Normalizing {} ({:.3} fps nominal, {:.3} fps average, rotated {} degrees) to {:.3} fps",
+                args.video_file,
+                format.nominal_fps,
+                format.average_fps,
+                format.rotation,
+                format.target_fps()
+            );
+            let normalized = tempfile::Builder::new().suffix(".mp4").tempfile()?;
+            format.normalize(&args.video_file, normalized.path())?;
+            args.video_file = normalized.path().to_str().unwrap().to_string();
+            Some(normalized)
+        }
+        Ok(_) => None,
+        Err(e) => {
+            warn!("Couldn't check the format of {}: {}", args.video_file, e);
+            None
+        }
+    };
+
+    // Load game stats
+    let annotations =
+        annotation_store::read_from_path(&args.card_file).expect("Could not load card file");
+    let format = args.format.or(annotations.header.format);
+    let mut setup = annotations.header.setup.clone();
+    // Older annotations can be missing a hero's starting life, which its card has
+    let has_life = |life: &str| life.trim().parse::<u32>().is_ok();
+    if !has_life(&setup.hero1_life) || !has_life(&setup.hero2_life) {
+        CardDB::init().complete_setup(&mut setup, format);
+    }
+    let intro_stats = args.intro_stats.then(|| {
+        let card_db = CardDB::init();
+        let players = &annotations.header.players;
+        let record1 = args.record1.as_ref().or(players[0].seed.as_ref());
+        let record2 = args.record2.as_ref().or(players[1].seed.as_ref());
+        [
+            HeroStats::build(
+                card_db.hero(&setup.hero1),
+                &setup.hero1_life,
+                record1.map(String::as_str),
+            ),
+            HeroStats::build(
+                card_db.hero(&setup.hero2),
+                &setup.hero2_life,
+                record2.map(String::as_str),
+            ),
+        ]
+    });
+    let events = annotations.aligned_events();
+    let outro = if args.skip_outro || simple {
+        None
+    } else {
+        Outro::find(&setup, &events)
+    };
+    let mut rows: VecDeque<Annotation> = VecDeque::from(events);
+    // The match score is only shown when there is more than one game
+    let multi_game = annotations.games().len() > 1;
+
+    let player1 = setup.player1.clone();
+    let player2 = setup.player2.clone();
+
+    // Create output
+    let output_path = {
+        if let Some(out) = args.output_file.clone() {
+            out
+        } else {
+            format!("output_videos/{}_output_video.mp4", chrono::Local::now())
+        }
+    };
+    let intro_music = match &args.intro_music {
+        Some(fp) => Some(AudioBed::build(fp, INTRO_TIME, args.intro_music_volume)?),
+        None => None,
+    };
+    let music_bed = match &args.music_bed {
+        Some(fp) => Some(MusicBed::build(
+            fp,
+            args.music_bed_volume,
+            args.match_volume,
+            args.duck_threshold,
+            args.duck_ratio,
+        )?),
+        None => None,
+    };
+    if let Some(chunks) = args.chunks.filter(|chunks| *chunks > 1) {
+        return render_chunks(
+            &args,
+            subcommand,
+            chunks,
+            &output_path,
+            gpu_report.in_use,
+            intro_music.as_ref(),
+            music_bed.as_ref(),
+            outro.as_ref(),
+        );
+    }
+    // Chunks are joined and given audio by the process that started them
+    let segment = args.start.is_some() || args.end.is_some();
+    let tmp_file = NamedTempFile::new()?;
+    let tmp_path = tmp_file.path().to_str().unwrap();
+
+    // Create capture
+    let mut cap = VideoCapture::from_file(&args.video_file, videoio::CAP_ANY)?;
+    let fps = cap.get(videoio::CAP_PROP_FPS)?;
+    let source_size = Size::new(
+        cap.get(videoio::CAP_PROP_FRAME_WIDTH)? as i32,
+        cap.get(videoio::CAP_PROP_FRAME_HEIGHT)? as i32,
+    );
+    let correction = match &args.perspective {
+        Some(fp) => Some(Perspective::load(fp)?.correction(source_size)?),
+        None => None,
+    };
+    // Part of the source inside any black bars. A straightened frame is all play area.
+    let content = if !args.auto_fit {
+        None
+    } else if let Some(correction) = &correction {
+        Some(Rect::from_point_size(Point::default(), correction.size()))
+    } else {
+        let content = detect_video_content(&mut cap)?;
+        if let Some(content) = content {
+            info!(
+                "Fitting the {}x{} picture found in the video",
+                content.width, content.height
+            );
+        }
+        content
+    };
+
+    let frame_size = args.layout.frame_size();
+    let Regions {
+        hero1: hero1_rel_roi,
+        hero2: hero2_rel_roi,
+        player1: player1_rel_roi,
+        player2: player2_rel_roi,
+        life1: life1_rel_roi,
+        life2: life2_rel_roi,
+        life_symbol: life_symbol_rel_roi,
+        innerframe: innerframe_rel_roi,
+        logo: logo_rel_roi,
+        card: card_rel_roi,
+        turn_counter: turn_counter_rel_roi,
+        player1_right,
+    } = args.layout.regions(args.side_panel, args.mirror_players)?;
+    let background_animation = Background::build(frame_size, args.background_colors, fps)?;
+
+    // Get hero images
+    let full_art_manager = FullArtHeroManager::new();
+    let hero1_animation_fp = full_art_manager.get_cropped_hero_art_animation_fp(&setup.hero1)?;
+    let hero2_animation_fp = full_art_manager.get_cropped_hero_art_animation_fp(&setup.hero2)?;
+
+    let mut hero1_animation = VideoCapLooperAdj::build(&hero1_animation_fp)?;
+    let mut hero2_animation = VideoCapLooperAdj::build(&hero2_animation_fp)?;
+
+    // Load card back
+    let card_back_img = load_image(&assets::path(CARD_BACK_FP))?;
+    let green_background =
+        UMat::new_size_with_default_def(card_back_img.size()?, card_back_img.typ(), REMOVAL_COLOR)?;
+    let card_back_img = remove_white_corners(&green_background, &card_back_img)?;
+    let card_back_img = card_rel_roi.resize(&frame_size, &card_back_img)?;
+    let card_rect = card_rel_roi.generate_roi(&frame_size, &card_back_img);
+
+    let increment = fps.recip() * MILLI;
+
+    // Generate output video
+    let mut out = VideoWriter::new(
+        if segment { &output_path } else { tmp_path },
+        // VideoWriter::fourcc('h', '2', '6', '4').unwrap(),
+        // VideoWriter::fourcc('a', 'v', 'c', '1').unwrap(),
+        VideoWriter::fourcc('m', 'p', '4', 'v').unwrap(),
+        fps,
+        frame_size,
+        true,
+    )?;
+
+    if !args.skip_intro && start.is_none() {
+        // Create intro
+        info!("Generating intro...");
+        generate_intro(
+            &hero1_animation_fp,
+            &player1,
+            &hero2_animation_fp,
+            &player2,
+            intro_stats.as_ref(),
+            &frame_size,
+            card_back_img.typ(),
+            fps,
+            &mut out,
+        )?;
+        info!("Intro generated!");
+    }
+
+    // Load GoToOne Logo
+    let logo_image = load_image(&assets::path(LOGO_FP))?;
+    let mut logo_image = logo_rel_roi.resize(&frame_size, &logo_image)?;
+    let logo_roi = logo_rel_roi.generate_roi(&frame_size, &logo_image);
+    imgproc::rectangle(
+        &mut logo_image,
+        core::Rect::new(0, 0, logo_roi.width, logo_roi.height),
+        Scalar::new(0., 0., 0., 0.),
+        (HERO_BORDER_THICKNESS as f64 * 2.0) as i32,
+        imgproc::LINE_8,
+        0,
+    )?;
+
+    // stop further mutations
+    let logo_image = logo_image;
+
+    // Load life symbol and boxes
+    let life_icon_fp = match &args.life_icon {
+        Some(fp) => fp.clone(),
+        None => assets::path(LIFE_FP),
+    };
+    let life_icon = LifeIcon::build(&life_icon_fp, &life_symbol_rel_roi, &frame_size)?;
+    let life_boxes = [life1_rel_roi, life2_rel_roi]
+        .map(|rel_roi| {
+            LifeBox::build(
+                rel_roi.generate_roi_raw(&frame_size),
+                args.life_box_color,
+                args.life_box_opacity,
+                args.life_box_radius,
+            )
+        })
+        .into_iter()
+        .collect::<Result<Vec<LifeBox>>>()?;
+
+    // Set init vars
+    let mut time_tick = TimeTick::new();
+    let mut scoreboard = Scoreboard::build(&setup, &annotations.header.players, increment);
+
+    // start progress bar
+    let bar = {
+        if args.timeout.is_some() {
+            ProgressBar::new(((args.timeout.unwrap() + 1) as f64 * MILLI) as u64)
+        } else {
+            ProgressBar::new(cap.get(CAP_PROP_FRAME_COUNT).unwrap() as u64)
+        }
+    };
+
+    let mut card_display_manager = CardDisplayManager::new(
+        &card_rect,
+        &card_back_img,
+        &time_tick,
+        args.memory_budget / CARD_CACHE_SHARE,
+    )
+    .with_reveal(args.card_enter, args.card_exit)
+    .with_queue_policy(args.card_queue, args.card_queue_limit)
+    .with_zoom(ZoomSettings {
+        scale: args.zoom_scale,
+        duration: args.zoom_time,
+        hold: args.zoom_hold,
+        target: args.zoom_position,
+    });
+    if args.card_stats || args.rules_text {
+        card_display_manager =
+            card_display_manager.with_card_data(CardDB::init(), args.card_stats, args.rules_text);
+    }
+    let turn_indicator = TurnIndicator::new(HERO_TURN_COLOR, HERO_BORDER_THICKNESS);
+
+    // Cut beginning of video where intro would be
+    let mut position = 0.0;
+    if !args.skip_intro {
+        let intro_frames = INTRO_TIME * fps;
+        cap.set(CAP_PROP_POS_FRAMES, intro_frames)?;
+        time_tick.increment_milli(increment * intro_frames);
+        position = intro_frames;
+    }
+
+    // A chunk starting part way through replays the events before it without drawing, so
+    // lives, turns and cards carry over from the chunks before
+    if let Some(start) = start {
+        let start_frame = (start * fps).round();
+        let source_size = match &correction {
+            Some(correction) => correction.size(),
+            None => source_size,
+        };
+        let frame_roi_rect = if args.auto_fit {
+            innerframe_rel_roi.generate_roi_raw(&frame_size)
+        } else {
+            let innerframe = UMat::new_size_with_default_def(
+                crop_rect(&args, source_size).size(),
+                card_back_img.typ(),
+                Scalar::default(),
+            )?;
+            innerframe_rel_roi.generate_roi(&frame_size, &innerframe)
+        };
+        let mut scratch =
+            UMat::new_size_with_default_def(frame_size, card_back_img.typ(), Scalar::default())?;
+        while position < start_frame {
+            time_tick.increment_milli(increment);
+            position += 1.0;
+            scoreboard.tick();
+            background_loop.read()?;
+            let hero_rects = [
+                hero1_rel_roi.generate_roi(&frame_size, &hero1_animation.read()?),
+                hero2_rel_roi.generate_roi(&frame_size, &hero2_animation.read()?),
+            ];
+            if rows.front().is_some_and(|row| row.time <= time_tick) {
+                let row = rows.pop_front().unwrap();
+                scoreboard.apply(
+                    row,
+                    time_tick,
+                    hero_rects,
+                    frame_roi_rect,
+                    &setup,
+                    &mut card_display_manager,
+                );
+            }
+            card_display_manager.tick(time_tick, &mut scratch, &frame_roi_rect)?;
+        }
+        cap.set(CAP_PROP_POS_FRAMES, position)?;
+    }
+    let end_frame = end.map(|end| (end * fps).round());
+    let outro_frame = outro
+        .as_ref()
+        .map(|outro| outro.frame(fps, cap.get(CAP_PROP_FRAME_COUNT).unwrap_or(f64::MAX)));
+
+    let mut profiler = Profiler::new();
+    let mut memory = MemoryMonitor::new(args.memory_budget, (MEMORY_REPORT_SECS * fps) as u64);
+
+    // Buffers reused by every frame rather than allocated for each
+    let mut source = UMat::new_def();
+    let mut background = UMat::new_def();
+    let mut innerframe = UMat::new_def();
+    let mut straightened = UMat::new_def();
+
+    // LOOP HERE
+    info!("overlaying video...");
+    loop {
+        // Check timeout
+        if let Some(sec) = args.timeout {
+            if time_tick.sec() > sec {
+                break;
+            }
+        }
+
+        if end_frame.is_some_and(|end_frame| position >= end_frame) {
+            break;
+        }
+        if outro_frame.is_some_and(|outro_frame| position >= outro_frame) {
+            break;
+        }
+
+        time_tick.increment_milli(increment);
+        position += 1.0;
+        let _frame = debug_span!("frame", index = position as u64).entered();
+
+        // Increment life ticker
+        scoreboard.tick();
+        
+        // Grab frame
+        let mut stage = Instant::now();
+        if !cap.read(&mut source).unwrap_or(false) {
+            break;
+        }
+        stage = profiler.record("read frame", stage);
+
+        if let Some(correction) = &correction {
+            correction.apply(&source, &mut straightened)?;
+            std::mem::swap(&mut source, &mut straightened);
+            stage = profiler.record("perspective", stage);
+        }
+
+        // Draw background. By position, so chunks carry on from each other.
+        background_animation.draw_at(position / fps, &mut background)?;
+        stage = profiler.record("background", stage);
+
+        // Crop frame
+        let source_rect = match content {
+            Some(content) => content,
+            None => crop_rect(&args, source.size()?),
+        };
+        let crop_roi = source.roi(source_rect)?;
+
+        // Reframe
+        let frame_roi_rect = if args.auto_fit {
+            // The whole inner frame is used whatever the footage's shape
+            let frame_roi_rect = innerframe_rel_roi.generate_roi_raw(&frame_size);
+            letterbox(
+                &crop_roi,
+                frame_roi_rect.size(),
+                args.fit_fill,
+                &mut innerframe,
+            )?;
+            innerframe.copy_to(&mut background.roi_mut(frame_roi_rect)?)?;
+            frame_roi_rect
+        } else {
+            crop_roi.copy_to(&mut innerframe)?;
+            let reframe = innerframe_rel_roi.resize(&frame_size, &innerframe)?;
+            let frame_roi_rect = innerframe_rel_roi.generate_roi(&frame_size, &innerframe);
+            let mut frame_roi = background.roi_mut(frame_roi_rect)?;
+            reframe.copy_to(frame_roi.borrow_mut())?;
+            frame_roi_rect
+        };
+        imgproc::rectangle(
+            &mut background,
+            frame_roi_rect,
+            Scalar::new(0.0, 0.0, 0.0, 0.0),
+            10, // Thickness of -1 fills the rectangle completely
+            LINE_8,
+            0,
+        )?;
+
+        // quick fix
+        let mut frame = std::mem::replace(&mut background, UMat::new_def());
+        stage = profiler.record("reframe", stage);
+
+        // Heroes
+        let hero1_image = hero1_animation.read()?;
+        stage = profiler.record("hero read", stage);
+
+        let hero1_rect = hero1_rel_roi.generate_roi(&frame_size, &hero1_image);
+        let mut hero1_image = hero1_rel_roi.resize(&frame_size, &hero1_image)?;
+        // Heroes face the middle of the scoreboard
+        if !player1_right {
+            flip(&hero1_image.clone(), &mut hero1_image, 1)?;
+        }
+        let (hero1_badge, hero2_badge) = if player1_right {
+            (BadgeSide::Left, BadgeSide::Right)
+        } else {
+            (BadgeSide::Right, BadgeSide::Left)
+        };
+
+        // Clips only show the lives and cards
+        if !simple {
+            let mut hero1_roi = frame.roi_mut(hero1_rect)?;
+            hero1_image.copy_to(hero1_roi.borrow_mut())?;
+
+            if scoreboard.winner.is_none() && scoreboard.turn_player == TurnPlayer::One {
+                turn_indicator.draw(&mut frame, hero1_rect, time_tick.as_f64(), hero1_badge)?;
+            } else {
+                let hero1_color = {
+                    if scoreboard.winner.is_some_and(|v| v == 1) {
+                        HERO_WIN_COLOR
+                    } else {
+                        HERO_DEF_COLOR
+                    }
+                };
+                imgproc::rectangle(
+                    &mut frame,
+                    hero1_rect,
+                    hero1_color,
+                    HERO_BORDER_THICKNESS,
+                    imgproc::LINE_8,
+                    0,
+                )?;
+            }
+        }
+
+        stage = profiler.record("hero resize", stage);
+
+        let hero2_image = hero2_animation.read()?;
+        stage = profiler.record("hero read", stage);
+        // let hero2_image = FullArtHeroManager::crop_hero_img(&hero2_image)?;
+        let hero2_rect = hero2_rel_roi.generate_roi(&frame_size, &hero2_image);
+        let mut hero2_image = hero2_rel_roi.resize(&frame_size, &hero2_image)?;
+        if player1_right {
+            flip(&hero2_image.clone(), &mut hero2_image, 1)?;
+        }
+
+        if !simple {
+            let mut hero2_roi = frame.roi_mut(hero2_rect)?;
+            hero2_image.copy_to(hero2_roi.borrow_mut())?;
+
+            if scoreboard.winner.is_none() && scoreboard.turn_player == TurnPlayer::Two {
+                turn_indicator.draw(&mut frame, hero2_rect, time_tick.as_f64(), hero2_badge)?;
+            } else {
+                let hero2_color = {
+                    if scoreboard.winner.is_some_and(|v| v == 2) {
+                        HERO_WIN_COLOR
+                    } else {
+                        HERO_DEF_COLOR
+                    }
+                };
+                imgproc::rectangle(
+                    &mut frame,
+                    hero2_rect,
+                    hero2_color,
+                    HERO_BORDER_THICKNESS,
+                    imgproc::LINE_8,
+                    0,
+                )?;
+            }
+        }
+        stage = profiler.record("hero resize", stage);
+
+        // Player details
+        for life_box in life_boxes.iter() {
+            life_box.draw(&mut frame)?;
+        }
+
+        center_text_at_rel(
+            &mut frame,
+            &scoreboard.player1_life_tracker.display(),
+            SCORE_FONT_STYLE,
+            SCORE_FONT_SCALE,
+            Scalar::new(255.0, 255.0, 255.0, 0.0),
+            SCORE_FONT_WIDTH,
+            life1_rel_roi,
+            20,
+        )?;
+        center_text_at_rel(
+            &mut frame,
+            &scoreboard.player2_life_tracker.display(),
+            SCORE_FONT_STYLE,
+            SCORE_FONT_SCALE,
+            Scalar::new(255.0, 255.0, 255.0, 0.0),
+            SCORE_FONT_WIDTH,
+            life2_rel_roi,
+            20,
+        )?;
+        if !simple {
+            center_text_at_rel(
+                &mut frame,
+                &player1,
+                TURN_FONT_FACE,
+                TURN_FONT_SCALE,
+                WHITE,
+                TURN_FONT_THICKNESS,
+                player1_rel_roi,
+                20,
+            )?;
+            center_text_at_rel(
+                &mut frame,
+                &player2,
+                TURN_FONT_FACE,
+                TURN_FONT_SCALE,
+                WHITE,
+                TURN_FONT_THICKNESS,
+                player2_rel_roi,
+                20,
+            )?;
+        }
+
+        // Life
+        life_icon.draw(&mut frame)?;
+
+        // Turn counter
+        if scoreboard.turn_counter > 0 && !simple {
+            let turn_counter_rect = match args.turn_counter {
+                TurnCounterSlot::Frame => Some(Rect::new(
+                    frame_roi_rect.x + 7 * frame_roi_rect.width.div_euclid(8),
+                    frame_roi_rect.y,
+                    frame_roi_rect.width.div_euclid(8),
+                    frame_roi_rect.height.div_euclid(16),
+                )),
+                TurnCounterSlot::TopPanel => {
+                    Some(turn_counter_rel_roi.generate_roi_raw(&frame_size))
+                }
+                TurnCounterSlot::Hidden => None,
+            };
+            if let Some(rect) = turn_counter_rect {
+                let style = args.turn_counter_colors;
+                style.draw(
+                    &mut frame,
+                    rect,
+                    scoreboard.turn_counter,
+                    scoreboard.extra_turns(),
+                )?;
+            }
+        }
+
+        // Match score
+        if multi_game && !simple {
+            let score_rect = Rect::new(
+                frame_roi_rect.x + 6 * frame_roi_rect.width.div_euclid(8),
+                frame_roi_rect.y,
+                frame_roi_rect.width.div_euclid(8),
+                frame_roi_rect.height.div_euclid(16),
+            );
+            imgproc::rectangle(
+                &mut frame,
+                score_rect,
+                Scalar::new(0., 0., 0., 0.),
+                -1,
+                imgproc::LINE_8,
+                0,
+            )?;
+            center_text_at_rect(
+                &mut frame,
+                &format!("{} - {}", scoreboard.score[0], scoreboard.score[1]),
+                TURN_FONT_FACE,
+                TURN_FONT_SCALE,
+                Scalar::new(255.0, 255.0, 255.0, 0.0),
+                TURN_FONT_THICKNESS,
+                score_rect,
+                20,
+            )?;
+        }
+
+        // Format label
+        if let Some(format) = format.filter(|_| !simple) {
+            let format_rect = Rect::new(
+                frame_roi_rect.x,
+                frame_roi_rect.y,
+                frame_roi_rect.width.div_euclid(4),
+                frame_roi_rect.height.div_euclid(16),
+            );
+            imgproc::rectangle(
+                &mut frame,
+                format_rect,
+                Scalar::new(0., 0., 0., 0.),
+                -1,
+                imgproc::LINE_8,
+                0,
+            )?;
+            center_text_at_rect(
+                &mut frame,
+                format.name(),
+                TURN_FONT_FACE,
+                TURN_FONT_SCALE,
+                Scalar::new(255.0, 255.0, 255.0, 0.0),
+                TURN_FONT_THICKNESS,
+                format_rect,
+                20,
+            )?;
+        }
+
+        if !simple {
+            let mut logo_roi = frame.roi_mut(logo_roi)?;
+            logo_image.copy_to(logo_roi.borrow_mut())?;
+        }
+        stage = profiler.record("text", stage);
+
+        // Parse Row Data
+        if let Some(row) = rows.front() {
+            // Card time just passed
+            if row.time <= time_tick {
+                let row = rows.pop_front().unwrap();
+                scoreboard.apply(
+                    row,
+                    time_tick,
+                    [hero1_rect, hero2_rect],
+                    frame_roi_rect,
+                    &setup,
+                    &mut card_display_manager,
+                );
+            }
+        }
+
+        if let Some(celebration) = &scoreboard.celebration {
+            celebration.draw(&mut frame, time_tick.as_f64())?;
+        }
+        if let Some(lower_third) = &scoreboard.lower_third {
+            lower_third.draw(&mut frame, time_tick.as_f64())?;
+        }
+        scoreboard
+            .tokens
+            .retain(|(_, badge)| !badge.is_finished(time_tick.as_f64()));
+        for (_, badge) in scoreboard.tokens.iter() {
+            badge.draw(&mut frame, time_tick.as_f64())?;
+        }
+        stage = profiler.record("events", stage);
+
+        card_display_manager.tick(time_tick, &mut frame, &frame_roi_rect)?;
+        stage = profiler.record("card display", stage);
+
+        out.write(&frame)?;
+        profiler.record("encode", stage);
+        // The next frame is drawn into this one's buffer
+        background = frame;
+        memory.tick();
+        if args.timeout.is_some() {
+            bar.inc(increment as u64);
+        } else {
+            bar.inc(1);
+        }
+    }
+
+    // A chunk that ends before the outro leaves it to the last chunk
+    if let Some(outro) = outro.filter(|_| outro_frame.is_some_and(|frame| position >= frame)) {
+        info!("Generating outro...");
+        let winner_fp = if outro.winner == 1 {
+            &hero1_animation_fp
+        } else {
+            &hero2_animation_fp
+        };
+        generate_outro(
+            winner_fp,
+            outro.winner,
+            [&player1, &player2],
+            outro.final_lives,
+            &args.credits,
+            &frame_size,
+            card_back_img.typ(),
+            fps,
+            &mut out,
+        )?;
+    }
+
+    // end progress bar
+    bar.finish();
+    out.release()?;
+    if args.profile {
+        println!("{}", profiler);
+        println!("{}", gpu_report);
+        println!("Peak resident memory: {}", format_bytes(memory.peak()));
+    } else {
+        debug!("Render loop stages:\n{}", profiler);
+        debug!("Peak resident memory: {}", format_bytes(memory.peak()));
+    }
+    if segment {
+        return Ok(());
+    }
+
+    info!("Adding audio...");
+    let mut cmd = Command::new("ffmpeg");
+    cmd.args(["-i", tmp_path]);
+    // A clip takes the audio from its part of the video
+    if let Some(clip) = &args.clip {
+        cmd.args(["-ss", &clip[0].to_string()])
+            .args(["-t", &(clip[1] - clip[0]).to_string()]);
+    }
+    cmd.args(["-i", &args.video_file]);
+    add_audio_args(&mut cmd, intro_music.as_ref(), music_bed.as_ref());
+    let status = cmd.args(["-shortest", &output_path, "-y"]).output()?.status;
+    if !status.success() {
+        return Err("Couldn't add the audio with ffmpeg".into());
+    }
+    info!("Finished!");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    /// Flags each chunk is given its own value for, that can't be used with --chunks, or for the
+    /// music mixed in when the chunks are joined
+    const NOT_FORWARDED: [&str; 16] = [
+        "output-file",
+        "start",
+        "end",
+        "chunks",
+        "clip",
+        "preset",
+        "log-file",
+        "intro-music",
+        "intro-music-volume",
+        "music-bed",
+        "music-bed-volume",
+        "match-volume",
+        "duck-threshold",
+        "duck-ratio",
+        "help",
+        "version",
+    ];
+
+    #[test]
+    fn test_chunk_args() {
+        // Some flags conflict, so they're split between two renders
+        let renders = [
+            vec![
+                "--timeout",
+                "60",
+                "--debug",
+                "--skip-intro",
+                "--skip-outro",
+                "--auto-fit",
+                "--fit-fill",
+                "blur",
+                "--background-colors",
+                "1a2b4c,0a1020,4f7fbf",
+                "--side-panel",
+                "right",
+                "--life-icon",
+                "icon.png",
+                "--life-box-color",
+                "123456",
+                "--life-box-opacity",
+                "0.25",
+                "--life-box-radius",
+                "8",
+                "--card-stats",
+                "--rules-text",
+                "--card-enter",
+                "fade",
+                "--card-exit",
+                "slide",
+                "--card-queue",
+                "grid",
+                "--card-queue-limit",
+                "3",
+                "--zoom-scale",
+                "2.5",
+                "--zoom-time",
+                "0.25",
+                "--zoom-hold",
+                "4",
+                "--zoom-position",
+                "0.7,0.5",
+                "--turn-counter",
+                "top-panel",
+                "--turn-counter-colors",
+                "101010,f0f0f0,ff0000",
+                "--mirror-players",
+                "--layout",
+                "portrait",
+                "--format",
+                "blitz",
+                "--perspective",
+                "corners.json",
+                "--profile",
+                "--memory-budget",
+                "2G",
+                "--gpu",
+                "on",
+                "--log-level",
+                "warn",
+                "--log-json",
+            ],
+            vec![
+                "--intro-stats",
+                "--record1",
+                "3",
+                "--record2",
+                "5-1",
+                "--credits",
+                "Casters",
+                "--credits",
+                "@handle",
+                "--crop-left",
+                "1",
+                "--crop-right",
+                "2",
+                "--crop-top",
+                "3.5",
+                "--crop-bottom",
+                "4",
+            ],
+        ];
+        let mut forwarded = Vec::new();
+        for flags in renders {
+            let argv = [
+                "overlay",
+                "--video-file",
+                "match.mp4",
+                "--card-file",
+                "match.csv",
+            ];
+            let args = Cli::parse_from(argv.into_iter().chain(flags));
+            let chunk_argv = chunk_args(&args, true, 1);
+            // A chunk given these renders the same as the render that started it
+            let chunk = Cli::parse_from(
+                ["overlay"]
+                    .into_iter()
+                    .map(String::from)
+                    .chain(chunk_argv.clone()),
+            );
+            assert_eq!(chunk_args(&chunk, true, 1), chunk_argv);
+            forwarded.extend(chunk_argv);
+        }
+
+        for arg in Cli::command().get_arguments() {
+            let Some(flag) = arg.get_long() else {
+                continue;
+            };
+            if !NOT_FORWARDED.contains(&flag) {
+                assert!(
+                    forwarded.contains(&format!("--{}", flag)),
+                    "--{} isn't passed to the chunks",
+                    flag
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_chunk_log_file() {
+        assert_eq!(chunk_log_file("log.txt", 1), "log.chunk2.txt");
+        assert_eq!(chunk_log_file("logs/render", 0), "logs/render.chunk1");
+    }
+}
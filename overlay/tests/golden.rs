@@ -0,0 +1,179 @@
+//! Renders a tiny match with the overlay binary and compares a few of its frames against the
+//! reference images in tests/golden, so changes to the fades, rotations and layout can't go
+//! unnoticed.
+//!
+//! A missing reference fails its test. References are only written, from the current output,
+//! when `UPDATE_GOLDEN` is set, and should be looked over before they're committed. A frame
+//! that no longer matches is saved beside its reference as `<name>.actual.png`.
+//!
+//! The references haven't been rendered yet, so the tests are ignored until they are. Render
+//! them with `UPDATE_GOLDEN=1 cargo test -p overlay --test golden -- --ignored`, then commit
+//! them and remove the `#[ignore]`s.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    sync::OnceLock,
+};
+
+use opencv::{
+    core::{self, Mat, MatTraitConst, Point, Rect, Scalar, Size, CV_8UC3},
+    imgcodecs, imgproc,
+    videoio::{VideoCapture, VideoCaptureTrait, VideoWriter, VideoWriterTrait, CAP_ANY},
+};
+use tempfile::TempDir;
+
+const FPS: f64 = 10.0;
+/// Length of the rendered video. The intro takes the first 8 seconds.
+const SECONDS: f64 = 15.0;
+const FIXTURE_SIZE: (i32, i32) = (320, 180);
+/// Mean absolute difference per channel, out of 255, before a frame counts as changed
+const TOLERANCE: f64 = 2.0;
+
+/// Reference name and index of each checked frame in the rendered video. After the intro,
+/// the frame `t` seconds into the source is at index `t * FPS - 1`.
+const FRAMES: [(&str, usize); 3] = [
+    ("intro_frame_10", 10),
+    // The card comes up at 9s and is turned to its front from 9.75s to 10.5s
+    ("card_rotate_in", 100),
+    // Zoomed at 11s, fully zoomed from 13s to 16s
+    ("zoom_apex", 134),
+];
+
+fn manifest_dir() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// Writes a video of a rectangle moving over a changing background, so every frame differs
+fn write_fixture_video(fp: &Path) -> opencv::Result<()> {
+    let (width, height) = FIXTURE_SIZE;
+    let mut out = VideoWriter::new(
+        fp.to_str().unwrap(),
+        VideoWriter::fourcc('m', 'p', '4', 'v')?,
+        FPS,
+        Size::new(width, height),
+        true,
+    )?;
+    for i in 0..(SECONDS * FPS) as i32 + 1 {
+        let background = Scalar::new((i * 2 % 256) as f64, 80.0, 160.0, 0.0);
+        let mut frame = Mat::new_rows_cols_with_default(height, width, CV_8UC3, background)?;
+        imgproc::rectangle(
+            &mut frame,
+            Rect::new(i * 2 % (width - 40), height / 3, 40, 40),
+            Scalar::new(255.0, 255.0, 255.0, 0.0),
+            -1,
+            imgproc::LINE_8,
+            0,
+        )?;
+        imgproc::put_text(
+            &mut frame,
+            &i.to_string(),
+            Point::new(10, height - 10),
+            imgproc::FONT_HERSHEY_SIMPLEX,
+            1.0,
+            Scalar::new(0.0, 0.0, 0.0, 0.0),
+            2,
+            imgproc::LINE_8,
+            false,
+        )?;
+        out.write(&frame)?;
+    }
+    out.release()
+}
+
+/// Renders the fixture once for every test and saves the checked frames as PNGs
+fn rendered() -> &'static Path {
+    static RENDER: OnceLock<TempDir> = OnceLock::new();
+    RENDER
+        .get_or_init(|| {
+            let dir = tempfile::tempdir().unwrap();
+            let video_fp = dir.path().join("fixture.mp4");
+            let output_fp = dir.path().join("rendered.mp4");
+            write_fixture_video(&video_fp).unwrap();
+
+            // Data files are found relative to the workspace root
+            let status = Command::new(env!("CARGO_BIN_EXE_overlay"))
+                .current_dir(manifest_dir().parent().unwrap())
+                .arg("--video-file")
+                .arg(&video_fp)
+                .arg("--card-file")
+                .arg(manifest_dir().join("tests/fixtures/golden.tsv"))
+                .arg("--output-file")
+                .arg(&output_fp)
+                .args(["--end", &SECONDS.to_string()])
+                .status()
+                .unwrap();
+            assert!(status.success(), "overlay failed: {}", status);
+
+            let mut cap = VideoCapture::from_file(output_fp.to_str().unwrap(), CAP_ANY).unwrap();
+            let mut frame = Mat::default();
+            let mut index = 0;
+            while cap.read(&mut frame).unwrap() {
+                for (name, _) in FRAMES.iter().filter(|(_, i)| *i == index) {
+                    let fp = dir.path().join(format!("{}.png", name));
+                    imgcodecs::imwrite_def(fp.to_str().unwrap(), &frame).unwrap();
+                }
+                index += 1;
+            }
+            dir
+        })
+        .path()
+}
+
+fn read_png(fp: &Path) -> Mat {
+    imgcodecs::imread(fp.to_str().unwrap(), imgcodecs::IMREAD_COLOR).unwrap()
+}
+
+fn check_frame(name: &str) {
+    let actual_fp = rendered().join(format!("{}.png", name));
+    assert!(actual_fp.exists(), "{} wasn't rendered", name);
+    let actual = read_png(&actual_fp);
+
+    let golden_dir: PathBuf = manifest_dir().join("tests/golden");
+    let reference_fp = golden_dir.join(format!("{}.png", name));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(&golden_dir).unwrap();
+        std::fs::copy(&actual_fp, &reference_fp).unwrap();
+        eprintln!("Wrote reference {}", reference_fp.display());
+        return;
+    }
+    assert!(
+        reference_fp.exists(),
+        "{} has no reference, render one with UPDATE_GOLDEN=1",
+        reference_fp.display()
+    );
+
+    let reference = read_png(&reference_fp);
+    assert_eq!(actual.size().unwrap(), reference.size().unwrap());
+    let values = (actual.total() * actual.channels() as usize) as f64;
+    let diff = core::norm2(&actual, &reference, core::NORM_L1, &core::no_array()).unwrap();
+    let diff = diff / values;
+    if diff > TOLERANCE {
+        let failed_fp = golden_dir.join(format!("{}.actual.png", name));
+        std::fs::copy(&actual_fp, &failed_fp).unwrap();
+        panic!(
+            "{} differs from its reference by {:.2} on average, see {}",
+            name,
+            diff,
+            failed_fp.display()
+        );
+    }
+}
+
+#[test]
+#[ignore = "no reference frames yet"]
+fn test_intro_frame() {
+    check_frame("intro_frame_10");
+}
+
+#[test]
+#[ignore = "no reference frames yet"]
+fn test_card_rotate_in() {
+    check_frame("card_rotate_in");
+}
+
+#[test]
+#[ignore = "no reference frames yet"]
+fn test_zoom_apex() {
+    check_frame("zoom_apex");
+}
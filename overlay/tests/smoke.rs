@@ -0,0 +1,216 @@
+//! Runs the whole pipeline through `overlay::render::run`, as the overlay binary does, on a
+//! generated 10 second video, once with a few events and once with only the match setup. Frames are compared between the two renders,
+//! so each event can be checked by the part of the frame it changed and when.
+
+use std::{path::Path, sync::OnceLock};
+
+use clap::Parser;
+use opencv::{
+    core::{self, Mat, MatTraitConst, Rect, Scalar, Size, CV_8UC3},
+    videoio::{
+        VideoCapture, VideoCaptureTrait, VideoCaptureTraitConst, VideoWriter, VideoWriterTrait,
+        CAP_ANY, CAP_PROP_FPS,
+    },
+};
+use overlay::render::{self, Cli};
+use tempfile::TempDir;
+
+const FPS: f64 = 10.0;
+const SECONDS: f64 = 10.0;
+const FIXTURE_SIZE: (i32, i32) = (320, 180);
+const FIXTURE_COLOR: Scalar = Scalar::new(200.0, 60.0, 120.0, 0.0);
+/// The intro replaces the first 8 seconds, so events have to come after it to be seen
+const SETUP: &str = "sec\tmilli\tname\tpitch\tplayer1_life\tplayer2_life\tupdate_type
+0\t0\tAlice\t\t\t\tplayer1
+0\t0\tBea\t\t\t\tplayer2
+0\t0\tMaxx 'The Hype' Nitro\t\t40\t\thero1
+0\t0\tKayo, Armed and Dangerous\t\t\t40\thero2
+";
+const EVENTS: &str = "8\t300\t\t\t\t\tturn
+8\t800\t\t\t-3\t\tlife
+9\t0\tSmoke Test Card\t1\t\t\tcard
+";
+
+/// Mean absolute difference per channel before a region counts as changed
+const CHANGED: f64 = 2.0;
+
+/// Writes a video of a single color, with its frame number so frames still differ
+fn write_fixture_video(fp: &Path) -> opencv::Result<()> {
+    let (width, height) = FIXTURE_SIZE;
+    let mut out = VideoWriter::new(
+        fp.to_str().unwrap(),
+        VideoWriter::fourcc('m', 'p', '4', 'v')?,
+        FPS,
+        Size::new(width, height),
+        true,
+    )?;
+    for i in 0..(SECONDS * FPS) as i32 {
+        let mut frame = Mat::new_rows_cols_with_default(height, width, CV_8UC3, FIXTURE_COLOR)?;
+        opencv::imgproc::put_text(
+            &mut frame,
+            &i.to_string(),
+            core::Point::new(10, height - 10),
+            opencv::imgproc::FONT_HERSHEY_SIMPLEX,
+            0.5,
+            Scalar::new(0.0, 0.0, 0.0, 0.0),
+            1,
+            opencv::imgproc::LINE_8,
+            false,
+        )?;
+        out.write(&frame)?;
+    }
+    out.release()
+}
+
+struct Render {
+    fps: f64,
+    frames: Vec<Mat>,
+}
+
+struct Renders {
+    _dir: TempDir,
+    with_events: Render,
+    setup_only: Render,
+}
+
+fn read_video(fp: &Path) -> Render {
+    let mut cap = VideoCapture::from_file(fp.to_str().unwrap(), CAP_ANY).unwrap();
+    let fps = cap.get(CAP_PROP_FPS).unwrap();
+    let mut frames = Vec::new();
+    let mut frame = Mat::default();
+    while cap.read(&mut frame).unwrap() {
+        frames.push(frame.clone());
+    }
+    Render { fps, frames }
+}
+
+fn render(dir: &Path, name: &str, annotations: &str) -> Render {
+    let annotation_fp = dir.join(format!("{}.tsv", name));
+    let output_fp = dir.join(format!("{}.mp4", name));
+    std::fs::write(&annotation_fp, annotations).unwrap();
+    let args = Cli::parse_from([
+        "overlay",
+        "--video-file",
+        dir.join("fixture.mp4").to_str().unwrap(),
+        "--card-file",
+        annotation_fp.to_str().unwrap(),
+        "--output-file",
+        output_fp.to_str().unwrap(),
+        // Keeps the output the same whether or not the machine has OpenCL
+        "--gpu",
+        "off",
+    ]);
+    render::run(args, None).unwrap();
+    read_video(&output_fp)
+}
+
+fn renders() -> &'static Renders {
+    static RENDERS: OnceLock<Renders> = OnceLock::new();
+    RENDERS.get_or_init(|| {
+        // Data files are found relative to the workspace root
+        std::env::set_current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        write_fixture_video(&dir.path().join("fixture.mp4")).unwrap();
+        let with_events = render(dir.path(), "events", &format!("{}{}", SETUP, EVENTS));
+        let setup_only = render(dir.path(), "setup", SETUP);
+        Renders {
+            _dir: dir,
+            with_events,
+            setup_only,
+        }
+    })
+}
+
+/// Frame that has just passed `t` seconds into the source. The intro takes the place of the
+/// source's start, and each frame after it is drawn a frame ahead of its index.
+fn frame_at(t: f64) -> usize {
+    (t * FPS).round() as usize - 1
+}
+
+/// Region of a 1920x1080 frame, given as fractions of its width and height
+fn region(x: f64, y: f64, width: f64, height: f64) -> Rect {
+    Rect::new(
+        (x * 1920.0) as i32,
+        (y * 1080.0) as i32,
+        (width * 1920.0) as i32,
+        (height * 1080.0) as i32,
+    )
+}
+
+/// Mean absolute difference per channel between the renders in `rect` of frame `idx`
+fn difference(idx: usize, rect: Rect) -> f64 {
+    let renders = renders();
+    let a = renders.with_events.frames[idx]
+        .roi(rect)
+        .unwrap()
+        .try_clone()
+        .unwrap();
+    let b = renders.setup_only.frames[idx]
+        .roi(rect)
+        .unwrap()
+        .try_clone()
+        .unwrap();
+    let values = (a.total() * a.channels() as usize) as f64;
+    core::norm2(&a, &b, core::NORM_L1, &core::no_array()).unwrap() / values
+}
+
+#[test]
+fn test_duration() {
+    let render = &renders().with_events;
+    assert_eq!(render.fps, FPS);
+    assert_eq!(render.frames.len(), (SECONDS * FPS) as usize);
+    let duration = render.frames.len() as f64 / render.fps;
+    assert!((duration - SECONDS).abs() < 1.0 / FPS, "{}s long", duration);
+    assert_eq!(render.frames[0].size().unwrap(), Size::new(1920, 1080));
+}
+
+#[test]
+fn test_video_is_framed() {
+    // The middle of the inner frame shows the source
+    let frame = &renders().setup_only.frames[frame_at(8.5)];
+    let center = frame
+        .roi(region(0.58, 0.55, 0.02, 0.03))
+        .unwrap()
+        .try_clone()
+        .unwrap();
+    let mean = core::mean_def(&center).unwrap();
+    for channel in 0..3 {
+        assert!(
+            (mean[channel] - FIXTURE_COLOR[channel]).abs() < 20.0,
+            "center is {:?}",
+            mean
+        );
+    }
+}
+
+#[test]
+fn test_nothing_changes_before_events() {
+    let whole = region(0.0, 0.0, 1.0, 1.0);
+    assert!(difference(0, whole) < 0.5);
+    assert!(difference(frame_at(8.1), whole) < 0.5);
+    assert!(difference(frame_at(8.2), whole) < 0.5);
+}
+
+#[test]
+fn test_turn_counter() {
+    // Drawn in the top right corner of the inner frame
+    let corner = region(0.85, 0.1, 0.15, 0.1);
+    assert!(difference(frame_at(8.2), corner) < CHANGED);
+    assert!(difference(frame_at(8.6), corner) > CHANGED);
+}
+
+#[test]
+fn test_life_update() {
+    // Player 1's life is left of the life symbol in the top panel
+    let life1 = region(0.47, 0.0, 0.13, 0.12);
+    assert!(difference(frame_at(8.7), life1) < CHANGED);
+    assert!(difference(frame_at(9.5), life1) > CHANGED);
+}
+
+#[test]
+fn test_card_display() {
+    // The card comes up in the bottom left at 9s, and is turning to its front from 9.75s
+    let card = region(0.0, 0.5, 0.2, 0.5);
+    assert!(difference(frame_at(8.9), card) < CHANGED);
+    assert!(difference(frame_at(9.9), card) > CHANGED);
+}